@@ -1,4 +1,7 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_build::compile_protos("proto/example.proto").unwrap();
+    let descriptors = std::path::PathBuf::from(std::env::var("OUT_DIR")?).join("example.bin");
+    tonic_build::configure()
+        .file_descriptor_set_path(descriptors)
+        .compile_protos(&["proto/example.proto"], &["proto"])?;
     Ok(())
 }
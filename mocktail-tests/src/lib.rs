@@ -1,3 +1,17 @@
+use std::time::Duration;
+
+use futures::{stream, StreamExt};
+
 pub mod pb {
     tonic::include_proto!("example");
 }
+
+/// Returns a request body sending `helloworld!` in three chunks,
+/// waiting for the delay before each chunk.
+pub fn slow_body(delay: Duration) -> reqwest::Body {
+    let chunks = stream::iter(["hello", "world", "!"]).then(move |chunk| async move {
+        tokio::time::sleep(delay).await;
+        Ok::<_, std::io::Error>(chunk)
+    });
+    reqwest::Body::wrap_stream(chunks)
+}
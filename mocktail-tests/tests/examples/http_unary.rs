@@ -147,6 +147,36 @@ async fn test_any() -> Result<(), Error> {
     Ok(())
 }
 
+#[test(tokio::test)]
+async fn test_any_method_any_path() -> Result<(), Error> {
+    let mut mocks = MockSet::new();
+    mocks.mock(|when, then| {
+        when.any_method().path("/maintenance");
+        then.service_unavailable();
+    });
+    mocks.mock(|when, then| {
+        when.delete().any_path();
+        then.forbidden();
+    });
+
+    let server = MockServer::new_http("any").with_mocks(mocks);
+    server.start().await?;
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client.get(server.url("/maintenance")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+    let response = client.put(server.url("/maintenance")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+
+    let response = client.delete(server.url("/a/b/c")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::FORBIDDEN);
+    let response = client.get(server.url("/a/b/c")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
 #[test(tokio::test)]
 async fn test_unary_headers() -> Result<(), Error> {
     let mut mocks = MockSet::new();
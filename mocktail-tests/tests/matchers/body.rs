@@ -0,0 +1,157 @@
+use std::time::Duration;
+
+use anyhow::Error;
+use futures::{stream, StreamExt};
+use mocktail::prelude::*;
+use test_log::test;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+/// SHA-256 of 1 MiB of bytes `i % 251`.
+const DIGEST: &str = "631b84027d6b9e52b539c4e8373622d23032dfadc64d60af87339c9037e4f769";
+
+fn payload() -> Vec<u8> {
+    (0..1 << 20).map(|i| (i % 251) as u8).collect()
+}
+
+#[test(tokio::test)]
+async fn test_body_sha256() -> Result<(), Error> {
+    let mut server = MockServer::new_http("body_sha256");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.put().path("/upload").body_sha256(DIGEST);
+        then.text("stored");
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    // Unary
+    let response = client
+        .put(server.url("/upload"))
+        .body(payload())
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(response.text().await?, "stored");
+
+    // Streaming
+    let chunks = payload()
+        .chunks(64 * 1024)
+        .map(|chunk| Ok::<_, std::io::Error>(chunk.to_vec()))
+        .collect::<Vec<_>>();
+    let response = client
+        .put(server.url("/upload"))
+        .body(reqwest::Body::wrap_stream(stream::iter(chunks)))
+        .send()
+        .await?;
+    assert_eq!(response.text().await?, "stored");
+
+    let response = client
+        .put(server.url("/upload"))
+        .body("unexpected")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_body_prefix_streaming() -> Result<(), Error> {
+    let mut server = MockServer::new_http("body_prefix");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post()
+            .path("/duplex")
+            .body_prefix(Body::bytes("hello "));
+        then.text("hi!");
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let (tx, rx) = mpsc::channel::<Result<&'static str, std::io::Error>>(4);
+    tx.send(Ok("hello ")).await?;
+    let response = client
+        .post(server.url("/duplex"))
+        .body(reqwest::Body::wrap_stream(ReceiverStream::new(rx)))
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    // The response is received before the request is complete
+    let mut body = response.bytes_stream();
+    let chunk = tokio::time::timeout(Duration::from_secs(5), body.next())
+        .await?
+        .unwrap()?;
+    assert_eq!(chunk, "hi!");
+    let _ = tx.send(Ok("world")).await;
+    drop(tx);
+
+    assert_eq!(server.journal().len(), 1);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_binary_body() -> Result<(), Error> {
+    let firmware = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/binary/firmware.bin"
+    );
+    let mut server = MockServer::new_http("binary_body");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.put().path("/firmware").bytes_file(firmware);
+        then.text("exact");
+    });
+    server.mock(|when, then| {
+        when.post()
+            .path("/firmware")
+            .bytes_prefix(&b"\x7fFW1"[..])
+            .bytes_at(4, &[1, 0][..])
+            .bytes_suffix(&b"\xde\xad\xbe\xef"[..]);
+        then.text("v1");
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let data = std::fs::read(firmware)?;
+    let response = client
+        .put(server.url("/firmware"))
+        .body(data.clone())
+        .send()
+        .await?;
+    assert_eq!(response.text().await?, "exact");
+
+    let mut changed = data.clone();
+    changed[100] ^= 0xff;
+    let response = client
+        .put(server.url("/firmware"))
+        .body(changed.clone())
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    let response = client
+        .post(server.url("/firmware"))
+        .body(changed)
+        .send()
+        .await?;
+    assert_eq!(response.text().await?, "v1");
+
+    // Wrong version at offset
+    let mut v2 = data.clone();
+    v2[4] = 2;
+    let response = client.post(server.url("/firmware")).body(v2).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    // Truncated
+    let response = client
+        .post(server.url("/firmware"))
+        .body(data[..5].to_vec())
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    Ok(())
+}
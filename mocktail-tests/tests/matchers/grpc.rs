@@ -0,0 +1,238 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::Error;
+use mocktail::prelude::*;
+use mocktail_tests::pb::{
+    hello_client::HelloClient, HelloClientStreamingResponse, HelloRequest, HelloResponse,
+};
+use prost::Message;
+use prost_types::{value::Kind, Any, Struct, Timestamp};
+use test_log::test;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::Channel;
+
+fn names(names: &[&str]) -> Vec<HelloRequest> {
+    names
+        .iter()
+        .map(|name| HelloRequest {
+            name: name.to_string(),
+        })
+        .collect()
+}
+
+#[test(tokio::test)]
+async fn test_grpc_message_at() -> Result<(), Error> {
+    let mut server = MockServer::new_grpc("example.Hello");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.path("/example.Hello/HelloClientStreaming")
+            .grpc_message_at(
+                2,
+                body(Body::pb(HelloRequest {
+                    name: "paulo".into(),
+                })),
+            );
+        then.pb(HelloClientStreamingResponse {
+            messages: vec!["hello paulo!".into()],
+        });
+    });
+
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+
+    let response = client
+        .hello_client_streaming(futures::stream::iter(names(&["dan", "mateus", "paulo"])))
+        .await?;
+    assert_eq!(response.into_inner().messages, ["hello paulo!"]);
+
+    // Only the message at the index is matched
+    let result = client
+        .hello_client_streaming(futures::stream::iter(names(&["paulo", "mateus"])))
+        .await;
+    assert_eq!(result.unwrap_err().code(), tonic::Code::NotFound);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_pb_stream_prefix() -> Result<(), Error> {
+    let mut server = MockServer::new_grpc("example.Hello");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.path("/example.Hello/HelloClientStreaming")
+            .pb_stream_prefix(names(&["dan"]));
+        then.pb(HelloClientStreamingResponse {
+            messages: vec!["hello dan!".into()],
+        });
+    });
+
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+
+    // The response is received before the client stream is closed
+    let (tx, rx) = mpsc::channel(4);
+    tx.send(names(&["dan"]).remove(0)).await?;
+    let response = tokio::time::timeout(
+        Duration::from_secs(5),
+        client.hello_client_streaming(ReceiverStream::new(rx)),
+    )
+    .await??;
+    assert_eq!(response.into_inner().messages, ["hello dan!"]);
+    drop(tx);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_grpc_service() -> Result<(), Error> {
+    let mut server = MockServer::new_grpc("example.Hello");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.path("/example.Hello/HelloUnary")
+            .pb(HelloRequest { name: "dan".into() });
+        then.pb(HelloResponse {
+            message: "hello dan!".into(),
+        });
+    });
+    server.mock_with_options(10, None, |when, then| {
+        when.grpc_method_regex(r"^example\.Hello/HelloClient");
+        then.grpc_invalid_argument("streams are not accepted");
+    });
+    server.mock_with_options(20, None, |when, then| {
+        when.grpc_service("example.Hello");
+        then.grpc_unavailable("maintenance");
+    });
+
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+
+    let response = client.hello_unary(names(&["dan"]).remove(0)).await?;
+    assert_eq!(response.into_inner().message, "hello dan!");
+
+    // Any other call of the service
+    let status = client
+        .hello_unary(names(&["mateus"]).remove(0))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unavailable);
+    assert_eq!(status.message(), "maintenance");
+
+    // Methods matching the regex
+    let status = client
+        .hello_client_streaming(futures::stream::iter(names(&["dan"])))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    Ok(())
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct Event {
+    #[prost(message, optional, tag = "1")]
+    created_at: Option<Timestamp>,
+    #[prost(message, optional, tag = "2")]
+    payload: Option<Any>,
+}
+
+/// Returns an `Any` packing a `Struct`, with the fields encoded in order.
+fn attributes(fields: &[(&str, &str)]) -> Any {
+    let value = fields
+        .iter()
+        .flat_map(|(key, value)| {
+            let value = prost_types::Value {
+                kind: Some(Kind::StringValue(value.to_string())),
+            };
+            Struct {
+                fields: [(key.to_string(), value)].into(),
+            }
+            .encode_to_vec()
+        })
+        .collect();
+    Any {
+        type_url: "type.googleapis.com/google.protobuf.Struct".into(),
+        value,
+    }
+}
+
+async fn publish(channel: Channel, event: Event) -> Result<HelloResponse, tonic::Status> {
+    let mut client = tonic::client::Grpc::new(channel);
+    client
+        .ready()
+        .await
+        .map_err(|err| tonic::Status::unknown(err.to_string()))?;
+    let path = http::uri::PathAndQuery::from_static("/example.Events/Publish");
+    let response = client
+        .unary(
+            tonic::Request::new(event),
+            path,
+            tonic::codec::ProstCodec::default(),
+        )
+        .await?;
+    Ok(response.into_inner())
+}
+
+#[test(tokio::test)]
+async fn test_pb_with() -> Result<(), Error> {
+    let created_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let mut server = MockServer::new_grpc("example.Events");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.path("/example.Events/Publish").pb_with(
+            Event {
+                created_at: Some(created_at.into()),
+                payload: Some(attributes(&[("region", "eu"), ("tier", "gold")])),
+            },
+            PbCompare::new().timestamp_tolerance(Duration::from_secs(1)),
+        );
+        then.pb(HelloResponse {
+            message: "published".into(),
+        });
+    });
+
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+
+    // Packed fields in another order, timestamp within the tolerance
+    let response = publish(
+        channel.clone(),
+        Event {
+            created_at: Some((created_at + Duration::from_millis(250)).into()),
+            payload: Some(attributes(&[("tier", "gold"), ("region", "eu")])),
+        },
+    )
+    .await?;
+    assert_eq!(response.message, "published");
+
+    // Timestamp outside the tolerance
+    let result = publish(
+        channel.clone(),
+        Event {
+            created_at: Some((created_at + Duration::from_secs(5)).into()),
+            payload: Some(attributes(&[("region", "eu"), ("tier", "gold")])),
+        },
+    )
+    .await;
+    assert_eq!(result.unwrap_err().code(), tonic::Code::NotFound);
+
+    // Different packed value
+    let result = publish(
+        channel,
+        Event {
+            created_at: Some(created_at.into()),
+            payload: Some(attributes(&[("region", "us"), ("tier", "gold")])),
+        },
+    )
+    .await;
+    assert_eq!(result.unwrap_err().code(), tonic::Code::NotFound);
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use anyhow::Error;
+use mocktail::prelude::*;
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_header_with() -> Result<(), Error> {
+    let mut server = MockServer::new_http("header_with");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get()
+            .path("/content")
+            .header_with(
+                "accept-encoding",
+                "gzip, br",
+                HeaderCompare::new().list_as_set(),
+            )
+            .header_with(
+                "x-client",
+                "mobile",
+                HeaderCompare::new().ignore_case().trim(),
+            );
+        then.ok();
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    for (encoding, client_name) in [
+        ("gzip, br", "mobile"),
+        ("br,gzip", " Mobile "),
+        ("br, gzip, br", "MOBILE"),
+    ] {
+        let response = client
+            .get(server.url("/content"))
+            .header("accept-encoding", encoding)
+            .header("x-client", client_name)
+            .send()
+            .await?;
+        assert_eq!(response.status(), http::StatusCode::OK, "{encoding}");
+    }
+
+    // Repeated headers are combined into one list
+    let response = client
+        .get(server.url("/content"))
+        .header("accept-encoding", "br")
+        .header("accept-encoding", "gzip")
+        .header("x-client", "mobile")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    for (encoding, client_name) in [("gzip", "mobile"), ("gzip, br", "mobile-web")] {
+        let response = client
+            .get(server.url("/content"))
+            .header("accept-encoding", encoding)
+            .header("x-client", client_name)
+            .send()
+            .await?;
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND, "{encoding}");
+    }
+
+    Ok(())
+}
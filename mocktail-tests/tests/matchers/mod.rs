@@ -1 +1,6 @@
-
+mod body;
+mod grpc;
+mod headers;
+mod path;
+mod peer;
+mod trace;
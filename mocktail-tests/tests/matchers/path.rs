@@ -0,0 +1,88 @@
+use anyhow::Error;
+use mocktail::{prelude::*, server::MockServerConfig};
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_path_normalization() -> Result<(), Error> {
+    let config = MockServerConfig::builder()
+        .path_normalization(PathNormalization::all())
+        .build()?;
+    let server = MockServer::new_http("normalization").with_config(config);
+    server.start().await?;
+    server.mocks().mock(|when, then| {
+        when.get().path("/Users/John Smith");
+        then.text("john");
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    for path in [
+        "/users/john%20smith",
+        "/USERS/John%20Smith/",
+        "/Users/John Smith",
+    ] {
+        let response = client.get(server.url(path)).send().await?;
+        assert_eq!(response.status(), http::StatusCode::OK, "{path}");
+        assert_eq!(response.text().await?, "john");
+    }
+
+    // Original path is preserved in the journal
+    let journal = server.journal();
+    let entry = journal.iter().next().unwrap();
+    assert_eq!(entry.request.path(), "/users/john%20smith");
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_path_no_normalization() -> Result<(), Error> {
+    let server = MockServer::new_http("normalization");
+    server.start().await?;
+    server.mocks().mock(|when, then| {
+        when.get().path("/users");
+        then.text("users");
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client.get(server.url("/users/")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_path_template() -> Result<(), Error> {
+    let server = MockServer::new_http("path_template");
+    server.start().await?;
+    server.mocks().mock(|when, then| {
+        when.get().path_template("/users/{user}/orders/{id}");
+        then.text("order");
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client
+        .get(server.url("/users/john%20smith/orders/42"))
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    for path in ["/users/john/orders", "/users/john/orders/42/items"] {
+        let response = client.get(server.url(path)).send().await?;
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND, "{path}");
+    }
+
+    // Path parameters are recorded in the journal
+    let journal = server.journal();
+    let entry = journal.iter().next().unwrap();
+    assert_eq!(entry.path_param("user"), Some("john smith"));
+    assert_eq!(entry.path_param_as::<u64>("id"), Some(42));
+    assert_eq!(entry.path_param_as::<u64>("user"), None);
+    assert_eq!(
+        journal.count(&JournalQuery::new().path_param("id", "42")),
+        1
+    );
+    assert!(journal.iter().nth(1).unwrap().path_params().is_empty());
+
+    Ok(())
+}
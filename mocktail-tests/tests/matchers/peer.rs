@@ -0,0 +1,44 @@
+use anyhow::Error;
+use mocktail::prelude::*;
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_peer_ip() -> Result<(), Error> {
+    let mut server = MockServer::new_http("peer_ip");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/region").peer_ip("10.0.0.0/8");
+        then.text("internal");
+    });
+    server.mock(|when, then| {
+        when.get().path("/region").peer_ip("127.0.0.0/8");
+        then.text("local");
+    });
+    server.mock(|when, then| {
+        when.get().path("/region");
+        then.text("external");
+    });
+    server.mock(|when, then| {
+        when.get().path("/exact").peer_ip("127.0.0.1");
+        then.ok();
+    });
+
+    let client = reqwest::Client::new();
+
+    let response = client.get(server.url("/region")).send().await?;
+    assert_eq!(response.text().await?, "local");
+
+    let response = client.get(server.url("/exact")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    Ok(())
+}
+
+#[test]
+#[should_panic(expected = "invalid peer ip cidr")]
+fn test_peer_ip_invalid() {
+    let _ = Mock::new(|when, then| {
+        when.peer_ip("10.0.0.0/33");
+        then.ok();
+    });
+}
@@ -0,0 +1,63 @@
+use anyhow::Error;
+use mocktail::prelude::*;
+use test_log::test;
+
+const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+#[test(tokio::test)]
+async fn test_traceparent() -> Result<(), Error> {
+    let mut server = MockServer::new_http("traceparent");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get()
+            .path("/trace")
+            .trace_id(TRACE_ID)
+            .baggage("tenant", "a");
+        then.ok();
+    });
+    server.mock(|when, then| {
+        when.get().path("/request-id").request_id();
+        then.ok();
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client
+        .get(server.url("/trace"))
+        .header("traceparent", TRACEPARENT)
+        .header("baggage", "tenant=a,user=b")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    // Malformed traceparent
+    let response = client
+        .get(server.url("/trace"))
+        .header("traceparent", "00-invalid-01")
+        .header("baggage", "tenant=a")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    let response = client
+        .get(server.url("/request-id"))
+        .header("x-request-id", "f058ebd6-02f7-4d3f-942e-904344e8cde5")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    let response = client.get(server.url("/request-id")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    let journal = server.journal();
+    assert_eq!(journal.trace_ids(), [TRACE_ID]);
+    assert_eq!(journal.missing_traceparent().count(), 3);
+    assert_eq!(journal.count(&JournalQuery::new().trace_id(TRACE_ID)), 1);
+    assert_eq!(
+        journal.count(&JournalQuery::new().request_id("f058ebd6-02f7-4d3f-942e-904344e8cde5")),
+        1
+    );
+
+    Ok(())
+}
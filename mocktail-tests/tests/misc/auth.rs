@@ -0,0 +1,376 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::Error;
+use md5::Md5;
+use mocktail::{
+    auth::{Digest, DigestAlgorithm, Qop, SigV4},
+    clock::MockClock,
+    prelude::*,
+    server::MockServerConfig,
+};
+use serde_json::json;
+use sha2::{Digest as _, Sha256};
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_basic_challenge() -> Result<(), Error> {
+    let mut server = MockServer::new_http("auth");
+    server.start().await?;
+    server.mock_with_challenge(
+        Challenge::basic("reports", "dan", "secret"),
+        |when, then| {
+            when.get().path("/reports");
+            then.json(json!([{"id": 1}]));
+        },
+    );
+
+    let client = reqwest::Client::new();
+
+    // Requests without credentials are challenged
+    let response = client.get(server.url("/reports")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        response.headers()["www-authenticate"],
+        "Basic realm=\"reports\", charset=\"UTF-8\""
+    );
+
+    let response = client
+        .get(server.url("/reports"))
+        .basic_auth("dan", Some("wrong"))
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
+
+    // The retry with the credentials is served the mock
+    let response = client
+        .get(server.url("/reports"))
+        .basic_auth("dan", Some("secret"))
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(
+        response.json::<serde_json::Value>().await?,
+        json!([{"id": 1}])
+    );
+
+    // Requests not matching the mock are not challenged
+    let response = client.get(server.url("/users")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_challenge_schemes() -> Result<(), Error> {
+    let mut server = MockServer::new_http("auth");
+    server.start().await?;
+    server.mock_with_challenge(
+        Challenge::negotiate(None).with_challenge("NTLM"),
+        |when, then| {
+            when.path("/intranet");
+            then.text("welcome");
+        },
+    );
+    server.mock(|when, then| {
+        when.path("/api").bearer_token("abc");
+        then.text("api");
+    });
+
+    let client = reqwest::Client::new();
+
+    // Several schemes are offered in separate headers
+    let response = client.get(server.url("/intranet")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
+    let challenges = response
+        .headers()
+        .get_all("www-authenticate")
+        .iter()
+        .map(|value| value.to_str())
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(challenges, ["Negotiate", "NTLM"]);
+
+    // Scheme names are case-insensitive
+    let response = client
+        .get(server.url("/intranet"))
+        .header("authorization", "negotiate YIIGhgYGKwYBBQUC")
+        .send()
+        .await?;
+    assert_eq!(response.text().await?, "welcome");
+
+    let response = client
+        .get(server.url("/api"))
+        .bearer_auth("abc")
+        .send()
+        .await?;
+    assert_eq!(response.text().await?, "api");
+    let response = client
+        .get(server.url("/api"))
+        .bearer_auth("xyz")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+/// Returns the quoted or unquoted value of a challenge parameter.
+fn param<'a>(challenge: &'a str, name: &str) -> Option<&'a str> {
+    let (_, rest) = challenge.split_once(&format!("{name}="))?;
+    match rest.strip_prefix('"') {
+        Some(rest) => rest.split('"').next(),
+        None => rest.split(',').next(),
+    }
+}
+
+/// Returns the `Digest` authorization responding to a challenge, with a nonce count.
+fn digest_authorization(
+    challenge: &str,
+    method: &str,
+    uri: &str,
+    body: &[u8],
+    nc: u32,
+    password: &str,
+) -> String {
+    let algorithm = param(challenge, "algorithm").unwrap();
+    let hash = |data: &[u8]| -> String {
+        let digest = match algorithm {
+            "SHA-256" => Sha256::digest(data).to_vec(),
+            _ => Md5::digest(data).to_vec(),
+        };
+        digest.iter().map(|byte| format!("{byte:02x}")).collect()
+    };
+    let realm = param(challenge, "realm").unwrap();
+    let nonce = param(challenge, "nonce").unwrap();
+    let opaque = param(challenge, "opaque").unwrap();
+    let qop = param(challenge, "qop").unwrap();
+    let qop = if qop.contains("auth-int") {
+        "auth-int"
+    } else {
+        "auth"
+    };
+    let nc = format!("{nc:08x}");
+    let cnonce = "0a4f113b";
+    let ha1 = hash(format!("dan:{realm}:{password}").as_bytes());
+    let ha2 = match qop {
+        "auth-int" => hash(format!("{method}:{uri}:{}", hash(body)).as_bytes()),
+        _ => hash(format!("{method}:{uri}").as_bytes()),
+    };
+    let response = hash(format!("{ha1}:{nonce}:{nc}:{cnonce}:{qop}:{ha2}").as_bytes());
+    format!(
+        "Digest username=\"dan\", realm=\"{realm}\", nonce=\"{nonce}\", uri=\"{uri}\", \
+        algorithm={algorithm}, qop={qop}, nc={nc}, cnonce=\"{cnonce}\", response=\"{response}\", \
+        opaque=\"{opaque}\""
+    )
+}
+
+#[test(tokio::test)]
+async fn test_digest_challenge() -> Result<(), Error> {
+    let clock = MockClock::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+    let config = MockServerConfig::builder().clock(clock.clone()).build()?;
+    let mut server = MockServer::new_http("auth").with_config(config);
+    server.start().await?;
+    let digest = Digest::new("reports", "dan", "secret");
+    server.mock_with_challenge(Challenge::digest(digest), |when, then| {
+        when.get().path("/reports");
+        then.text("reports");
+    });
+
+    let client = reqwest::Client::new();
+    let get = |authorization: &str| {
+        client
+            .get(server.url("/reports?page=1"))
+            .header("authorization", authorization)
+            .send()
+    };
+
+    let response = client.get(server.url("/reports?page=1")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
+    let challenge = response.headers()["www-authenticate"].to_str()?.to_string();
+    assert!(challenge.starts_with("Digest realm=\"reports\", qop=\"auth\", algorithm=MD5"));
+    assert_eq!(param(&challenge, "stale"), None);
+
+    let response = get(&digest_authorization(
+        &challenge,
+        "GET",
+        "/reports?page=1",
+        b"",
+        1,
+        "wrong",
+    ))
+    .await?;
+    assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
+
+    let authorization =
+        digest_authorization(&challenge, "GET", "/reports?page=1", b"", 1, "secret");
+    let response = get(&authorization).await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(response.text().await?, "reports");
+
+    // Nonce counts must increase
+    let response = get(&authorization).await?;
+    assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
+    let response = get(&digest_authorization(
+        &challenge,
+        "GET",
+        "/reports?page=1",
+        b"",
+        2,
+        "secret",
+    ))
+    .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    // Expired nonces are challenged with a fresh stale nonce
+    clock.advance(Duration::from_secs(301));
+    let response = get(&digest_authorization(
+        &challenge,
+        "GET",
+        "/reports?page=1",
+        b"",
+        3,
+        "secret",
+    ))
+    .await?;
+    assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
+    let stale = response.headers()["www-authenticate"].to_str()?.to_string();
+    assert_eq!(param(&stale, "stale"), Some("true"));
+    assert_ne!(param(&stale, "nonce"), param(&challenge, "nonce"));
+    let response = get(&digest_authorization(
+        &stale,
+        "GET",
+        "/reports?page=1",
+        b"",
+        1,
+        "secret",
+    ))
+    .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_digest_nonce_count_served() -> Result<(), Error> {
+    let mut server = MockServer::new_http("auth");
+    server.start().await?;
+    let digest = Digest::new("reports", "dan", "secret");
+    server.mock_with_challenge(Challenge::digest(digest), |when, then| {
+        when.get().path("/reports").header("x-version", "2");
+        then.text("reports");
+    });
+
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(server.url("/reports"))
+        .header("x-version", "2")
+        .send()
+        .await?;
+    let challenge = response.headers()["www-authenticate"].to_str()?.to_string();
+    let authorization = digest_authorization(&challenge, "GET", "/reports", b"", 1, "secret");
+
+    // Nonce counts are only used by requests served by the mock
+    let response = client
+        .get(server.url("/reports"))
+        .header("authorization", &authorization)
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    for status in [http::StatusCode::OK, http::StatusCode::UNAUTHORIZED] {
+        let response = client
+            .get(server.url("/reports"))
+            .header("x-version", "2")
+            .header("authorization", &authorization)
+            .send()
+            .await?;
+        assert_eq!(response.status(), status);
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_digest_auth_int() -> Result<(), Error> {
+    let mut server = MockServer::new_http("auth");
+    server.start().await?;
+    let digest = Digest::new("reports", "dan", "secret")
+        .algorithm(DigestAlgorithm::Sha256)
+        .qop([Qop::AuthInt]);
+    server.mock_with_challenge(Challenge::digest(digest), |when, then| {
+        when.post().path("/reports");
+        then.created();
+    });
+
+    let client = reqwest::Client::new();
+
+    let response = client.post(server.url("/reports")).send().await?;
+    let challenge = response.headers()["www-authenticate"].to_str()?.to_string();
+    assert_eq!(param(&challenge, "algorithm"), Some("SHA-256"));
+    assert_eq!(param(&challenge, "qop"), Some("auth-int"));
+
+    // The body is authenticated
+    let authorization = digest_authorization(&challenge, "POST", "/reports", b"{}", 1, "secret");
+    let response = client
+        .post(server.url("/reports"))
+        .header("authorization", &authorization)
+        .body("[]")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
+    let response = client
+        .post(server.url("/reports"))
+        .header("authorization", &authorization)
+        .body("{}")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::CREATED);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_aws_sigv4() -> Result<(), Error> {
+    // The get-vanilla-query-order-key-case example of the AWS test suite
+    let clock = MockClock::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1_440_938_160));
+    let config = MockServerConfig::builder().clock(clock.clone()).build()?;
+    let mut server = MockServer::new_http("auth").with_config(config);
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/").aws_sigv4(
+            SigV4::new("AKIDEXAMPLE", "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY")
+                .region("us-east-1")
+                .service("service"),
+        );
+        then.text("signed");
+    });
+
+    let client = reqwest::Client::new();
+    let send = |query: &str| {
+        client
+            .get(server.url(&format!("/?{query}")))
+            .header("host", "example.amazonaws.com")
+            .header("x-amz-date", "20150830T123600Z")
+            .header(
+                "authorization",
+                "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+                SignedHeaders=host;x-amz-date, \
+                Signature=b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500",
+            )
+            .send()
+    };
+
+    let response = send("Param2=value2&Param1=value1").await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(response.text().await?, "signed");
+
+    // Requests modified after signing are not matched
+    let response = send("Param2=value2&Param1=value3").await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    // Signatures are valid within the clock skew
+    clock.advance(Duration::from_secs(16 * 60));
+    let response = send("Param2=value2&Param1=value1").await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::Error;
+use mocktail::{prelude::*, CachePolicy};
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_cache_policy() -> Result<(), Error> {
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let mut server = MockServer::new_http("cache");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.path("/report");
+        then.text("report").cache(
+            CachePolicy::new()
+                .public()
+                .max_age(Duration::from_secs(60))
+                .etag()
+                .last_modified(modified)
+                .expires(),
+        );
+    });
+
+    let client = reqwest::Client::new();
+
+    let response = client.get(server.url("/report")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    let headers = response.headers();
+    assert_eq!(headers["cache-control"], "public, max-age=60");
+    assert_eq!(headers["last-modified"], "Tue, 14 Nov 2023 22:13:20 GMT");
+    let etag = headers["etag"].to_str()?.to_string();
+    assert!(etag.starts_with('"') && etag.len() == 34);
+    let date = httpdate::parse_http_date(headers["date"].to_str()?)?;
+    let expires = httpdate::parse_http_date(headers["expires"].to_str()?)?;
+    assert_eq!(expires.duration_since(date)?, Duration::from_secs(60));
+
+    // Entity tags are compared with the weak comparison
+    for tags in [etag.clone(), format!("\"other\", W/{etag}"), "*".into()] {
+        let response = client
+            .get(server.url("/report"))
+            .header("if-none-match", tags)
+            .send()
+            .await?;
+        assert_eq!(response.status(), http::StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()["etag"], etag.as_str());
+        assert!(response.bytes().await?.is_empty());
+    }
+    let response = client
+        .get(server.url("/report"))
+        .header("if-none-match", "\"other\"")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(response.text().await?, "report");
+
+    let response = client
+        .get(server.url("/report"))
+        .header("if-modified-since", "Tue, 14 Nov 2023 22:13:20 GMT")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_MODIFIED);
+    let response = client
+        .get(server.url("/report"))
+        .header("if-modified-since", "Tue, 14 Nov 2023 22:13:19 GMT")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    // Only GET and HEAD requests are conditional
+    let response = client
+        .post(server.url("/report"))
+        .header("if-none-match", "*")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_cache_policy_explicit_headers() -> Result<(), Error> {
+    let mut server = MockServer::new_http("cache");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/config");
+        then.headers([("cache-control", "no-store")])
+            .text("config")
+            .cache(
+                CachePolicy::new()
+                    .max_age(Duration::from_secs(60))
+                    .etag_value("v1"),
+            );
+    });
+
+    let client = reqwest::Client::new();
+
+    // Explicit headers are not overridden
+    let response = client.get(server.url("/config")).send().await?;
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    assert_eq!(response.headers()["etag"], "\"v1\"");
+
+    let response = client
+        .get(server.url("/config"))
+        .header("if-none-match", "\"v1\"")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_MODIFIED);
+
+    Ok(())
+}
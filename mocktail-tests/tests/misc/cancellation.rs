@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use anyhow::Error;
+use futures::StreamExt;
+use mocktail::prelude::*;
+use mocktail_tests::pb::{
+    hello_client::HelloClient, HelloRequest, HelloResponse, HelloServerStreamingRequest,
+};
+use test_log::test;
+use tonic::transport::Channel;
+
+async fn client(server: &MockServer) -> Result<HelloClient<Channel>, Error> {
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    Ok(HelloClient::new(channel))
+}
+
+#[test(tokio::test)]
+async fn test_grpc_cancellation() -> Result<(), Error> {
+    let mut server = MockServer::new_grpc("example.Hello");
+    server.start().await?;
+    let id = server.mock(|when, then| {
+        when.path("/example.Hello/HelloUnary");
+        then.pb(HelloResponse {
+            message: "hello dan!".into(),
+        })
+        .delay(Duration::from_secs(5));
+    });
+    let mut client = client(&server).await?;
+
+    // The client cancels the call when the timeout elapses
+    let call = client.hello_unary(HelloRequest { name: "dan".into() });
+    let result = tokio::time::timeout(Duration::from_millis(100), call).await;
+    assert!(result.is_err());
+
+    let entry =
+        tokio::time::timeout(Duration::from_secs(1), server.wait_for_cancellation(id)).await?;
+    assert_eq!(entry.mock_id(), Some(&id));
+    assert!(entry.is_cancelled());
+    // Measured by the server from receiving the request head
+    let after = entry.cancelled_after().unwrap();
+    assert!(after >= Duration::from_millis(50) && after < Duration::from_secs(5));
+    assert_eq!(server.journal().len(), 1);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_grpc_streaming_cancellation() -> Result<(), Error> {
+    let mut server = MockServer::new_grpc("example.Hello");
+    server.start().await?;
+    let id = server.mock(|when, then| {
+        when.path("/example.Hello/HelloServerStreaming");
+        then.pb_stream(["dan", "mateus", "paulo"].map(|name| HelloResponse {
+            message: format!("hello {name}!"),
+        }))
+        .chunk_interval(Duration::from_millis(200));
+    });
+    let mut client = client(&server).await?;
+
+    // The client drops the response stream after the first message
+    let mut stream = client
+        .hello_server_streaming(HelloServerStreamingRequest {
+            names: vec!["dan".into()],
+        })
+        .await?
+        .into_inner();
+    assert!(stream.next().await.is_some());
+    drop(stream);
+
+    let entry =
+        tokio::time::timeout(Duration::from_secs(1), server.wait_for_cancellation(id)).await?;
+    // The stream is cancelled before the second message is sent
+    assert!(entry.cancelled_after().unwrap() < Duration::from_millis(200));
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_http_disconnect() -> Result<(), Error> {
+    let mut server = MockServer::new_http("cancellation");
+    server.start().await?;
+
+    for client in [
+        reqwest::Client::new(),
+        reqwest::Client::builder().http2_prior_knowledge().build()?,
+    ] {
+        let id = server.mock(|when, then| {
+            when.get().path("/slow");
+            then.text("done").delay(Duration::from_secs(5));
+        });
+
+        // The client disconnects when the timeout elapses
+        let request = client.get(server.url("/slow")).send();
+        let result = tokio::time::timeout(Duration::from_millis(100), request).await;
+        assert!(result.is_err());
+        drop(client);
+
+        let entry =
+            tokio::time::timeout(Duration::from_secs(1), server.wait_for_cancellation(id)).await?;
+        let after = entry.cancelled_after().unwrap();
+        assert!(after >= Duration::from_millis(50) && after < Duration::from_secs(5));
+        server.reset();
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_http_disconnect_mid_body() -> Result<(), Error> {
+    let mut server = MockServer::new_http("cancellation");
+    server.start().await?;
+    let id = server.mock(|when, then| {
+        when.get().path("/download");
+        then.text("0123456789")
+            .chunk_size(2)
+            .chunk_interval(Duration::from_millis(100));
+    });
+    server.mock(|when, then| {
+        when.get().path("/hello");
+        then.text("hello");
+    });
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    server.on_cancellation(move |entry| {
+        let _ = tx.send(entry.request().path().to_string());
+    });
+
+    let client = reqwest::Client::new();
+
+    // Complete responses are not cancelled
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.text().await?, "hello");
+
+    // The client disconnects after the first chunk
+    let mut response = client.get(server.url("/download")).send().await?;
+    assert_eq!(response.chunk().await?.unwrap(), "01");
+    drop(response);
+    drop(client);
+
+    let entry =
+        tokio::time::timeout(Duration::from_secs(1), server.wait_for_cancellation(id)).await?;
+    assert!(entry.cancelled_after().unwrap() < Duration::from_millis(500));
+    assert!(!server.journal().iter().next().unwrap().is_cancelled());
+    assert_eq!(rx.recv().await.unwrap(), "/download");
+    assert!(rx.try_recv().is_err());
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+use anyhow::Error;
+use futures::StreamExt;
+use mocktail::prelude::*;
+use serde_json::json;
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_chunk_size_and_interval() -> Result<(), Error> {
+    let mut server = MockServer::new_http("chunking");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/user");
+        then.json(json!({"name": "dan"}))
+            .chunk_size(8)
+            .chunk_interval(Duration::from_millis(20));
+    });
+    server.mock(|when, then| {
+        when.get().path("/stream");
+        then.bytes_stream(["abcdef", "ghij"]).chunk_size(4);
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    // One JSON object split across frames
+    let mut response = client.get(server.url("/user")).send().await?;
+    let mut chunks = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if !chunk.is_empty() {
+            chunks.push(chunk);
+        }
+    }
+    assert_eq!(chunks, ["{\"name\":", "\"dan\"}"]);
+
+    let response = client.get(server.url("/stream")).send().await?;
+    let chunks = response
+        .bytes_stream()
+        .map(|chunk| chunk.unwrap())
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(chunks.concat(), b"abcdefghij");
+    assert!(chunks.iter().all(|chunk| chunk.len() <= 4));
+
+    Ok(())
+}
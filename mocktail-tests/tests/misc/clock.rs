@@ -0,0 +1,86 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::Error;
+use mocktail::{clock::MockClock, prelude::*, server::MockServerConfig};
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_mock_clock() -> Result<(), Error> {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let clock = MockClock::new(start);
+    let config = MockServerConfig::builder().clock(clock.clone()).build()?;
+    let mut server = MockServer::new_http("clock").with_config(config);
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/busy");
+        then.service_unavailable()
+            .retry_after_date(Duration::from_secs(30));
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client.get(server.url("/busy")).send().await?;
+    assert_eq!(response.headers()["date"], "Tue, 14 Nov 2023 22:13:20 GMT");
+    assert_eq!(
+        response.headers()["retry-after"],
+        "Tue, 14 Nov 2023 22:13:50 GMT"
+    );
+
+    clock.advance(Duration::from_secs(60));
+    let response = client.get(server.url("/busy")).send().await?;
+    assert_eq!(response.headers()["date"], "Tue, 14 Nov 2023 22:14:20 GMT");
+
+    let timestamps = server
+        .journal()
+        .iter()
+        .map(|entry| entry.timestamp)
+        .collect::<Vec<_>>();
+    assert_eq!(timestamps, [start, start + Duration::from_secs(60)]);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_mock_expiration() -> Result<(), Error> {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let clock = MockClock::new(start);
+    let config = MockServerConfig::builder().clock(clock.clone()).build()?;
+    let server = MockServer::new_http("expiration").with_config(config);
+    server.start().await?;
+    // Maintenance window for 5 minutes
+    server.mocks().insert(
+        Mock::new(|when, then| {
+            when.get().path("/orders");
+            then.service_unavailable();
+        })
+        .with_priority(1)
+        .expires_after(Duration::from_secs(300)),
+    );
+    server.mocks().insert(
+        Mock::new(|when, then| {
+            when.get().path("/orders");
+            then.text("closed");
+        })
+        .with_priority(2)
+        .expires_at(start + Duration::from_secs(600)),
+    );
+    server.mocks().mock(|when, then| {
+        when.get().path("/orders");
+        then.text("orders");
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client.get(server.url("/orders")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+
+    clock.advance(Duration::from_secs(300));
+    let response = client.get(server.url("/orders")).send().await?;
+    assert_eq!(response.text().await?, "closed");
+
+    clock.advance(Duration::from_secs(300));
+    let response = client.get(server.url("/orders")).send().await?;
+    assert_eq!(response.text().await?, "orders");
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use std::io::Read;
+
+use anyhow::Error;
+use flate2::read::GzDecoder;
+use mocktail::{prelude::*, server::MockServerConfig, Compression, Encoding};
+use mocktail_tests::pb::{hello_client::HelloClient, HelloRequest, HelloResponse};
+use test_log::test;
+use tonic::{codec::CompressionEncoding, transport::Channel};
+
+#[test(tokio::test)]
+async fn test_http_compression() -> Result<(), Error> {
+    let config = MockServerConfig::builder()
+        .compression(Compression::new().encodings([Encoding::Gzip]).min_size(16))
+        .build()?;
+    let mut server = MockServer::new_http("compression").with_config(config);
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/large");
+        then.text("hello ".repeat(10));
+    });
+    server.mock(|when, then| {
+        when.get().path("/small");
+        then.text("hello");
+    });
+    server.mock(|when, then| {
+        when.get().path("/uncompressed");
+        then.text("hello ".repeat(10))
+            .compression(Compression::none());
+    });
+
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(server.url("/large"))
+        .header("accept-encoding", "br, gzip")
+        .send()
+        .await?;
+    assert_eq!(response.headers()["content-encoding"], "gzip");
+    let mut body = String::new();
+    GzDecoder::new(&response.bytes().await?[..]).read_to_string(&mut body)?;
+    assert_eq!(body, "hello ".repeat(10));
+
+    // Clients not accepting an enabled encoding
+    let response = client
+        .get(server.url("/large"))
+        .header("accept-encoding", "deflate")
+        .send()
+        .await?;
+    assert!(response.headers().get("content-encoding").is_none());
+    assert_eq!(response.text().await?, "hello ".repeat(10));
+
+    // Bodies smaller than the minimum size
+    let response = client
+        .get(server.url("/small"))
+        .header("accept-encoding", "gzip")
+        .send()
+        .await?;
+    assert!(response.headers().get("content-encoding").is_none());
+    assert_eq!(response.text().await?, "hello");
+
+    // Mocks overriding the server settings
+    let response = client
+        .get(server.url("/uncompressed"))
+        .header("accept-encoding", "gzip")
+        .send()
+        .await?;
+    assert!(response.headers().get("content-encoding").is_none());
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_grpc_compression() -> Result<(), Error> {
+    let config = MockServerConfig::builder()
+        .compression(Compression::new())
+        .build()?;
+    let mut server = MockServer::new_grpc("example.Hello").with_config(config);
+    server.start().await?;
+    server.mock(|when, then| {
+        when.path("/example.Hello/HelloUnary");
+        then.pb(HelloResponse {
+            message: "hello dan!".into(),
+        });
+    });
+
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel).accept_compressed(CompressionEncoding::Gzip);
+
+    let response = client
+        .hello_unary(HelloRequest { name: "dan".into() })
+        .await?;
+    assert_eq!(response.metadata().get("grpc-encoding").unwrap(), "gzip");
+    assert_eq!(response.into_inner().message, "hello dan!");
+
+    Ok(())
+}
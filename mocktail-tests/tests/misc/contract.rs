@@ -0,0 +1,94 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Error;
+use mocktail::{
+    contract::{Contract, Verifier},
+    prelude::*,
+};
+use serde_json::json;
+use test_log::test;
+
+const WEB_USERS: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/contracts/web-users.json"
+);
+
+#[test(tokio::test)]
+async fn test_verify_pact() -> Result<(), Error> {
+    let contract = Contract::from_pact_file(WEB_USERS)?;
+    assert_eq!(contract.interactions().len(), 3);
+
+    // The provider, which does not support deleting users
+    let mut provider = MockServer::new_http("provider");
+    provider.start().await?;
+    provider.mock(|when, then| {
+        when.get()
+            .path("/users/1")
+            .header("accept", "application/json");
+        then.json(json!({"id": 1, "name": "dan", "admin": false}));
+    });
+    provider.mock(|when, then| {
+        when.post().path("/users").json(json!({"name": "sam"}));
+        then.created().json(json!({"id": 2}));
+    });
+
+    let states = Arc::new(Mutex::new(Vec::new()));
+    let report = Verifier::to_url(provider.url("/"))
+        .provider_states({
+            let states = states.clone();
+            move |state| {
+                states.lock().unwrap().push(state);
+                async {}
+            }
+        })
+        .verify(&contract)
+        .await;
+    assert_eq!(*states.lock().unwrap(), ["user 1 exists", "user 1 exists"]);
+
+    // Objects may have keys not in the expected body
+    assert!(report.results()[0].is_match());
+    assert!(report.results()[1].is_match());
+    let failures = report.failures().collect::<Vec<_>>();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].expected_status, 204);
+    assert_eq!(failures[0].actual_status, 404);
+    assert!(!report.is_ok());
+    assert!(report
+        .to_string()
+        .starts_with("verified 3 interactions, 1 failed\n2: a request to delete a user"));
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_verify_snapshot() -> Result<(), Error> {
+    // Record the contract with the consumer's mocks
+    let mut server = MockServer::new_http("consumer");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/users/1");
+        then.json(json!({"id": 1, "name": "dan"}));
+    });
+    let client = reqwest::Client::new();
+    client.get(server.url("/users/1")).send().await?;
+    client.get(server.url("/users/2")).send().await?;
+    let contract = Contract::from_snapshot(&server.snapshot())?;
+    // Unmatched requests have no expected response
+    assert_eq!(contract.interactions().len(), 1);
+
+    // The provider, where user 1 was renamed
+    let mut provider = MockServer::new_http("provider");
+    provider.start().await?;
+    provider.mock(|when, then| {
+        when.get().path("/users/1");
+        then.json(json!({"id": 1, "name": "paulo"}));
+    });
+
+    let report = Verifier::to_url(provider.url("/")).verify(&contract).await;
+    let failures = report.failures().collect::<Vec<_>>();
+    assert_eq!(failures.len(), 1);
+    assert!(!failures[0].body_matches);
+    assert_eq!(failures[0].actual_body, json!({"id": 1, "name": "paulo"}));
+
+    Ok(())
+}
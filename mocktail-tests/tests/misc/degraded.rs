@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use anyhow::Error;
+use mocktail::{
+    prelude::*,
+    server::{DegradedMode, DEGRADED_MODE_LABEL},
+};
+use mocktail_tests::pb::{hello_client::HelloClient, HelloRequest, HelloResponse};
+use test_log::test;
+use tonic::transport::Channel;
+
+#[test(tokio::test)]
+async fn test_degraded_http() -> Result<(), Error> {
+    let mut server = MockServer::new_http("degraded");
+    server.start().await?;
+    server.stub_get_text("/hello", "hello!");
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    server.set_degraded(DegradedMode::unavailable(
+        "maintenance",
+        Duration::from_secs(30),
+    ));
+    assert_eq!(server.degraded().as_deref(), Some("maintenance"));
+    for path in ["/hello", "/other"] {
+        let response = client.get(server.url(path)).send().await?;
+        assert_eq!(response.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()["retry-after"], "30");
+    }
+
+    server.clear_degraded();
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.text().await?, "hello!");
+
+    let journal = server.journal();
+    let labels = journal
+        .iter()
+        .map(|entry| entry.labels().get(DEGRADED_MODE_LABEL).cloned())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        labels,
+        [Some("maintenance".into()), Some("maintenance".into()), None]
+    );
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_degraded_grpc() -> Result<(), Error> {
+    let mut server = MockServer::new_grpc("example.Hello");
+    server.start().await?;
+    server.stub_grpc_unary(
+        "example.Hello/HelloUnary",
+        HelloRequest { name: "dan".into() },
+        HelloResponse {
+            message: "hello dan!".into(),
+        },
+    );
+
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+
+    server.set_degraded(DegradedMode::unavailable("outage", Duration::from_secs(1)));
+    let status = client
+        .hello_unary(HelloRequest { name: "dan".into() })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unavailable);
+
+    server.clear_degraded();
+    let response = client
+        .hello_unary(HelloRequest { name: "dan".into() })
+        .await?;
+    assert_eq!(response.into_inner().message, "hello dan!");
+
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Error;
+use mocktail::prelude::*;
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_delay_with_jitter() -> Result<(), Error> {
+    let mut server = MockServer::new_http("delay");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/slow");
+        then.delay_with_jitter(Duration::from_millis(100), Jitter::Percent(50.0))
+            .text("done");
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    for _ in 0..3 {
+        let start = Instant::now();
+        let response = client.get(server.url("/slow")).send().await?;
+        let elapsed = start.elapsed();
+        assert_eq!(response.text().await?, "done");
+        assert!(elapsed >= Duration::from_millis(100), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(1000), "{elapsed:?}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use std::convert::Infallible;
+
+use anyhow::Error;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use mocktail::prelude::*;
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_delegate_to() -> Result<(), Error> {
+    let mut upstream = MockServer::new_http("upstream");
+    upstream.start().await?;
+    upstream.mock(|when, then| {
+        when.post().path("/api/users").query_param("page", "2");
+        then.status(StatusCode::CREATED)
+            .headers([("x-upstream", "true")])
+            .text("created upstream");
+    });
+
+    let mut server = MockServer::new_http("delegate");
+    server.start().await?;
+    let id = server.mock(|when, then| {
+        when.post().path("/users");
+        then.delegate_to(upstream.url("/api/").as_str());
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client
+        .post(server.url("/users?page=2"))
+        .body("dan")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::CREATED);
+    assert_eq!(response.headers()["x-upstream"], "true");
+    assert_eq!(response.text().await?, "created upstream");
+
+    // Recorded by both servers
+    assert_eq!(server.attempts(id).count(), 1);
+    assert_eq!(upstream.journal().len(), 1);
+    assert_eq!(
+        upstream.journal().iter().next().unwrap().request.body(),
+        &Body::bytes("dan")
+    );
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_delegate_to_service() -> Result<(), Error> {
+    let mut server = MockServer::new_http("delegate-service");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post().path("/reverse");
+        then.delegate_to_service(tower::service_fn(
+            |req: http::Request<Full<Bytes>>| async move {
+                let body = req.into_body().collect().await?.to_bytes();
+                let reversed = body.iter().rev().copied().collect::<Vec<_>>();
+                Ok::<_, Infallible>(http::Response::new(Full::new(Bytes::from(reversed))))
+            },
+        ));
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client
+        .post(server.url("/reverse"))
+        .body("hello")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(response.text().await?, "olleh");
+    assert_eq!(server.journal().len(), 1);
+
+    Ok(())
+}
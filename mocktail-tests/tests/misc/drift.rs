@@ -0,0 +1,159 @@
+use anyhow::Error;
+use mocktail::{
+    drift::{Descriptors, Drift, DriftKind},
+    openapi::OpenApi,
+    prelude::*,
+};
+use mocktail_tests::pb::{HelloRequest, HelloResponse};
+use serde_json::json;
+use test_log::test;
+
+const USERS: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/openapi/users.json"
+);
+
+const DESCRIPTORS: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/example.bin"));
+
+/// A newer version of `HelloRequest`, with a field the descriptors don't define.
+#[derive(Clone, PartialEq, prost::Message)]
+struct HelloRequestV2 {
+    #[prost(string, tag = "1")]
+    name: String,
+    #[prost(string, tag = "2")]
+    locale: String,
+}
+
+#[test]
+fn test_openapi_drift() -> Result<(), Error> {
+    let api = OpenApi::from_file(USERS)?;
+    // Mocks built from the document don't drift
+    assert!(api.to_mocks()?.check_drift(&api).is_empty());
+
+    let mut mocks = MockSet::new();
+    mocks.mock(|when, then| {
+        when.get().path_prefix("/users");
+        then.ok();
+    });
+    let user = mocks.mock(|when, then| {
+        when.get().path("/users/7");
+        then.json(json!({"id": 7, "name": "dan", "nickname": "d"}));
+    });
+    let users = mocks.mock(|when, then| {
+        when.get().path("/users");
+        then.json(json!([{"id": 7, "nick": "d"}]));
+    });
+    let create = mocks.mock(|when, then| {
+        when.post()
+            .path("/users")
+            .json(json!({"name": "dan", "email": "dan@example.com"}));
+        then.created().json(json!({"id": 7}));
+    });
+    let delete = mocks.mock(|when, then| {
+        when.delete().path_template("/users/{user_id}");
+        then.no_content();
+    });
+    let accounts = mocks.mock(|when, then| {
+        when.get().path("/accounts");
+        then.ok();
+    });
+
+    let drift = mocks.check_drift(&api);
+    assert_eq!(
+        drift,
+        vec![
+            Drift {
+                mock_id: user,
+                kind: DriftKind::UnknownField("response.nickname".into()),
+            },
+            Drift {
+                mock_id: users,
+                kind: DriftKind::UnknownField("response[].nick".into()),
+            },
+            Drift {
+                mock_id: create,
+                kind: DriftKind::UnknownField("request.email".into()),
+            },
+            Drift {
+                mock_id: delete,
+                kind: DriftKind::UnknownMethod(Method::DELETE, "/users/{user_id}".into()),
+            },
+            Drift {
+                mock_id: accounts,
+                kind: DriftKind::UnknownPath("/accounts".into()),
+            },
+        ]
+    );
+    assert_eq!(
+        drift[4].to_string(),
+        format!("mock {accounts} matches path `/accounts` not defined by the spec")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_descriptor_drift() -> Result<(), Error> {
+    let descriptors = Descriptors::from_bytes(DESCRIPTORS)?;
+    assert_eq!(
+        descriptors.services().collect::<Vec<_>>(),
+        ["example.Hello"]
+    );
+
+    let mut mocks = MockSet::new();
+    mocks.mock(|when, then| {
+        when.path("/example.Hello/HelloUnary")
+            .pb(HelloRequest { name: "dan".into() });
+        then.pb(HelloResponse {
+            message: "hello dan".into(),
+        });
+    });
+    let renamed = mocks.mock(|when, then| {
+        when.path("/example.Hello/HelloRenamed");
+        then.ok();
+    });
+    let service = mocks.mock(|when, then| {
+        when.path("/example.Goodbye/GoodbyeUnary");
+        then.ok();
+    });
+    // The service is reported once for the service matcher and path
+    let both = mocks.mock(|when, then| {
+        when.grpc_service("example.Goodbye")
+            .path("/example.Goodbye/GoodbyeUnary");
+        then.ok();
+    });
+    let field = mocks.mock(|when, then| {
+        when.path("/example.Hello/HelloUnary").pb(HelloRequestV2 {
+            name: "dan".into(),
+            locale: "en".into(),
+        });
+        then.ok();
+    });
+
+    let drift = mocks.check_drift(&descriptors);
+    assert_eq!(
+        drift,
+        vec![
+            Drift {
+                mock_id: renamed,
+                kind: DriftKind::UnknownPath("/example.Hello/HelloRenamed".into()),
+            },
+            Drift {
+                mock_id: service,
+                kind: DriftKind::UnknownService("example.Goodbye".into()),
+            },
+            Drift {
+                mock_id: both,
+                kind: DriftKind::UnknownService("example.Goodbye".into()),
+            },
+            Drift {
+                mock_id: field,
+                kind: DriftKind::UnknownField("request.2".into()),
+            },
+        ]
+    );
+
+    assert!(Descriptors::from_bytes(b"\xff").is_err());
+
+    Ok(())
+}
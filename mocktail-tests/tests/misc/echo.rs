@@ -0,0 +1,51 @@
+use anyhow::Error;
+use mocktail::prelude::*;
+use serde_json::json;
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_echo() -> Result<(), Error> {
+    let mut server = MockServer::new_http("echo");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.path("/echo");
+        then.echo();
+    });
+    server.mock(|when, then| {
+        when.path("/echo/body");
+        then.echo_with(Echo::new().headers(false).query(false));
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client
+        .post(server.url("/echo?page=2"))
+        .header("x-custom", "hello")
+        .json(&json!({"name": "dan"}))
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/json"
+    );
+    let body = response.json::<serde_json::Value>().await?;
+    assert_eq!(body["method"], "POST");
+    assert_eq!(body["path"], "/echo");
+    assert_eq!(body["query"], json!({"page": "2"}));
+    assert_eq!(body["headers"]["x-custom"], "hello");
+    assert_eq!(body["body"], json!({"name": "dan"}));
+
+    let response = client
+        .put(server.url("/echo/body"))
+        .body("plain text")
+        .send()
+        .await?;
+    let body = response.json::<serde_json::Value>().await?;
+    assert_eq!(
+        body,
+        json!({"method": "PUT", "path": "/echo/body", "body": "plain text"})
+    );
+
+    Ok(())
+}
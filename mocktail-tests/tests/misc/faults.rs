@@ -0,0 +1,205 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Error;
+use mocktail::{
+    prelude::*,
+    server::{MockServerConfig, ReadStall},
+    Reason,
+};
+use mocktail_tests::pb::{hello_client::HelloClient, HelloRequest, HelloResponse};
+use test_log::test;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+use tonic::transport::Channel;
+
+#[test(tokio::test)]
+async fn test_goaway_after() -> Result<(), Error> {
+    let config = MockServerConfig::builder().goaway_after(2).build()?;
+    let mut server = MockServer::new_http("goaway").with_config(config);
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/hello");
+        then.text("hello!");
+    });
+
+    // The connection is closed after the second request
+    let mut stream = TcpStream::connect(("127.0.0.1", server.port().unwrap())).await?;
+    for _ in 0..2 {
+        stream
+            .write_all(b"GET /hello HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .await?;
+        let mut buf = [0; 1024];
+        let n = stream.read(&mut buf).await?;
+        let response = String::from_utf8_lossy(&buf[..n]);
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+    }
+    let _ = stream
+        .write_all(b"GET /hello HTTP/1.1\r\nhost: localhost\r\n\r\n")
+        .await;
+    let mut response = Vec::new();
+    let _ = stream.read_to_end(&mut response).await;
+    assert!(response.is_empty());
+
+    // HTTP/2 clients reconnect after GOAWAY
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+    for _ in 0..5 {
+        let response = client.get(server.url("/hello")).send().await?;
+        assert_eq!(response.text().await?, "hello!");
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_reset_stream() -> Result<(), Error> {
+    let mut server = MockServer::new_http("reset");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/partial");
+        then.text("hello world")
+            .reset_stream_after(5, Reason::CANCEL);
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    // The reset may be received before or after the response headers
+    let result = async {
+        client
+            .get(server.url("/partial"))
+            .send()
+            .await?
+            .bytes()
+            .await
+    }
+    .await;
+    assert!(result.is_err());
+    assert_eq!(server.journal().len(), 1);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_reset_stream_grpc() -> Result<(), Error> {
+    let mut server = MockServer::new_grpc("example.Hello");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.path("/example.Hello/HelloUnary");
+        then.pb(HelloResponse {
+            message: "hello dan!".into(),
+        })
+        .reset_stream(Reason::REFUSED_STREAM);
+    });
+
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+
+    let status = client
+        .hello_unary(HelloRequest { name: "dan".into() })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unavailable);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_connection_behaviors() -> Result<(), Error> {
+    let mut server = MockServer::new_http("connections");
+    server.start().await?;
+    // The first connection is always reset, subsequent connections succeed
+    server.mock_with_options(1, None, |when, then| {
+        when.get().path("/hello").connection(1);
+        then.reset_stream(Reason::INTERNAL_ERROR);
+    });
+    server.mock(|when, then| {
+        when.get().path("/hello");
+        then.text("hello!");
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+    for _ in 0..2 {
+        let result = async { client.get(server.url("/hello")).send().await?.text().await }.await;
+        assert!(result.is_err());
+    }
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.text().await?, "hello!");
+    assert_eq!(server.connection_count(), 2);
+    let connections = server
+        .journal()
+        .iter()
+        .map(|entry| entry.request.connection())
+        .collect::<Vec<_>>();
+    assert_eq!(connections, [Some(1), Some(1), Some(2)]);
+
+    // Connections are numbered from 1 again after a reset
+    server.reset();
+    server.mock(|when, then| {
+        when.get().path("/hello").connection(1);
+        then.text("first");
+    });
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.text().await?, "first");
+    assert_eq!(server.connection_count(), 1);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_read_stall() -> Result<(), Error> {
+    let payload = vec![7u8; 20_000];
+
+    // Throttled
+    let config = MockServerConfig::builder()
+        .read_stall(ReadStall::Throttle {
+            bytes_per_second: 40_000,
+        })
+        .build()?;
+    let mut server = MockServer::new_http("read_stall").with_config(config);
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post().path("/upload").bytes(payload.clone());
+        then.text("stored");
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+    let start = Instant::now();
+    let response = client
+        .post(server.url("/upload"))
+        .body(payload.clone())
+        .send()
+        .await?;
+    assert_eq!(response.text().await?, "stored");
+    assert!(start.elapsed() >= Duration::from_millis(400));
+
+    // Stopped
+    let config = MockServerConfig::builder()
+        .read_stall(ReadStall::Stop { after: 0 })
+        .build()?;
+    let mut server = MockServer::new_http("read_stall").with_config(config);
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post().path("/upload");
+        then.text("stored");
+    });
+
+    let client = reqwest::Client::builder()
+        .http2_prior_knowledge()
+        .timeout(Duration::from_millis(300))
+        .build()?;
+    let result = client
+        .post(server.url("/upload"))
+        .body(vec![7u8; 1 << 20])
+        .send()
+        .await;
+    assert!(result.unwrap_err().is_timeout());
+    assert!(server.journal().is_empty());
+
+    Ok(())
+}
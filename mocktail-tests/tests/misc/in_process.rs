@@ -0,0 +1,60 @@
+use anyhow::Error;
+use http_body_util::BodyExt;
+use mocktail::prelude::*;
+use mocktail_tests::pb::{hello_client::HelloClient, HelloRequest, HelloResponse};
+use test_log::test;
+use tonic::transport::Endpoint;
+use tower::ServiceExt;
+
+#[test(tokio::test)]
+async fn test_http_service() -> Result<(), Error> {
+    let mut server = MockServer::new_http("in_process");
+    server.mock(|when, then| {
+        when.post().path("/hello").text("dan");
+        then.text("hello dan!");
+    });
+
+    let request = http::Request::post("/hello").body("dan".to_string())?;
+    let response = server.http_service().oneshot(request).await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    let body = response
+        .into_body()
+        .collect()
+        .await
+        .map_err(Error::msg)?
+        .to_bytes();
+    assert_eq!(body, "hello dan!");
+
+    let request = http::Request::get("/missing").body(String::new())?;
+    let response = server.http_service().oneshot(request).await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    assert!(!server.is_running());
+    assert_eq!(server.journal().len(), 2);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_grpc_connector() -> Result<(), Error> {
+    let mut server = MockServer::new_grpc("example.Hello");
+    server.mock(|when, then| {
+        when.path("/example.Hello/HelloUnary")
+            .pb(HelloRequest { name: "dan".into() });
+        then.pb(HelloResponse {
+            message: "hello dan!".into(),
+        });
+    });
+
+    let channel = Endpoint::from_static("http://in-process")
+        .connect_with_connector(server.connector())
+        .await?;
+    let mut client = HelloClient::new(channel);
+
+    let response = client
+        .hello_unary(HelloRequest { name: "dan".into() })
+        .await?;
+    assert_eq!(response.into_inner().message, "hello dan!");
+
+    Ok(())
+}
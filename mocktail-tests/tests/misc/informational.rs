@@ -0,0 +1,116 @@
+use anyhow::Error;
+use mocktail::prelude::*;
+use test_log::test;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+#[test(tokio::test)]
+async fn test_early_hints() -> Result<(), Error> {
+    let mut server = MockServer::new_http("early-hints");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/page");
+        then.early_hints([
+            "</style.css>; rel=preload; as=style",
+            "</app.js>; rel=preload",
+        ])
+        .text("page");
+    });
+    server.mock(|when, then| {
+        when.get().path("/processing");
+        then.informational(StatusCode::PROCESSING, [("x-progress", "50")])
+            .text("done");
+    });
+
+    let mut stream = TcpStream::connect(("127.0.0.1", server.port().unwrap())).await?;
+    stream
+        .write_all(b"GET /page HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+        .await?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    let response = String::from_utf8_lossy(&response);
+    assert!(
+        response.starts_with(
+            "HTTP/1.1 103 Early Hints\r\n\
+             link: </style.css>; rel=preload; as=style\r\n\
+             link: </app.js>; rel=preload\r\n\r\n\
+             HTTP/1.1 200 OK\r\n"
+        ),
+        "{response}"
+    );
+    assert!(response.ends_with("\r\n\r\npage"), "{response}");
+
+    let mut stream = TcpStream::connect(("127.0.0.1", server.port().unwrap())).await?;
+    stream
+        .write_all(b"GET /processing HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+        .await?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    let response = String::from_utf8_lossy(&response);
+    assert!(
+        response.starts_with("HTTP/1.1 102 Processing\r\nx-progress: 50\r\n\r\nHTTP/1.1 200 OK"),
+        "{response}"
+    );
+
+    // HTTP/1 clients skip informational responses
+    let client = reqwest::Client::builder().http1_only().build()?;
+    let response = client.get(server.url("/page")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(response.text().await?, "page");
+
+    // Informational responses are not sent over HTTP/2
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+    let response = client.get(server.url("/page")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(response.text().await?, "page");
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_reason_phrase() -> Result<(), Error> {
+    let mut server = MockServer::new_http("reason-phrase");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/weird");
+        then.status(StatusCode::NOT_FOUND)
+            .reason("Gone Fishing")
+            .text("nope");
+    });
+
+    let mut stream = TcpStream::connect(("127.0.0.1", server.port().unwrap())).await?;
+    stream
+        .write_all(b"GET /weird HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+        .await?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    let response = String::from_utf8_lossy(&response);
+    assert!(
+        response.starts_with("HTTP/1.1 404 Gone Fishing\r\n"),
+        "{response}"
+    );
+
+    // An invalid reason phrase set on the response is replaced by the canonical reason
+    let mut mock = Mock::new(|when, then| {
+        when.get().path("/invalid");
+        then.status(StatusCode::NOT_FOUND);
+    });
+    mock.response.reason = Some("Gone\nFishing".into());
+    server.mocks().insert(mock);
+
+    let mut stream = TcpStream::connect(("127.0.0.1", server.port().unwrap())).await?;
+    stream
+        .write_all(b"GET /invalid HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+        .await?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    let response = String::from_utf8_lossy(&response);
+    assert!(
+        response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+        "{response}"
+    );
+
+    Ok(())
+}
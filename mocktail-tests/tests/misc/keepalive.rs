@@ -0,0 +1,85 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Error;
+use mocktail::{
+    prelude::*,
+    server::{Http2Settings, MockServerConfig},
+};
+use mocktail_tests::pb::{hello_client::HelloClient, HelloRequest, HelloResponse};
+use test_log::test;
+use tonic::transport::Endpoint;
+
+async fn hello(ignore_pings: bool) -> Result<Result<(), tonic::Status>, Error> {
+    let config = MockServerConfig::builder()
+        .http2(Http2Settings::new().ignore_pings(ignore_pings))
+        .build()?;
+    let mut server = MockServer::new_grpc("example.Hello").with_config(config);
+    server.start().await?;
+    server.mock(|when, then| {
+        when.path("/example.Hello/HelloUnary");
+        then.pb(HelloResponse {
+            message: "hello dan!".into(),
+        })
+        .delay(Duration::from_secs(2));
+    });
+
+    let channel = Endpoint::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .http2_keep_alive_interval(Duration::from_millis(100))
+        .keep_alive_timeout(Duration::from_millis(200))
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+
+    let result = client
+        .hello_unary(HelloRequest { name: "dan".into() })
+        .await
+        .map(|_| ());
+    Ok(result)
+}
+
+#[test(tokio::test)]
+async fn test_keepalive_pings_answered() -> Result<(), Error> {
+    assert!(hello(false).await?.is_ok());
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_keepalive_pings_ignored() -> Result<(), Error> {
+    let start = Instant::now();
+    let result = hello(true).await?;
+    assert!(result.is_err(), "{result:?}");
+    assert!(start.elapsed() < Duration::from_secs(2));
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_server_keepalive() -> Result<(), Error> {
+    let config = MockServerConfig::builder()
+        .http2(
+            Http2Settings::new()
+                .keep_alive_interval(Duration::from_millis(100))
+                .keep_alive_timeout(Duration::from_secs(1)),
+        )
+        .build()?;
+    let mut server = MockServer::new_grpc("example.Hello").with_config(config);
+    server.start().await?;
+    server.stub_grpc_unary(
+        "example.Hello/HelloUnary",
+        HelloRequest { name: "dan".into() },
+        HelloResponse {
+            message: "hello dan!".into(),
+        },
+    );
+
+    let channel = Endpoint::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let response = client
+        .hello_unary(HelloRequest { name: "dan".into() })
+        .await?;
+    assert_eq!(response.into_inner().message, "hello dan!");
+
+    Ok(())
+}
@@ -0,0 +1,255 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::Error;
+use futures::stream;
+use mocktail::{
+    prelude::*,
+    server::{Http2Settings, MockServerConfig},
+};
+use mocktail_tests::pb::{hello_client::HelloClient, HelloRequest};
+use test_log::test;
+use tonic::transport::Channel;
+
+#[test(tokio::test)]
+async fn test_max_body_size() -> Result<(), Error> {
+    let config = MockServerConfig::builder().max_body_size(16).build()?;
+    let server = MockServer::new_http("limits").with_config(config);
+    server.start().await?;
+    server.mocks().mock(|when, then| {
+        when.post().path("/upload");
+        then.ok();
+    });
+    server.mocks().mock(|when, then| {
+        when.post().path("/stream").text("done");
+        then.ok();
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client
+        .post(server.url("/upload"))
+        .body("small")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    let response = client
+        .post(server.url("/upload"))
+        .body("this body is larger than the limit")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::PAYLOAD_TOO_LARGE);
+
+    // Chunked upload, exceeding the limit before a mock is matched
+    let chunks = stream::iter(["0123456789", "0123456789"].map(Ok::<_, std::io::Error>));
+    let response = client
+        .post(server.url("/stream"))
+        .body(reqwest::Body::wrap_stream(chunks))
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::PAYLOAD_TOO_LARGE);
+
+    // Chunked upload within the limit, not matched
+    let chunks = stream::iter(["01234", "56789"].map(Ok::<_, std::io::Error>));
+    let response = client
+        .post(server.url("/stream"))
+        .body(reqwest::Body::wrap_stream(chunks))
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    // Chunked upload, matched from its first chunk within the limit
+    let chunks = stream::iter(["01234", "56789"].map(Ok::<_, std::io::Error>));
+    let response = client
+        .post(server.url("/upload"))
+        .body(reqwest::Body::wrap_stream(chunks))
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(response.text().await?, "");
+    assert_eq!(server.journal().unmatched().count(), 3);
+
+    // Chunked upload, exceeding the limit once the response was sent
+    let chunks = stream::iter(["0123456789", "0123456789"].map(Ok::<_, std::io::Error>));
+    let response = client
+        .post(server.url("/upload"))
+        .body(reqwest::Body::wrap_stream(chunks))
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    // Recorded once, with its match, when the chunk exceeding the limit is received
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let journal = server.journal();
+    assert_eq!(journal.unmatched().count(), 3);
+    let entry = journal.iter().last().unwrap();
+    assert!(entry.is_matched());
+    assert_eq!(entry.request.body(), &Body::bytes("0123456789"));
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_max_body_size_strict() -> Result<(), Error> {
+    let config = MockServerConfig::builder()
+        .max_body_size(16)
+        .strict(true)
+        .build()?;
+    let mut server = MockServer::new_http("limits").with_config(config);
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post().path("/upload");
+        then.ok();
+    });
+    let unmatched = Arc::new(AtomicUsize::new(0));
+    server.on_unmatched({
+        let unmatched = unmatched.clone();
+        move |_| {
+            unmatched.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client
+        .post(server.url("/upload"))
+        .body("this body is larger than the limit")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::PAYLOAD_TOO_LARGE);
+    let err = server.verify().unwrap_err();
+    assert_eq!(err.failures(), ["unexpected request: POST /upload"]);
+    assert_eq!(unmatched.load(Ordering::SeqCst), 1);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_max_header_size() -> Result<(), Error> {
+    let config = MockServerConfig::builder().max_header_size(8192).build()?;
+    let server = MockServer::new_http("limits").with_config(config);
+    server.start().await?;
+    server.mocks().mock(|when, then| {
+        when.get().path("/hello");
+        then.ok();
+    });
+
+    let client = reqwest::Client::new();
+
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    let response = client
+        .get(server.url("/hello"))
+        .header("x-large", "x".repeat(16384))
+        .send()
+        .await?;
+    assert_eq!(
+        response.status(),
+        http::StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+    );
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_grpc_max_body_size() -> Result<(), Error> {
+    let config = MockServerConfig::builder().max_body_size(16).build()?;
+    let server = MockServer::new_grpc("example.Hello").with_config(config);
+    server.start().await?;
+
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+
+    let result = client
+        .hello_unary(HelloRequest {
+            name: "this name is larger than the limit".into(),
+        })
+        .await;
+    assert!(result.is_err_and(|e| e.code() == tonic::Code::ResourceExhausted));
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_http2_settings() -> Result<(), Error> {
+    let config = MockServerConfig::builder()
+        .http2(
+            Http2Settings::new()
+                .max_concurrent_streams(1)
+                .initial_stream_window_size(1024)
+                .initial_connection_window_size(1024)
+                .max_frame_size(16_384),
+        )
+        .build()?;
+    let server = MockServer::new_http("http2-settings").with_config(config);
+    server.start().await?;
+    server.mocks().mock(|when, then| {
+        when.post().path("/upload");
+        then.text("x".repeat(64 * 1024));
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    // Bodies larger than the windows are sent within the stream limit
+    let responses = futures::future::try_join_all((0..4).map(|_| {
+        client
+            .post(server.url("/upload"))
+            .body(vec![0u8; 32 * 1024])
+            .send()
+    }))
+    .await?;
+    for response in responses {
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(response.bytes().await?.len(), 64 * 1024);
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_mock_limit_phases() -> Result<(), Error> {
+    let mut server = MockServer::new_http("limits");
+    server.start().await?;
+    let id = server.mock_with_options(1, Some(3), |when, then| {
+        when.get().path("/status");
+        then.text("pending");
+    });
+    server.mock(|when, then| {
+        when.get().path("/status");
+        then.text("done");
+    });
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    server.on_limit_reached(id, move |mock| {
+        let _ = tx.send(mock.match_count());
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    assert_eq!(server.remaining_matches(&id), Some(3));
+    for _ in 0..3 {
+        let response = client.get(server.url("/status")).send().await?;
+        assert_eq!(response.text().await?, "pending");
+    }
+    assert_eq!(server.remaining_matches(&id), Some(0));
+    assert_eq!(rx.recv().await, Some(3));
+
+    let response = client.get(server.url("/status")).send().await?;
+    assert_eq!(response.text().await?, "done");
+
+    // Resetting the match count matches the limited mock again
+    server.reset_match_count(&id);
+    assert_eq!(server.remaining_matches(&id), Some(3));
+    let response = client.get(server.url("/status")).send().await?;
+    assert_eq!(response.text().await?, "pending");
+    assert!(rx.try_recv().is_err());
+
+    Ok(())
+}
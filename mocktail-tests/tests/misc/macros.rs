@@ -0,0 +1,37 @@
+use anyhow::Error;
+use mocktail::{mock, prelude::*};
+use serde_json::{json, Value};
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_mock_macro() -> Result<(), Error> {
+    let mocks = mock! {
+        GET "/users/1" => 200 json(json!({"id": 1, "name": "dan"}));
+        POST "/users" => 201 json(json!({"id": 2}));
+        GET "/health" => 200 text("ok");
+        DELETE "/users/1" => 204;
+    };
+    let server = MockServer::new_http("macros").with_mocks(mocks);
+    server.start().await?;
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client.get(server.url("/users/1")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>().await?,
+        json!({"id": 1, "name": "dan"})
+    );
+
+    let response = client.post(server.url("/users")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::CREATED);
+    assert_eq!(response.json::<Value>().await?, json!({"id": 2}));
+
+    let response = client.get(server.url("/health")).send().await?;
+    assert_eq!(response.text().await?, "ok");
+
+    let response = client.delete(server.url("/users/1")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NO_CONTENT);
+
+    Ok(())
+}
@@ -0,0 +1,260 @@
+use anyhow::Error;
+use mocktail::{
+    mock_file::{Loader, MockFileFormat},
+    prelude::*,
+};
+use serde_json::{json, Value};
+use test_log::test;
+
+const TENANTS: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/mocks/tenants.json"
+);
+
+#[test(tokio::test)]
+async fn test_mock_file_variables() -> Result<(), Error> {
+    std::env::set_var("MOCKTAIL_TENANTS_API_KEY", "secret");
+    let mocks = Loader::new()
+        .var("tenant", "acme")
+        .var("region", "eu")
+        .load_file(TENANTS)?;
+    let server = MockServer::new_http("mock_file").with_mocks(mocks);
+    server.start().await?;
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client
+        .get(server.url("/tenants/acme"))
+        .header("x-api-key", "secret")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>().await?,
+        json!({"name": "acme", "region": "eu"})
+    );
+
+    let response = client
+        .get(server.url("/tenants/other"))
+        .header("x-api-key", "secret")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    assert_eq!(response.text().await?, "unknown tenant");
+
+    Ok(())
+}
+
+const SERVICES: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/mocks/services.json"
+);
+
+#[test(tokio::test)]
+async fn test_mock_file_include() -> Result<(), Error> {
+    std::env::set_var("MOCKTAIL_TENANTS_API_KEY", "secret");
+    // Variables are resolved in included files
+    let mocks = Loader::new()
+        .var("tenant", "acme")
+        .var("region", "eu")
+        .load_file(SERVICES)?;
+    assert_eq!(mocks.len(), 4);
+    let server = MockServer::new_http("mock_file").with_mocks(mocks);
+    server.start().await?;
+
+    let client = reqwest::Client::new();
+
+    let response = client.get(server.url("/health")).send().await?;
+    assert_eq!(response.text().await?, "ok");
+
+    // Included mocks inherit the defaults of the including file
+    let response = client.post(server.url("/oauth/token")).send().await?;
+    assert_eq!(response.headers()["x-service"], "tenants");
+    assert_eq!(
+        response.json::<Value>().await?,
+        json!({"access_token": "token"})
+    );
+
+    let response = client
+        .get(server.url("/tenants/acme"))
+        .header("x-api-key", "secret")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    Ok(())
+}
+
+const SERVICES_YAML: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/mocks/services.yaml"
+);
+
+#[test(tokio::test)]
+async fn test_mock_file_include_yaml() -> Result<(), Error> {
+    std::env::set_var("MOCKTAIL_TENANTS_API_KEY", "secret");
+    // Included files are loaded by extension
+    let mocks = Loader::new()
+        .var("tenant", "acme")
+        .var("region", "eu")
+        .load_file(SERVICES_YAML)?;
+    assert_eq!(mocks.len(), 5);
+    let server = MockServer::new_http("mock_file").with_mocks(mocks);
+    server.start().await?;
+
+    let client = reqwest::Client::new();
+
+    let response = client.get(server.url("/health")).send().await?;
+    assert_eq!(response.headers()["x-service"], "tenants");
+    assert_eq!(response.text().await?, "ok");
+
+    let response = client.get(server.url("/status/eu")).send().await?;
+    assert_eq!(response.headers()["x-service"], "tenants");
+    assert_eq!(
+        response.json::<Value>().await?,
+        json!({"region": "eu", "up": true})
+    );
+
+    let response = client.post(server.url("/oauth/token")).send().await?;
+    assert_eq!(
+        response.json::<Value>().await?,
+        json!({"access_token": "token"})
+    );
+
+    let response = client
+        .get(server.url("/tenants/acme"))
+        .header("x-api-key", "secret")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    let err = Loader::new()
+        .load_yaml("include: [missing.yaml]")
+        .unwrap_err();
+    assert!(err.to_string().contains("failed to include missing.yaml"));
+
+    Ok(())
+}
+
+#[test]
+fn test_mock_file_include_errors() {
+    let cycle = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/mocks/cycle.json"
+    );
+    let err = Loader::new().load_file(cycle).unwrap_err();
+    assert!(err.to_string().contains("include cycle"));
+
+    let err = Loader::new()
+        .load_json(r#"{ "include": ["missing.json"] }"#)
+        .unwrap_err();
+    assert!(err.to_string().contains("failed to include missing.json"));
+}
+
+#[test]
+fn test_mock_file_undefined_variable() {
+    let result = Loader::new().var("tenant", "acme").load_file(TENANTS);
+    assert!(result.is_err());
+}
+
+#[test(tokio::test)]
+async fn test_mock_file_export() -> Result<(), Error> {
+    let mut mocks = MockSet::new();
+    mocks.mock(|when, then| {
+        when.post()
+            .path("/orders")
+            .header("x-api-key", "secret")
+            .json(json!({"sku": "A-1"}));
+        then.created().json(json!({"id": 1}));
+    });
+    mocks.insert(
+        Mock::new(|when, then| {
+            when.path_prefix("/orders");
+            then.not_found().text("no such order");
+        })
+        .with_priority(10),
+    );
+    let path = std::env::temp_dir().join(format!("mocktail-export-{}.json", std::process::id()));
+    mocks.to_file(&path, MockFileFormat::Json)?;
+
+    let server = MockServer::new_http("mock_file_export").with_mocks(MockSet::from_file(&path)?);
+    std::fs::remove_file(&path)?;
+    server.start().await?;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .post(server.url("/orders"))
+        .header("x-api-key", "secret")
+        .json(&json!({"sku": "A-1"}))
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::CREATED);
+    assert_eq!(response.json::<Value>().await?, json!({"id": 1}));
+
+    let response = client.get(server.url("/orders/2")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    assert_eq!(response.text().await?, "no such order");
+
+    // Exported as YAML by extension
+    let path = std::env::temp_dir().join(format!("mocktail-export-{}.yaml", std::process::id()));
+    mocks.to_file(&path, MockFileFormat::from_path(&path))?;
+    let loaded = MockSet::from_file(&path)?;
+    std::fs::remove_file(&path)?;
+    assert_eq!(loaded.to_mock_file()?, mocks.to_mock_file()?);
+
+    // Matchers without a definition can't be exported
+    let mut mocks = MockSet::new();
+    mocks.mock(|when, then| {
+        when.path_template("/orders/{id}");
+        then.ok();
+    });
+    assert!(mocks.to_mock_file().is_err());
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_mock_file_defaults() -> Result<(), Error> {
+    let mocks = Loader::new().load_json(
+        r#"{
+            "defaults": {
+                "headers": { "x-api-version": "2" },
+                "content_type": "text/plain",
+                "priority": 2
+            },
+            "mocks": [
+                {
+                    "request": { "method": "GET", "path": "/status" },
+                    "response": { "body": { "text": "ok" } }
+                },
+                {
+                    "request": { "method": "GET", "path": "/orders" },
+                    "response": {
+                        "headers": { "x-api-version": "3" },
+                        "body": { "json": [] }
+                    },
+                    "priority": 1
+                }
+            ]
+        }"#,
+    )?;
+    assert_eq!(
+        mocks.iter().map(|mock| mock.priority()).collect::<Vec<_>>(),
+        [1, 2]
+    );
+    let server = MockServer::new_http("mock_file").with_mocks(mocks);
+    server.start().await?;
+
+    let client = reqwest::Client::new();
+
+    let response = client.get(server.url("/status")).send().await?;
+    assert_eq!(response.headers()["x-api-version"], "2");
+    assert_eq!(response.headers()["content-type"], "text/plain");
+
+    // Overridden by the mock
+    let response = client.get(server.url("/orders")).send().await?;
+    assert_eq!(response.headers()["x-api-version"], "3");
+    assert_eq!(response.headers()["content-type"], "application/json");
+
+    Ok(())
+}
@@ -1 +1,44 @@
+mod auth;
+mod cache;
+mod cancellation;
+mod chunking;
+mod clock;
+mod compression;
+mod contract;
+mod degraded;
+mod delay;
+mod delegate;
+mod drift;
+mod echo;
+mod faults;
+mod in_process;
+mod informational;
+mod keepalive;
+mod limits;
+mod macros;
+mod mock_file;
+mod multipart;
+mod namespace;
+mod openapi;
+mod panic;
+mod presets;
+mod replay;
+mod resource;
+mod retry;
+mod runtime;
+mod script;
+mod sequence;
+mod server;
+mod session;
+mod snapshot;
+mod soap;
+mod state;
+mod stubs;
+mod template;
+mod timeout;
+mod tls;
+mod transfer;
 mod validation;
+mod variants;
+mod verify;
+mod webhook;
@@ -0,0 +1,55 @@
+use anyhow::Error;
+use mocktail::{multipart::Part, prelude::*};
+use serde_json::json;
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_multipart() -> Result<(), Error> {
+    let mut server = MockServer::new_http("multipart");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post().path("/batch");
+        then.multipart(
+            Multipart::mixed()
+                .with_boundary("batch_1")
+                .part(Part::json(json!({"id": 1})))
+                .part(Part::text("done").with_header("x-op", "2")),
+        );
+    });
+    server.mock(|when, then| {
+        when.get().path("/file");
+        then.byteranges("0123456789", "text/plain", [0..2, 8..10]);
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client.post(server.url("/batch")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "multipart/mixed; boundary=batch_1"
+    );
+    assert_eq!(
+        response.text().await?,
+        "--batch_1\r\ncontent-type: application/json\r\n\r\n{\"id\":1}\r\n\
+         --batch_1\r\ncontent-type: text/plain; charset=utf-8\r\nx-op: 2\r\n\r\ndone\r\n\
+         --batch_1--\r\n"
+    );
+
+    let response = client
+        .get(server.url("/file"))
+        .header("range", "bytes=0-1,8-9")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::PARTIAL_CONTENT);
+    let content_type = response.headers()["content-type"].to_str()?.to_string();
+    let boundary = content_type
+        .strip_prefix("multipart/byteranges; boundary=")
+        .unwrap();
+    let body = response.text().await?;
+    assert!(body.contains("content-range: bytes 0-1/10\r\n\r\n01\r\n"));
+    assert!(body.contains("content-range: bytes 8-9/10\r\n\r\n89\r\n"));
+    assert!(body.ends_with(&format!("--{boundary}--\r\n")));
+
+    Ok(())
+}
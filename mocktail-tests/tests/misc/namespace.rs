@@ -0,0 +1,94 @@
+use anyhow::Error;
+use mocktail::{prelude::*, server::MockServerConfig, server::NamespaceMode};
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_namespace_header() -> Result<(), Error> {
+    let server = MockServer::new_http("shared");
+    server.start().await?;
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let ns1 = server.namespace();
+    ns1.mock(|when, then| {
+        when.get().path("/hello");
+        then.text("hello from ns1");
+    });
+    let ns2 = server.namespace();
+    ns2.mock(|when, then| {
+        when.get().path("/hello");
+        then.text("hello from ns2");
+    });
+
+    let (name, value) = ns1.header().unwrap();
+    let response = client
+        .get(ns1.url("/hello"))
+        .header(name, value)
+        .send()
+        .await?;
+    assert_eq!(response.text().await?, "hello from ns1");
+
+    let (name, value) = ns2.header().unwrap();
+    let response = client
+        .get(ns2.url("/hello"))
+        .header(name, value)
+        .send()
+        .await?;
+    assert_eq!(response.text().await?, "hello from ns2");
+
+    // Requests without a namespace don't match namespaced mocks
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    assert_eq!(ns1.journal().len(), 1);
+    assert_eq!(ns2.journal().len(), 1);
+    assert_eq!(server.journal().len(), 3);
+
+    // Dropping a namespace removes its mocks and journal entries
+    drop(ns1);
+    assert_eq!(server.mocks().len(), 1);
+    assert_eq!(server.journal().len(), 2);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_namespace_path_prefix() -> Result<(), Error> {
+    let config = MockServerConfig::builder()
+        .namespace_mode(NamespaceMode::PathPrefix)
+        .build()?;
+    let server = MockServer::new_http("shared").with_config(config);
+    server.start().await?;
+
+    // Shared mock, matched by all namespaces
+    server.mocks().mock(|when, then| {
+        when.get().path("/health");
+        then.text("ok");
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let ns = server.namespace();
+    ns.mock(|when, then| {
+        when.get().path("/hello");
+        then.text("hello!");
+    });
+    assert!(ns.header().is_none());
+
+    let response = client.get(ns.url("/hello")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(response.text().await?, "hello!");
+
+    let response = client.get(ns.url("/health")).send().await?;
+    assert_eq!(response.text().await?, "ok");
+
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    let journal = ns.journal();
+    assert_eq!(journal.len(), 2);
+    assert!(journal.iter().all(|entry| entry.is_matched()));
+    assert_eq!(journal.iter().next().unwrap().request.path(), "/hello");
+
+    Ok(())
+}
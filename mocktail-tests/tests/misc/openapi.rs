@@ -0,0 +1,107 @@
+use anyhow::Error;
+use mocktail::{
+    openapi::{ExampleSelection, OpenApi},
+    prelude::*,
+};
+use serde_json::{json, Value};
+use test_log::test;
+
+const USERS: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/openapi/users.json"
+);
+
+#[test(tokio::test)]
+async fn test_openapi_first_success() -> Result<(), Error> {
+    let mocks = MockSet::from_openapi(USERS)?;
+    assert_eq!(mocks.len(), 3);
+    let server = MockServer::new_http("openapi").with_mocks(mocks);
+    server.start().await?;
+
+    let client = reqwest::Client::new();
+
+    let response = client.get(server.url("/users")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>().await?,
+        json!([{"id": 1, "name": "dan"}])
+    );
+
+    // Named examples are ordered by name
+    let response = client.get(server.url("/users/7")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(response.json::<Value>().await?["name"], "admin");
+
+    let response = client.post(server.url("/users")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::CREATED);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_openapi_selection() -> Result<(), Error> {
+    let mocks = OpenApi::from_file(USERS)?
+        .selection(ExampleSelection::Status(422))
+        .select("getUser", ExampleSelection::Named("dan".into()))
+        .to_mocks()?;
+    let server = MockServer::new_http("openapi").with_mocks(mocks);
+    server.start().await?;
+
+    let client = reqwest::Client::new();
+
+    let response = client.post(server.url("/users")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/problem+json"
+    );
+
+    let response = client.get(server.url("/users/1")).send().await?;
+    assert_eq!(
+        response.json::<Value>().await?,
+        json!({"id": 1, "name": "dan"})
+    );
+
+    // Operations without a 422 response serve the first success
+    let response = client.get(server.url("/users")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_openapi_header_selection() -> Result<(), Error> {
+    let mocks = OpenApi::from_file(USERS)?
+        .selection(ExampleSelection::Header("x-example".into()))
+        .to_mocks()?;
+    let server = MockServer::new_http("openapi").with_mocks(mocks);
+    server.start().await?;
+
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(server.url("/users/1"))
+        .header("x-example", "404")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    assert_eq!(response.headers()["content-type"], "text/plain");
+    assert_eq!(response.text().await?, "user not found");
+
+    let response = client
+        .get(server.url("/users/1"))
+        .header("x-example", "dan")
+        .send()
+        .await?;
+    assert_eq!(response.json::<Value>().await?["name"], "dan");
+
+    let response = client.get(server.url("/users/1")).send().await?;
+    assert_eq!(response.json::<Value>().await?["name"], "admin");
+
+    Ok(())
+}
+
+#[test]
+fn test_openapi_invalid() {
+    assert!(OpenApi::from_json(r#"{"openapi": "3.0.3"}"#).is_err());
+}
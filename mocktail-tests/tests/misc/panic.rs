@@ -0,0 +1,176 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Error;
+use bytes::Bytes;
+use mocktail::{
+    prelude::*,
+    server::{MockServerConfig, ProtocolErrors},
+    Delegate, IdGenerator,
+};
+use mocktail_tests::pb::{hello_client::HelloClient, HelloRequest, HelloResponse};
+use test_log::test;
+use tonic::transport::Channel;
+
+/// A matcher panicking on requests with a `panic` header.
+#[derive(Debug, PartialEq, PartialOrd)]
+struct PanickingMatcher;
+
+impl Matcher for PanickingMatcher {
+    fn name(&self) -> &str {
+        "panicking"
+    }
+
+    fn matches(&self, req: &Request) -> bool {
+        if req.headers().get("panic").is_some() {
+            panic!("matcher failed");
+        }
+        true
+    }
+}
+
+async fn matcher_panic(config: MockServerConfig) -> Result<(), Error> {
+    let mut server = MockServer::new_http("panic").with_config(config);
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/hello").matcher(PanickingMatcher);
+        then.text("hello");
+    });
+
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(server.url("/hello"))
+        .header("panic", "true")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(response.text().await?, "matcher failed");
+
+    // The connection and the server keep serving requests
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(response.text().await?, "hello");
+
+    let err = server.verify().unwrap_err();
+    assert_eq!(err.failures(), ["panic: GET /hello: matcher failed"]);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_matcher_panic() -> Result<(), Error> {
+    matcher_panic(MockServerConfig::default()).await
+}
+
+#[test(tokio::test)]
+async fn test_matcher_panic_with_timeout() -> Result<(), Error> {
+    let config = MockServerConfig::builder()
+        .handler_timeout(Duration::from_secs(5))
+        .build()?;
+    matcher_panic(config).await
+}
+
+#[test(tokio::test)]
+async fn test_responder_panic() -> Result<(), Error> {
+    let config = MockServerConfig::builder()
+        .protocol_errors(ProtocolErrors::new().panic_status(StatusCode::SERVICE_UNAVAILABLE))
+        .build()?;
+    let mut server = MockServer::new_http("panic").with_config(config);
+    server.start().await?;
+    let id = server.mock(|when, then| {
+        when.post().path("/hello");
+        then.delegate(Delegate::from_fn(|_| async {
+            if true {
+                panic!("responder failed");
+            }
+            Ok(http::Response::new(Bytes::new()))
+        }));
+    });
+
+    let response = reqwest::Client::new()
+        .post(server.url("/hello"))
+        .body("dan")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.text().await?, "responder failed");
+
+    // Matched before the responder panicked
+    assert_eq!(server.attempts(id).count(), 1);
+    let err = server.verify().unwrap_err();
+    assert_eq!(err.failures(), ["panic: POST /hello: responder failed"]);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_render_panic() -> Result<(), Error> {
+    let mut server = MockServer::new_http("panic");
+    server.start().await?;
+    let resource = Resource::new("/things")
+        .with_id_generator(IdGenerator::Custom(Arc::new(|| panic!("render failed"))));
+    server.resource_with(resource);
+    server.mock(|when, then| {
+        when.get().path("/hello");
+        then.text("hello");
+    });
+
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(server.url("/things"))
+        .json(&serde_json::json!({"name": "dan"}))
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(response.text().await?, "render failed");
+
+    // The server keeps serving requests
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(response.text().await?, "hello");
+    let response = client.get(server.url("/things")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    let err = server.verify().unwrap_err();
+    assert_eq!(err.failures(), ["panic: POST /things: render failed"]);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_grpc_matcher_panic() -> Result<(), Error> {
+    let mut server = MockServer::new_grpc("example.Hello");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.path("/example.Hello/HelloUnary")
+            .matcher(PanickingMatcher);
+        then.pb(HelloResponse {
+            message: "hello dan!".into(),
+        });
+    });
+
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+
+    let mut request = tonic::Request::new(HelloRequest { name: "dan".into() });
+    request.metadata_mut().insert("panic", "true".parse()?);
+    let status = client.hello_unary(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Internal);
+    assert_eq!(status.message(), "matcher failed");
+
+    let response = client
+        .hello_unary(HelloRequest { name: "dan".into() })
+        .await?;
+    assert_eq!(response.into_inner().message, "hello dan!");
+
+    let err = server.verify().unwrap_err();
+    assert_eq!(
+        err.failures(),
+        ["panic: POST /example.Hello/HelloUnary: matcher failed"]
+    );
+
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use anyhow::Error;
+use mocktail::prelude::*;
+use mocktail_tests::pb::{hello_client::HelloClient, HelloRequest};
+use prost::Message;
+use serde_json::{json, Value};
+use test_log::test;
+use tonic::transport::Channel;
+
+#[test(tokio::test)]
+async fn test_status_presets() -> Result<(), Error> {
+    let mut server = MockServer::new_http("presets");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/private");
+        then.unauthorized_json(json!({"error": "invalid token"}));
+    });
+    server.mock(|when, then| {
+        when.get().path("/limited");
+        then.too_many_requests(Duration::from_secs(30));
+    });
+    server.mock(|when, then| {
+        when.delete().path("/users/1");
+        then.no_content();
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client.get(server.url("/private")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers()["content-type"], "application/json");
+    assert_eq!(
+        response.json::<Value>().await?,
+        json!({"error": "invalid token"})
+    );
+
+    let response = client.get(server.url("/limited")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()["retry-after"], "30");
+
+    let response = client.delete(server.url("/users/1")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NO_CONTENT);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_grpc_status_presets() -> Result<(), Error> {
+    let mut server = MockServer::new_grpc("example.Hello");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.path("/example.Hello/HelloUnary")
+            .pb(HelloRequest { name: "dan".into() });
+        then.grpc_permission_denied("not allowed");
+    });
+
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+
+    let status = client
+        .hello_unary(HelloRequest { name: "dan".into() })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    assert_eq!(status.message(), "not allowed");
+
+    Ok(())
+}
+
+/// A `google.rpc.Status` message.
+#[derive(Clone, PartialEq, prost::Message)]
+struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    code: i32,
+    #[prost(string, tag = "2")]
+    message: String,
+    #[prost(message, repeated, tag = "3")]
+    details: Vec<prost_types::Any>,
+}
+
+#[test(tokio::test)]
+async fn test_grpc_error_with_details() -> Result<(), Error> {
+    let mut server = MockServer::new_grpc("example.Hello");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.path("/example.Hello/HelloUnary");
+        then.pb_error_with_details(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "try again later",
+            [ErrorDetail::new(prost_types::Duration {
+                seconds: 5,
+                nanos: 0,
+            })],
+        );
+    });
+
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+
+    let status = client
+        .hello_unary(HelloRequest { name: "dan".into() })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unavailable);
+    assert_eq!(status.message(), "try again later");
+    let details = RpcStatus::decode(status.details())?;
+    assert_eq!(details.code, tonic::Code::Unavailable as i32);
+    assert_eq!(details.message, "try again later");
+    assert_eq!(details.details.len(), 1);
+    assert_eq!(
+        details.details[0].type_url,
+        "type.googleapis.com/google.protobuf.Duration"
+    );
+    let retry_delay = prost_types::Duration::decode(&details.details[0].value[..])?;
+    assert_eq!(retry_delay.seconds, 5);
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Error;
+use mocktail::{
+    prelude::*,
+    replay::{Pacing, Replay},
+};
+use serde_json::json;
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_replay() -> Result<(), Error> {
+    let mut server = MockServer::new_http("replay");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/users/1");
+        then.json(json!({"id": 1, "name": "dan"}));
+    });
+    server.mock(|when, then| {
+        when.get().path("/users/2");
+        then.json(json!({"id": 2, "name": "mateus"}));
+    });
+
+    // The real server, where user 2 was renamed
+    let mut upstream = MockServer::new_http("upstream");
+    upstream.start().await?;
+    upstream.mock(|when, then| {
+        when.get().path("/users/1");
+        then.text(r#"{"name":"dan","id":1}"#);
+    });
+    upstream.mock(|when, then| {
+        when.get().path("/users/2");
+        then.json(json!({"id": 2, "name": "paulo"}));
+    });
+
+    let client = reqwest::Client::new();
+    for path in ["/users/1", "/users/2", "/users/3"] {
+        client.get(server.url(path)).send().await?;
+    }
+
+    let replay =
+        Replay::to_url(upstream.url("/")).pacing(Pacing::Interval(Duration::from_millis(100)));
+    let start = Instant::now();
+    let report = server.replay(&replay).await;
+    assert!(start.elapsed() >= Duration::from_millis(100));
+
+    // Unmatched requests are not replayed
+    assert_eq!(report.results().len(), 2);
+    assert_eq!(upstream.journal().len(), 2);
+    assert!(!report.is_ok());
+    let diffs = report.diffs().collect::<Vec<_>>();
+    assert_eq!(diffs.len(), 1);
+    assert_eq!(diffs[0].path, "/users/2");
+    assert_eq!(diffs[0].actual_status, 200);
+    assert!(!diffs[0].body_matches);
+    assert!(report
+        .to_string()
+        .starts_with("replayed 2 requests, 1 differ"));
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use anyhow::Error;
+use mocktail::prelude::*;
+use serde_json::{json, Value};
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_resource() -> Result<(), Error> {
+    let server = MockServer::new_http("resource");
+    server.start().await?;
+    let things = server.resource("/things");
+    things.insert(json!({"name": "seeded"}));
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client
+        .post(server.url("/things"))
+        .json(&json!({"name": "created"}))
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::CREATED);
+    assert_eq!(response.headers().get("location").unwrap(), "/things/2");
+    let created = response.json::<Value>().await?;
+    assert_eq!(created, json!({"id": "2", "name": "created"}));
+
+    let response = client
+        .put(server.url("/things/2"))
+        .json(&json!({"name": "updated"}))
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    let response = client.get(server.url("/things/2")).send().await?;
+    assert_eq!(
+        response.json::<Value>().await?,
+        json!({"id": "2", "name": "updated"})
+    );
+
+    let response = client.delete(server.url("/things/1")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NO_CONTENT);
+
+    let response = client.get(server.url("/things")).send().await?;
+    assert_eq!(
+        response.json::<Value>().await?,
+        json!([{"id": "2", "name": "updated"}])
+    );
+
+    let response = client.get(server.url("/things/1")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    assert_eq!(things.len(), 1);
+
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use anyhow::Error;
+use mocktail::{prelude::*, server::MockServerConfig};
+use mocktail_tests::pb::{hello_client::HelloClient, HelloRequest, HelloResponse};
+use test_log::test;
+use tonic::transport::Channel;
+
+#[test(tokio::test)]
+async fn test_fail_times_http() -> Result<(), Error> {
+    let mut server = MockServer::new_http("fail_times");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/flaky");
+        then.text("ok")
+            .fail_times(2, StatusCode::SERVICE_UNAVAILABLE);
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    for _ in 0..2 {
+        let response = client.get(server.url("/flaky")).send().await?;
+        assert_eq!(response.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+    }
+    let response = client.get(server.url("/flaky")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(response.text().await?, "ok");
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_fail_times_grpc() -> Result<(), Error> {
+    let mut server = MockServer::new_grpc("example.Hello");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.path("/example.Hello/HelloUnary");
+        then.pb(HelloResponse {
+            message: "hello dan!".into(),
+        })
+        .fail_times(1, StatusCode::SERVICE_UNAVAILABLE);
+    });
+
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+
+    let err = client
+        .hello_unary(HelloRequest { name: "dan".into() })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), tonic::Code::Unavailable);
+
+    let response = client
+        .hello_unary(HelloRequest { name: "dan".into() })
+        .await?;
+    assert_eq!(response.into_inner().message, "hello dan!");
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_assert_retried() -> Result<(), Error> {
+    let mut server = MockServer::new_http("assert_retried");
+    server.start().await?;
+    let flaky = server.mock(|when, then| {
+        when.get().path("/flaky");
+        then.ok().fail_times(2, StatusCode::SERVICE_UNAVAILABLE);
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    // Retry with backoff until success
+    let mut backoff = Duration::from_millis(20);
+    while !client
+        .get(server.url("/flaky"))
+        .send()
+        .await?
+        .status()
+        .is_success()
+    {
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+    }
+
+    let attempts = server.assert_retried(flaky, 2, 2, Duration::from_secs(5))?;
+    let intervals = attempts.intervals();
+    assert!(intervals[0] >= Duration::from_millis(20));
+    assert!(intervals[1] >= Duration::from_millis(40));
+
+    assert!(server
+        .assert_retried(flaky, 3, 5, Duration::from_secs(5))
+        .is_err());
+    assert!(server
+        .assert_retried(flaky, 2, 2, Duration::from_millis(10))
+        .is_err());
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_idempotency_key() -> Result<(), Error> {
+    let config = MockServerConfig::builder().idempotency().build()?;
+    let mut server = MockServer::new_http("idempotency").with_config(config);
+    server.start().await?;
+    let create = server.mock(|when, then| {
+        when.post().path("/orders");
+        then.status(StatusCode::CREATED)
+            .text("created")
+            .fail_times(1, StatusCode::SERVICE_UNAVAILABLE);
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    // The first response is replayed for the same key
+    for _ in 0..2 {
+        let response = client
+            .post(server.url("/orders"))
+            .header("Idempotency-Key", "a")
+            .send()
+            .await?;
+        assert_eq!(response.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    // A new key is matched as usual
+    for _ in 0..2 {
+        let response = client
+            .post(server.url("/orders"))
+            .header("Idempotency-Key", "b")
+            .send()
+            .await?;
+        assert_eq!(response.status(), http::StatusCode::CREATED);
+        assert_eq!(response.text().await?, "created");
+    }
+
+    assert_eq!(server.attempts(create).count(), 4);
+    assert_eq!(server.mocks().iter().next().unwrap().match_count(), 2);
+
+    Ok(())
+}
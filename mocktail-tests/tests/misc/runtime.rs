@@ -0,0 +1,55 @@
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+};
+
+use anyhow::Error;
+use futures::executor::block_on;
+use mocktail::prelude::*;
+
+#[test]
+fn test_start_without_tokio_runtime() -> Result<(), Error> {
+    let mut server = MockServer::new_http("runtime");
+    block_on(server.start())?;
+    server.mock(|when, then| {
+        when.get().path("/hello");
+        then.text("hello!");
+    });
+
+    let mut stream = TcpStream::connect(("127.0.0.1", server.port().unwrap()))?;
+    stream.write_all(b"GET /hello HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+    assert!(response.ends_with("hello!"), "{response}");
+
+    block_on(server.stop());
+    assert!(!server.is_running());
+
+    Ok(())
+}
+
+#[test]
+fn test_blocking() -> Result<(), Error> {
+    let mut server = MockServer::new_http("blocking");
+    server.start_blocking()?;
+    let hello = server.mock(|when, then| {
+        when.get().path("/hello");
+        then.text("hello!");
+    });
+    server.expect_sequence(Sequence::new([hello]));
+
+    let mut stream = TcpStream::connect(("127.0.0.1", server.port().unwrap()))?;
+    stream.write_all(b"GET /hello HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+
+    assert_eq!(server.journal().len(), 1);
+    server.verify()?;
+
+    server.stop_blocking();
+    assert!(!server.is_running());
+
+    Ok(())
+}
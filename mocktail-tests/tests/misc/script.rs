@@ -0,0 +1,57 @@
+use anyhow::Error;
+use mocktail::prelude::*;
+use serde_json::json;
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_script() -> Result<(), Error> {
+    let mut server = MockServer::new_http("script");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post().path("/users");
+        then.script(
+            r#"
+            if request.json == () || request.json.name == "" {
+                #{ status: 400, body: "name required" }
+            } else {
+                #{ status: 201, headers: #{ "x-user": request.json.name }, json: #{ id: 1, name: request.json.name } }
+            }
+            "#,
+        );
+    });
+    server.mock(|when, then| {
+        when.get().path("/greet");
+        then.script(r#"`hello ${request.query.name}`"#);
+    });
+    server.mock(|when, then| {
+        when.get().path("/broken");
+        then.script(r#"throw "oops""#);
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client
+        .post(server.url("/users"))
+        .json(&json!({"name": "dan"}))
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::CREATED);
+    assert_eq!(response.headers().get("x-user").unwrap(), "dan");
+    assert_eq!(
+        response.json::<serde_json::Value>().await?,
+        json!({"id": 1, "name": "dan"})
+    );
+
+    let response = client.post(server.url("/users")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    assert_eq!(response.text().await?, "name required");
+
+    let response = client.get(server.url("/greet?name=sam")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(response.text().await?, "hello sam");
+
+    let response = client.get(server.url("/broken")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use anyhow::Error;
+use mocktail::prelude::*;
+use test_log::test;
+
+async fn texts(server: &MockServer, path: &str, n: usize) -> Result<Vec<String>, Error> {
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+    let mut texts = Vec::with_capacity(n);
+    for _ in 0..n {
+        let response = client.get(server.url(path)).send().await?;
+        texts.push(format!(
+            "{} {}",
+            response.status().as_u16(),
+            response.text().await?
+        ));
+    }
+    Ok(texts)
+}
+
+#[test(tokio::test)]
+async fn test_sequence_exhaustion() -> Result<(), Error> {
+    let mut server = MockServer::new_http("sequence");
+    server.start().await?;
+    for (path, exhausted) in [
+        ("/repeat", Exhausted::RepeatLast),
+        ("/cycle", Exhausted::Cycle),
+        ("/fall", Exhausted::FallThrough),
+        ("/error", Exhausted::Error),
+    ] {
+        server.mock(|when, then| {
+            when.get().path(path);
+            then.sequence(|then| {
+                then.text("pending");
+            })
+            .sequence(|then| {
+                then.text("done");
+            })
+            .on_exhausted(exhausted);
+        });
+    }
+    server.mock_with_options(10, None, |when, then| {
+        when.get().path("/fall");
+        then.status(StatusCode::GONE).text("fallback");
+    });
+
+    assert_eq!(
+        texts(&server, "/repeat", 3).await?,
+        ["200 pending", "200 done", "200 done"]
+    );
+    assert_eq!(
+        texts(&server, "/cycle", 3).await?,
+        ["200 pending", "200 done", "200 pending"]
+    );
+    assert_eq!(
+        texts(&server, "/fall", 3).await?,
+        ["200 pending", "200 done", "410 fallback"]
+    );
+    assert_eq!(
+        texts(&server, "/error", 3).await?,
+        ["200 pending", "200 done", "500 sequence exhausted"]
+    );
+
+    Ok(())
+}
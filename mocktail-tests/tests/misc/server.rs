@@ -0,0 +1,569 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Error;
+use mocktail::{
+    prelude::*,
+    server::{Listener, MockServerConfig, MockServerPool, PortStrategy, UnusedMocks},
+    TlsConfig,
+};
+use mocktail_tests::slow_body;
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_reset() -> Result<(), Error> {
+    let server = MockServer::new_http("reset");
+    server.start().await?;
+    server.mocks().mock(|when, then| {
+        when.get().path("/hello");
+        then.text("hello!");
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(server.journal().len(), 1);
+
+    server.reset();
+    assert!(server.mocks().is_empty());
+    assert!(server.journal().is_empty());
+
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_reset_fresh_server() -> Result<(), Error> {
+    let config = MockServerConfig::builder().seed(42).build()?;
+    let mut server = MockServer::new_http("reset_fresh").with_config(config);
+    server.start().await?;
+    let flaky = |server: &mut MockServer| {
+        server.mock(|when, then| {
+            when.get().path("/flaky");
+            then.variant(50, |then| {
+                then.text("ok");
+            })
+            .variant(50, |then| {
+                then.service_unavailable();
+            });
+        });
+    };
+    flaky(&mut server);
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+    let statuses = |server: &MockServer| {
+        let client = client.clone();
+        let url = server.url("/flaky");
+        async move {
+            let mut statuses = Vec::new();
+            for _ in 0..20 {
+                let response = client.get(url.clone()).send().await?;
+                statuses.push(response.status().as_u16());
+            }
+            Ok::<_, Error>(statuses)
+        }
+    };
+
+    let first = statuses(&server).await?;
+    let mut mirror = server.mirror();
+    server.pause();
+
+    server.reset();
+    assert!(!server.is_paused());
+    // The mirror hook is removed, closing its channel
+    assert!(mirror.recv().await.is_none());
+
+    // The rng is re-seeded, so the same choices are made
+    flaky(&mut server);
+    assert_eq!(statuses(&server).await?, first);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_pause_resume() -> Result<(), Error> {
+    let server = MockServer::new_http("pause");
+    server.start().await?;
+    server.mocks().mock(|when, then| {
+        when.get().path("/hello");
+        then.text("hello!");
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    server.pause();
+    assert!(server.is_paused());
+    let result = client.get(server.url("/hello")).send().await;
+    assert!(result.is_err());
+
+    server.resume();
+    assert!(!server.is_paused());
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_start_with_timeout() -> Result<(), Error> {
+    let server = MockServer::new_http("start");
+    server
+        .start_with_timeout(Duration::from_millis(100))
+        .await?;
+    assert!(server.is_running());
+
+    // Already running
+    assert!(server.start().await.is_err());
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_journal_capacity() -> Result<(), Error> {
+    let config = MockServerConfig::builder().journal_capacity(2).build()?;
+    let mut server = MockServer::new_http("journal_capacity").with_config(config);
+    server.start().await?;
+    let hello = server.mock(|when, then| {
+        when.get().path("/hello");
+        then.ok();
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    client.get(server.url("/missing")).send().await?;
+    client.get(server.url("/hello")).send().await?;
+    client.post(server.url("/hello")).send().await?;
+
+    let journal = server.journal();
+    assert_eq!(journal.len(), 2);
+    assert_eq!(journal.count(&JournalQuery::new().mock_id(hello)), 1);
+    assert_eq!(
+        journal.count(&JournalQuery::new().path_regex("^/missing")),
+        0
+    );
+    assert_eq!(
+        journal.count(&JournalQuery::new().method(Method::POST).unmatched()),
+        1
+    );
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_journal_file() -> Result<(), Error> {
+    let path = std::env::temp_dir().join(format!("mocktail-journal-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let config = MockServerConfig::builder().journal_file(&path).build()?;
+    let client = reqwest::Client::new();
+
+    let mut server = MockServer::new_http("journal_file").with_config(config.clone());
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post().path("/orders");
+        then.created();
+    });
+    client
+        .post(server.url("/orders"))
+        .body("first")
+        .send()
+        .await?;
+    server.stop().await;
+    drop(server);
+
+    // Restarted server loads the recorded traffic and appends to it
+    let server = MockServer::new_http("journal_file").with_config(config);
+    server.start().await?;
+    assert_eq!(server.journal().len(), 1);
+    assert_eq!(
+        server.last_request().unwrap().body().clone().as_bytes(),
+        "first"
+    );
+    client.get(server.url("/missing")).send().await?;
+    assert_eq!(Journal::load(&path)?.len(), 2);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_stop_drains_requests() -> Result<(), Error> {
+    let mut server = MockServer::new_http("stop");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post().path("/upload");
+        then.text("done");
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let request = tokio::spawn(
+        client
+            .post(server.url("/upload"))
+            .body(slow_body(Duration::from_millis(100)))
+            .send(),
+    );
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    server.stop().await;
+    assert!(!server.is_running());
+
+    // The in-flight request completed
+    let response = request.await??;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(response.text().await?, "done");
+
+    // New connections are refused
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+    assert!(client.post(server.url("/upload")).send().await.is_err());
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_stop_grace_period() -> Result<(), Error> {
+    let config = MockServerConfig::builder()
+        .shutdown_grace_period(Duration::from_millis(50))
+        .build()?;
+    let mut server = MockServer::new_http("stop_grace_period").with_config(config);
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post().path("/upload");
+        then.text("done");
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let request = tokio::spawn(
+        client
+            .post(server.url("/upload"))
+            .body(slow_body(Duration::from_secs(5)))
+            .send(),
+    );
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let start = Instant::now();
+    server.stop().await;
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    // The in-flight request was aborted
+    assert!(request.await?.is_err());
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_mirror() -> Result<(), Error> {
+    let mut server = MockServer::new_http("mirror");
+    server.start().await?;
+    let id = server.mock(|when, then| {
+        when.get().path("/hello");
+        then.text("hello");
+    });
+    let mut mirror = server.mirror();
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    client.get(server.url("/hello")).send().await?;
+    client.post(server.url("/missing")).send().await?;
+
+    let entry = mirror.recv().await.unwrap();
+    assert_eq!(entry.request.path(), "/hello");
+    assert_eq!(entry.mock_id, Some(id));
+    let entry = mirror.recv().await.unwrap();
+    assert_eq!(entry.request.method(), &Method::POST);
+    assert!(!entry.is_matched());
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_unused_mocks() -> Result<(), Error> {
+    let mut server = MockServer::new_http("unused-mocks").with_config(
+        MockServerConfig::builder()
+            .unused_mocks(UnusedMocks::Warn)
+            .build()?,
+    );
+    server.start().await?;
+    let used = server.mock(|when, then| {
+        when.get().path("/used");
+        then.ok();
+    });
+    let unused = server.mock(|when, then| {
+        when.get().path("/unused");
+        then.ok();
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+    client.get(server.url("/used")).send().await?;
+
+    let mocks = server.unused_mocks();
+    assert_eq!(mocks.len(), 1);
+    assert_eq!(mocks[0].id, unused);
+    assert_ne!(mocks[0].id, used);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+#[should_panic(expected = "1 unused mocks")]
+async fn test_unused_mocks_panic() {
+    let mut server = MockServer::new_http("unused-mocks-panic").with_config(
+        MockServerConfig::builder()
+            .unused_mocks(UnusedMocks::Panic)
+            .build()
+            .unwrap(),
+    );
+    server.mock(|when, then| {
+        when.get().path("/unused");
+        then.ok();
+    });
+}
+
+#[test(tokio::test)]
+async fn test_start_scoped() -> Result<(), Error> {
+    let mut server = MockServer::new_http("scoped").start_scoped().await?;
+    server.mock(|when, then| {
+        when.get().path("/hello");
+        then.ok();
+    });
+    assert!(server.is_running());
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert!(server.diagnostics().is_none());
+
+    let response = client.get(server.url("/missing")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    assert_eq!(
+        server.diagnostics().unwrap(),
+        "scoped: 1 unmatched requests:\n  GET /missing"
+    );
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+#[should_panic(expected = "unexpected request: GET /missing")]
+async fn test_start_scoped_verify() {
+    let config = MockServerConfig::builder().strict(true).build().unwrap();
+    let server = MockServer::new_http("scoped-verify")
+        .with_config(config)
+        .start_scoped()
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::builder()
+        .http2_prior_knowledge()
+        .build()
+        .unwrap();
+    client.get(server.url("/missing")).send().await.unwrap();
+}
+
+#[test(tokio::test)]
+async fn test_pool() -> Result<(), Error> {
+    let pool = MockServerPool::start(2, |_| MockServer::new_http("pool")).await?;
+    assert_eq!(pool.size(), 2);
+
+    let mut a = pool.lease().await;
+    let b = pool.lease().await;
+    assert_ne!(a.port(), b.port());
+    assert_eq!(pool.available(), 0);
+    assert!(pool.try_lease().is_none());
+
+    a.mock(|when, then| {
+        when.get().path("/hello");
+        then.ok();
+    });
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+    let response = client.get(a.url("/hello")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    let port = a.port();
+    drop(a);
+
+    // The returned server is reset for the next lease
+    let mut a = pool.try_lease().unwrap();
+    assert_eq!(a.port(), port);
+    assert!(a.mocks().is_empty());
+    assert!(a.journal().is_empty());
+    let response = client.get(a.url("/hello")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    // Connections are numbered from 1 again for each lease
+    a.mock(|when, then| {
+        when.get().path("/first").connection(1);
+        then.ok();
+    });
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+    let response = client.get(a.url("/first")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    drop((a, b));
+    assert_eq!(pool.available(), 2);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_pool_restores_servers() -> Result<(), Error> {
+    let pool = MockServerPool::start(1, |_| {
+        let config = MockServerConfig::builder().seed(42).build().unwrap();
+        let mut server = MockServer::new_http("pool_restore").with_config(config);
+        server.mock(|when, then| {
+            when.get().path("/flaky");
+            then.variant(50, |then| {
+                then.text("ok");
+            })
+            .variant(50, |then| {
+                then.service_unavailable();
+            });
+        });
+        server
+    })
+    .await?;
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+    let statuses = |server: &MockServer| {
+        let client = client.clone();
+        let url = server.url("/flaky");
+        async move {
+            let mut statuses = Vec::new();
+            for _ in 0..20 {
+                let response = client.get(url.clone()).send().await?;
+                statuses.push(response.status().as_u16());
+            }
+            Ok::<_, Error>(statuses)
+        }
+    };
+
+    let mut server = pool.lease().await;
+    let first = statuses(&server).await?;
+    server.mock(|when, then| {
+        when.get().path("/hello");
+        then.ok();
+    });
+    drop(server);
+
+    // The initial mocks are restored and the rng is re-seeded for the next lease
+    let server = pool.lease().await;
+    assert_eq!(server.mocks().len(), 1);
+    assert!(server.mocks().iter().all(|mock| mock.match_count() == 0));
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    assert_eq!(statuses(&server).await?, first);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_pool_drops_stopped_servers() -> Result<(), Error> {
+    let pool = MockServerPool::start(2, |_| MockServer::new_http("pool_stopped")).await?;
+
+    let server = pool.lease().await;
+    server.stop().await;
+    drop(server);
+    assert_eq!(pool.size(), 1);
+    assert_eq!(pool.available(), 1);
+
+    let server = pool.lease().await;
+    assert!(server.is_running());
+    assert!(pool.try_lease().is_none());
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_port_strategy() -> Result<(), Error> {
+    // Occupy a port, with the next port free
+    let (listener, port) = loop {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        if port < u16::MAX - 1 && std::net::TcpListener::bind(("127.0.0.1", port + 1)).is_ok() {
+            break (listener, port);
+        }
+    };
+
+    let server = MockServer::new_http("port-strategy").with_config(
+        MockServerConfig::builder()
+            .port_range(port, port + 2)
+            .port_strategy(PortStrategy::Sequential)
+            .build()?,
+    );
+    server.start().await?;
+    assert_eq!(server.port(), Some(port + 1));
+
+    let server = MockServer::new_http("port-exhausted").with_config(
+        MockServerConfig::builder()
+            .port_range(port, port + 1)
+            .bind_max_retries(1)
+            .build()?,
+    );
+    let err = server.start().await.unwrap_err().to_string();
+    assert!(
+        err.contains(&format!(
+            "in ports {port}..{} after 2 attempts: {port} (",
+            port + 1
+        )),
+        "{err}"
+    );
+    assert!(err.contains("os error"), "{err}");
+
+    drop(listener);
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_listeners() -> Result<(), Error> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let tls = TlsConfig::generate();
+    let ca = reqwest::Certificate::from_pem(tls.ca_pem().unwrap().as_bytes())?;
+    let path = std::env::temp_dir().join(format!("mocktail-{}.sock", std::process::id()));
+    let config = MockServerConfig::builder()
+        .listener(Listener::tcp().tls(tls))
+        .listener(Listener::unix(&path))
+        .build()?;
+    let mut server = MockServer::new_http("listeners").with_config(config);
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/hello");
+        then.text("hello!");
+    });
+    let [https, unix] = server.listener_addrs() else {
+        panic!("expected 2 listeners");
+    };
+    let https = https.url("/hello").unwrap();
+    assert_eq!(https.scheme(), "https");
+    assert_ne!(https.port(), server.port());
+    assert_eq!(unix.path(), Some(path.as_path()));
+
+    let client = reqwest::Client::builder()
+        .tls_built_in_root_certs(false)
+        .add_root_certificate(ca)
+        .build()?;
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.text().await?, "hello!");
+    let response = client.get(https).send().await?;
+    assert_eq!(response.text().await?, "hello!");
+
+    let mut stream = tokio::net::UnixStream::connect(&path).await?;
+    stream
+        .write_all(b"GET /hello HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+        .await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+    assert!(response.ends_with("hello!"), "{response}");
+
+    // All listeners share the mocks and journal
+    assert_eq!(server.journal().len(), 3);
+    assert_eq!(server.journal().unmatched().count(), 0);
+
+    server.stop().await;
+    assert!(!path.exists());
+
+    Ok(())
+}
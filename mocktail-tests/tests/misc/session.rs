@@ -0,0 +1,93 @@
+use anyhow::Error;
+use mocktail::{
+    prelude::*,
+    server::{MockServerConfig, SessionMode},
+};
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_session_cookie() -> Result<(), Error> {
+    let mut server = MockServer::new_http("session");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post().path("/login");
+        then.no_content().start_session();
+    });
+    server.mock_with_options(1, None, |when, then| {
+        when.get().path("/cart").same_session();
+        then.text("cart");
+    });
+    server.mock(|when, then| {
+        when.get().path("/cart");
+        then.unauthorized();
+    });
+
+    let client = reqwest::Client::new();
+
+    let response = client.get(server.url("/cart")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
+
+    let response = client.post(server.url("/login")).send().await?;
+    let cookie = response.headers()["set-cookie"].to_str()?;
+    assert!(cookie.starts_with("mocktail-session="));
+    let session = cookie.split(';').next().unwrap().to_string();
+
+    let response = client
+        .get(server.url("/cart"))
+        .header("cookie", format!("theme=dark; {session}"))
+        .send()
+        .await?;
+    assert_eq!(response.text().await?, "cart");
+
+    // Session IDs not issued by the server
+    let response = client
+        .get(server.url("/cart"))
+        .header("cookie", "mocktail-session=unknown")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
+
+    let session_id = session.split_once('=').unwrap().1;
+    let journal = server.journal();
+    let sessions = journal
+        .iter()
+        .filter_map(|entry| entry.request().session())
+        .collect::<Vec<_>>();
+    assert_eq!(sessions, [session_id]);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_session_header() -> Result<(), Error> {
+    let config = MockServerConfig::builder()
+        .session_mode(SessionMode::Header("x-session-id".into()))
+        .build()?;
+    let mut server = MockServer::new_http("session").with_config(config);
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post().path("/login");
+        then.ok().start_session();
+    });
+    server.mock(|when, then| {
+        when.get().path("/cart").same_session();
+        then.text("cart");
+    });
+
+    let client = reqwest::Client::new();
+
+    let response = client.post(server.url("/login")).send().await?;
+    let session = response.headers()["x-session-id"].clone();
+
+    let response = client
+        .get(server.url("/cart"))
+        .header("x-session-id", session)
+        .send()
+        .await?;
+    assert_eq!(response.text().await?, "cart");
+
+    let response = client.get(server.url("/cart")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use anyhow::Error;
+use mocktail::prelude::*;
+use serde_json::json;
+use test_log::test;
+
+async fn run_client(server: &MockServer, name: &str) -> Result<(), Error> {
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+    client
+        .post(server.url("/users"))
+        .header("x-request-id", request_id())
+        .json(&json!({"name": name}))
+        .send()
+        .await?;
+    client.get(server.url("/users?page=1")).send().await?;
+    Ok(())
+}
+
+fn request_id() -> String {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos()
+        .to_string()
+}
+
+#[test(tokio::test)]
+async fn test_snapshot() -> Result<(), Error> {
+    let path = std::env::temp_dir().join(format!("mocktail-snapshot-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut server = MockServer::new_http("snapshot");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post().path("/users");
+        then.status(StatusCode::CREATED).json(json!({"id": 1}));
+    });
+    server.mock(|when, then| {
+        when.get().path("/users");
+        then.json(json!([{"id": 1}]));
+    });
+
+    // The first run writes the golden file
+    run_client(&server, "dan").await?;
+    let snapshot = server.snapshot().ignore_header("x-request-id");
+    snapshot.verify_file(&path)?;
+    let golden = std::fs::read_to_string(&path)?;
+    assert!(golden.contains(r#""name": "dan""#));
+    assert_eq!(snapshot.entries().len(), 2);
+    assert_eq!(snapshot.entries()[0].response.as_ref().unwrap().status, 201);
+    assert_eq!(snapshot.entries()[1].query.as_deref(), Some("page=1"));
+
+    // An identical run matches
+    server.clear_journal();
+    run_client(&server, "dan").await?;
+    server
+        .snapshot()
+        .ignore_header("x-request-id")
+        .verify_file(&path)?;
+
+    // A changed client payload fails
+    server.clear_journal();
+    run_client(&server, "sam").await?;
+    let err = server
+        .snapshot()
+        .ignore_header("x-request-id")
+        .verify_file(&path)
+        .unwrap_err();
+    assert_eq!(err.failures().len(), 1);
+    assert!(err.failures()[0].contains(r#""name":"sam""#));
+
+    // Varying headers are included unless ignored
+    server.clear_journal();
+    run_client(&server, "dan").await?;
+    assert!(server.assert_snapshot(&path).is_err());
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
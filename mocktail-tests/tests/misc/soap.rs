@@ -0,0 +1,88 @@
+use anyhow::Error;
+use mocktail::prelude::*;
+use test_log::test;
+
+const GET_USER: &str = r#"<?xml version="1.0"?>
+<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/">
+  <soap:Body>
+    <m:GetUser xmlns:m="urn:users"><m:id>42</m:id></m:GetUser>
+  </soap:Body>
+</soap:Envelope>"#;
+
+#[test(tokio::test)]
+async fn test_soap() -> Result<(), Error> {
+    let mut server = MockServer::new_http("soap");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post()
+            .soap_action("urn:users/GetUser")
+            .xpath_eq("//GetUser/id", "42");
+        then.soap(
+            SoapVersion::V11,
+            r#"<m:GetUserResponse xmlns:m="urn:users"><m:name>dan</m:name></m:GetUserResponse>"#,
+        );
+    });
+    server.mock(|when, then| {
+        when.post().soap_action("urn:users/GetUser");
+        then.soap_fault(SoapVersion::V11, "Client", "user not found");
+    });
+    server.mock(|when, then| {
+        when.post()
+            .soap_action("urn:users/DeleteUser")
+            .xpath("/Envelope/Body/DeleteUser");
+        then.soap_fault(SoapVersion::V12, "Sender", "not allowed");
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client
+        .post(server.url("/users"))
+        .header("content-type", "text/xml")
+        .header("soapaction", "\"urn:users/GetUser\"")
+        .body(GET_USER)
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "text/xml; charset=utf-8"
+    );
+    let body = response.text().await?;
+    assert!(body.contains("<soap:Body><m:GetUserResponse"));
+    assert!(body.contains("<m:name>dan</m:name>"));
+
+    let response = client
+        .post(server.url("/users"))
+        .header("soapaction", "urn:users/GetUser")
+        .body(GET_USER.replace("42", "7"))
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+    let body = response.text().await?;
+    assert!(body.contains("<faultcode>soap:Client</faultcode>"));
+    assert!(body.contains("<faultstring>user not found</faultstring>"));
+
+    // SOAP 1.2 sends the action as a content type parameter
+    let response = client
+        .post(server.url("/users"))
+        .header(
+            "content-type",
+            r#"application/soap+xml; charset=utf-8; action="urn:users/DeleteUser""#,
+        )
+        .body(
+            r#"<env:Envelope xmlns:env="http://www.w3.org/2003/05/soap-envelope"><env:Body><DeleteUser/></env:Body></env:Envelope>"#,
+        )
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/soap+xml; charset=utf-8"
+    );
+    assert!(response
+        .text()
+        .await?
+        .contains("<soap:Value>soap:Sender</soap:Value>"));
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use anyhow::Error;
+use mocktail::prelude::*;
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_after() -> Result<(), Error> {
+    let mut server = MockServer::new_http("after");
+    server.start().await?;
+    let login = server.mock(|when, then| {
+        when.post().path("/login");
+        then.ok();
+    });
+    server.mock(|when, then| {
+        when.get().path("/profile").after(login);
+        then.text("profile");
+    });
+    server.mock_with_options(10, None, |when, then| {
+        when.get().path("/profile");
+        then.unauthorized();
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client.get(server.url("/profile")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
+
+    client.post(server.url("/login")).send().await?;
+
+    let response = client.get(server.url("/profile")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(response.text().await?, "profile");
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_counter() -> Result<(), Error> {
+    let mut server = MockServer::new_http("counter");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post().path("/orders").counter_lt("orders", 3);
+        then.status(StatusCode::CREATED).increment("orders");
+    });
+    server.mock_with_options(10, None, |when, then| {
+        when.post().path("/orders");
+        then.status(StatusCode::TOO_MANY_REQUESTS);
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    for _ in 0..3 {
+        let response = client.post(server.url("/orders")).send().await?;
+        assert_eq!(response.status(), http::StatusCode::CREATED);
+    }
+    let response = client.post(server.url("/orders")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(server.counter("orders"), 3);
+
+    server.set_counter("orders", 0);
+    let response = client.post(server.url("/orders")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::CREATED);
+
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use anyhow::Error;
+use futures::StreamExt;
+use mocktail::prelude::*;
+use mocktail_tests::pb::{hello_client::HelloClient, HelloRequest, HelloResponse};
+use serde_json::{json, Value};
+use test_log::test;
+use tonic::transport::Channel;
+
+#[test(tokio::test)]
+async fn test_stubs() -> Result<(), Error> {
+    let mut server = MockServer::new_http("stubs");
+    server.start().await?;
+    server.stub_get_json("/users/1", json!({"id": 1, "name": "dan"}));
+    server.stub_post_json("/users", json!({"name": "gaurav"}), json!({"id": 2}));
+    server.stub_status(Method::DELETE, "/users/1", StatusCode::NO_CONTENT);
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client.get(server.url("/users/1")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(
+        response.json::<Value>().await?,
+        json!({"id": 1, "name": "dan"})
+    );
+
+    let response = client
+        .post(server.url("/users"))
+        .json(&json!({"name": "gaurav"}))
+        .send()
+        .await?;
+    assert_eq!(response.json::<Value>().await?, json!({"id": 2}));
+
+    let response = client.delete(server.url("/users/1")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NO_CONTENT);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_stub_grpc_unary() -> Result<(), Error> {
+    let mut server = MockServer::new_grpc("example.Hello");
+    server.start().await?;
+    server.stub_grpc_unary(
+        "example.Hello/HelloUnary",
+        HelloRequest { name: "dan".into() },
+        HelloResponse {
+            message: "hello dan!".into(),
+        },
+    );
+
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+
+    let response = client
+        .hello_unary(HelloRequest { name: "dan".into() })
+        .await?;
+    assert_eq!(response.into_inner().message, "hello dan!");
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_grpc_echo_service() -> Result<(), Error> {
+    let mut server = MockServer::new_grpc("example.Hello");
+    server.start().await?;
+    server.grpc_echo_service("example.Hello");
+    server.stub_grpc_unary(
+        "example.Hello/HelloUnary",
+        HelloRequest { name: "dan".into() },
+        HelloResponse {
+            message: "hello dan!".into(),
+        },
+    );
+
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+
+    // Other mocks are matched first
+    let response = client
+        .hello_unary(HelloRequest { name: "dan".into() })
+        .await?;
+    assert_eq!(response.into_inner().message, "hello dan!");
+
+    let response = client
+        .hello_unary(HelloRequest {
+            name: "gaurav".into(),
+        })
+        .await?;
+    assert_eq!(response.into_inner().message, "gaurav");
+
+    let response = client
+        .hello_bidi_streaming(futures::stream::iter([
+            HelloRequest {
+                name: "mateus".into(),
+            },
+            HelloRequest {
+                name: "paulo".into(),
+            },
+        ]))
+        .await?;
+    let messages = response
+        .into_inner()
+        .map(|response| response.unwrap().message)
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(messages, ["mateus", "paulo"]);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_grpc_unimplemented_service() -> Result<(), Error> {
+    let mut server = MockServer::new_grpc("example.Hello");
+    server.start().await?;
+    server.grpc_unimplemented_service("example.Hello");
+
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+
+    let status = client
+        .hello_unary(HelloRequest { name: "dan".into() })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unimplemented);
+    assert_eq!(
+        status.message(),
+        "method not implemented: /example.Hello/HelloUnary"
+    );
+
+    Ok(())
+}
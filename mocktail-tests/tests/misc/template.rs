@@ -0,0 +1,102 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::Error;
+use mocktail::{clock::MockClock, prelude::*, server::MockServerConfig};
+use mocktail_tests::pb::{hello_client::HelloClient, HelloRequest};
+use serde_json::{json, Value};
+use test_log::test;
+use tonic::transport::Channel;
+
+#[test(tokio::test)]
+async fn test_template_helpers() -> Result<(), Error> {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let config = MockServerConfig::builder()
+        .clock(MockClock::new(start))
+        .build()?;
+    let mut server = MockServer::new_http("template").with_config(config);
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post().path("/users");
+        then.template(
+            r#"{"id": "{{ uuid() }}", "name": "{{ jsonPath(request.body, '$.name') }}", "created": "{{ now() }}", "token": "{{ base64(request.headers.x-user) }}", "roll": {{ random_int(1, 6) }}}"#,
+        );
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client
+        .post(server.url("/users"))
+        .header("x-user", "dan")
+        .json(&json!({"name": "Dan"}))
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    let body = response.json::<Value>().await?;
+    assert_eq!(body["id"].as_str().unwrap().len(), 36);
+    assert_eq!(body["name"], "Dan");
+    assert_eq!(body["created"], "2023-11-14T22:13:20Z");
+    assert_eq!(body["token"], "ZGFu");
+    assert!((1..=6).contains(&body["roll"].as_i64().unwrap()));
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_header_templates() -> Result<(), Error> {
+    let mut server = MockServer::new_http("template_headers");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post().path("/orders");
+        then.status(StatusCode::CREATED)
+            .increment("orders")
+            .headers([("location", "/orders")])
+            .header_template("location", "/orders/{{ state.orders }}")
+            .header_template("x-correlation-id", "{{ request.headers.x-request-id }}");
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    for (id, request_id) in [(0, "a"), (1, "b")] {
+        let response = client
+            .post(server.url("/orders"))
+            .header("x-request-id", request_id)
+            .send()
+            .await?;
+        assert_eq!(response.status(), http::StatusCode::CREATED);
+        let headers = response.headers();
+        assert_eq!(headers.get_all("location").iter().count(), 1);
+        assert_eq!(headers["location"], format!("/orders/{id}").as_str());
+        assert_eq!(headers["x-correlation-id"], request_id);
+    }
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_message_template() -> Result<(), Error> {
+    let mut server = MockServer::new_grpc("example.Hello");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.path("/example.Hello/HelloUnary");
+        then.grpc_invalid_argument("invalid")
+            .message_template("invalid request: {{ request.path }}\n100% ünvalid");
+    });
+
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+
+    // Messages are percent-encoded, e.g. with control characters
+    let status = client
+        .hello_unary(HelloRequest { name: "dan".into() })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    assert_eq!(
+        status.message(),
+        "invalid request: /example.Hello/HelloUnary\n100% ünvalid"
+    );
+
+    Ok(())
+}
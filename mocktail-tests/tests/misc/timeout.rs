@@ -0,0 +1,253 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Error;
+use mocktail::{prelude::*, server::MockServerConfig, Delegate, IdGenerator};
+use mocktail_tests::pb::{hello_client::HelloClient, HelloRequest, HelloResponse};
+use test_log::test;
+use tonic::transport::Channel;
+
+/// A matcher blocking on requests with a `hang` header.
+#[derive(Debug, PartialEq, PartialOrd)]
+struct HangingMatcher;
+
+impl Matcher for HangingMatcher {
+    fn name(&self) -> &str {
+        "hanging"
+    }
+
+    fn matches(&self, req: &Request) -> bool {
+        if req.headers().get("hang").is_some() {
+            std::thread::sleep(Duration::from_secs(2));
+        }
+        true
+    }
+}
+
+/// A matcher never returning on requests with a `never` header.
+#[derive(Debug, PartialEq, PartialOrd)]
+struct NeverMatcher;
+
+impl Matcher for NeverMatcher {
+    fn name(&self) -> &str {
+        "never"
+    }
+
+    fn matches(&self, req: &Request) -> bool {
+        if req.headers().get("never").is_some() {
+            loop {
+                std::thread::park();
+            }
+        }
+        true
+    }
+}
+
+fn server(name: &'static str, grpc: bool) -> Result<MockServer, Error> {
+    let config = MockServerConfig::builder()
+        .handler_timeout(Duration::from_millis(100))
+        .build()?;
+    let server = if grpc {
+        MockServer::new_grpc(name)
+    } else {
+        MockServer::new_http(name)
+    };
+    Ok(server.with_config(config))
+}
+
+#[test(tokio::test)]
+async fn test_matcher_timeout() -> Result<(), Error> {
+    let mut server = server("timeout", false)?;
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/hello").matcher(HangingMatcher);
+        then.text("hello");
+    });
+
+    let client = reqwest::Client::new();
+
+    let start = Instant::now();
+    let response = client
+        .get(server.url("/hello"))
+        .header("hang", "true")
+        .send()
+        .await?;
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert_eq!(response.status(), http::StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(
+        response.text().await?,
+        "matching did not complete within 100ms"
+    );
+
+    // Requests completing in time are matched
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.text().await?, "hello");
+
+    let err = server.verify().unwrap_err();
+    assert_eq!(
+        err.failures(),
+        ["timeout: GET /hello: matching did not complete within 100ms"]
+    );
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_responder_timeout() -> Result<(), Error> {
+    let mut server = server("timeout", false)?;
+    server.start().await?;
+    let id = server.mock(|when, then| {
+        when.get().path("/hello");
+        then.delegate(Delegate::from_fn(|_| futures::future::pending()));
+    });
+
+    let response = reqwest::get(server.url("/hello")).await?;
+    assert_eq!(response.status(), http::StatusCode::GATEWAY_TIMEOUT);
+
+    let err = server.verify().unwrap_err();
+    assert_eq!(
+        err.failures(),
+        [format!(
+            "timeout: GET /hello: responder of mock {id} did not complete within 100ms"
+        )]
+    );
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_matcher_timeout_not_recorded() -> Result<(), Error> {
+    let mut server = server("timeout", false)?;
+    server.start().await?;
+    let id = server.mock_with_options(0, Some(1), |when, then| {
+        when.get().path("/hello").matcher(HangingMatcher);
+        then.text("hello");
+    });
+
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(server.url("/hello"))
+        .header("hang", "true")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::GATEWAY_TIMEOUT);
+
+    // The matcher completes after the request timed out, without using the limit
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    assert_eq!(
+        server
+            .mocks()
+            .find(|mock| mock.id == id)
+            .unwrap()
+            .match_count(),
+        0
+    );
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.text().await?, "hello");
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_matcher_never_returns() -> Result<(), Error> {
+    let mut server = server("timeout", false)?;
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/hello").matcher(NeverMatcher);
+        then.text("hello");
+    });
+
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(server.url("/hello"))
+        .header("never", "true")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::GATEWAY_TIMEOUT);
+
+    // The hung matcher holds no lock on the mocks
+    server.mock(|when, then| {
+        when.get().path("/world");
+        then.text("world");
+    });
+    assert_eq!(server.mocks().len(), 2);
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.text().await?, "hello");
+    let response = client.get(server.url("/world")).send().await?;
+    assert_eq!(response.text().await?, "world");
+
+    // The test runtime shuts down without waiting for the matcher
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_render_timeout() -> Result<(), Error> {
+    let mut server = server("timeout", false)?;
+    server.start().await?;
+    let resource =
+        Resource::new("/things").with_id_generator(IdGenerator::Custom(Arc::new(|| {
+            std::thread::sleep(Duration::from_secs(2));
+            "1".into()
+        })));
+    let id = server.mock(|when, then| {
+        when.path_prefix("/things");
+        then.resource(resource);
+    });
+
+    let start = Instant::now();
+    let response = reqwest::Client::new()
+        .post(server.url("/things"))
+        .json(&serde_json::json!({"name": "dan"}))
+        .send()
+        .await?;
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert_eq!(response.status(), http::StatusCode::GATEWAY_TIMEOUT);
+
+    let err = server.verify().unwrap_err();
+    assert_eq!(
+        err.failures(),
+        [format!(
+            "timeout: POST /things: responder of mock {id} did not complete within 100ms"
+        )]
+    );
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_grpc_matcher_timeout() -> Result<(), Error> {
+    let mut server = server("example.Hello", true)?;
+    server.start().await?;
+    server.mock(|when, then| {
+        when.path("/example.Hello/HelloUnary")
+            .matcher(HangingMatcher);
+        then.pb(HelloResponse {
+            message: "hello dan!".into(),
+        });
+    });
+
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+
+    let mut request = tonic::Request::new(HelloRequest { name: "dan".into() });
+    request.metadata_mut().insert("hang", "true".parse()?);
+    let status = client.hello_unary(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
+    assert!(server.verify().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_zero_handler_timeout() {
+    let result = MockServerConfig::builder()
+        .handler_timeout(Duration::ZERO)
+        .build();
+    assert!(result.is_err());
+}
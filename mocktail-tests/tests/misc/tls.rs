@@ -0,0 +1,154 @@
+use anyhow::Error;
+use std::time::{Duration, SystemTime};
+
+use mocktail::{prelude::*, server::MockServerConfig, GeneratedCert, TlsConfig};
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_tls_generated_ca() -> Result<(), Error> {
+    let config = MockServerConfig::builder()
+        .tls(TlsConfig::generate())
+        .build()?;
+    let mut server = MockServer::new_http("tls").with_config(config);
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/hello");
+        then.text("hello!");
+    });
+    assert_eq!(server.base_url().unwrap().scheme(), "https");
+    assert!(server.tls_cert_der().is_some_and(|der| !der.is_empty()));
+
+    // Untrusted clients fail certificate verification
+    let client = reqwest::Client::new();
+    assert!(client.get(server.url("/hello")).send().await.is_err());
+
+    let ca = reqwest::Certificate::from_pem(server.tls_ca_pem().unwrap().as_bytes())?;
+    let client = reqwest::Client::builder()
+        .tls_built_in_root_certs(false)
+        .add_root_certificate(ca)
+        .build()?;
+
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(response.version(), http::Version::HTTP_2);
+    assert_eq!(response.text().await?, "hello!");
+
+    Ok(())
+}
+
+/// Starts a server with the TLS configuration, returning a client
+/// trusting `ca_pem`.
+async fn serve(tls: TlsConfig, ca_pem: &str) -> Result<(MockServer, reqwest::Client), Error> {
+    let config = MockServerConfig::builder().tls(tls).build()?;
+    let mut server = MockServer::new_http("tls").with_config(config);
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/hello");
+        then.text("hello!");
+    });
+    let client = reqwest::Client::builder()
+        .tls_built_in_root_certs(false)
+        .add_root_certificate(reqwest::Certificate::from_pem(ca_pem.as_bytes())?)
+        .build()?;
+    Ok((server, client))
+}
+
+#[test(tokio::test)]
+async fn test_tls_hostname_mismatch() -> Result<(), Error> {
+    let tls = GeneratedCert::new()
+        .common_name("example.test")
+        .subject_alt_names(["example.test", "10.0.0.1"])
+        .build()?;
+    let ca_pem = tls.ca_pem().unwrap().to_string();
+    let (server, client) = serve(tls, &ca_pem).await?;
+
+    let err = client.get(server.url("/hello")).send().await.unwrap_err();
+    assert!(err.is_connect());
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_tls_expired() -> Result<(), Error> {
+    let now = SystemTime::now();
+    let tls = GeneratedCert::new()
+        .not_before(now - Duration::from_secs(7200))
+        .not_after(now - Duration::from_secs(3600))
+        .build()?;
+    let ca_pem = tls.ca_pem().unwrap().to_string();
+    let (server, client) = serve(tls, &ca_pem).await?;
+
+    let err = client.get(server.url("/hello")).send().await.unwrap_err();
+    assert!(err.is_connect());
+
+    Ok(())
+}
+
+#[test]
+fn test_tls_invalid_generated_cert() {
+    let err = GeneratedCert::new()
+        .subject_alt_names(["exämple.test"])
+        .build()
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("invalid subject alternative name"),
+        "{err}"
+    );
+}
+
+#[test(tokio::test)]
+async fn test_tls_from_pem_files() -> Result<(), Error> {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tls");
+    let tls = TlsConfig::from_pem_files(format!("{dir}/cert.pem"), format!("{dir}/key.pem"))?;
+    assert!(tls.ca_pem().is_none());
+    let ca_pem = std::fs::read_to_string(format!("{dir}/cert.pem"))?;
+    let (server, client) = serve(tls, &ca_pem).await?;
+    assert!(server.tls_ca_pem().is_none());
+
+    let response = client.get(server.url("/hello")).send().await?;
+    assert_eq!(response.text().await?, "hello!");
+
+    assert!(TlsConfig::from_pem(&ca_pem, "").is_err());
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_connection_info() -> Result<(), Error> {
+    let tls = TlsConfig::generate();
+    let ca_pem = tls.ca_pem().unwrap().to_string();
+    let (mut server, client) = serve(tls, &ca_pem).await?;
+    server.mock(|when, then| {
+        when.get().path("/protocol");
+        then.template("{{ request.protocol }} {{ request.alpn }}");
+    });
+    let port = server.port().unwrap();
+
+    let response = client
+        .get(format!("https://localhost:{port}/protocol"))
+        .send()
+        .await?;
+    assert_eq!(response.text().await?, "HTTP/2.0 h2");
+
+    let request = server.last_request().unwrap();
+    assert_eq!(request.protocol(), Some("HTTP/2.0"));
+    assert!(request.peer_addr().unwrap().ip().is_loopback());
+    let info = request.connection_info().unwrap();
+    assert_eq!(info.alpn.as_deref(), Some("h2"));
+    let tls = info.tls.as_ref().unwrap();
+    assert_eq!(tls.version, "TLSv1_3");
+    assert!(!tls.cipher_suite.is_empty());
+    assert_eq!(tls.server_name.as_deref(), Some("localhost"));
+
+    // Plain connections have no TLS details
+    let server = MockServer::new_http("plain");
+    server.start().await?;
+    reqwest::get(server.url("/hello")).await?;
+    let request = server.last_request().unwrap();
+    assert_eq!(request.protocol(), Some("HTTP/1.1"));
+    let info = request.connection_info().unwrap();
+    assert_eq!(info.alpn, None);
+    assert_eq!(info.tls, None);
+
+    Ok(())
+}
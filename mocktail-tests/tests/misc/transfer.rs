@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+use anyhow::Error;
+use futures::{stream, StreamExt};
+use mocktail::{prelude::*, server::MockServerConfig};
+use mocktail_tests::{
+    pb::{hello_client::HelloClient, HelloRequest},
+    slow_body,
+};
+use test_log::test;
+use tonic::transport::Channel;
+
+#[test(tokio::test)]
+async fn test_http_transfer() -> Result<(), Error> {
+    let server = MockServer::new_http("transfer");
+    server.start().await?;
+    server.mocks().mock(|when, then| {
+        when.post().path("/upload").text("helloworld!");
+        then.ok();
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let response = client
+        .post(server.url("/upload"))
+        .body(slow_body(Duration::from_millis(50)))
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    // Streaming requests are recorded when matched, after the response head is sent
+    response.bytes().await?;
+
+    let response = client.get(server.url("/upload")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    let journal = server.journal();
+    let entries = journal.iter().collect::<Vec<_>>();
+    let transfer = entries[0].transfer().unwrap();
+    assert_eq!(transfer.bytes, 11);
+    assert_eq!(transfer.chunks, 3);
+    assert_eq!(transfer.messages, 0);
+    // The first chunk is sent after a delay as well
+    assert!(transfer.duration >= Duration::from_millis(150));
+
+    let transfer = entries[1].transfer().unwrap();
+    assert_eq!(transfer.bytes, 0);
+    assert_eq!(transfer.chunks, 0);
+    assert_eq!(transfer.duration, Duration::ZERO);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_limited_transfer() -> Result<(), Error> {
+    let config = MockServerConfig::builder().max_body_size(1024).build()?;
+    let server = MockServer::new_http("transfer").with_config(config);
+    server.start().await?;
+    server.mocks().mock(|when, then| {
+        when.post().path("/upload").text("helloworld!");
+        then.ok();
+    });
+
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(server.url("/upload"))
+        .body(slow_body(Duration::from_millis(20)))
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    let journal = server.journal();
+    let transfer = journal.iter().next().unwrap().transfer().unwrap();
+    assert_eq!(transfer.bytes, 11);
+    assert_eq!(transfer.chunks, 3);
+    assert!(transfer.duration >= Duration::from_millis(60));
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_grpc_transfer() -> Result<(), Error> {
+    let server = MockServer::new_grpc("example.Hello");
+    server.start().await?;
+
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+
+    let names = ["dan", "mateus", "paulo"].map(|name| HelloRequest { name: name.into() });
+    let result = client
+        .hello_client_streaming(stream::iter(names).then(|name| async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            name
+        }))
+        .await;
+    assert_eq!(result.unwrap_err().code(), tonic::Code::NotFound);
+
+    let journal = server.journal();
+    let transfer = journal.iter().next().unwrap().transfer().unwrap();
+    // Each message has a 5 byte prefix and a 2 byte field header
+    assert_eq!(transfer.bytes, 3 * 7 + 14);
+    assert_eq!(transfer.messages, 3);
+    assert!(transfer.duration >= Duration::from_millis(60));
+
+    Ok(())
+}
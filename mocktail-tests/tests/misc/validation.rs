@@ -1,6 +1,20 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
 use anyhow::Error;
-use mocktail::prelude::*;
+use bytes::Bytes;
+use http_body::Frame;
+use http_body_util::BodyExt;
+use mocktail::{
+    prelude::*,
+    server::{MockServerConfig, ProtocolErrors},
+};
+use mocktail_tests::pb::{hello_client::HelloClient, HelloRequest};
 use test_log::test;
+use tonic::transport::Channel;
+use tower::ServiceExt;
 
 #[test(tokio::test)]
 async fn test_grpc_service() -> Result<(), Error> {
@@ -25,6 +39,78 @@ async fn test_grpc_service() -> Result<(), Error> {
         .get("accept-post")
         .is_some_and(|value| value == "application/grpc"));
 
+    // Protocol errors are recorded
+    let errors = server
+        .journal()
+        .iter()
+        .map(|entry| entry.protocol_error().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        [
+            "gRPC requires POST, received GET",
+            "gRPC requires an application/grpc content type, received \"\""
+        ]
+    );
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_protocol_errors() -> Result<(), Error> {
+    // HTTP/1 requests to a gRPC server
+    let server = MockServer::new_grpc("protocol-errors").with_config(
+        MockServerConfig::builder()
+            .protocol_errors(ProtocolErrors::new().http1_status(StatusCode::BAD_REQUEST))
+            .build()?,
+    );
+    server.start().await?;
+    let client = reqwest::Client::builder().http1_only().build()?;
+    let response = client
+        .post(server.url("/example.Hello/HelloUnary"))
+        .header("content-type", "application/grpc")
+        .send()
+        .await?;
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.text().await?,
+        "gRPC requires HTTP/2, received HTTP/1.1"
+    );
+    let entry = server.journal().iter().next_back().unwrap().clone();
+    assert_eq!(
+        entry.protocol_error(),
+        Some("gRPC requires HTTP/2, received HTTP/1.1")
+    );
+    assert!(entry.mock_id().is_none());
+
+    // gRPC requests to an HTTP server
+    let server = MockServer::new_http("protocol-errors").with_config(
+        MockServerConfig::builder()
+            .protocol_errors(ProtocolErrors::new().grpc_code(Code::Unimplemented))
+            .build()?,
+    );
+    server.start().await?;
+    let channel = Channel::from_shared(server.base_url().unwrap().to_string())?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+    let status = client
+        .hello_unary(HelloRequest { name: "Dan".into() })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unimplemented);
+    assert_eq!(status.message(), "gRPC request sent to an HTTP server");
+    assert_eq!(
+        server
+            .journal()
+            .iter()
+            .next_back()
+            .unwrap()
+            .clone()
+            .protocol_error(),
+        Some("gRPC request sent to an HTTP server")
+    );
+
     Ok(())
 }
 
@@ -33,12 +119,107 @@ async fn test_http_service() -> Result<(), Error> {
     let server = MockServer::new_http("test");
     server.start().await?;
 
+    server.mocks().mock(|when, then| {
+        when.method_any([
+            Method::try_from("PROPFIND").unwrap(),
+            Method::try_from("MKCOL").unwrap(),
+        ])
+        .path("/dav");
+        then.text("dav!");
+    });
+
     let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
 
-    // Invalid method
+    // Non-standard methods are matched like any other method
     let response = client.patch(server.url("/hello")).send().await?;
-    assert_eq!(response.status(), http::StatusCode::METHOD_NOT_ALLOWED);
-    assert!(response.headers().get("allow").is_some());
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    let propfind = http::Method::from_bytes(b"PROPFIND")?;
+    let response = client.request(propfind, server.url("/dav")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::OK);
+    assert_eq!(response.text().await?, "dav!");
+
+    let report = http::Method::from_bytes(b"REPORT")?;
+    let response = client.request(report, server.url("/dav")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+type FailingBody = http_body_util::StreamBody<
+    futures::stream::Iter<std::vec::IntoIter<Result<Frame<Bytes>, std::io::Error>>>,
+>;
+
+/// Returns a request body failing after the chunks.
+fn failing_body(chunks: &[&'static str]) -> FailingBody {
+    let mut frames = chunks
+        .iter()
+        .map(|chunk| Ok(Frame::data(Bytes::from_static(chunk.as_bytes()))))
+        .collect::<Vec<_>>();
+    frames.push(Err(std::io::Error::other("connection reset")));
+    http_body_util::StreamBody::new(futures::stream::iter(frames))
+}
+
+#[test(tokio::test)]
+async fn test_decode_errors() -> Result<(), Error> {
+    let server = MockServer::new_http("decode-errors").with_config(
+        MockServerConfig::builder()
+            .max_body_size(1024)
+            .protocol_errors(ProtocolErrors::new().decode_status(StatusCode::UNPROCESSABLE_ENTITY))
+            .build()?,
+    );
+    let errors = Arc::new(AtomicUsize::new(0));
+    server.on_protocol_error({
+        let errors = errors.clone();
+        move |_| {
+            errors.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    // Body failing to be received
+    let request = http::Request::post("/upload").body(failing_body(&["hello", " world"]))?;
+    let response = server.http_service().oneshot(request).await?;
+    assert_eq!(response.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(body, "failed to receive request body: connection reset");
+    let entry = server.journal().iter().next_back().unwrap().clone();
+    assert_eq!(
+        entry.protocol_error(),
+        Some("failed to receive request body: connection reset")
+    );
+    assert_eq!(entry.request().body().clone().as_bytes(), "hello world");
+    assert_eq!(errors.load(Ordering::SeqCst), 1);
+
+    // Malformed gRPC message frame
+    let server = MockServer::new_grpc("decode-errors");
+    let request = http::Request::post("/example.Hello/HelloUnary")
+        .header("content-type", "application/grpc")
+        .body(http_body_util::Full::new(Bytes::from_static(&[
+            0, 0, 0, 0, 10, 1, 2,
+        ])))?;
+    let response = server.grpc_service().oneshot(request).await?;
+    let trailers = response
+        .into_body()
+        .collect()
+        .await
+        .unwrap()
+        .trailers()
+        .cloned()
+        .unwrap();
+    assert_eq!(trailers["grpc-status"], "13");
+    assert_eq!(
+        trailers["grpc-message"],
+        "incomplete message: expected 10 bytes, received 2"
+    );
+    let entry = server.journal().iter().next_back().unwrap().clone();
+    assert_eq!(
+        entry.protocol_error(),
+        Some("incomplete message: expected 10 bytes, received 2")
+    );
+    assert_eq!(
+        entry.request().body().clone().as_bytes(),
+        [0, 0, 0, 0, 10, 1, 2].as_slice()
+    );
 
     Ok(())
 }
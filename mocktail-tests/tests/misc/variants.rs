@@ -0,0 +1,121 @@
+use anyhow::Error;
+use mocktail::{prelude::*, server::MockServerConfig};
+use test_log::test;
+
+async fn statuses(seed: u64) -> Result<Vec<u16>, Error> {
+    let config = MockServerConfig::builder().seed(seed).build()?;
+    let mut server = MockServer::new_http("variants").with_config(config);
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/flaky");
+        then.variant(90, |then| {
+            then.text("ok");
+        })
+        .variant(10, |then| {
+            then.service_unavailable();
+        });
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let mut statuses = Vec::new();
+    for _ in 0..100 {
+        let response = client.get(server.url("/flaky")).send().await?;
+        statuses.push(response.status().as_u16());
+    }
+    Ok(statuses)
+}
+
+#[test(tokio::test)]
+async fn test_weighted_variants() -> Result<(), Error> {
+    let first = statuses(42).await?;
+    assert!(first.iter().all(|status| [200, 503].contains(status)));
+    let failures = first.iter().filter(|status| **status == 503).count();
+    assert!((1..30).contains(&failures), "{failures}");
+
+    // Same seed, same choices
+    assert_eq!(statuses(42).await?, first);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_variant_journal_counts() -> Result<(), Error> {
+    let config = MockServerConfig::builder().seed(7).build()?;
+    let mut server = MockServer::new_http("variant_counts").with_config(config);
+    server.start().await?;
+    let id = server.mock(|when, then| {
+        when.get().path("/flaky");
+        then.variant(50, |then| {
+            then.text("ok");
+        })
+        .variant(50, |then| {
+            then.service_unavailable();
+        });
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    let mut failures = 0;
+    for _ in 0..20 {
+        let response = client.get(server.url("/flaky")).send().await?;
+        if response.status() == http::StatusCode::SERVICE_UNAVAILABLE {
+            failures += 1;
+        }
+    }
+
+    let journal = server.journal();
+    let counts = journal.variant_counts(&id);
+    assert_eq!(counts.values().sum::<usize>(), 20);
+    assert_eq!(counts.get(&1).copied().unwrap_or_default(), failures);
+    assert_eq!(counts.len(), 2, "{counts:?}");
+    assert_eq!(
+        journal.count(&JournalQuery::new().variant(1)),
+        failures,
+        "entries served by the failing variant"
+    );
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_language_variants() -> Result<(), Error> {
+    let mut server = MockServer::new_http("languages");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/greeting");
+        then.text("hello")
+            .language("fr", |then| {
+                then.text("bonjour");
+            })
+            .language("de", |then| {
+                then.text("hallo");
+            });
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    for (accept, body, language) in [
+        (Some("fr-CA, de;q=0.5"), "bonjour", Some("fr")),
+        (Some("es, de;q=0.8, fr;q=0.2"), "hallo", Some("de")),
+        (Some("es"), "hello", None),
+        (None, "hello", None),
+    ] {
+        let mut request = client.get(server.url("/greeting"));
+        if let Some(accept) = accept {
+            request = request.header("accept-language", accept);
+        }
+        let response = request.send().await?;
+        assert_eq!(
+            response
+                .headers()
+                .get("content-language")
+                .map(|value| value.to_str().unwrap()),
+            language
+        );
+        assert_eq!(response.headers()["vary"], "accept-language");
+        assert_eq!(response.text().await?, body);
+    }
+
+    Ok(())
+}
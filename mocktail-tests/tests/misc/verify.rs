@@ -0,0 +1,211 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use anyhow::Error;
+use mocktail::{mock_builder::When, prelude::*, server::MockServerConfig};
+use mocktail_tests::pb::{hello_client::HelloClient, HelloRequest, HelloResponse};
+use serde::Deserialize;
+use serde_json::json;
+use test_log::test;
+use tonic::transport::Channel;
+
+#[test(tokio::test)]
+async fn test_verify_sequence() -> Result<(), Error> {
+    let mut server = MockServer::new_http("verify");
+    server.start().await?;
+    let login = server.mock(|when, then| {
+        when.post().path("/login");
+        then.ok();
+    });
+    let orders = server.mock(|when, then| {
+        when.get().path("/orders");
+        then.ok();
+    });
+    server.expect_sequence(Sequence::new([login, orders]));
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    // Out of order
+    client.get(server.url("/orders")).send().await?;
+    client.post(server.url("/login")).send().await?;
+    let err = server.verify().unwrap_err();
+    assert_eq!(err.failures().len(), 1);
+    assert!(err.to_string().contains("0: GET /orders"));
+
+    // In order
+    server.clear_journal();
+    client.post(server.url("/login")).send().await?;
+    client.get(server.url("/health")).send().await?;
+    client.get(server.url("/orders")).send().await?;
+    server.verify()?;
+
+    // Consecutive
+    server.expect_sequence(Sequence::new([login, orders]).consecutive());
+    assert!(server.verify().is_err());
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_strict_mode() -> Result<(), Error> {
+    let config = MockServerConfig::builder().strict(true).build()?;
+    let mut server = MockServer::new_http("strict").with_config(config);
+    server.start().await?;
+    server.mock(|when, then| {
+        when.get().path("/hello");
+        then.ok();
+    });
+    let unmatched = Arc::new(AtomicUsize::new(0));
+    server.on_unmatched({
+        let unmatched = unmatched.clone();
+        move |_| {
+            unmatched.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+
+    client.get(server.url("/hello")).send().await?;
+    server.verify()?;
+
+    let response = client.get(server.url("/missing")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+    let err = server.verify().unwrap_err();
+    assert_eq!(err.failures(), ["unexpected request: GET /missing"]);
+    assert_eq!(unmatched.load(Ordering::SeqCst), 1);
+
+    server.reset();
+    server.verify()?;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_labels() -> Result<(), Error> {
+    let server = MockServer::new_http("labels");
+    server.start().await?;
+    let mock = Mock::new(|when, then| {
+        when.get().path("/orders");
+        then.ok();
+    })
+    .with_label("fixture", "orders.yaml")
+    .with_label("case", "list");
+    let orders = mock.id;
+    server.mocks().insert(mock);
+
+    let client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+    client.get(server.url("/orders")).send().await?;
+    client.get(server.url("/unknown")).send().await?;
+
+    let journal = server.journal();
+    let entries = journal.iter().collect::<Vec<_>>();
+    assert_eq!(entries[0].labels()["fixture"], "orders.yaml");
+    assert!(entries[1].labels().is_empty());
+    drop(journal);
+
+    let err = server
+        .assert_retried(orders, 1, 2, std::time::Duration::from_secs(1))
+        .unwrap_err();
+    assert!(err.failures()[0].contains("[case=list, fixture=orders.yaml]"));
+
+    server.expect_sequence(Sequence::new([orders, orders]).consecutive());
+    let err = server.verify().unwrap_err();
+    assert!(err.failures()[0].contains("[case=list, fixture=orders.yaml]"));
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Order {
+    sku: String,
+    quantity: u32,
+}
+
+#[test(tokio::test)]
+async fn test_last_request() -> Result<(), Error> {
+    let mut server = MockServer::new_http("last_request");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post().path("/orders");
+        then.created();
+    });
+
+    assert!(server.last_request().is_none());
+    assert!(server.last_request_json::<Order>().is_err());
+
+    let client = reqwest::Client::new();
+    client
+        .post(server.url("/orders"))
+        .json(&json!({"sku": "A-1", "quantity": 2}))
+        .send()
+        .await?;
+    assert_eq!(
+        server.last_request_json::<Order>()?,
+        Order {
+            sku: "A-1".into(),
+            quantity: 2,
+        }
+    );
+
+    let mut server = MockServer::new_grpc("example.Hello");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.path("/example.Hello/HelloUnary");
+        then.pb(HelloResponse {
+            message: "hello!".into(),
+        });
+    });
+
+    let channel = Channel::from_shared(format!("http://0.0.0.0:{}", server.port().unwrap()))?
+        .connect()
+        .await?;
+    let mut client = HelloClient::new(channel);
+    client
+        .hello_unary(HelloRequest { name: "dan".into() })
+        .await?;
+    assert_eq!(server.last_request_pb::<HelloRequest>()?.name, "dan");
+    assert_eq!(server.journal().len(), 1);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_assert_received() -> Result<(), Error> {
+    let mut server = MockServer::new_http("assert_received");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.path_prefix("/orders");
+        then.ok();
+    });
+
+    let client = reqwest::Client::new();
+    for quantity in [1, 2] {
+        client
+            .post(server.url("/orders"))
+            .json(&json!({"sku": "A-1", "quantity": quantity}))
+            .send()
+            .await?;
+    }
+    client.get(server.url("/orders/1")).send().await?;
+
+    server.assert_received(request_matching(When::new().post().path("/orders")).times(2))?;
+    server.assert_received(
+        request_matching(
+            When::new()
+                .post()
+                .json(json!({"sku": "A-1", "quantity": 2})),
+        )
+        .times(1),
+    )?;
+    server.assert_received(request_matching(When::new().get().path("/orders/1")))?;
+    server.assert_received(request_matching(When::new().delete()).never())?;
+
+    let err = server
+        .assert_received(request_matching(When::new().post().path("/orders")).times(3))
+        .unwrap_err();
+    assert!(err.failures()[0].starts_with("expected 3 requests matching"));
+
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Error;
+use mocktail::prelude::*;
+use serde_json::json;
+use test_log::test;
+
+#[test(tokio::test)]
+async fn test_webhook_retries() -> Result<(), Error> {
+    let mut receiver = MockServer::new_http("webhook_receiver");
+    receiver.start().await?;
+    receiver.mock(|when, then| {
+        when.post()
+            .path("/hooks")
+            .json(json!({"status": "complete"}));
+        then.fail_times(1, StatusCode::SERVICE_UNAVAILABLE);
+    });
+
+    let mut server = MockServer::new_http("webhook");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post().path("/jobs");
+        then.status(StatusCode::ACCEPTED).webhook(
+            Webhook::post(receiver.url("/hooks"))
+                .with_json(json!({"status": "complete"}))
+                .after(Duration::from_millis(200))
+                .with_retries(2, Duration::from_millis(50)),
+        );
+    });
+
+    let client = reqwest::Client::new();
+    let start = Instant::now();
+    let response = client.post(server.url("/jobs")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::ACCEPTED);
+    assert!(receiver.journal().is_empty());
+
+    // The first attempt fails and is retried
+    while receiver.journal().len() < 2 {
+        assert!(start.elapsed() < Duration::from_secs(5), "webhook not sent");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(start.elapsed() >= Duration::from_millis(250));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(receiver.journal().len(), 2);
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_webhook_cancelled_on_reset() -> Result<(), Error> {
+    let mut receiver = MockServer::new_http("webhook_reset_receiver");
+    receiver.start().await?;
+    receiver.mock(|when, then| {
+        when.post().path("/hooks");
+        then.ok();
+    });
+
+    let mut server = MockServer::new_http("webhook_reset");
+    server.start().await?;
+    server.mock(|when, then| {
+        when.post().path("/jobs");
+        then.status(StatusCode::ACCEPTED)
+            .webhook(Webhook::post(receiver.url("/hooks")).after(Duration::from_millis(200)));
+    });
+
+    let client = reqwest::Client::new();
+    let response = client.post(server.url("/jobs")).send().await?;
+    assert_eq!(response.status(), http::StatusCode::ACCEPTED);
+
+    server.reset();
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert!(receiver.journal().is_empty());
+
+    Ok(())
+}
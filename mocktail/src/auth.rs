@@ -0,0 +1,208 @@
+//! Authentication
+//!
+//! Mocks can require credentials with [`When::authorization`], or answer
+//! requests without them with a `401 Unauthorized` challenge until the client
+//! retries with the expected credentials, with
+//! [`MockServer::mock_with_challenge`], e.g.
+//!
+//! ```no_run
+//! # use mocktail::prelude::*;
+//! # use serde_json::json;
+//! # fn f(mut server: MockServer) {
+//! server.mock_with_challenge(Challenge::basic("reports", "dan", "secret"), |when, then| {
+//!     when.get().path("/reports");
+//!     then.json(json!([]));
+//! });
+//! # }
+//! ```
+//!
+//! Digest challenges issue a nonce with each `401 Unauthorized` response,
+//! see [`Digest`]. Requests signed with AWS Signature Version 4 are matched
+//! with [`When::aws_sigv4`], see [`SigV4`].
+//!
+//! [`When::authorization`]: crate::mock_builder::When::authorization
+//! [`When::aws_sigv4`]: crate::mock_builder::When::aws_sigv4
+//! [`MockServer::mock_with_challenge`]: crate::server::MockServer::mock_with_challenge
+use std::sync::Arc;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+#[cfg(feature = "digest")]
+mod digest;
+#[cfg(feature = "digest")]
+use digest::Check;
+#[cfg(feature = "digest")]
+pub use digest::{Digest, DigestAlgorithm, Qop};
+#[cfg(feature = "sigv4")]
+mod sigv4;
+#[cfg(feature = "sigv4")]
+pub use sigv4::SigV4;
+
+use crate::{
+    headers::Headers, matchers, mock::Mock, request::Request, response::Response,
+    status::StatusCode,
+};
+
+/// The credentials of an `Authorization` header.
+///
+/// Scheme names are compared case-insensitively.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum Credentials {
+    /// `Basic` credentials with a user and password.
+    Basic { user: String, password: String },
+    /// `Bearer` token.
+    Bearer(String),
+    /// `Negotiate` token, e.g. a SPNEGO token, or any token if unset.
+    Negotiate(Option<String>),
+    /// An exact header value.
+    Header(String),
+    /// `Digest` credentials, responding to a nonce issued by a challenge.
+    #[cfg(feature = "digest")]
+    Digest(Digest),
+}
+
+impl Credentials {
+    /// Creates `Basic` credentials.
+    pub fn basic(user: impl Into<String>, password: impl Into<String>) -> Self {
+        Self::Basic {
+            user: user.into(),
+            password: password.into(),
+        }
+    }
+
+    /// Creates a `Bearer` token.
+    pub fn bearer(token: impl Into<String>) -> Self {
+        Self::Bearer(token.into())
+    }
+
+    /// Returns true if the `Authorization` header of a request has the credentials.
+    ///
+    /// Verifying has no side effects: the nonce count of `Digest` credentials
+    /// is recorded, and cannot be used again, once the mock is served.
+    pub fn verify(&self, req: &Request) -> bool {
+        let Some(authorization) = req.headers.get("authorization") else {
+            return false;
+        };
+        let (scheme, token) = split_scheme(authorization);
+        match self {
+            Credentials::Basic { user, password } => {
+                scheme.eq_ignore_ascii_case("basic")
+                    && STANDARD
+                        .decode(token)
+                        .is_ok_and(|decoded| decoded == format!("{user}:{password}").as_bytes())
+            }
+            Credentials::Bearer(expected) => {
+                scheme.eq_ignore_ascii_case("bearer") && token == expected
+            }
+            Credentials::Negotiate(expected) => {
+                scheme.eq_ignore_ascii_case("negotiate")
+                    && !token.is_empty()
+                    && expected.as_ref().is_none_or(|expected| token == expected)
+            }
+            Credentials::Header(expected) => authorization.as_str() == expected,
+            #[cfg(feature = "digest")]
+            Credentials::Digest(digest) => {
+                scheme.eq_ignore_ascii_case("digest")
+                    && digest.check(req, token, false) == Check::Valid
+            }
+        }
+    }
+}
+
+/// Splits an `Authorization` header value into its scheme and token.
+fn split_scheme(authorization: &str) -> (&str, &str) {
+    let authorization = authorization.trim();
+    authorization
+        .split_once(' ')
+        .map(|(scheme, token)| (scheme, token.trim()))
+        .unwrap_or((authorization, ""))
+}
+
+/// An authentication challenge, sent in the `WWW-Authenticate` headers of
+/// `401 Unauthorized` responses until a request has the expected credentials.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Challenge {
+    challenges: Vec<String>,
+    credentials: Credentials,
+}
+
+impl Challenge {
+    /// Creates a challenge with a `WWW-Authenticate` header value,
+    /// e.g. `Basic realm="reports"`, and the expected credentials.
+    pub fn new(challenge: impl Into<String>, credentials: Credentials) -> Self {
+        Self {
+            challenges: vec![challenge.into()],
+            credentials,
+        }
+    }
+
+    /// Creates a `Basic` challenge for a realm.
+    pub fn basic(
+        realm: impl Into<String>,
+        user: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        let challenge = format!("Basic realm=\"{}\", charset=\"UTF-8\"", realm.into());
+        Self::new(challenge, Credentials::basic(user, password))
+    }
+
+    /// Creates a `Bearer` challenge for a realm.
+    pub fn bearer(realm: impl Into<String>, token: impl Into<String>) -> Self {
+        let challenge = format!("Bearer realm=\"{}\"", realm.into());
+        Self::new(challenge, Credentials::bearer(token))
+    }
+
+    /// Creates a `Negotiate` challenge, expecting a token, or any token if unset.
+    pub fn negotiate(token: Option<String>) -> Self {
+        Self::new("Negotiate", Credentials::Negotiate(token))
+    }
+
+    /// Creates a `Digest` challenge, issuing a nonce with each response.
+    #[cfg(feature = "digest")]
+    pub fn digest(digest: Digest) -> Self {
+        Self {
+            challenges: Vec::new(),
+            credentials: Credentials::Digest(digest),
+        }
+    }
+
+    /// Adds a `WWW-Authenticate` header value, e.g. to offer several schemes.
+    pub fn with_challenge(mut self, challenge: impl Into<String>) -> Self {
+        self.challenges.push(challenge.into());
+        self
+    }
+
+    /// Returns the static `WWW-Authenticate` header values.
+    pub fn challenges(&self) -> &[String] {
+        &self.challenges
+    }
+
+    /// Returns the expected credentials.
+    pub fn credentials(&self) -> &Credentials {
+        &self.credentials
+    }
+
+    /// Returns a mock requiring the credentials, and the challenge mock
+    /// answering its requests without them, matched after it.
+    pub(crate) fn mocks(self, mut mock: Mock) -> (Mock, Mock) {
+        let headers = self
+            .challenges
+            .iter()
+            .map(|challenge| ("www-authenticate", challenge.as_str()))
+            .collect::<Headers>();
+        let mut challenge = Mock::new(|_, _| {}).with_priority(mock.priority.saturating_add(1));
+        challenge.matchers = mock.matchers.clone();
+        challenge.namespace = mock.namespace.clone();
+        challenge.response = Response::default()
+            .with_status(StatusCode::UNAUTHORIZED)
+            .with_headers(headers);
+        #[cfg(feature = "digest")]
+        if let Credentials::Digest(digest) = &self.credentials {
+            challenge.response.digest = Some(digest.clone());
+        }
+        mock.matchers
+            .push(Arc::new(matchers::authorization(self.credentials)));
+        mock.matchers.sort_unstable();
+        (mock, challenge)
+    }
+}
@@ -0,0 +1,401 @@
+//! Digest access authentication
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use md5::Md5;
+use sha2::{Digest as _, Sha256};
+
+use super::split_scheme;
+use crate::{body::Body, request::Request, response::Response};
+
+/// A `Digest` hash algorithm.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DigestAlgorithm {
+    /// `MD5`, the default of legacy clients.
+    #[default]
+    Md5,
+    /// `SHA-256`.
+    Sha256,
+}
+
+impl DigestAlgorithm {
+    /// Returns the algorithm name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DigestAlgorithm::Md5 => "MD5",
+            DigestAlgorithm::Sha256 => "SHA-256",
+        }
+    }
+
+    /// Returns the lowercase hex digest of data.
+    fn hash(&self, data: impl IntoIterator<Item = impl AsRef<[u8]>>) -> String {
+        fn hex(digest: &[u8]) -> String {
+            digest.iter().map(|byte| format!("{byte:02x}")).collect()
+        }
+        match self {
+            DigestAlgorithm::Md5 => {
+                let mut hasher = Md5::new();
+                data.into_iter().for_each(|chunk| hasher.update(chunk));
+                hex(&hasher.finalize())
+            }
+            DigestAlgorithm::Sha256 => {
+                let mut hasher = Sha256::new();
+                data.into_iter().for_each(|chunk| hasher.update(chunk));
+                hex(&hasher.finalize())
+            }
+        }
+    }
+}
+
+/// A `Digest` quality of protection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Qop {
+    /// `auth`, authenticating the method and URI.
+    Auth,
+    /// `auth-int`, also authenticating the body.
+    AuthInt,
+}
+
+impl Qop {
+    /// Returns the directive value.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Qop::Auth => "auth",
+            Qop::AuthInt => "auth-int",
+        }
+    }
+}
+
+/// `Digest` access authentication (RFC 7616) with a user and password.
+///
+/// Each challenge response issues a fresh nonce, valid for the nonce lifetime.
+/// Responses to an expired or unknown nonce are challenged again with
+/// `stale=true`, so clients retry without prompting for credentials, and the
+/// nonce count of a nonce must increase with each request, rejecting replays.
+/// Without qualities of protection, the RFC 2069 response is expected and
+/// nonces may be reused.
+///
+/// Clones share the issued nonces.
+#[derive(Debug, Clone)]
+pub struct Digest {
+    realm: String,
+    user: String,
+    password: String,
+    algorithm: DigestAlgorithm,
+    qop: Vec<Qop>,
+    nonce_lifetime: Duration,
+    opaque: String,
+    nonces: Arc<Mutex<HashMap<String, Nonce>>>,
+}
+
+/// An issued nonce.
+#[derive(Debug, Clone, Copy)]
+struct Nonce {
+    issued_at: SystemTime,
+    /// Highest nonce count accepted.
+    count: u32,
+}
+
+/// The result of checking `Digest` credentials.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Check {
+    Valid,
+    /// Valid credentials for an expired or unknown nonce.
+    Stale,
+    Invalid,
+}
+
+impl Digest {
+    /// Creates a `Digest` authentication for a realm, with the `MD5` algorithm,
+    /// the `auth` quality of protection and a 5 minute nonce lifetime.
+    pub fn new(
+        realm: impl Into<String>,
+        user: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        Self {
+            realm: realm.into(),
+            user: user.into(),
+            password: password.into(),
+            algorithm: DigestAlgorithm::default(),
+            qop: vec![Qop::Auth],
+            nonce_lifetime: Duration::from_secs(300),
+            opaque: format!("{:032x}", rand::random::<u128>()),
+            nonces: Arc::default(),
+        }
+    }
+
+    /// Sets the hash algorithm.
+    pub fn algorithm(mut self, algorithm: DigestAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Sets the qualities of protection offered, or none for RFC 2069 clients.
+    pub fn qop(mut self, qop: impl IntoIterator<Item = Qop>) -> Self {
+        self.qop = qop.into_iter().collect();
+        self
+    }
+
+    /// Sets the time a nonce is valid after it is issued.
+    pub fn nonce_lifetime(mut self, lifetime: Duration) -> Self {
+        self.nonce_lifetime = lifetime;
+        self
+    }
+
+    /// Returns the realm.
+    pub fn realm(&self) -> &str {
+        &self.realm
+    }
+
+    fn is_expired(&self, nonce: &Nonce, now: SystemTime) -> bool {
+        now.duration_since(nonce.issued_at)
+            .is_ok_and(|age| age > self.nonce_lifetime)
+    }
+
+    /// Adds a `WWW-Authenticate` challenge with a fresh nonce to a response,
+    /// marked stale if the request has valid credentials for an expired nonce.
+    pub(crate) fn challenge(&self, response: &mut Response, request: &Request, now: SystemTime) {
+        let stale = request
+            .headers
+            .get("authorization")
+            .map(|authorization| split_scheme(authorization))
+            .is_some_and(|(scheme, token)| {
+                scheme.eq_ignore_ascii_case("digest")
+                    && self.check_at(request, token, false, now) == Check::Stale
+            });
+        let nonce = format!("{:032x}", rand::random::<u128>());
+        {
+            let mut nonces = self.nonces.lock().unwrap();
+            nonces.retain(|_, nonce| !self.is_expired(nonce, now));
+            nonces.insert(
+                nonce.clone(),
+                Nonce {
+                    issued_at: now,
+                    count: 0,
+                },
+            );
+        }
+        let mut challenge = format!("Digest realm=\"{}\"", self.realm);
+        if !self.qop.is_empty() {
+            let qop = self.qop.iter().map(Qop::as_str).collect::<Vec<_>>();
+            challenge.push_str(&format!(", qop=\"{}\"", qop.join(", ")));
+        }
+        challenge.push_str(&format!(
+            ", algorithm={}, nonce=\"{nonce}\", opaque=\"{}\"",
+            self.algorithm.as_str(),
+            self.opaque
+        ));
+        if stale {
+            challenge.push_str(", stale=true");
+        }
+        response.headers.insert("www-authenticate", challenge);
+    }
+
+    /// Checks the `Digest` credentials of a request, updating the nonce count if valid
+    /// and recorded.
+    pub(super) fn check(&self, req: &Request, token: &str, record: bool) -> Check {
+        let now = req.interactions.now.unwrap_or_else(SystemTime::now);
+        self.check_at(req, token, record, now)
+    }
+
+    /// Records the nonce count of the valid `Digest` credentials of a request
+    /// served by a mock, so that it cannot be used again.
+    pub(crate) fn record(&self, request: &Request, now: SystemTime) {
+        if let Some((scheme, token)) = request
+            .headers
+            .get("authorization")
+            .map(|authorization| split_scheme(authorization))
+        {
+            if scheme.eq_ignore_ascii_case("digest") {
+                self.check_at(request, token, true, now);
+            }
+        }
+    }
+
+    fn check_at(&self, req: &Request, token: &str, record: bool, now: SystemTime) -> Check {
+        let Some(params) = parse_params(token) else {
+            return Check::Invalid;
+        };
+        let param = |name: &str| params.get(name).map(String::as_str);
+        let algorithm = param("algorithm").unwrap_or("MD5");
+        let uri = param("uri").unwrap_or_default();
+        if param("username") != Some(self.user.as_str())
+            || param("realm") != Some(self.realm.as_str())
+            || param("opaque").is_some_and(|opaque| opaque != self.opaque)
+            || !algorithm.eq_ignore_ascii_case(self.algorithm.as_str())
+            || (uri != &req.url[url::Position::BeforePath..] && uri != req.url.as_str())
+        {
+            return Check::Invalid;
+        }
+        let (Some(nonce), Some(response)) = (param("nonce"), param("response")) else {
+            return Check::Invalid;
+        };
+        let qop = match param("qop") {
+            Some("auth") => Some(Qop::Auth),
+            Some("auth-int") => Some(Qop::AuthInt),
+            Some(_) => return Check::Invalid,
+            None => None,
+        };
+        let count = match qop {
+            Some(qop) if self.qop.contains(&qop) => {
+                match param("nc").and_then(|nc| u32::from_str_radix(nc, 16).ok()) {
+                    Some(count) if param("cnonce").is_some() => Some(count),
+                    _ => return Check::Invalid,
+                }
+            }
+            None if self.qop.is_empty() => None,
+            _ => return Check::Invalid,
+        };
+        let expected = self.response(
+            req.method.as_str(),
+            uri,
+            &req.body,
+            nonce,
+            qop.map(|qop| (qop, param("nc").unwrap(), param("cnonce").unwrap())),
+        );
+        if !response.eq_ignore_ascii_case(&expected) {
+            return Check::Invalid;
+        }
+        let mut nonces = self.nonces.lock().unwrap();
+        match nonces.get_mut(nonce) {
+            Some(issued) if !self.is_expired(issued, now) => match count {
+                Some(count) if count <= issued.count => Check::Invalid,
+                Some(count) => {
+                    if record {
+                        issued.count = count;
+                    }
+                    Check::Valid
+                }
+                None => Check::Valid,
+            },
+            _ => Check::Stale,
+        }
+    }
+
+    /// Returns the expected `response` directive, given the quality of protection,
+    /// nonce count and client nonce, if any.
+    fn response(
+        &self,
+        method: &str,
+        uri: &str,
+        body: &Body,
+        nonce: &str,
+        qop: Option<(Qop, &str, &str)>,
+    ) -> String {
+        let hash = |parts: &[&str]| self.algorithm.hash([parts.join(":")]);
+        let ha1 = hash(&[&self.user, &self.realm, &self.password]);
+        let ha2 = match qop {
+            Some((Qop::AuthInt, ..)) => hash(&[method, uri, &self.algorithm.hash(body.iter())]),
+            _ => hash(&[method, uri]),
+        };
+        match qop {
+            Some((qop, nc, cnonce)) => hash(&[&ha1, nonce, nc, cnonce, qop.as_str(), &ha2]),
+            None => hash(&[&ha1, nonce, &ha2]),
+        }
+    }
+
+    fn key(&self) -> (&str, &str, &str, DigestAlgorithm, &[Qop], Duration) {
+        (
+            &self.realm,
+            &self.user,
+            &self.password,
+            self.algorithm,
+            &self.qop,
+            self.nonce_lifetime,
+        )
+    }
+}
+
+impl PartialEq for Digest {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key() && Arc::ptr_eq(&self.nonces, &other.nonces)
+    }
+}
+
+impl PartialOrd for Digest {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.key().partial_cmp(&other.key()) {
+            Some(Ordering::Equal) => {
+                Some(Arc::as_ptr(&self.nonces).cmp(&Arc::as_ptr(&other.nonces)))
+            }
+            ordering => ordering,
+        }
+    }
+}
+
+/// Parses the comma-separated `name=value` parameters of a token,
+/// with lowercase names and unquoted values.
+fn parse_params(token: &str) -> Option<HashMap<String, String>> {
+    let mut params = HashMap::new();
+    let mut chars = token.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace() || *c == ',').is_some() {}
+        if chars.peek().is_none() {
+            return Some(params);
+        }
+        let mut name = String::new();
+        while let Some(c) = chars.next_if(|c| *c != '=') {
+            name.push(c);
+        }
+        chars.next()?;
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut value = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next()? {
+                    '"' => break,
+                    '\\' => value.push(chars.next()?),
+                    c => value.push(c),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                value.push(c);
+            }
+        }
+        params.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digest_response() {
+        // The examples of RFC 7616 section 3.9.1
+        let nonce = "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v";
+        let qop = Some((
+            Qop::Auth,
+            "00000001",
+            "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ",
+        ));
+        let digest = Digest::new("http-auth@example.org", "Mufasa", "Circle of Life");
+        assert_eq!(
+            digest.response("GET", "/dir/index.html", &Body::empty(), nonce, qop),
+            "8ca523f5e9506fed4657c9700eebdbec"
+        );
+        let digest = digest.algorithm(DigestAlgorithm::Sha256);
+        assert_eq!(
+            digest.response("GET", "/dir/index.html", &Body::empty(), nonce, qop),
+            "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1"
+        );
+    }
+
+    #[test]
+    fn test_parse_params() {
+        let params =
+            parse_params(r#"username="Mufasa", realm="a \"b\", c", nc=00000001, qop=auth"#)
+                .unwrap();
+        assert_eq!(params["username"], "Mufasa");
+        assert_eq!(params["realm"], "a \"b\", c");
+        assert_eq!(params["nc"], "00000001");
+        assert_eq!(params["qop"], "auth");
+        assert!(parse_params(r#"username="Mufasa"#).is_none());
+    }
+}
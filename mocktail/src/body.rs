@@ -4,6 +4,7 @@ use std::{collections::vec_deque, convert::Infallible, pin::Pin, task::Poll};
 use bytes::{Buf, Bytes};
 use futures::Stream;
 use http_body::Frame;
+use sha2::{Digest, Sha256};
 
 use crate::ext::MessageExt;
 
@@ -79,6 +80,30 @@ impl Body {
         self.bufs.as_bytes()
     }
 
+    /// Returns the SHA-256 digest of the body, hashing each chunk in turn.
+    pub fn sha256(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        for chunk in self.bufs.iter() {
+            hasher.update(chunk);
+        }
+        hasher.finalize().into()
+    }
+
+    /// Returns the length-prefixed gRPC messages of the body,
+    /// including the prefix; an incomplete trailing message is skipped.
+    pub fn grpc_messages(&self) -> Vec<Bytes> {
+        let mut bytes = self.bufs.clone().as_bytes();
+        let mut messages = Vec::new();
+        while bytes.len() >= 5 {
+            let len = u32::from_be_bytes(bytes[1..5].try_into().unwrap()) as usize;
+            if bytes.len() < 5 + len {
+                break;
+            }
+            messages.push(bytes.split_to(5 + len));
+        }
+        messages
+    }
+
     pub fn iter(&self) -> vec_deque::Iter<'_, Bytes> {
         self.bufs.iter()
     }
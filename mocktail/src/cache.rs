@@ -0,0 +1,234 @@
+//! Response caching
+use std::time::{Duration, SystemTime};
+
+use crate::{
+    body::Body,
+    headers::HeaderValue,
+    request::{Method, Request},
+    response::Response,
+    status::StatusCode,
+};
+
+/// A declarative cache policy of a response, set with
+/// [`Then::cache`](crate::mock_builder::Then::cache).
+///
+/// Sets the `Cache-Control`, `ETag`, `Last-Modified` and `Expires` headers
+/// of a response, unless set explicitly, and answers conditional `GET` and
+/// `HEAD` requests of successful responses with `304 Not Modified`: requests
+/// with an `If-None-Match` header matching the `ETag`, or else with an
+/// `If-Modified-Since` header not before the `Last-Modified` date.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CachePolicy {
+    max_age: Option<Duration>,
+    shared_max_age: Option<Duration>,
+    visibility: Option<&'static str>,
+    no_cache: bool,
+    no_store: bool,
+    must_revalidate: bool,
+    immutable: bool,
+    etag: Option<ETag>,
+    last_modified: Option<SystemTime>,
+    expires: bool,
+}
+
+/// How the `ETag` of a [`CachePolicy`] is set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ETag {
+    /// Derived from the SHA-256 digest of the body.
+    Digest { weak: bool },
+    /// A fixed entity tag, e.g. `"v1"`.
+    Value(String),
+}
+
+impl CachePolicy {
+    /// Creates a policy without directives.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `max-age` directive, in seconds.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// `s-maxage` directive, in seconds, for shared caches.
+    pub fn shared_max_age(mut self, max_age: Duration) -> Self {
+        self.shared_max_age = Some(max_age);
+        self
+    }
+
+    /// `public` directive.
+    pub fn public(mut self) -> Self {
+        self.visibility = Some("public");
+        self
+    }
+
+    /// `private` directive.
+    pub fn private(mut self) -> Self {
+        self.visibility = Some("private");
+        self
+    }
+
+    /// `no-cache` directive.
+    pub fn no_cache(mut self) -> Self {
+        self.no_cache = true;
+        self
+    }
+
+    /// `no-store` directive.
+    pub fn no_store(mut self) -> Self {
+        self.no_store = true;
+        self
+    }
+
+    /// `must-revalidate` directive.
+    pub fn must_revalidate(mut self) -> Self {
+        self.must_revalidate = true;
+        self
+    }
+
+    /// `immutable` directive.
+    pub fn immutable(mut self) -> Self {
+        self.immutable = true;
+        self
+    }
+
+    /// Strong `ETag` derived from the body.
+    pub fn etag(mut self) -> Self {
+        self.etag = Some(ETag::Digest { weak: false });
+        self
+    }
+
+    /// Weak `ETag` derived from the body.
+    pub fn weak_etag(mut self) -> Self {
+        self.etag = Some(ETag::Digest { weak: true });
+        self
+    }
+
+    /// Fixed `ETag`, quoted if needed, e.g. `v1` or `W/"v1"`.
+    pub fn etag_value(mut self, etag: impl Into<String>) -> Self {
+        let etag = etag.into();
+        let etag = if etag.ends_with('"') {
+            etag
+        } else {
+            format!("\"{etag}\"")
+        };
+        self.etag = Some(ETag::Value(etag));
+        self
+    }
+
+    /// `Last-Modified` header.
+    pub fn last_modified(mut self, time: SystemTime) -> Self {
+        self.last_modified = Some(time);
+        self
+    }
+
+    /// `Expires` header, the HTTP date `max-age` after the response
+    /// is sent according to the server clock.
+    pub fn expires(mut self) -> Self {
+        self.expires = true;
+        self
+    }
+
+    /// Returns the `Cache-Control` header value, if any directive is set.
+    pub fn cache_control(&self) -> Option<String> {
+        let mut directives = Vec::new();
+        directives.extend(self.visibility.map(String::from));
+        if self.no_cache {
+            directives.push("no-cache".into());
+        }
+        if self.no_store {
+            directives.push("no-store".into());
+        }
+        if let Some(max_age) = self.max_age {
+            directives.push(format!("max-age={}", max_age.as_secs()));
+        }
+        if let Some(max_age) = self.shared_max_age {
+            directives.push(format!("s-maxage={}", max_age.as_secs()));
+        }
+        if self.must_revalidate {
+            directives.push("must-revalidate".into());
+        }
+        if self.immutable {
+            directives.push("immutable".into());
+        }
+        (!directives.is_empty()).then(|| directives.join(", "))
+    }
+
+    /// Returns the `ETag` of a body, if set.
+    fn etag_of(&self, body: &Body) -> Option<String> {
+        match self.etag.as_ref()? {
+            ETag::Digest { weak } => {
+                let digest = body.sha256()[..16]
+                    .iter()
+                    .map(|byte| format!("{byte:02x}"))
+                    .collect::<String>();
+                Some(match weak {
+                    true => format!("W/\"{digest}\""),
+                    false => format!("\"{digest}\""),
+                })
+            }
+            ETag::Value(etag) => Some(etag.clone()),
+        }
+    }
+
+    /// Sets the caching headers of a response, answering the
+    /// request with `304 Not Modified` if it is not modified.
+    pub(crate) fn apply(&self, response: &mut Response, request: &Request, now: SystemTime) {
+        let etag = self.etag_of(&response.body);
+        let headers = &mut response.headers;
+        let mut set = |name: &str, value: Option<String>| {
+            if let Some(value) = value.filter(|_| !headers.contains_name(name)) {
+                headers.insert(name, value);
+            }
+        };
+        set("cache-control", self.cache_control());
+        set("etag", etag);
+        set(
+            "last-modified",
+            self.last_modified.map(httpdate::fmt_http_date),
+        );
+        set(
+            "expires",
+            self.max_age
+                .filter(|_| self.expires)
+                .map(|max_age| httpdate::fmt_http_date(now + max_age)),
+        );
+
+        if !response.status.is_success() || !matches!(request.method, Method::GET | Method::HEAD) {
+            return;
+        }
+        if not_modified(request, response) {
+            response.status = StatusCode::NOT_MODIFIED;
+            response.body = Body::empty();
+            response.headers.remove("content-length");
+        }
+    }
+}
+
+/// Returns true if a conditional request is not modified, given the
+/// `ETag` and `Last-Modified` headers of the response.
+fn not_modified(request: &Request, response: &Response) -> bool {
+    if let Some(tags) = request.headers.get("if-none-match") {
+        // Entity tags are compared with the weak comparison
+        let etag = response.headers.get("etag").map(|etag| weak(etag));
+        return tags
+            .split(',')
+            .map(str::trim)
+            .any(|tag| tag == "*" || etag.is_some_and(|etag| weak(tag) == etag));
+    }
+    let date =
+        |value: Option<&HeaderValue>| value.and_then(|value| httpdate::parse_http_date(value).ok());
+    match (
+        date(request.headers.get("if-modified-since")),
+        date(response.headers.get("last-modified")),
+    ) {
+        (Some(since), Some(modified)) => modified <= since,
+        _ => false,
+    }
+}
+
+fn weak(etag: &str) -> &str {
+    etag.strip_prefix("W/").unwrap_or(etag)
+}
@@ -0,0 +1,177 @@
+//! Response compression
+use std::io::Write;
+
+use bytes::{BufMut, Bytes, BytesMut};
+use flate2::{
+    write::{GzEncoder, ZlibEncoder},
+    Compression as Level,
+};
+
+/// The default compression level.
+const DEFAULT_LEVEL: u32 = 6;
+
+/// Response compression settings.
+///
+/// Set for a server with
+/// [`MockServerConfigBuilder::compression`](crate::server::MockServerConfigBuilder::compression),
+/// and overridden for a mock with [`Then::compression`](crate::mock_builder::Then::compression).
+///
+/// HTTP response bodies are compressed with the first encoding accepted by the
+/// client's `Accept-Encoding` header, and gRPC messages with the first encoding
+/// accepted by its `grpc-accept-encoding` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Compression {
+    pub(crate) encodings: Vec<Encoding>,
+    pub(crate) min_size: usize,
+    pub(crate) level: u32,
+}
+
+impl Compression {
+    /// Creates settings with gzip and deflate enabled, no minimum size
+    /// and the default level.
+    pub fn new() -> Self {
+        Self {
+            encodings: vec![Encoding::Gzip, Encoding::Deflate],
+            min_size: 0,
+            level: DEFAULT_LEVEL,
+        }
+    }
+
+    /// Creates settings with compression disabled.
+    pub fn none() -> Self {
+        Self {
+            encodings: Vec::new(),
+            ..Self::new()
+        }
+    }
+
+    /// Sets the enabled encodings, in order of preference.
+    pub fn encodings(mut self, encodings: impl IntoIterator<Item = Encoding>) -> Self {
+        self.encodings = encodings.into_iter().collect();
+        self
+    }
+
+    /// Sets the minimum size of bodies and messages to compress.
+    pub fn min_size(mut self, size: usize) -> Self {
+        self.min_size = size;
+        self
+    }
+
+    /// Sets the compression level, from 0 (none) to 9 (best).
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Returns the first enabled encoding accepted by an `Accept-Encoding`
+    /// or `grpc-accept-encoding` header value.
+    pub(crate) fn negotiate(&self, accept: &str) -> Option<Encoding> {
+        let accepted = accept
+            .split(',')
+            .filter_map(|value| {
+                let mut parts = value.split(';');
+                let name = parts.next()?.trim();
+                let rejected = parts.any(|param| {
+                    param
+                        .trim()
+                        .strip_prefix("q=")
+                        .and_then(|q| q.parse::<f32>().ok())
+                        == Some(0.0)
+                });
+                (!rejected).then_some(name)
+            })
+            .collect::<Vec<_>>();
+        self.encodings.iter().copied().find(|encoding| {
+            accepted
+                .iter()
+                .any(|name| *name == "*" || name.eq_ignore_ascii_case(encoding.as_str()))
+        })
+    }
+
+    /// Compresses data with an encoding.
+    pub(crate) fn compress(&self, encoding: Encoding, data: &[u8]) -> Bytes {
+        let level = Level::new(self.level);
+        let writer = BytesMut::new().writer();
+        let buf = match encoding {
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(writer, level);
+                encoder.write_all(data).unwrap();
+                encoder.finish().unwrap()
+            }
+            Encoding::Deflate => {
+                let mut encoder = ZlibEncoder::new(writer, level);
+                encoder.write_all(data).unwrap();
+                encoder.finish().unwrap()
+            }
+        };
+        buf.into_inner().freeze()
+    }
+
+    /// Compresses a length-prefixed gRPC message, if it is at least the minimum size.
+    pub(crate) fn compress_grpc_message(&self, encoding: Encoding, message: Bytes) -> Bytes {
+        let data = &message[5..];
+        if message[0] != 0 || data.len() < self.min_size {
+            return message;
+        }
+        let data = self.compress(encoding, data);
+        let mut buf = BytesMut::with_capacity(5 + data.len());
+        buf.put_u8(1);
+        buf.put_u32(data.len() as u32);
+        buf.put(data);
+        buf.freeze()
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A compression encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// `gzip`
+    Gzip,
+    /// `deflate`, zlib-wrapped as specified by HTTP.
+    Deflate,
+}
+
+impl Encoding {
+    /// Returns the encoding name used in headers.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use super::*;
+
+    #[test]
+    fn test_negotiate() {
+        let compression = Compression::new();
+        assert_eq!(compression.negotiate("gzip, br"), Some(Encoding::Gzip));
+        assert_eq!(
+            compression.negotiate("gzip;q=0, deflate"),
+            Some(Encoding::Deflate)
+        );
+        assert_eq!(compression.negotiate("*"), Some(Encoding::Gzip));
+        assert_eq!(compression.negotiate("identity"), None);
+        assert_eq!(Compression::none().negotiate("gzip"), None);
+
+        let compressed = compression.compress(Encoding::Gzip, b"hello");
+        let mut data = String::new();
+        GzDecoder::new(&compressed[..])
+            .read_to_string(&mut data)
+            .unwrap();
+        assert_eq!(data, "hello");
+    }
+}
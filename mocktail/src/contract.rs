@@ -0,0 +1,581 @@
+//! Contract verification
+//!
+//! Verifies a provider against the interactions its consumers expect, by
+//! sending the request of each interaction to a real server and comparing its
+//! response to the expected response, e.g. in a provider test:
+//!
+//! ```no_run
+//! # use mocktail::contract::{Contract, Verifier};
+//! # async fn seed(state: &str) {}
+//! # #[cfg(feature = "client")]
+//! # async fn f(server_url: url::Url) -> Result<(), mocktail::Error> {
+//! let contract = Contract::from_pact_file("pacts/web-users.json")?;
+//! let report = Verifier::to_url(server_url)
+//!     .provider_states(|state| async move { seed(&state).await })
+//!     .verify(&contract)
+//!     .await;
+//! assert!(report.is_ok(), "{report}");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Contracts are loaded from Pact files, or from recorded [`Snapshot`]s.
+//! Responses match if the status is equal, every expected header is sent with
+//! the expected value and the body matches the expected body, if any. As with
+//! Pact, JSON objects may have keys not in the expected body, other values
+//! must be equal; bodies are represented as in snapshots.
+use std::{collections::BTreeMap, fmt, path::Path, sync::Arc};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures::future::BoxFuture;
+use serde_json::Value;
+use url::Url;
+
+use crate::{
+    body::Body,
+    delegate::Delegate,
+    headers::Headers,
+    request::{Method, Request},
+    snapshot::{body_value, value_bytes, Snapshot},
+    Error,
+};
+
+type StateFn = dyn Fn(String) -> BoxFuture<'static, ()> + Send + Sync;
+
+/// The interactions a consumer expects of a provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contract {
+    interactions: Vec<Interaction>,
+}
+
+/// An interaction of a [`Contract`]: a request and the expected response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interaction {
+    pub description: String,
+    /// The provider states the interaction expects, see [`Verifier::provider_states`].
+    pub states: Vec<String>,
+    pub method: Method,
+    pub path: String,
+    pub query: Option<String>,
+    pub headers: BTreeMap<String, String>,
+    pub body: Value,
+    pub response: ExpectedResponse,
+}
+
+/// The expected response of an [`Interaction`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpectedResponse {
+    pub status: u16,
+    pub headers: BTreeMap<String, String>,
+    /// The expected body, not compared if null.
+    pub body: Value,
+}
+
+impl Contract {
+    /// Creates a contract with interactions.
+    pub fn new(interactions: impl IntoIterator<Item = Interaction>) -> Self {
+        Self {
+            interactions: interactions.into_iter().collect(),
+        }
+    }
+
+    /// Loads a contract from a Pact file.
+    pub fn from_pact_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let json = std::fs::read_to_string(path)?;
+        Self::from_pact_json(&json)
+    }
+
+    /// Loads a contract from Pact JSON.
+    ///
+    /// Pact specification versions 2 to 4 are supported; in version 4,
+    /// only synchronous HTTP interactions are verified.
+    pub fn from_pact_json(json: &str) -> Result<Self, Error> {
+        let pact: Value = serde_json::from_str(json)?;
+        let invalid = |err: &str| Error::Invalid(format!("pact: {err}"));
+        let v4 = pact
+            .pointer("/metadata/pactSpecification/version")
+            .and_then(Value::as_str)
+            .is_some_and(|version| version.starts_with('4'));
+        let interactions = pact
+            .get("interactions")
+            .and_then(Value::as_array)
+            .ok_or_else(|| invalid("missing `interactions` array"))?;
+        let mut contract = Vec::new();
+        for interaction in interactions {
+            if v4
+                && interaction
+                    .get("type")
+                    .and_then(Value::as_str)
+                    .is_some_and(|kind| kind != "Synchronous/HTTP")
+            {
+                continue;
+            }
+            let description = interaction
+                .get("description")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            let invalid = |err: &str| invalid(&format!("interaction `{description}`: {err}"));
+            let request = interaction
+                .get("request")
+                .ok_or_else(|| invalid("missing `request`"))?;
+            let response = interaction
+                .get("response")
+                .ok_or_else(|| invalid("missing `response`"))?;
+            let method = request
+                .get("method")
+                .and_then(Value::as_str)
+                .ok_or_else(|| invalid("missing request `method`"))?
+                .to_uppercase()
+                .parse()
+                .map_err(|err: String| invalid(&err))?;
+            let status = response
+                .get("status")
+                .and_then(Value::as_u64)
+                .and_then(|status| u16::try_from(status).ok())
+                .ok_or_else(|| invalid("missing response `status`"))?;
+            contract.push(Interaction {
+                states: pact_states(interaction),
+                method,
+                path: request
+                    .get("path")
+                    .and_then(Value::as_str)
+                    .unwrap_or("/")
+                    .to_string(),
+                query: request.get("query").and_then(pact_query),
+                headers: pact_headers(request),
+                body: pact_body(request, v4),
+                response: ExpectedResponse {
+                    status,
+                    headers: pact_headers(response),
+                    body: pact_body(response, v4),
+                },
+                description,
+            });
+        }
+        Ok(Self::new(contract))
+    }
+
+    /// Creates a contract from the interactions of a snapshot that matched a mock.
+    pub fn from_snapshot(snapshot: &Snapshot) -> Result<Self, Error> {
+        let mut interactions = Vec::new();
+        for entry in snapshot.entries() {
+            let Some(response) = &entry.response else {
+                continue;
+            };
+            interactions.push(Interaction {
+                description: format!("{} {}", entry.method, entry.path),
+                states: Vec::new(),
+                method: entry.method.parse().map_err(Error::Invalid)?,
+                path: entry.path.clone(),
+                query: entry.query.clone(),
+                headers: entry.headers.clone(),
+                body: entry.body.clone(),
+                response: ExpectedResponse {
+                    status: response.status,
+                    headers: BTreeMap::new(),
+                    body: response.body.clone(),
+                },
+            });
+        }
+        Ok(Self::new(interactions))
+    }
+
+    /// Returns the interactions.
+    pub fn interactions(&self) -> &[Interaction] {
+        &self.interactions
+    }
+}
+
+/// Returns the provider states of a Pact interaction.
+fn pact_states(interaction: &Value) -> Vec<String> {
+    if let Some(state) = interaction.get("providerState").and_then(Value::as_str) {
+        return vec![state.to_string()];
+    }
+    interaction
+        .get("providerStates")
+        .and_then(Value::as_array)
+        .map(|states| {
+            states
+                .iter()
+                .filter_map(|state| Some(state.get("name")?.as_str()?.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns a Pact query, a string or values by key.
+fn pact_query(query: &Value) -> Option<String> {
+    match query {
+        Value::String(query) => Some(query.clone()),
+        Value::Object(params) => {
+            let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+            for (key, values) in params {
+                match values {
+                    Value::Array(values) => {
+                        for value in values.iter().filter_map(Value::as_str) {
+                            serializer.append_pair(key, value);
+                        }
+                    }
+                    Value::String(value) => {
+                        serializer.append_pair(key, value);
+                    }
+                    _ => {}
+                }
+            }
+            Some(serializer.finish()).filter(|query| !query.is_empty())
+        }
+        _ => None,
+    }
+}
+
+/// Returns the headers of a Pact request or response, joining multiple values.
+fn pact_headers(message: &Value) -> BTreeMap<String, String> {
+    let Some(headers) = message.get("headers").and_then(Value::as_object) else {
+        return BTreeMap::new();
+    };
+    headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                Value::Array(values) => values
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                _ => return None,
+            };
+            Some((name.to_lowercase(), value))
+        })
+        .collect()
+}
+
+/// Returns the body of a Pact request or response.
+///
+/// Version 4 bodies are objects with the `content`, possibly base64 `encoded`.
+fn pact_body(message: &Value, v4: bool) -> Value {
+    let body = message.get("body").cloned().unwrap_or_default();
+    if !v4 {
+        return body;
+    }
+    let content = body.get("content").cloned().unwrap_or_default();
+    match (&content, body.get("encoded")) {
+        (Value::String(encoded), Some(Value::String(_) | Value::Bool(true))) => {
+            match STANDARD.decode(encoded) {
+                Ok(bytes) => body_value(&Body::bytes(bytes)),
+                Err(_) => content,
+            }
+        }
+        _ => content,
+    }
+}
+
+/// Verifies a provider against contracts.
+#[derive(Clone)]
+pub struct Verifier {
+    delegate: Delegate,
+    states: Option<Arc<StateFn>>,
+}
+
+impl Verifier {
+    /// Creates a verifier sending requests with a delegate.
+    pub fn new(delegate: Delegate) -> Self {
+        Self {
+            delegate,
+            states: None,
+        }
+    }
+
+    /// Creates a verifier sending requests to a server, joining
+    /// the request path and query to the base url, see [`Delegate::url`].
+    #[cfg(feature = "client")]
+    pub fn to_url(base_url: Url) -> Self {
+        Self::new(Delegate::url(base_url))
+    }
+
+    /// Sets up the provider states of each interaction, in order,
+    /// before its request is sent.
+    pub fn provider_states<F, Fut>(mut self, f: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        self.states = Some(Arc::new(move |state| Box::pin(f(state))));
+        self
+    }
+
+    /// Verifies the interactions of a contract, in order.
+    pub async fn verify(&self, contract: &Contract) -> ContractReport {
+        let mut results = Vec::new();
+        for (index, interaction) in contract.interactions.iter().enumerate() {
+            if let Some(states) = &self.states {
+                for state in &interaction.states {
+                    states(state.clone()).await;
+                }
+            }
+            let mut url = format!("http://localhost{}", interaction.path);
+            if let Some(query) = &interaction.query {
+                url = format!("{url}?{query}");
+            }
+            let url = match Url::parse(&url) {
+                Ok(url) => url,
+                Err(err) => {
+                    results.push(ContractResult::invalid(index, interaction, err));
+                    continue;
+                }
+            };
+            let mut headers = interaction
+                .headers
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect::<Headers>();
+            if matches!(interaction.body, Value::Object(_) | Value::Array(_))
+                && !headers.contains_name("content-type")
+            {
+                headers.insert("content-type", "application/json");
+            }
+            let request = Request::new(interaction.method.clone(), url)
+                .with_headers(headers)
+                .with_body(Body::bytes(value_bytes(&interaction.body)));
+            let actual = self.delegate.call(&request).await;
+
+            let expected = &interaction.response;
+            let header_mismatches = expected
+                .headers
+                .iter()
+                .filter(|(name, value)| {
+                    actual
+                        .headers()
+                        .get(name)
+                        .is_none_or(|actual| actual.trim() != value.trim())
+                })
+                .map(|(name, _)| name.clone())
+                .collect();
+            let mut actual_body = body_value(actual.body());
+            if let (Value::Null, Some(message)) = (&actual_body, actual.message()) {
+                actual_body = Value::String(message.to_string());
+            }
+            results.push(ContractResult {
+                index,
+                description: interaction.description.clone(),
+                method: interaction.method.to_string(),
+                path: interaction.path.clone(),
+                expected_status: expected.status,
+                actual_status: actual.status().as_u16(),
+                header_mismatches,
+                body_matches: expected.body.is_null() || contains(&expected.body, &actual_body),
+                expected_body: expected.body.clone(),
+                actual_body,
+            });
+        }
+        ContractReport { results }
+    }
+}
+
+impl fmt::Debug for Verifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Verifier")
+            .field("delegate", &self.delegate)
+            .field("provider_states", &self.states.is_some())
+            .finish()
+    }
+}
+
+/// Returns true if a value matches an expected value, where
+/// objects may have keys not in the expected object.
+fn contains(expected: &Value, actual: &Value) -> bool {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            expected.iter().all(|(key, expected)| {
+                actual
+                    .get(key)
+                    .is_some_and(|actual| contains(expected, actual))
+            })
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            expected.len() == actual.len()
+                && expected
+                    .iter()
+                    .zip(actual)
+                    .all(|(expected, actual)| contains(expected, actual))
+        }
+        (expected, actual) => expected == actual,
+    }
+}
+
+/// The result of a verified interaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractResult {
+    /// Index of the interaction in the contract.
+    pub index: usize,
+    pub description: String,
+    pub method: String,
+    pub path: String,
+    /// Status of the expected response.
+    pub expected_status: u16,
+    /// Status of the server response.
+    pub actual_status: u16,
+    /// Expected headers missing from the server response or with another value.
+    pub header_mismatches: Vec<String>,
+    /// Body of the expected response.
+    pub expected_body: Value,
+    /// Body of the server response.
+    pub actual_body: Value,
+    /// Whether the body matches the expected body.
+    pub body_matches: bool,
+}
+
+impl ContractResult {
+    /// Returns a result for an interaction whose request could not be sent.
+    fn invalid(index: usize, interaction: &Interaction, err: impl fmt::Display) -> Self {
+        Self {
+            index,
+            description: interaction.description.clone(),
+            method: interaction.method.to_string(),
+            path: interaction.path.clone(),
+            expected_status: interaction.response.status,
+            actual_status: 0,
+            header_mismatches: Vec::new(),
+            expected_body: interaction.response.body.clone(),
+            actual_body: Value::String(format!("invalid request: {err}")),
+            body_matches: false,
+        }
+    }
+
+    /// Returns true if the server response matches the expected response.
+    pub fn is_match(&self) -> bool {
+        self.expected_status == self.actual_status
+            && self.header_mismatches.is_empty()
+            && self.body_matches
+    }
+}
+
+impl fmt::Display for ContractResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} ({} {})",
+            self.index, self.description, self.method, self.path
+        )?;
+        if self.expected_status != self.actual_status {
+            write!(
+                f,
+                "\n  status: expected {}, got {}",
+                self.expected_status, self.actual_status
+            )?;
+        }
+        if !self.header_mismatches.is_empty() {
+            write!(f, "\n  headers: {}", self.header_mismatches.join(", "))?;
+        }
+        if !self.body_matches {
+            write!(
+                f,
+                "\n  body: expected {}, got {}",
+                self.expected_body, self.actual_body
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The results of a contract verification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractReport {
+    results: Vec<ContractResult>,
+}
+
+impl ContractReport {
+    /// Returns the results of all verified interactions.
+    pub fn results(&self) -> &[ContractResult] {
+        &self.results
+    }
+
+    /// Returns the results where the server response differs from the expected response.
+    pub fn failures(&self) -> impl Iterator<Item = &ContractResult> {
+        self.results.iter().filter(|result| !result.is_match())
+    }
+
+    /// Returns true if all server responses match the expected responses.
+    pub fn is_ok(&self) -> bool {
+        self.failures().next().is_none()
+    }
+}
+
+impl fmt::Display for ContractReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failures = self.failures().collect::<Vec<_>>();
+        write!(
+            f,
+            "verified {} interactions, {} failed",
+            self.results.len(),
+            failures.len()
+        )?;
+        for failure in failures {
+            write!(f, "\n{failure}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_pact_json() {
+        let v2 = r#"{
+            "interactions": [{
+                "description": "a user",
+                "providerState": "user 1 exists",
+                "request": { "method": "get", "path": "/users/1", "query": "verbose=true" },
+                "response": { "status": 200, "headers": { "Content-Type": "application/json" }, "body": { "id": 1 } }
+            }]
+        }"#;
+        let contract = Contract::from_pact_json(v2).unwrap();
+        let interaction = &contract.interactions()[0];
+        assert_eq!(interaction.states, ["user 1 exists"]);
+        assert_eq!(interaction.method, Method::GET);
+        assert_eq!(interaction.query.as_deref(), Some("verbose=true"));
+        assert_eq!(
+            interaction.response.headers["content-type"],
+            "application/json"
+        );
+
+        let v4 = r#"{
+            "metadata": { "pactSpecification": { "version": "4.0" } },
+            "interactions": [
+                { "type": "Asynchronous/Messages", "description": "an event" },
+                {
+                    "type": "Synchronous/HTTP",
+                    "description": "a report",
+                    "providerStates": [{ "name": "reports exist" }],
+                    "request": { "method": "GET", "path": "/reports", "query": { "tag": ["a", "b"] } },
+                    "response": { "status": 200, "body": { "content": "aGk=", "encoded": "base64" } }
+                }
+            ]
+        }"#;
+        let contract = Contract::from_pact_json(v4).unwrap();
+        assert_eq!(contract.interactions().len(), 1);
+        let interaction = &contract.interactions()[0];
+        assert_eq!(interaction.states, ["reports exist"]);
+        assert_eq!(interaction.query.as_deref(), Some("tag=a&tag=b"));
+        assert_eq!(interaction.response.body, "hi");
+
+        assert!(Contract::from_pact_json(r#"{"interactions": [{}]}"#).is_err());
+    }
+
+    #[test]
+    fn test_contains() {
+        let expected = serde_json::json!({"id": 1, "tags": [{"name": "a"}]});
+        assert!(contains(
+            &expected,
+            &serde_json::json!({"id": 1, "name": "dan", "tags": [{"name": "a", "id": 2}]})
+        ));
+        assert!(!contains(
+            &expected,
+            &serde_json::json!({"id": 1, "tags": [{"name": "a"}, {"name": "b"}]})
+        ));
+        assert!(!contains(&expected, &serde_json::json!({"id": 2})));
+    }
+}
@@ -0,0 +1,140 @@
+//! Response delegation
+use std::{fmt, sync::Arc};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+#[cfg(any(feature = "client", feature = "tower"))]
+use http_body_util::BodyExt;
+use http_body_util::Full;
+#[cfg(feature = "client")]
+use hyper_util::{
+    client::legacy::{connect::HttpConnector, Client},
+    rt::TokioExecutor,
+};
+use tracing::debug;
+#[cfg(feature = "client")]
+use url::Url;
+
+use crate::{request::Request, response::Response, status::StatusCode};
+
+/// Connection-specific headers not forwarded from delegate responses.
+const HOP_BY_HOP_HEADERS: &[&str] = &["connection", "keep-alive", "transfer-encoding", "upgrade"];
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+type DelegateFn = dyn Fn(http::Request<Full<Bytes>>) -> BoxFuture<'static, Result<http::Response<Bytes>, BoxError>>
+    + Send
+    + Sync;
+
+/// A responder the request is handed off to, e.g. another server.
+///
+/// The request is matched and recorded in the journal as usual;
+/// the delegate's response is sent in place of the mock response.
+/// Delegate failures are answered with `502 Bad Gateway`.
+#[derive(Clone)]
+pub struct Delegate(Arc<DelegateFn>);
+
+impl Delegate {
+    /// Creates a delegate forwarding requests to a server, joining
+    /// the request path and query to the base url.
+    ///
+    /// Only `http` urls are supported; requests are forwarded with HTTP/1.1.
+    #[cfg(feature = "client")]
+    pub fn url(base_url: Url) -> Self {
+        Self(Arc::new(move |mut req: http::Request<Full<Bytes>>| {
+            let base_url = base_url.clone();
+            Box::pin(async move {
+                let path_and_query = req
+                    .uri()
+                    .path_and_query()
+                    .map(|v| v.as_str())
+                    .unwrap_or("/");
+                let url = base_url.join(path_and_query.trim_start_matches('/'))?;
+                *req.uri_mut() = url.as_str().parse()?;
+                req.headers_mut().remove(http::header::HOST);
+                let client: Client<HttpConnector, Full<Bytes>> =
+                    Client::builder(TokioExecutor::new()).build_http();
+                let response = client.request(req).await?;
+                let (parts, body) = response.into_parts();
+                let body = body.collect().await?.to_bytes();
+                Ok(http::Response::from_parts(parts, body))
+            })
+        }))
+    }
+
+    /// Creates a delegate calling a function.
+    pub fn from_fn<F, Fut>(f: F) -> Self
+    where
+        F: Fn(http::Request<Full<Bytes>>) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<http::Response<Bytes>, BoxError>> + Send + 'static,
+    {
+        Self(Arc::new(move |req| Box::pin(f(req))))
+    }
+
+    /// Creates a delegate calling a tower service.
+    #[cfg(feature = "tower")]
+    pub fn service<S, B>(service: S) -> Self
+    where
+        S: tower_service::Service<http::Request<Full<Bytes>>, Response = http::Response<B>>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send + 'static,
+        B: http_body::Body<Data = Bytes> + Send + 'static,
+        B::Error: Into<BoxError>,
+    {
+        Self(Arc::new(move |req| {
+            let mut service = service.clone();
+            Box::pin(async move {
+                std::future::poll_fn(|cx| service.poll_ready(cx))
+                    .await
+                    .map_err(Into::into)?;
+                let response = service.call(req).await.map_err(Into::into)?;
+                let (parts, body) = response.into_parts();
+                let body = body.collect().await.map_err(Into::into)?.to_bytes();
+                Ok(http::Response::from_parts(parts, body))
+            })
+        }))
+    }
+
+    /// Hands off the request, returning the delegate response.
+    pub async fn call(&self, request: &Request) -> Response {
+        let mut req = http::Request::builder()
+            .method(http::Method::from(request.method().clone()))
+            .uri(request.url().as_str())
+            .body(Full::new(request.body().clone().as_bytes()))
+            .unwrap();
+        *req.headers_mut() = request.headers().clone().into();
+        match (self.0)(req).await {
+            Ok(response) => {
+                let (mut parts, body) = response.into_parts();
+                for name in HOP_BY_HOP_HEADERS {
+                    parts.headers.remove(*name);
+                }
+                Response::new(body)
+                    .with_status(parts.status)
+                    .with_headers(parts.headers.into())
+            }
+            Err(err) => {
+                debug!(%err, "delegate failed");
+                Response::default()
+                    .with_status(StatusCode::BAD_GATEWAY)
+                    .with_message(format!("delegate failed: {err}"))
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Delegate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Delegate")
+    }
+}
+
+impl PartialEq for Delegate {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
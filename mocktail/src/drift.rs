@@ -0,0 +1,272 @@
+//! Spec drift
+//!
+//! Mocks defined in code drift from the API they mock as it evolves. A
+//! [`Spec`] reports the mocks of a set matching paths, methods or body
+//! fields the API no longer defines, e.g. in a test:
+//!
+//! ```no_run
+//! # use mocktail::{openapi::OpenApi, MockSet};
+//! # fn f(mocks: MockSet) -> Result<(), mocktail::Error> {
+//! let drift = mocks.check_drift(&OpenApi::from_file("openapi.json")?);
+//! assert!(drift.is_empty(), "{drift:?}");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! OpenAPI documents are checked with [`OpenApi`](crate::openapi::OpenApi),
+//! gRPC services with [`Descriptors`]. Mocks are checked against the whole
+//! spec, so each set should mock a single API.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::Path,
+};
+
+use bytes::Bytes;
+use prost::Message;
+use prost_types::{field_descriptor_proto::Type, DescriptorProto, FileDescriptorSet};
+use uuid::Uuid;
+
+use crate::{
+    body::Body,
+    matchers::{self, RoutePath},
+    mock::Mock,
+    mock_set::MockSet,
+    request::Method,
+    wire, Error,
+};
+
+/// An API definition mocks are checked against.
+pub trait Spec {
+    /// Returns how a mock drifted from the spec.
+    fn check(&self, mock: &Mock) -> Vec<DriftKind>;
+}
+
+/// A mock drifted from a spec, reported by [`MockSet::check_drift`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Drift {
+    /// ID of the mock.
+    pub mock_id: Uuid,
+    pub kind: DriftKind,
+}
+
+/// The kind of [`Drift`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DriftKind {
+    /// No operation or gRPC method of the spec has the path.
+    UnknownPath(String),
+    /// The path has no operation with the method.
+    UnknownMethod(Method, String),
+    /// No gRPC service of the spec has the name.
+    UnknownService(String),
+    /// A body field the spec does not define, as a dotted path, e.g.
+    /// `request.user.nickname`, `response.users[].id` or `request.7`
+    /// for protobuf fields by number.
+    UnknownField(String),
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let id = self.mock_id;
+        match &self.kind {
+            DriftKind::UnknownPath(path) => {
+                write!(f, "mock {id} matches path `{path}` not defined by the spec")
+            }
+            DriftKind::UnknownMethod(method, path) => {
+                write!(
+                    f,
+                    "mock {id} matches method {method} not defined for path `{path}`"
+                )
+            }
+            DriftKind::UnknownService(service) => {
+                write!(
+                    f,
+                    "mock {id} matches service `{service}` not defined by the spec"
+                )
+            }
+            DriftKind::UnknownField(field) => {
+                write!(f, "mock {id} has field `{field}` not defined by the spec")
+            }
+        }
+    }
+}
+
+impl MockSet {
+    /// Returns the mocks matching paths, methods or body fields
+    /// a spec does not define, see [`drift`](crate::drift).
+    pub fn check_drift(&self, spec: &impl Spec) -> Vec<Drift> {
+        self.iter()
+            .flat_map(|mock| {
+                spec.check(mock).into_iter().map(|kind| Drift {
+                    mock_id: mock.id,
+                    kind,
+                })
+            })
+            .collect()
+    }
+}
+
+/// The services and messages of protobuf descriptors.
+///
+/// Mocks are checked by their gRPC path, e.g. `/pkg.Service/Method`, or
+/// service, and their protobuf request and response messages. Fields of
+/// types missing from the descriptors, e.g. well-known types, are not checked.
+#[derive(Debug, Clone, Default)]
+pub struct Descriptors {
+    /// Methods by full service name, with their input and output types.
+    services: BTreeMap<String, BTreeMap<String, (String, String)>>,
+    /// Fields by full message name.
+    messages: BTreeMap<String, BTreeMap<u32, Field>>,
+}
+
+#[derive(Debug, Clone)]
+struct Field {
+    name: String,
+    /// The full name of the type, for message fields.
+    message: Option<String>,
+}
+
+impl Descriptors {
+    /// Loads descriptors from a file with an encoded `FileDescriptorSet`,
+    /// e.g. written by `protoc --descriptor_set_out`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Loads descriptors from an encoded `FileDescriptorSet`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let set = FileDescriptorSet::decode(bytes)
+            .map_err(|err| Error::Invalid(format!("descriptors: {err}")))?;
+        let mut descriptors = Self::default();
+        for file in &set.file {
+            let prefix = match file.package() {
+                "" => String::new(),
+                package => format!("{package}."),
+            };
+            for service in &file.service {
+                let methods = service
+                    .method
+                    .iter()
+                    .map(|method| {
+                        let types = (
+                            type_name(method.input_type()),
+                            type_name(method.output_type()),
+                        );
+                        (method.name().to_string(), types)
+                    })
+                    .collect();
+                descriptors
+                    .services
+                    .insert(format!("{prefix}{}", service.name()), methods);
+            }
+            for message in &file.message_type {
+                descriptors.insert_message(&prefix, message);
+            }
+        }
+        Ok(descriptors)
+    }
+
+    /// Returns the full names of the services.
+    pub fn services(&self) -> impl Iterator<Item = &str> {
+        self.services.keys().map(String::as_str)
+    }
+
+    fn insert_message(&mut self, prefix: &str, message: &DescriptorProto) {
+        let name = format!("{prefix}{}", message.name());
+        for nested in &message.nested_type {
+            self.insert_message(&format!("{name}."), nested);
+        }
+        let fields = message
+            .field
+            .iter()
+            .map(|field| {
+                let message =
+                    (field.r#type() == Type::Message).then(|| type_name(field.type_name()));
+                let name = field.name().to_string();
+                (field.number() as u32, Field { name, message })
+            })
+            .collect();
+        self.messages.insert(name, fields);
+    }
+
+    /// Adds the fields of an encoded message not defined by its type
+    /// to `unknown`, as dotted paths from `path`.
+    fn unknown_fields(
+        &self,
+        message: &str,
+        buf: &[u8],
+        path: &str,
+        unknown: &mut BTreeSet<String>,
+    ) {
+        let (Some(fields), Some(values)) = (self.messages.get(message), wire::fields(buf)) else {
+            return;
+        };
+        for (number, bytes) in values {
+            match fields.get(&number) {
+                Some(field) => {
+                    if let (Some(message), Some(bytes)) = (&field.message, bytes) {
+                        let path = format!("{path}.{}", field.name);
+                        self.unknown_fields(message, &bytes, &path, unknown);
+                    }
+                }
+                None => {
+                    unknown.insert(format!("{path}.{number}"));
+                }
+            }
+        }
+    }
+}
+
+impl Spec for Descriptors {
+    fn check(&self, mock: &Mock) -> Vec<DriftKind> {
+        let route = matchers::route(&mock.matchers);
+        let mut drift = Vec::new();
+        if let Some(service) = &route.grpc_service {
+            if !self.services.contains_key(service) {
+                drift.push(DriftKind::UnknownService(service.clone()));
+            }
+        }
+        let Some(RoutePath::Exact(path)) = &route.path else {
+            return drift;
+        };
+        let Some((service, method)) = matchers::grpc_method(path) else {
+            drift.push(DriftKind::UnknownPath(path.clone()));
+            return drift;
+        };
+        let Some(methods) = self.services.get(service) else {
+            // The service of the path may be reported by its service matcher
+            let kind = DriftKind::UnknownService(service.to_string());
+            if !drift.contains(&kind) {
+                drift.push(kind);
+            }
+            return drift;
+        };
+        let Some((input, output)) = methods.get(method) else {
+            drift.push(DriftKind::UnknownPath(path.clone()));
+            return drift;
+        };
+        let mut unknown = BTreeSet::new();
+        for message in route.bodies.iter().flat_map(pb_messages) {
+            self.unknown_fields(input, &message, "request", &mut unknown);
+        }
+        for message in pb_messages(&mock.response.body) {
+            self.unknown_fields(output, &message, "response", &mut unknown);
+        }
+        drift.extend(unknown.into_iter().map(DriftKind::UnknownField));
+        drift
+    }
+}
+
+/// Returns the uncompressed gRPC messages of a body, without their prefix.
+fn pb_messages(body: &Body) -> Vec<Bytes> {
+    body.grpc_messages()
+        .into_iter()
+        .filter(|message| message[0] == 0)
+        .map(|message| message.slice(5..))
+        .collect()
+}
+
+/// Returns the full name of a type without the leading `.`.
+fn type_name(name: &str) -> String {
+    name.trim_start_matches('.').to_string()
+}
@@ -0,0 +1,253 @@
+//! Fake data
+use rand::{rngs::SmallRng, seq::IndexedRandom, Rng, SeedableRng};
+
+const FIRST_NAMES: &[&str] = &[
+    "Ada", "Alan", "Alice", "Ben", "Carla", "Chen", "Dan", "Diana", "Elena", "Emeka", "Farah",
+    "Grace", "Hiro", "Ines", "Jon", "Kai", "Lena", "Marco", "Nia", "Omar", "Priya", "Quinn",
+    "Rosa", "Sam", "Tariq", "Uma", "Victor", "Wen", "Yara", "Zoe",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Adams", "Baker", "Clark", "Diaz", "Evans", "Fischer", "Garcia", "Hughes", "Ito", "Jensen",
+    "Kim", "Lopez", "Martin", "Nguyen", "Okafor", "Patel", "Quinn", "Rossi", "Smith", "Tanaka",
+    "Usman", "Vargas", "Walker", "Xu", "Young", "Zhang",
+];
+
+const STREETS: &[&str] = &[
+    "Main St",
+    "Oak Ave",
+    "Maple Dr",
+    "Cedar Ln",
+    "Park Rd",
+    "Elm St",
+    "Lake View",
+    "Hill St",
+    "River Rd",
+    "Station Rd",
+];
+
+const CITIES: &[&str] = &[
+    "Springfield",
+    "Riverside",
+    "Fairview",
+    "Greenville",
+    "Franklin",
+    "Madison",
+    "Georgetown",
+    "Clinton",
+    "Salem",
+    "Bristol",
+];
+
+const COUNTRIES: &[&str] = &[
+    "Australia",
+    "Brazil",
+    "Canada",
+    "France",
+    "Germany",
+    "India",
+    "Japan",
+    "Kenya",
+    "Mexico",
+    "United Kingdom",
+    "United States",
+];
+
+const COMPANY_SUFFIXES: &[&str] = &["Inc", "LLC", "Group", "Labs", "Systems", "Partners"];
+
+const DOMAINS: &[&str] = &["example.com", "example.org", "example.net"];
+
+const WORDS: &[&str] = &[
+    "alpha", "bright", "cloud", "delta", "echo", "field", "green", "harbor", "iron", "jade",
+    "kite", "lumen", "meadow", "north", "orbit", "pixel", "quartz", "river", "stone", "tide",
+    "union", "vector", "willow", "zenith",
+];
+
+/// The kinds of fake values available from templates via `fake(kind)`.
+pub const KINDS: &[&str] = &[
+    "first_name",
+    "last_name",
+    "name",
+    "username",
+    "email",
+    "phone",
+    "street_address",
+    "city",
+    "country",
+    "address",
+    "company",
+    "word",
+    "sentence",
+];
+
+/// A fake data generator.
+///
+/// Use [`Faker::seeded`] for deterministic values.
+#[derive(Debug, Clone)]
+pub struct Faker<R: Rng = SmallRng> {
+    rng: R,
+}
+
+impl Faker<SmallRng> {
+    /// Creates a generator seeded from the operating system.
+    pub fn new() -> Self {
+        Self::with_rng(SmallRng::from_os_rng())
+    }
+
+    /// Creates a generator with a seed, producing the same values for the same seed.
+    pub fn seeded(seed: u64) -> Self {
+        Self::with_rng(SmallRng::seed_from_u64(seed))
+    }
+}
+
+impl Default for Faker<SmallRng> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: Rng> Faker<R> {
+    /// Creates a generator using the random number generator.
+    pub fn with_rng(rng: R) -> Self {
+        Self { rng }
+    }
+
+    fn pick(&mut self, values: &[&'static str]) -> &'static str {
+        values.choose(&mut self.rng).unwrap()
+    }
+
+    /// Returns a first name.
+    pub fn first_name(&mut self) -> String {
+        self.pick(FIRST_NAMES).into()
+    }
+
+    /// Returns a last name.
+    pub fn last_name(&mut self) -> String {
+        self.pick(LAST_NAMES).into()
+    }
+
+    /// Returns a full name.
+    pub fn name(&mut self) -> String {
+        format!("{} {}", self.first_name(), self.last_name())
+    }
+
+    /// Returns a username.
+    pub fn username(&mut self) -> String {
+        let n = self.rng.random_range(1..1000);
+        format!(
+            "{}{}{n}",
+            self.first_name().to_lowercase(),
+            self.last_name().to_lowercase()
+        )
+    }
+
+    /// Returns an email address at a reserved example domain.
+    pub fn email(&mut self) -> String {
+        let domain = self.pick(DOMAINS);
+        format!(
+            "{}.{}@{domain}",
+            self.first_name().to_lowercase(),
+            self.last_name().to_lowercase()
+        )
+    }
+
+    /// Returns a phone number in the reserved `555-01xx` range.
+    pub fn phone(&mut self) -> String {
+        let area = self.rng.random_range(200..1000);
+        let line = self.rng.random_range(100..200);
+        format!("+1-{area}-555-0{line}")
+    }
+
+    /// Returns a street address.
+    pub fn street_address(&mut self) -> String {
+        let number = self.rng.random_range(1..10000);
+        format!("{number} {}", self.pick(STREETS))
+    }
+
+    /// Returns a city.
+    pub fn city(&mut self) -> String {
+        self.pick(CITIES).into()
+    }
+
+    /// Returns a country.
+    pub fn country(&mut self) -> String {
+        self.pick(COUNTRIES).into()
+    }
+
+    /// Returns a full address.
+    pub fn address(&mut self) -> String {
+        format!(
+            "{}, {}, {}",
+            self.street_address(),
+            self.city(),
+            self.country()
+        )
+    }
+
+    /// Returns a company name.
+    pub fn company(&mut self) -> String {
+        format!("{} {}", self.last_name(), self.pick(COMPANY_SUFFIXES))
+    }
+
+    /// Returns a word.
+    pub fn word(&mut self) -> String {
+        self.pick(WORDS).into()
+    }
+
+    /// Returns a sentence of 4 to 10 words.
+    pub fn sentence(&mut self) -> String {
+        let n = self.rng.random_range(4..=10);
+        let mut sentence = (0..n).map(|_| self.word()).collect::<Vec<_>>().join(" ");
+        sentence[..1].make_ascii_uppercase();
+        sentence.push('.');
+        sentence
+    }
+
+    /// Returns a value by kind, one of [`KINDS`].
+    pub fn kind(&mut self, kind: &str) -> Option<String> {
+        let value = match kind {
+            "first_name" => self.first_name(),
+            "last_name" => self.last_name(),
+            "name" => self.name(),
+            "username" => self.username(),
+            "email" => self.email(),
+            "phone" => self.phone(),
+            "street_address" => self.street_address(),
+            "city" => self.city(),
+            "country" => self.country(),
+            "address" => self.address(),
+            "company" => self.company(),
+            "word" => self.word(),
+            "sentence" => self.sentence(),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// Returns `n` values built by `f`, e.g. a list of JSON objects
+    /// for a response body.
+    pub fn list<T, F>(&mut self, n: usize, mut f: F) -> Vec<T>
+    where
+        F: FnMut(&mut Self) -> T,
+    {
+        (0..n).map(|_| f(self)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded() {
+        let people =
+            |seed| Faker::seeded(seed).list(3, |fake| (fake.name(), fake.email(), fake.address()));
+        assert_eq!(people(7), people(7));
+        assert_eq!(people(7).len(), 3);
+        assert!(people(7)[0].1.contains("@example."));
+        for kind in KINDS {
+            assert!(Faker::seeded(1).kind(kind).is_some(), "{kind}");
+        }
+        assert!(Faker::seeded(1).kind("nope").is_none());
+    }
+}
@@ -112,10 +112,17 @@ impl From<Headers> for http::HeaderMap {
 
 impl From<http::HeaderMap> for Headers {
     fn from(value: http::HeaderMap) -> Self {
+        // Repeated values of a header are yielded without a name
+        let mut last: Option<HeaderName> = None;
         Self(
             value
                 .into_iter()
-                .map(|(name, value)| (name.unwrap().into(), value.into()))
+                .filter_map(|(name, value)| {
+                    if let Some(name) = name {
+                        last = Some(name.into());
+                    }
+                    Some((last.clone()?, value.into()))
+                })
                 .collect::<Vec<_>>(),
         )
     }
@@ -0,0 +1,28 @@
+//! Prior interactions
+use std::{collections::HashMap, time::SystemTime};
+
+use uuid::Uuid;
+
+/// A snapshot of prior interactions with a mock server,
+/// taken when a request is matched.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Interactions {
+    /// Match counts by mock ID.
+    pub hits: HashMap<Uuid, usize>,
+    /// Counter values by name.
+    pub counters: HashMap<String, i64>,
+    /// Time of the snapshot, by the server clock.
+    pub now: Option<SystemTime>,
+}
+
+impl Interactions {
+    /// Returns the number of times the mock has been matched.
+    pub fn hits(&self, mock_id: &Uuid) -> usize {
+        self.hits.get(mock_id).copied().unwrap_or_default()
+    }
+
+    /// Returns the counter value, defaulting to zero.
+    pub fn counter(&self, name: &str) -> i64 {
+        self.counters.get(name).copied().unwrap_or_default()
+    }
+}
@@ -0,0 +1,641 @@
+//! Request journal
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    body::Body,
+    request::{ConnectionInfo, Method, Request, Transfer},
+    Error,
+};
+
+/// A record of requests received by a mock server.
+#[derive(Default, Debug, Clone)]
+pub struct Journal {
+    entries: VecDeque<JournalEntry>,
+    capacity: Option<usize>,
+    file: Option<PathBuf>,
+}
+
+impl Journal {
+    /// Creates an empty, unbounded journal.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty journal holding at most `capacity` entries.
+    pub fn bounded(capacity: usize) -> Self {
+        Self {
+            capacity: Some(capacity),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the maximum number of entries, if bounded.
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    /// Sets the maximum number of entries, evicting the oldest
+    /// entries if it is exceeded.
+    pub fn set_capacity(&mut self, capacity: Option<usize>) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Records a request and the ID of the mock it matched, if any.
+    ///
+    /// If the journal is full, the oldest entry is evicted.
+    pub fn record(&mut self, request: Request, mock_id: Option<Uuid>) {
+        self.record_at(request, mock_id, SystemTime::now());
+    }
+
+    /// Records a request at a time.
+    pub fn record_at(&mut self, request: Request, mock_id: Option<Uuid>, timestamp: SystemTime) {
+        self.push(JournalEntry {
+            request,
+            mock_id,
+            timestamp,
+            labels: BTreeMap::new(),
+            path_params: BTreeMap::new(),
+            variant: None,
+            protocol_error: None,
+            cancelled: None,
+        });
+    }
+
+    /// Marks the last entry of a request matched to a mock as cancelled by
+    /// the client, returning it. Returns `None` if the entry was not found,
+    /// e.g. if evicted.
+    pub(crate) fn cancel(
+        &mut self,
+        mock_id: Uuid,
+        request: &Request,
+        after: Duration,
+    ) -> Option<JournalEntry> {
+        let entry = self.entries.iter_mut().rev().find(|entry| {
+            entry.mock_id == Some(mock_id) && entry.cancelled.is_none() && entry.request == *request
+        });
+        entry.map(|entry| {
+            entry.cancelled = Some(after);
+            entry.clone()
+        })
+    }
+
+    /// Records an entry.
+    pub(crate) fn push(&mut self, entry: JournalEntry) {
+        if let Some(path) = &self.file {
+            if let Err(err) = append(path, &entry) {
+                warn!(
+                    "failed to persist journal entry to {}: {err}",
+                    path.display()
+                );
+            }
+        }
+        self.entries.push_back(entry);
+        self.evict();
+    }
+
+    /// Serializes the entries as JSON lines, oldest entry first.
+    ///
+    /// Bodies are base64-encoded.
+    pub fn to_jsonl(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| serde_json::to_string(&EntryRecord::from(entry)).unwrap() + "\n")
+            .collect()
+    }
+
+    /// Deserializes a journal from JSON lines, see [`Journal::to_jsonl`].
+    pub fn from_jsonl(jsonl: &str) -> Result<Self, Error> {
+        let mut journal = Self::new();
+        for line in jsonl.lines().filter(|line| !line.trim().is_empty()) {
+            let record: EntryRecord = serde_json::from_str(line)?;
+            journal.entries.push_back(record.try_into()?);
+        }
+        Ok(journal)
+    }
+
+    /// Loads a journal from a JSON lines file, see [`Journal::to_jsonl`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_jsonl(&std::fs::read_to_string(path)?)
+    }
+
+    /// Writes the entries to a JSON lines file, see [`Journal::to_jsonl`].
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        Ok(std::fs::write(path, self.to_jsonl())?)
+    }
+
+    /// Persists the journal to a JSON lines file: entries already in the file
+    /// are loaded, and entries recorded from now on are appended to it.
+    ///
+    /// Clearing or evicting entries does not remove them from the file.
+    pub fn persist(&mut self, path: impl Into<PathBuf>) -> Result<(), Error> {
+        let path = path.into();
+        if path.exists() {
+            let loaded = Self::load(&path)?;
+            self.entries.extend(loaded.entries);
+            self.evict();
+        }
+        self.file = Some(path);
+        Ok(())
+    }
+
+    fn evict(&mut self) {
+        if let Some(capacity) = self.capacity {
+            while self.entries.len() > capacity {
+                self.entries.pop_front();
+            }
+        }
+    }
+
+    /// Retains only the entries specified by the predicate.
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&JournalEntry) -> bool,
+    {
+        self.entries.retain(f)
+    }
+
+    /// Clears the journal.
+    pub fn clear(&mut self) {
+        self.entries.clear()
+    }
+
+    /// Returns an iterator over the journal, oldest entry first.
+    pub fn iter(&self) -> std::collections::vec_deque::Iter<'_, JournalEntry> {
+        self.entries.iter()
+    }
+
+    /// Returns the entries that did not match a mock.
+    pub fn unmatched(&self) -> impl Iterator<Item = &JournalEntry> {
+        self.entries.iter().filter(|entry| entry.mock_id.is_none())
+    }
+
+    /// Returns the trace IDs of well-formed `traceparent` headers, in order.
+    pub fn trace_ids(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter_map(|entry| entry.request.traceparent())
+            .map(|ctx| ctx.trace_id)
+            .collect()
+    }
+
+    /// Returns the entries without a well-formed `traceparent` header.
+    pub fn missing_traceparent(&self) -> impl Iterator<Item = &JournalEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.request.traceparent().is_none())
+    }
+
+    /// Returns the entries matching the query.
+    pub fn query<'a>(&'a self, query: &'a JournalQuery) -> impl Iterator<Item = &'a JournalEntry> {
+        self.entries.iter().filter(|entry| query.matches(entry))
+    }
+
+    /// Returns the number of entries matching the query.
+    pub fn count(&self, query: &JournalQuery) -> usize {
+        self.query(query).count()
+    }
+
+    /// Returns the number of entries served by each weighted response
+    /// variant of a mock, by variant index.
+    pub fn variant_counts(&self, mock_id: &Uuid) -> BTreeMap<usize, usize> {
+        let mut counts = BTreeMap::new();
+        for entry in &self.entries {
+            if let Some(variant) = entry
+                .variant
+                .filter(|_| entry.mock_id.as_ref() == Some(mock_id))
+            {
+                *counts.entry(variant).or_default() += 1;
+            }
+        }
+        counts
+    }
+}
+
+impl IntoIterator for Journal {
+    type Item = JournalEntry;
+
+    type IntoIter = std::collections::vec_deque::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl FromIterator<JournalEntry> for Journal {
+    fn from_iter<I: IntoIterator<Item = JournalEntry>>(iter: I) -> Self {
+        Self {
+            entries: iter.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A query over journal entries.
+///
+/// An empty query matches all entries; each filter narrows it.
+#[derive(Default, Debug, Clone)]
+pub struct JournalQuery {
+    method: Option<Method>,
+    path: Option<Regex>,
+    since: Option<SystemTime>,
+    until: Option<SystemTime>,
+    mock_id: Option<Option<Uuid>>,
+    trace_id: Option<String>,
+    request_id: Option<String>,
+    path_params: Vec<(String, String)>,
+    variant: Option<usize>,
+}
+
+impl JournalQuery {
+    /// Creates an empty query.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Matches entries with the method.
+    pub fn method(mut self, method: Method) -> Self {
+        self.method = Some(method);
+        self
+    }
+
+    /// Matches entries with a path matching the regex.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the regex is invalid.
+    pub fn path_regex(mut self, regex: &str) -> Self {
+        self.path = Some(Regex::new(regex).expect("invalid path regex"));
+        self
+    }
+
+    /// Matches entries recorded at or after the time.
+    pub fn since(mut self, time: SystemTime) -> Self {
+        self.since = Some(time);
+        self
+    }
+
+    /// Matches entries recorded before the time.
+    pub fn until(mut self, time: SystemTime) -> Self {
+        self.until = Some(time);
+        self
+    }
+
+    /// Matches entries that matched the mock.
+    pub fn mock_id(mut self, id: Uuid) -> Self {
+        self.mock_id = Some(Some(id));
+        self
+    }
+
+    /// Matches entries that did not match a mock.
+    pub fn unmatched(mut self) -> Self {
+        self.mock_id = Some(None);
+        self
+    }
+
+    /// Matches entries with a well-formed `traceparent` header with the trace ID.
+    pub fn trace_id(mut self, trace_id: impl Into<String>) -> Self {
+        self.trace_id = Some(trace_id.into());
+        self
+    }
+
+    /// Matches entries with the `x-request-id` header.
+    pub fn request_id(mut self, id: impl Into<String>) -> Self {
+        self.request_id = Some(id.into());
+        self
+    }
+
+    /// Matches entries with the path parameter, e.g. `path_param("id", "42")`.
+    pub fn path_param(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.path_params.push((name.into(), value.into()));
+        self
+    }
+
+    /// Matches entries served by the weighted response variant at the index.
+    pub fn variant(mut self, index: usize) -> Self {
+        self.variant = Some(index);
+        self
+    }
+
+    /// Returns true if the entry matches the query.
+    pub fn matches(&self, entry: &JournalEntry) -> bool {
+        self.method
+            .as_ref()
+            .is_none_or(|method| entry.request.method() == method)
+            && self
+                .path
+                .as_ref()
+                .is_none_or(|regex| regex.is_match(entry.request.path()))
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp < until)
+            && self.mock_id.is_none_or(|id| entry.mock_id == id)
+            && self.trace_id.as_ref().is_none_or(|trace_id| {
+                entry
+                    .request
+                    .traceparent()
+                    .is_some_and(|ctx| ctx.trace_id == *trace_id)
+            })
+            && self
+                .request_id
+                .as_ref()
+                .is_none_or(|id| entry.request.request_id() == Some(id))
+            && self
+                .path_params
+                .iter()
+                .all(|(name, value)| entry.path_param(name) == Some(value.as_str()))
+            && self
+                .variant
+                .is_none_or(|variant| entry.variant == Some(variant))
+    }
+}
+
+/// Appends an entry to a JSON lines file.
+fn append(path: &Path, entry: &JournalEntry) -> Result<(), Error> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    let line = serde_json::to_string(&EntryRecord::from(entry))? + "\n";
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// A persisted journal entry.
+#[derive(Debug, Serialize, Deserialize)]
+struct EntryRecord {
+    timestamp: SystemTime,
+    method: String,
+    url: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    headers: Vec<(String, String)>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    body: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    namespace: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    session: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    transfer: Option<Transfer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    protocol: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    connection_info: Option<ConnectionInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mock_id: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    labels: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    path_params: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    variant: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    protocol_error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cancelled: Option<Duration>,
+}
+
+impl From<&JournalEntry> for EntryRecord {
+    fn from(entry: &JournalEntry) -> Self {
+        let request = &entry.request;
+        Self {
+            timestamp: entry.timestamp,
+            method: request.method.to_string(),
+            url: request.url.to_string(),
+            headers: request
+                .headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: STANDARD.encode(request.body.clone().as_bytes()),
+            namespace: request.namespace.clone(),
+            session: request.session.clone(),
+            transfer: request.transfer,
+            protocol: request.protocol.clone(),
+            connection_info: request.connection_info.clone(),
+            mock_id: entry.mock_id.map(|id| id.to_string()),
+            labels: entry.labels.clone(),
+            path_params: entry.path_params.clone(),
+            variant: entry.variant,
+            protocol_error: entry.protocol_error.clone(),
+            cancelled: entry.cancelled,
+        }
+    }
+}
+
+impl TryFrom<EntryRecord> for JournalEntry {
+    type Error = Error;
+
+    fn try_from(record: EntryRecord) -> Result<Self, Self::Error> {
+        let method = record.method.parse::<Method>().map_err(Error::Invalid)?;
+        let url = record
+            .url
+            .parse()
+            .map_err(|err| Error::Invalid(format!("invalid journal url: {err}")))?;
+        let body = STANDARD
+            .decode(&record.body)
+            .map_err(|err| Error::Invalid(format!("invalid journal body: {err}")))?;
+        let mock_id = record
+            .mock_id
+            .map(|id| Uuid::parse_str(&id))
+            .transpose()
+            .map_err(|err| Error::Invalid(format!("invalid journal mock id: {err}")))?;
+        let mut request = Request::new(method, url)
+            .with_headers(record.headers.into_iter().collect())
+            .with_body(Body::bytes(body));
+        request.namespace = record.namespace;
+        request.session = record.session;
+        request.transfer = record.transfer;
+        request.protocol = record.protocol;
+        request.connection_info = record.connection_info;
+        Ok(Self {
+            request,
+            mock_id,
+            timestamp: record.timestamp,
+            labels: record.labels,
+            path_params: record.path_params,
+            variant: record.variant,
+            protocol_error: record.protocol_error,
+            cancelled: record.cancelled,
+        })
+    }
+}
+
+/// A journaled request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalEntry {
+    /// The request.
+    pub request: Request,
+    /// ID of the matched mock.
+    pub mock_id: Option<Uuid>,
+    /// Time the request was recorded.
+    pub timestamp: SystemTime,
+    /// Labels of the matched mock.
+    pub labels: BTreeMap<String, String>,
+    /// Path parameters extracted by the path template of the matched mock.
+    pub path_params: BTreeMap<String, String>,
+    /// Index of the weighted response variant served, in the order the variants were added.
+    pub variant: Option<usize>,
+    /// The protocol error the request was rejected with, e.g. for an HTTP/1
+    /// request sent to a gRPC server.
+    pub protocol_error: Option<String>,
+    /// Time from receiving the request head to the client cancelling the
+    /// request, e.g. by resetting its stream.
+    pub cancelled: Option<Duration>,
+}
+
+impl JournalEntry {
+    /// Returns the request.
+    pub fn request(&self) -> &Request {
+        &self.request
+    }
+
+    /// Returns the ID of the matched mock.
+    pub fn mock_id(&self) -> Option<&Uuid> {
+        self.mock_id.as_ref()
+    }
+
+    /// Returns the labels of the matched mock.
+    pub fn labels(&self) -> &BTreeMap<String, String> {
+        &self.labels
+    }
+
+    /// Returns the path parameters extracted by the path template of the matched mock.
+    pub fn path_params(&self) -> &BTreeMap<String, String> {
+        &self.path_params
+    }
+
+    /// Returns a path parameter.
+    pub fn path_param(&self, name: &str) -> Option<&str> {
+        self.path_params.get(name).map(String::as_str)
+    }
+
+    /// Returns a path parameter parsed as a type, e.g. `entry.path_param_as::<u64>("id")`.
+    ///
+    /// Returns `None` if the parameter is missing or cannot be parsed.
+    pub fn path_param_as<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.path_param(name)?.parse().ok()
+    }
+
+    /// Returns the index of the weighted response variant served, if any.
+    pub fn variant(&self) -> Option<usize> {
+        self.variant
+    }
+
+    /// Returns the protocol error the request was rejected with, if any.
+    pub fn protocol_error(&self) -> Option<&str> {
+        self.protocol_error.as_deref()
+    }
+
+    /// Returns true if the client cancelled the request.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.is_some()
+    }
+
+    /// Returns the time from receiving the request head to the client
+    /// cancelling the request, if cancelled.
+    pub fn cancelled_after(&self) -> Option<Duration> {
+        self.cancelled
+    }
+
+    /// Returns the transfer metrics of the request body, if received by a server.
+    pub fn transfer(&self) -> Option<&Transfer> {
+        self.request.transfer()
+    }
+
+    /// Returns true if the request matched a mock.
+    pub fn is_matched(&self) -> bool {
+        self.mock_id.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: Method, path: &str) -> Request {
+        Request::new(method, format!("http://localhost{path}").parse().unwrap())
+    }
+
+    #[test]
+    fn test_bounded() {
+        let mut journal = Journal::bounded(2);
+        journal.record(request(Method::GET, "/1"), None);
+        journal.record(request(Method::GET, "/2"), None);
+        journal.record(request(Method::GET, "/3"), None);
+        let paths = journal
+            .iter()
+            .map(|entry| entry.request.path())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["/2", "/3"]);
+    }
+
+    #[test]
+    fn test_jsonl() {
+        let mut journal = Journal::new();
+        let mut request = request(Method::POST, "/orders?dry_run=true")
+            .with_headers(
+                [("content-type", "application/octet-stream")]
+                    .into_iter()
+                    .collect(),
+            )
+            .with_body(Body::bytes(vec![0, 159, 146, 150]));
+        request.session = Some("abc".into());
+        journal.record(request, Some(Uuid::now_v7()));
+        journal.record(self::request(Method::GET, "/orders"), None);
+
+        let jsonl = journal.to_jsonl();
+        assert_eq!(jsonl.lines().count(), 2);
+        let loaded = Journal::from_jsonl(&jsonl).unwrap();
+        assert_eq!(
+            loaded.iter().collect::<Vec<_>>(),
+            journal.iter().collect::<Vec<_>>()
+        );
+        assert!(Journal::from_jsonl("{").is_err());
+    }
+
+    #[test]
+    fn test_query() {
+        let id = Uuid::now_v7();
+        let mut journal = Journal::new();
+        journal.record(request(Method::GET, "/users/1"), Some(id));
+        journal.record(request(Method::POST, "/users"), None);
+        journal.record(request(Method::GET, "/orders/1"), None);
+
+        assert_eq!(journal.count(&JournalQuery::new()), 3);
+        assert_eq!(journal.count(&JournalQuery::new().method(Method::GET)), 2);
+        assert_eq!(
+            journal.count(&JournalQuery::new().path_regex(r"^/users/\d+$")),
+            1
+        );
+        assert_eq!(journal.count(&JournalQuery::new().mock_id(id)), 1);
+        assert_eq!(
+            journal.count(&JournalQuery::new().method(Method::GET).unmatched()),
+            1
+        );
+        let later = SystemTime::now() + std::time::Duration::from_secs(1);
+        assert_eq!(journal.count(&JournalQuery::new().since(later)), 0);
+        assert_eq!(journal.count(&JournalQuery::new().until(later)), 3);
+    }
+}
@@ -1,35 +1,94 @@
 #![doc = include_str!("../README.md")]
+pub mod auth;
 pub mod body;
+mod cache;
+pub use cache::CachePolicy;
+pub mod clock;
+#[cfg(feature = "compression")]
+mod compression;
+#[cfg(feature = "compression")]
+pub use compression::{Compression, Encoding};
+pub mod contract;
+mod delegate;
+pub mod drift;
+pub use delegate::Delegate;
+pub mod fake;
 mod headers;
 pub use headers::Headers;
+mod interactions;
+pub use interactions::Interactions;
+mod journal;
+pub use journal::{Journal, JournalEntry, JournalQuery};
+mod macros;
 pub mod matchers;
 mod mock;
 pub use mock::Mock;
 pub mod mock_builder;
+pub mod mock_file;
 mod mock_set;
-pub use mock_set::MockSet;
+pub mod multipart;
+pub mod openapi;
+pub use mock_set::{Conflict, ConflictKind, Evaluation, MockDefaults, MockSet};
+pub mod replay;
 mod request;
-pub use request::{Method, Request};
+pub use request::{
+    ConnectionInfo, ExtensionMethod, Method, PathNormalization, Request, TlsInfo, Transfer,
+};
+mod resource;
+pub use resource::{IdGenerator, Resource};
 mod response;
-pub use response::Response;
+pub use response::{Chunking, Delay, Echo, Exhausted, Jitter, Reset, Response};
+#[cfg(feature = "scripting")]
+pub mod script;
 pub mod server;
+mod snapshot;
+#[cfg(feature = "soap")]
+pub mod soap;
+pub use snapshot::{Snapshot, SnapshotEntry, SnapshotResponse, UPDATE_SNAPSHOTS_ENV};
 mod status;
-pub use status::{Code, StatusCode};
+pub use status::{Code, ErrorDetail, StatusCode};
+pub mod template;
+#[cfg(feature = "tls")]
+mod tls;
+#[cfg(feature = "tls")]
+pub use tls::{GeneratedCert, KeyAlgorithm, TlsConfig};
+pub mod trace;
 pub mod prelude {
+    #[cfg(feature = "soap")]
+    pub use crate::soap::SoapVersion;
+    #[cfg(feature = "client")]
+    pub use crate::webhook::Webhook;
     pub use crate::{
+        auth::{Challenge, Credentials},
         body::Body,
         headers::Headers,
+        journal::{Journal, JournalEntry, JournalQuery},
         matchers::*,
         mock::Mock,
-        mock_set::MockSet,
-        request::{Method, Request},
-        response::Response,
-        server::MockServer,
-        status::{Code, StatusCode},
+        mock_set::{MockDefaults, MockSet},
+        multipart::Multipart,
+        request::{ConnectionInfo, Method, PathNormalization, Request, TlsInfo, Transfer},
+        resource::Resource,
+        response::{Echo, Exhausted, Jitter, Response},
+        server::{MockServer, Namespace, ScopedMockServer},
+        status::{Code, ErrorDetail, StatusCode},
+        trace::TraceContext,
+        verify::{request_matching, Attempts, Expectation, Sequence},
     };
 }
 mod ext;
 mod service;
+#[cfg(feature = "tower")]
+pub use service::MockConnector;
+pub use service::{GrpcMockService, HttpMockService, RequestBody};
+pub mod verify;
+#[cfg(feature = "client")]
+mod webhook;
+#[cfg(feature = "client")]
+pub use webhook::Webhook;
+mod wire;
+
+pub use h2::Reason;
 
 /// Represents errors that can occur while serving mocks.
 #[derive(thiserror::Error, Debug)]
@@ -38,8 +97,15 @@ pub enum Error {
     Invalid(String),
     #[error("json error: {0}")]
     JsonError(#[from] serde_json::Error),
+    #[cfg(feature = "yaml")]
+    #[error("yaml error: {0}")]
+    YamlError(#[from] serde_norway::Error),
     #[error("io error: {0}")]
     IoError(#[from] std::io::Error),
     #[error("server error: {0}")]
     ServerError(String),
+    #[error("config error: {0}")]
+    ConfigError(#[from] server::ConfigError),
+    #[error(transparent)]
+    VerificationError(#[from] verify::VerificationError),
 }
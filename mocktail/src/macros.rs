@@ -0,0 +1,40 @@
+//! Macros
+
+/// Builds a [`MockSet`](crate::MockSet) from a compact declarative block
+/// of mocks, each with a method, path, status and optional body.
+///
+/// The body is written as a call to a single-argument [`Then`](crate::mock_builder::Then)
+/// body method, e.g. `json(..)`, `text(..)`, `bytes(..)` or `pb(..)`.
+///
+/// ```
+/// use mocktail::mock;
+/// use serde_json::json;
+///
+/// let mocks = mock! {
+///     GET "/users/1" => 200 json(json!({"id": 1, "name": "dan"}));
+///     POST "/users" => 201 json(json!({"id": 2}));
+///     GET "/health" => 200 text("ok");
+///     DELETE "/users/1" => 204;
+/// };
+/// assert_eq!(mocks.len(), 4);
+/// ```
+///
+/// # Panics
+///
+/// Panics if a status code is invalid.
+#[macro_export]
+macro_rules! mock {
+    ($($method:ident $path:literal => $status:literal $($body_fn:ident($body:expr))?);* $(;)?) => {{
+        let mut mocks = $crate::MockSet::new();
+        $(
+            mocks.mock(|when, then| {
+                when.method($crate::Method::$method).path($path);
+                let then = then.status(
+                    $crate::StatusCode::from_u16($status).expect("invalid status code"),
+                );
+                $(then.$body_fn($body);)?
+            });
+        )*
+        mocks
+    }};
+}
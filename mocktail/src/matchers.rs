@@ -1,8 +1,28 @@
 //! Mock request matchers
-use std::{any::Any, borrow::Cow, cmp::Ordering};
+use std::{
+    any::Any, borrow::Cow, cmp::Ordering, collections::BTreeMap, net::IpAddr, sync::Arc,
+    time::Duration,
+};
+
+use bytes::Bytes;
+use percent_encoding::percent_decode_str;
+use regex::Regex;
+use uuid::Uuid;
 
 use super::{body::Body, headers::Headers, request::Request};
-use crate::request::Method;
+#[cfg(feature = "digest")]
+use crate::auth::Digest;
+#[cfg(feature = "sigv4")]
+use crate::auth::SigV4;
+#[cfg(feature = "soap")]
+use crate::soap;
+use crate::{
+    auth::Credentials,
+    ext::MessageExt,
+    mock_file::{BodyDefinition, RequestDefinition},
+    request::Method,
+    wire,
+};
 
 /// A matcher.
 pub trait Matcher: std::fmt::Debug + Send + Sync + 'static + AsMatcherEq {
@@ -29,6 +49,40 @@ pub fn any() -> AnyMatcher {
     AnyMatcher
 }
 
+/// Any HTTP method matcher.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct AnyMethodMatcher;
+
+impl Matcher for AnyMethodMatcher {
+    fn name(&self) -> &str {
+        "any_method"
+    }
+    fn matches(&self, _req: &Request) -> bool {
+        true
+    }
+}
+
+pub fn any_method() -> AnyMethodMatcher {
+    AnyMethodMatcher
+}
+
+/// Any path matcher.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct AnyPathMatcher;
+
+impl Matcher for AnyPathMatcher {
+    fn name(&self) -> &str {
+        "any_path"
+    }
+    fn matches(&self, _req: &Request) -> bool {
+        true
+    }
+}
+
+pub fn any_path() -> AnyPathMatcher {
+    AnyPathMatcher
+}
+
 /// HTTP method matcher.
 #[derive(Debug, PartialEq, PartialOrd)]
 pub struct MethodMatcher(Method);
@@ -46,6 +100,23 @@ pub fn method(method: Method) -> MethodMatcher {
     MethodMatcher(method)
 }
 
+/// HTTP method any matcher, matches any of a set of methods.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct MethodAnyMatcher(Vec<Method>);
+
+impl Matcher for MethodAnyMatcher {
+    fn name(&self) -> &str {
+        "method_any"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        self.0.contains(&req.method)
+    }
+}
+
+pub fn method_any(methods: impl IntoIterator<Item = impl Into<Method>>) -> MethodAnyMatcher {
+    MethodAnyMatcher(methods.into_iter().map(Into::into).collect())
+}
+
 /// Path matcher.
 #[derive(Debug, PartialEq, PartialOrd)]
 pub struct PathMatcher(String);
@@ -55,7 +126,7 @@ impl Matcher for PathMatcher {
         "path"
     }
     fn matches(&self, req: &Request) -> bool {
-        req.path() == self.0
+        req.normalized_path() == req.path_normalization.normalize(&self.0)
     }
 }
 
@@ -72,7 +143,8 @@ impl Matcher for PathPrefixMatcher {
         "path_prefix"
     }
     fn matches(&self, req: &Request) -> bool {
-        req.path().starts_with(&self.0)
+        req.normalized_path()
+            .starts_with(req.path_normalization.normalize(&self.0).as_ref())
     }
 }
 
@@ -80,6 +152,52 @@ pub fn path_prefix(prefix: impl Into<String>) -> PathPrefixMatcher {
     PathPrefixMatcher(prefix.into())
 }
 
+/// Path template matcher, matches a path with `{name}` parameter segments,
+/// e.g. `/orders/{id}`.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct PathTemplateMatcher(String);
+
+impl PathTemplateMatcher {
+    /// Returns the parameters of the request path, if it matches the template.
+    pub fn params(&self, req: &Request) -> Option<BTreeMap<String, String>> {
+        let template = req.path_normalization.normalize(&self.0);
+        let path = req.normalized_path();
+        let (template, path) = (template.split('/'), path.split('/'));
+        if template.clone().count() != path.clone().count() {
+            return None;
+        }
+        let mut params = BTreeMap::new();
+        for (segment, value) in template.zip(path) {
+            match segment
+                .strip_prefix('{')
+                .and_then(|name| name.strip_suffix('}'))
+            {
+                Some(_) if value.is_empty() => return None,
+                Some(name) => {
+                    let value = percent_decode_str(value).decode_utf8_lossy();
+                    params.insert(name.to_string(), value.into_owned());
+                }
+                None if segment != value => return None,
+                None => {}
+            }
+        }
+        Some(params)
+    }
+}
+
+impl Matcher for PathTemplateMatcher {
+    fn name(&self) -> &str {
+        "path_template"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        self.params(req).is_some()
+    }
+}
+
+pub fn path_template(template: impl Into<String>) -> PathTemplateMatcher {
+    PathTemplateMatcher(template.into())
+}
+
 /// Body matcher.
 #[derive(Debug, PartialEq, PartialOrd)]
 pub struct BodyMatcher(Body);
@@ -97,6 +215,92 @@ pub fn body(body: Body) -> BodyMatcher {
     BodyMatcher(body)
 }
 
+/// Body prefix matcher, matches a body starting with the prefix.
+///
+/// Streaming requests are matched as soon as the prefix is received,
+/// and the response is sent while the rest of the request is received.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct BodyPrefixMatcher(Body);
+
+impl Matcher for BodyPrefixMatcher {
+    fn name(&self) -> &str {
+        "body_prefix"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        let prefix = self.0.clone().as_bytes();
+        req.body.clone().as_bytes().starts_with(&prefix)
+    }
+}
+
+pub fn body_prefix(prefix: Body) -> BodyPrefixMatcher {
+    BodyPrefixMatcher(prefix)
+}
+
+/// Body suffix matcher, matches a body ending with the suffix.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct BodySuffixMatcher(Bytes);
+
+impl Matcher for BodySuffixMatcher {
+    fn name(&self) -> &str {
+        "body_suffix"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        req.body.clone().as_bytes().ends_with(&self.0)
+    }
+}
+
+pub fn body_suffix(suffix: impl Into<Bytes>) -> BodySuffixMatcher {
+    BodySuffixMatcher(suffix.into())
+}
+
+/// Body bytes at offset matcher, matches a body with the bytes at the offset.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct BodyAtMatcher(usize, Bytes);
+
+impl Matcher for BodyAtMatcher {
+    fn name(&self) -> &str {
+        "body_at"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        let body = req.body.clone().as_bytes();
+        self.0
+            .checked_add(self.1.len())
+            .and_then(|end| body.get(self.0..end))
+            .is_some_and(|bytes| bytes == self.1)
+    }
+}
+
+pub fn body_at(offset: usize, bytes: impl Into<Bytes>) -> BodyAtMatcher {
+    BodyAtMatcher(offset, bytes.into())
+}
+
+/// Body SHA-256 matcher, matches the body by its hex-encoded SHA-256 digest.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct BodySha256Matcher(String);
+
+impl Matcher for BodySha256Matcher {
+    fn name(&self) -> &str {
+        "body_sha256"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        let digest = req.body_sha256();
+        self.0.len() == digest.len() * 2
+            && digest
+                .iter()
+                .zip(self.0.as_bytes().chunks(2))
+                .all(|(byte, hex)| {
+                    std::str::from_utf8(hex)
+                        .ok()
+                        .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                        == Some(*byte)
+                })
+    }
+}
+
+pub fn body_sha256(hex: impl Into<String>) -> BodySha256Matcher {
+    BodySha256Matcher(hex.into())
+}
+
 /// Headers matcher.
 #[derive(Debug, PartialEq, PartialOrd)]
 pub struct HeadersMatcher(Headers);
@@ -148,6 +352,136 @@ pub fn header(name: impl Into<String>, value: impl Into<String>) -> HeaderMatche
     HeaderMatcher(name.into(), value.into())
 }
 
+/// Authorization matcher.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct AuthorizationMatcher(Credentials);
+
+impl Matcher for AuthorizationMatcher {
+    fn name(&self) -> &str {
+        "authorization"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        self.0.verify(req)
+    }
+}
+
+pub fn authorization(credentials: Credentials) -> AuthorizationMatcher {
+    AuthorizationMatcher(credentials)
+}
+
+/// AWS Signature Version 4 matcher.
+#[cfg(feature = "sigv4")]
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct AwsSigV4Matcher(SigV4);
+
+#[cfg(feature = "sigv4")]
+impl Matcher for AwsSigV4Matcher {
+    fn name(&self) -> &str {
+        "aws_sigv4"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        self.0.verify(req)
+    }
+}
+
+#[cfg(feature = "sigv4")]
+pub fn aws_sigv4(sigv4: SigV4) -> AwsSigV4Matcher {
+    AwsSigV4Matcher(sigv4)
+}
+
+/// Header comparison options, see [`header_with`].
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
+pub struct HeaderCompare {
+    ignore_case: bool,
+    trim: bool,
+    list_as_set: bool,
+}
+
+impl HeaderCompare {
+    /// Creates options comparing values exactly.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compares values case-insensitively.
+    pub fn ignore_case(mut self) -> Self {
+        self.ignore_case = true;
+        self
+    }
+
+    /// Ignores leading and trailing whitespace.
+    pub fn trim(mut self) -> Self {
+        self.trim = true;
+        self
+    }
+
+    /// Compares comma-separated lists as sets, ignoring order, duplicates
+    /// and whitespace around items, e.g. `gzip, br` equals `br,gzip`.
+    ///
+    /// Values of repeated headers are combined into one list.
+    pub fn list_as_set(mut self) -> Self {
+        self.list_as_set = true;
+        self
+    }
+
+    /// Returns true if the values are equal under these options.
+    fn eq(&self, a: &str, b: &str) -> bool {
+        if self.list_as_set {
+            return self.items(a) == self.items(b);
+        }
+        self.normalize(a) == self.normalize(b)
+    }
+
+    fn normalize<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        let value = if self.trim { value.trim() } else { value };
+        if self.ignore_case {
+            Cow::Owned(value.to_lowercase())
+        } else {
+            Cow::Borrowed(value)
+        }
+    }
+
+    fn items<'a>(&self, value: &'a str) -> std::collections::BTreeSet<Cow<'a, str>> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| self.normalize(item))
+            .collect()
+    }
+}
+
+/// Header matcher, comparing values with options.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct HeaderCompareMatcher(String, String, HeaderCompare);
+
+impl Matcher for HeaderCompareMatcher {
+    fn name(&self) -> &str {
+        "header_with"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        let mut values = req
+            .headers
+            .iter()
+            .filter(|(name, _)| *name == *self.0)
+            .map(|(_, value)| value.as_str());
+        if self.2.list_as_set {
+            let combined = values.collect::<Vec<_>>();
+            !combined.is_empty() && self.2.eq(&combined.join(","), &self.1)
+        } else {
+            values.any(|value| self.2.eq(value, &self.1))
+        }
+    }
+}
+
+pub fn header_with(
+    name: impl Into<String>,
+    value: impl Into<String>,
+    compare: HeaderCompare,
+) -> HeaderCompareMatcher {
+    HeaderCompareMatcher(name.into().to_lowercase(), value.into(), compare)
+}
+
 /// Header exists matcher.
 #[derive(Debug, PartialEq, PartialOrd)]
 pub struct HeaderExistsMatcher(String);
@@ -165,6 +499,405 @@ pub fn header_exists(name: impl Into<String>) -> HeaderExistsMatcher {
     HeaderExistsMatcher(name.into())
 }
 
+/// SOAP action matcher, matches the SOAP 1.1 `SOAPAction` header
+/// or SOAP 1.2 `action` content type parameter.
+#[cfg(feature = "soap")]
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct SoapActionMatcher(String);
+
+#[cfg(feature = "soap")]
+impl Matcher for SoapActionMatcher {
+    fn name(&self) -> &str {
+        "soap_action"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        soap::action(req).is_some_and(|action| action == self.0)
+    }
+}
+
+#[cfg(feature = "soap")]
+pub fn soap_action(action: impl Into<String>) -> SoapActionMatcher {
+    SoapActionMatcher(action.into())
+}
+
+/// XPath matcher, matches an XML body with a node at the path,
+/// optionally with the text value.
+///
+/// See [`soap::select`] for the supported XPath subset.
+#[cfg(feature = "soap")]
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct XPathMatcher(String, Option<String>);
+
+#[cfg(feature = "soap")]
+impl Matcher for XPathMatcher {
+    fn name(&self) -> &str {
+        "xpath"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        let body = req.body.clone().as_bytes();
+        let Ok(xml) = std::str::from_utf8(&body) else {
+            return false;
+        };
+        soap::select(xml, &self.0).is_some_and(|values| match &self.1 {
+            Some(value) => values.contains(value),
+            None => !values.is_empty(),
+        })
+    }
+}
+
+#[cfg(feature = "soap")]
+pub fn xpath(path: impl Into<String>) -> XPathMatcher {
+    XPathMatcher(path.into(), None)
+}
+
+#[cfg(feature = "soap")]
+pub fn xpath_eq(path: impl Into<String>, value: impl Into<String>) -> XPathMatcher {
+    XPathMatcher(path.into(), Some(value.into()))
+}
+
+/// gRPC message matcher, matches the message of a streaming request
+/// at an index, starting at 0, with a matcher applied to the message body.
+#[derive(Debug)]
+pub struct GrpcMessageAtMatcher(usize, Arc<dyn Matcher>);
+
+impl PartialEq for GrpcMessageAtMatcher {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0 && *self.1 == *other.1
+    }
+}
+
+impl PartialOrd for GrpcMessageAtMatcher {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.0.cmp(&other.0).then_with(|| (*self.1).cmp(&*other.1)))
+    }
+}
+
+impl Matcher for GrpcMessageAtMatcher {
+    fn name(&self) -> &str {
+        "grpc_message_at"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        req.body
+            .grpc_messages()
+            .get(self.0)
+            .is_some_and(|message| self.1.matches(&req.clone().with_body(message.clone())))
+    }
+}
+
+pub fn grpc_message_at(index: usize, matcher: impl Matcher) -> GrpcMessageAtMatcher {
+    GrpcMessageAtMatcher(index, Arc::new(matcher))
+}
+
+/// Returns the service and method of a gRPC request path, e.g. `/pkg.Service/Method`.
+pub(crate) fn grpc_method(path: &str) -> Option<(&str, &str)> {
+    let (service, method) = path.strip_prefix('/')?.split_once('/')?;
+    (!service.is_empty() && !method.is_empty() && !method.contains('/'))
+        .then_some((service, method))
+}
+
+/// gRPC service matcher, matches a call of any method of the service, e.g. `pkg.Service`.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct GrpcServiceMatcher(String);
+
+impl Matcher for GrpcServiceMatcher {
+    fn name(&self) -> &str {
+        "grpc_service"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        grpc_method(req.path()).is_some_and(|(service, _)| service == self.0)
+    }
+}
+
+pub fn grpc_service(service: impl Into<String>) -> GrpcServiceMatcher {
+    GrpcServiceMatcher(service.into().trim_matches('/').to_string())
+}
+
+/// gRPC method regex matcher, matches a call with a full method name,
+/// e.g. `pkg.Service/Method`, matching the regex.
+///
+/// The regex is not anchored, use `^` and `$` to match the whole name.
+#[derive(Debug)]
+pub struct GrpcMethodRegexMatcher(Regex);
+
+impl PartialEq for GrpcMethodRegexMatcher {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl PartialOrd for GrpcMethodRegexMatcher {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.0.as_str().cmp(other.0.as_str()))
+    }
+}
+
+impl Matcher for GrpcMethodRegexMatcher {
+    fn name(&self) -> &str {
+        "grpc_method_regex"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        grpc_method(req.path()).is_some_and(|_| self.0.is_match(&req.path()[1..]))
+    }
+}
+
+/// Panics if the regex is invalid.
+pub fn grpc_method_regex(regex: &str) -> GrpcMethodRegexMatcher {
+    GrpcMethodRegexMatcher(Regex::new(regex).expect("invalid grpc method regex"))
+}
+
+/// Protobuf comparison options, see [`pb_with`].
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
+pub struct PbCompare {
+    timestamp_tolerance: Option<Duration>,
+}
+
+impl PbCompare {
+    /// Creates options comparing values exactly.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows `Timestamp` and `Duration` values to differ by up to the tolerance.
+    pub fn timestamp_tolerance(mut self, tolerance: Duration) -> Self {
+        self.timestamp_tolerance = Some(tolerance);
+        self
+    }
+}
+
+/// Protobuf matcher, comparing a unary gRPC message semantically.
+///
+/// Fields are compared independently of encoding order, nested messages,
+/// including `google.protobuf.Any` values, are unpacked and compared as
+/// messages, and map entries, including `google.protobuf.Struct` fields,
+/// are compared independently of order.
+///
+/// Messages are decoded without a schema, so repeated messages with only
+/// field 1 once and optionally field 2 are compared as map entries, and
+/// messages with only `seconds` (field 1) and `nanos` (field 2) varints as
+/// timestamps when a tolerance is set.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct PbCompareMatcher(Bytes, PbCompare);
+
+impl Matcher for PbCompareMatcher {
+    fn name(&self) -> &str {
+        "pb_with"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        let compare = wire::Compare {
+            timestamp_tolerance: self.1.timestamp_tolerance,
+        };
+        match req.body.grpc_messages().as_slice() {
+            [message] if message.len() == req.body.len() && message[0] == 0 => {
+                compare.eq(&message.slice(5..), &self.0.slice(5..))
+            }
+            _ => false,
+        }
+    }
+}
+
+pub fn pb_with(message: impl prost::Message, compare: PbCompare) -> PbCompareMatcher {
+    PbCompareMatcher(message.to_bytes(), compare)
+}
+
+/// Traceparent matcher, matches a well-formed W3C `traceparent` header,
+/// optionally with a trace ID.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct TraceparentMatcher(Option<String>);
+
+impl Matcher for TraceparentMatcher {
+    fn name(&self) -> &str {
+        "traceparent"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        req.traceparent().is_some_and(|ctx| {
+            self.0
+                .as_ref()
+                .is_none_or(|trace_id| ctx.trace_id == *trace_id)
+        })
+    }
+}
+
+pub fn traceparent() -> TraceparentMatcher {
+    TraceparentMatcher(None)
+}
+
+pub fn trace_id(trace_id: impl Into<String>) -> TraceparentMatcher {
+    TraceparentMatcher(Some(trace_id.into()))
+}
+
+/// Baggage matcher, matches a W3C `baggage` header member.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct BaggageMatcher(String, String);
+
+impl Matcher for BaggageMatcher {
+    fn name(&self) -> &str {
+        "baggage"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        req.baggage()
+            .iter()
+            .any(|(key, value)| *key == self.0 && *value == self.1)
+    }
+}
+
+pub fn baggage(key: impl Into<String>, value: impl Into<String>) -> BaggageMatcher {
+    BaggageMatcher(key.into(), value.into())
+}
+
+/// Request ID matcher, matches a well-formed `x-request-id` header,
+/// optionally with a value.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct RequestIdMatcher(Option<String>);
+
+impl Matcher for RequestIdMatcher {
+    fn name(&self) -> &str {
+        "request_id"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        req.request_id()
+            .is_some_and(|id| self.0.as_ref().is_none_or(|expected| id == expected))
+    }
+}
+
+pub fn request_id() -> RequestIdMatcher {
+    RequestIdMatcher(None)
+}
+
+pub fn request_id_eq(id: impl Into<String>) -> RequestIdMatcher {
+    RequestIdMatcher(Some(id.into()))
+}
+
+/// After matcher, matches once another mock has been matched at least `n` times.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct AfterMatcher(Uuid, usize);
+
+impl Matcher for AfterMatcher {
+    fn name(&self) -> &str {
+        "after"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        req.interactions.hits(&self.0) >= self.1
+    }
+}
+
+pub fn after(mock_id: Uuid, n: usize) -> AfterMatcher {
+    AfterMatcher(mock_id, n)
+}
+
+/// Connection matcher, matches requests received on the nth connection
+/// accepted by the server, starting at 1.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct ConnectionMatcher(usize);
+
+impl Matcher for ConnectionMatcher {
+    fn name(&self) -> &str {
+        "connection"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        req.connection() == Some(self.0)
+    }
+}
+
+pub fn connection(n: usize) -> ConnectionMatcher {
+    ConnectionMatcher(n)
+}
+
+/// Peer IP matcher, matches requests from a client address in a CIDR block,
+/// e.g. `10.0.0.0/8`.
+///
+/// IPv4-mapped IPv6 addresses match IPv4 blocks. Requests without a peer
+/// address, e.g. received on Unix domain sockets or served in-process,
+/// are not matched.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct PeerIpMatcher(IpAddr, u8);
+
+impl Matcher for PeerIpMatcher {
+    fn name(&self) -> &str {
+        "peer_ip"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        let Some(addr) = req.peer_addr() else {
+            return false;
+        };
+        let (ip, len) = ip_bits(addr.ip().to_canonical());
+        let (net, net_len) = ip_bits(self.0);
+        if len != net_len {
+            return false;
+        }
+        self.1 == 0 || (ip ^ net) >> (len - u32::from(self.1)) == 0
+    }
+}
+
+/// Returns the bits of an address, with their number.
+fn ip_bits(ip: IpAddr) -> (u128, u32) {
+    match ip {
+        IpAddr::V4(ip) => (u32::from(ip).into(), 32),
+        IpAddr::V6(ip) => (u128::from(ip), 128),
+    }
+}
+
+/// Parses a CIDR block, e.g. `10.0.0.0/8`, or an address, a block of one address.
+fn parse_cidr(cidr: &str) -> Result<(IpAddr, u8), String> {
+    let (ip, prefix) = match cidr.split_once('/') {
+        Some((ip, prefix)) => (ip, Some(prefix)),
+        None => (cidr, None),
+    };
+    let ip = ip
+        .parse::<IpAddr>()
+        .map_err(|err| format!("invalid address `{ip}`: {err}"))?;
+    let len = ip_bits(ip).1 as u8;
+    let prefix = match prefix {
+        Some(prefix) => prefix
+            .parse::<u8>()
+            .ok()
+            .filter(|prefix| *prefix <= len)
+            .ok_or_else(|| format!("invalid prefix length `{prefix}`"))?,
+        None => len,
+    };
+    Ok((ip, prefix))
+}
+
+/// Panics if the CIDR block is invalid.
+pub fn peer_ip(cidr: &str) -> PeerIpMatcher {
+    let (ip, prefix) = parse_cidr(cidr).expect("invalid peer ip cidr");
+    PeerIpMatcher(ip, prefix)
+}
+
+/// Session matcher, matches requests sent in a session issued by the server.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct SessionMatcher;
+
+impl Matcher for SessionMatcher {
+    fn name(&self) -> &str {
+        "session"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        req.session().is_some()
+    }
+}
+
+pub fn same_session() -> SessionMatcher {
+    SessionMatcher
+}
+
+/// Counter matcher, matches when a server counter compares to the value
+/// with the ordering, e.g. `Ordering::Greater` for `counter > value`.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct CounterMatcher(String, Ordering, i64);
+
+impl Matcher for CounterMatcher {
+    fn name(&self) -> &str {
+        "counter"
+    }
+    fn matches(&self, req: &Request) -> bool {
+        req.interactions.counter(&self.0).cmp(&self.2) == self.1
+    }
+}
+
+pub fn counter(name: impl Into<String>, ordering: Ordering, value: i64) -> CounterMatcher {
+    CounterMatcher(name.into(), ordering, value)
+}
+
 /// Query params matcher.
 #[derive(Debug, PartialEq, PartialOrd)]
 pub struct QueryParamsMatcher(Vec<(Cow<'static, str>, Cow<'static, str>)>);
@@ -224,6 +957,143 @@ pub fn query_param_exists(key: impl Into<String>) -> QueryParamExistsMatcher {
     QueryParamExistsMatcher(key.into())
 }
 
+/// Adds the match condition of a matcher to a mock definition,
+/// failing if it can't be expressed in a mock definition file.
+pub(crate) fn define(matcher: &dyn Matcher, request: &mut RequestDefinition) -> Result<(), String> {
+    let any = matcher.as_matcher_eq().as_any();
+    let mut header = |name: &str, value: &str| match request.headers.get(name) {
+        Some(other) if other != value => Err(format!("header `{name}` has multiple values")),
+        _ => {
+            request.headers.insert(name.to_string(), value.to_string());
+            Ok(())
+        }
+    };
+    if let Some(HeaderMatcher(name, value)) = any.downcast_ref() {
+        return header(name, value);
+    }
+    if let Some(HeadersMatcher(headers)) = any.downcast_ref() {
+        return headers
+            .iter()
+            .try_for_each(|(name, value)| header(name.as_str(), value.as_str()));
+    }
+    if let Some(MethodMatcher(method)) = any.downcast_ref() {
+        request.method = Some(method.to_string());
+    } else if let Some(PathMatcher(path)) = any.downcast_ref() {
+        request.path = Some(path.clone());
+    } else if let Some(PathPrefixMatcher(prefix)) = any.downcast_ref() {
+        request.path_prefix = Some(prefix.clone());
+    } else if let Some(QueryParamMatcher(key, value)) = any.downcast_ref() {
+        if request.query.insert(key.clone(), value.clone()).is_some() {
+            return Err(format!("query param `{key}` has multiple values"));
+        }
+    } else if let Some(BodyMatcher(body)) = any.downcast_ref() {
+        request.body = BodyDefinition::from_body(body, None)?;
+    } else {
+        return Err(format!("matcher `{}` is not supported", matcher.name()));
+    }
+    Ok(())
+}
+
+/// The route of a mock, inferred from its matchers.
+#[derive(Debug, Default)]
+pub(crate) struct Route {
+    /// The matched methods, any method if empty.
+    pub methods: Vec<Method>,
+    pub path: Option<RoutePath>,
+    pub grpc_service: Option<String>,
+    /// The matched bodies.
+    pub bodies: Vec<Body>,
+}
+
+/// The path of a [`Route`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum RoutePath {
+    Exact(String),
+    Template(String),
+    Prefix(String),
+}
+
+impl RoutePath {
+    pub fn as_str(&self) -> &str {
+        match self {
+            RoutePath::Exact(path) | RoutePath::Template(path) | RoutePath::Prefix(path) => path,
+        }
+    }
+}
+
+/// Returns the `Digest` credentials required by a mock's matchers.
+#[cfg(feature = "digest")]
+pub(crate) fn digests(matchers: &[Arc<dyn Matcher>]) -> impl Iterator<Item = &Digest> {
+    matchers.iter().filter_map(
+        |matcher| match matcher.as_matcher_eq().as_any().downcast_ref() {
+            Some(AuthorizationMatcher(Credentials::Digest(digest))) => Some(digest),
+            _ => None,
+        },
+    )
+}
+
+/// Returns the route of a mock's matchers.
+pub(crate) fn route(matchers: &[Arc<dyn Matcher>]) -> Route {
+    let mut route = Route::default();
+    for matcher in matchers {
+        let any = matcher.as_matcher_eq().as_any();
+        if let Some(MethodMatcher(method)) = any.downcast_ref() {
+            route.methods.push(method.clone());
+        } else if let Some(MethodAnyMatcher(methods)) = any.downcast_ref() {
+            route.methods.extend(methods.iter().cloned());
+        } else if let Some(PathMatcher(path)) = any.downcast_ref() {
+            route.path = Some(RoutePath::Exact(path.clone()));
+        } else if let Some(PathTemplateMatcher(template)) = any.downcast_ref() {
+            route.path = Some(RoutePath::Template(template.clone()));
+        } else if let Some(PathPrefixMatcher(prefix)) = any.downcast_ref() {
+            route.path = Some(RoutePath::Prefix(prefix.clone()));
+        } else if let Some(GrpcServiceMatcher(service)) = any.downcast_ref() {
+            route.grpc_service = Some(service.clone());
+        } else if let Some(BodyMatcher(body)) = any.downcast_ref() {
+            route.bodies.push(body.clone());
+        } else if let Some(PbCompareMatcher(message, _)) = any.downcast_ref() {
+            route.bodies.push(Body::bytes(message.clone()));
+        }
+    }
+    route
+}
+
+/// Returns true if every request matched by `b` is also matched by `a`,
+/// as far as can be determined without a request.
+pub(crate) fn subsumes(a: &dyn Matcher, b: &dyn Matcher) -> bool {
+    if a == b {
+        return true;
+    }
+    let a = a.as_matcher_eq().as_any();
+    let b = b.as_matcher_eq().as_any();
+    if a.is::<AnyMatcher>() {
+        return true;
+    }
+    if a.is::<AnyMethodMatcher>() {
+        return b.is::<MethodMatcher>() || b.is::<MethodAnyMatcher>();
+    }
+    if a.is::<AnyPathMatcher>() {
+        return b.is::<PathMatcher>() || b.is::<PathPrefixMatcher>();
+    }
+    if let Some(MethodAnyMatcher(methods)) = a.downcast_ref() {
+        if let Some(MethodMatcher(method)) = b.downcast_ref() {
+            return methods.contains(method);
+        }
+        if let Some(MethodAnyMatcher(other)) = b.downcast_ref() {
+            return other.iter().all(|method| methods.contains(method));
+        }
+    }
+    if let Some(PathPrefixMatcher(prefix)) = a.downcast_ref() {
+        if let Some(PathMatcher(path)) = b.downcast_ref() {
+            return path.starts_with(prefix.as_str());
+        }
+        if let Some(PathPrefixMatcher(other)) = b.downcast_ref() {
+            return other.starts_with(prefix.as_str());
+        }
+    }
+    false
+}
+
 #[doc(hidden)]
 pub trait AsMatcherEq {
     /// Returns matcher as [`&dyn MatcherEq`] to compare to another matcher.
@@ -1,19 +1,24 @@
 //! Mock
-use std::sync::{
-    atomic::{AtomicUsize, Ordering},
-    Arc,
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
 };
 
+use rand::Rng;
 use uuid::Uuid;
 
 use crate::{
-    matchers::Matcher,
+    matchers::{BodyPrefixMatcher, Matcher, PathTemplateMatcher},
     mock_builder::{Then, When},
     request::Request,
     response::Response,
 };
 
-const DEFAULT_PRIORITY: u8 = 5;
+pub(crate) const DEFAULT_PRIORITY: u8 = 5;
 
 /// A mock.
 #[derive(Debug)]
@@ -30,6 +35,18 @@ pub struct Mock {
     pub match_count: AtomicUsize,
     /// Limit on how many times this mock can be matched.
     pub limit: Option<usize>,
+    /// Namespace this mock is scoped to.
+    pub namespace: Option<String>,
+    /// Metadata identifying the mock, e.g. the fixture it was defined in.
+    pub labels: BTreeMap<String, String>,
+    /// Index of the response variant chosen for the matched request, if any.
+    pub variant: Option<usize>,
+    /// Time after which this mock stops matching.
+    pub expires_at: Option<SystemTime>,
+    /// Duration after insertion after which this mock stops matching.
+    pub expires_after: Option<Duration>,
+    /// Time this mock was inserted into a mock set.
+    pub inserted_at: Option<SystemTime>,
 }
 
 impl Mock {
@@ -49,6 +66,12 @@ impl Mock {
             priority: DEFAULT_PRIORITY,
             match_count: AtomicUsize::new(0),
             limit: None,
+            namespace: None,
+            labels: BTreeMap::new(),
+            variant: None,
+            expires_at: None,
+            expires_after: None,
+            inserted_at: None,
         }
     }
 
@@ -64,6 +87,40 @@ impl Mock {
         self
     }
 
+    /// Sets a duration after which the mock stops matching, starting when it
+    /// is inserted into a mock set, e.g. for a temporary maintenance window.
+    ///
+    /// Time is measured by the server clock.
+    pub fn expires_after(mut self, duration: Duration) -> Self {
+        self.expires_after = Some(duration);
+        self
+    }
+
+    /// Sets a time after which the mock stops matching.
+    ///
+    /// Time is measured by the server clock.
+    pub fn expires_at(mut self, time: SystemTime) -> Self {
+        self.expires_at = Some(time);
+        self
+    }
+
+    /// Sets the mock namespace.
+    ///
+    /// A namespaced mock only matches requests sent with the same namespace.
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Adds a label.
+    ///
+    /// Labels are included in logs, journal entries and verification
+    /// failures, to trace which mock produced a response.
+    pub fn with_label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.labels.insert(key.into(), value.into());
+        self
+    }
+
     /// Returns the mock ID.
     pub fn id(&self) -> &Uuid {
         &self.id
@@ -74,30 +131,121 @@ impl Mock {
         &self.response
     }
 
+    /// Consumes the mock, returning the response for its current match count.
+    pub fn into_response(self) -> Response {
+        self.into_response_with(&mut rand::rng())
+    }
+
+    /// Consumes the mock, returning the response for its current match count,
+    /// choosing a response variant with the random number generator
+    /// unless one was chosen when it was matched.
+    pub fn into_response_with(self, rng: &mut impl Rng) -> Response {
+        let n = self.match_count();
+        let response = self.response.into_nth(n);
+        match self.variant {
+            Some(i) => response.into_variant_at(i),
+            None => response.into_variant(rng),
+        }
+    }
+
+    /// Chooses the response variant for its current match count
+    /// with the random number generator.
+    pub fn choose_variant(&mut self, rng: &mut impl Rng) {
+        let n = self.match_count();
+        self.variant = self.response.clone().into_nth(n).choose_variant(rng);
+    }
+
     /// Returns the mock priority.
     pub fn priority(&self) -> u8 {
         self.priority
     }
 
+    /// Returns the mock namespace.
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// Returns the mock labels.
+    pub fn labels(&self) -> &BTreeMap<String, String> {
+        &self.labels
+    }
+
+    /// Returns true if the mock matches a body prefix, so the rest of
+    /// a streaming request is not matched after the response is sent.
+    pub fn matches_prefix(&self) -> bool {
+        self.matchers
+            .iter()
+            .any(|matcher| matcher.as_matcher_eq().as_any().is::<BodyPrefixMatcher>())
+    }
+
+    /// Returns the path parameters of a request matched by a path template.
+    pub fn path_params(&self, req: &Request) -> BTreeMap<String, String> {
+        self.matchers
+            .iter()
+            .filter_map(|matcher| {
+                matcher
+                    .as_matcher_eq()
+                    .as_any()
+                    .downcast_ref::<PathTemplateMatcher>()
+            })
+            .filter_map(|matcher| matcher.params(req))
+            .flatten()
+            .collect()
+    }
+
     /// Returns the match count.
     pub fn match_count(&self) -> usize {
         self.match_count.load(Ordering::Relaxed)
     }
 
-    /// Evaluates a request against match conditions.
-    pub fn matches(&self, req: &Request) -> bool {
-        if let Some(limit) = self.limit {
-            if self.match_count.load(Ordering::Relaxed) >= limit {
-                return false;
-            }
+    /// Returns the time after which the mock stops matching, if it expires.
+    pub fn expiry(&self) -> Option<SystemTime> {
+        let after = self
+            .expires_after
+            .zip(self.inserted_at)
+            .map(|(duration, inserted_at)| inserted_at + duration);
+        match (self.expires_at, after) {
+            (Some(at), Some(after)) => Some(at.min(after)),
+            (at, after) => at.or(after),
         }
-        let matched = self.matchers.iter().all(|matcher| matcher.matches(req));
+    }
+
+    /// Returns the number of matches remaining until the limit is reached,
+    /// or `None` if the mock has no limit.
+    pub fn remaining_matches(&self) -> Option<usize> {
+        self.limit
+            .map(|limit| limit.saturating_sub(self.match_count()))
+    }
+
+    /// Evaluates a request against match conditions,
+    /// incrementing the match counter if matched.
+    pub fn matches(&self, req: &Request) -> bool {
+        let matched = self.would_match(req);
         if matched {
             self.match_count.fetch_add(1, Ordering::Relaxed);
         }
         matched
     }
 
+    /// Evaluates a request against match conditions,
+    /// without incrementing the match counter.
+    pub fn would_match(&self, req: &Request) -> bool {
+        if self.namespace.is_some() && self.namespace() != req.namespace() {
+            return false;
+        }
+        if let Some(expiry) = self.expiry() {
+            if req.interactions.now.unwrap_or_else(SystemTime::now) >= expiry {
+                return false;
+            }
+        }
+        if let Some(limit) = self.limit.or(self.response.sequence_limit()) {
+            if self.match_count.load(Ordering::Relaxed) >= limit {
+                return false;
+            }
+        }
+        self.matchers.iter().all(|matcher| matcher.matches(req))
+    }
+
     /// Resets the match counter.
     pub fn reset(&self) {
         self.match_count.store(0, Ordering::Relaxed);
@@ -112,6 +260,8 @@ impl PartialEq for Mock {
             && self.priority == other.priority
             && self.match_count.load(Ordering::Relaxed) == other.match_count.load(Ordering::Relaxed)
             && self.limit == other.limit
+            && self.namespace == other.namespace
+            && self.labels == other.labels
     }
 }
 
@@ -124,6 +274,12 @@ impl Clone for Mock {
             priority: self.priority,
             match_count: AtomicUsize::new(self.match_count.load(Ordering::Relaxed)),
             limit: self.limit,
+            namespace: self.namespace.clone(),
+            labels: self.labels.clone(),
+            variant: self.variant,
+            expires_at: self.expires_at,
+            expires_after: self.expires_after,
+            inserted_at: self.inserted_at,
         }
     }
 }
@@ -131,7 +287,7 @@ impl Clone for Mock {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::Method;
+    use crate::{Method, StatusCode};
 
     #[test]
     fn test_match_counter() {
@@ -160,4 +316,56 @@ mod tests {
         assert!(mock.matches(&request));
         assert!(!mock.matches(&request));
     }
+
+    #[test]
+    fn test_fail_times() {
+        let mock = Mock::new(|when, then| {
+            when.get();
+            then.text("ok")
+                .fail_times(2, StatusCode::SERVICE_UNAVAILABLE);
+        });
+        let request = Request::new(Method::GET, "http://localhost/".parse().unwrap());
+        let statuses = (0..3)
+            .map(|_| {
+                mock.matches(&request);
+                mock.clone().into_response().status().as_u16()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(statuses, [503, 503, 200]);
+    }
+
+    #[test]
+    fn test_variants() {
+        use rand::{rngs::SmallRng, SeedableRng};
+
+        let mock = Mock::new(|when, then| {
+            when.get();
+            then.variant(9, |then| {
+                then.ok();
+            })
+            .variant(1, |then| {
+                then.service_unavailable();
+            });
+        });
+        let mut rng = SmallRng::seed_from_u64(0);
+        let failures = (0..1000)
+            .filter(|_| mock.clone().into_response_with(&mut rng).is_error())
+            .count();
+        assert!((50..150).contains(&failures), "{failures}");
+    }
+
+    #[test]
+    fn test_namespace() {
+        let mock = Mock::new(|when, then| {
+            when.get();
+            then.ok();
+        })
+        .with_namespace("a");
+        let mut request = Request::new(Method::GET, "http://localhost/".parse().unwrap());
+        assert!(!mock.matches(&request));
+        request.namespace = Some("b".into());
+        assert!(!mock.matches(&request));
+        request.namespace = Some("a".into());
+        assert!(mock.matches(&request));
+    }
 }
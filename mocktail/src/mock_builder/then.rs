@@ -1,13 +1,24 @@
 //! Then
-use std::{cell::Cell, rc::Rc};
+use std::{cell::Cell, rc::Rc, time::Duration};
 
 use bytes::Bytes;
 
+#[cfg(feature = "compression")]
+use crate::compression::Compression;
+#[cfg(feature = "soap")]
+use crate::soap::{self, SoapVersion};
+#[cfg(feature = "client")]
+use crate::webhook::Webhook;
 use crate::{
     body::Body,
+    cache::CachePolicy,
+    delegate::Delegate,
     headers::{HeaderName, HeaderValue, Headers},
-    response::Response,
-    status::StatusCode,
+    multipart::Multipart,
+    resource::Resource,
+    response::{Delay, Echo, Exhausted, Failure, Jitter, Reset, Response},
+    status::{self, ErrorDetail, StatusCode},
+    template::Template,
 };
 
 /// A response builder.
@@ -51,6 +62,83 @@ impl Then {
         self
     }
 
+    /// HTTP/1.1 reason phrase sent in place of the canonical reason for the status,
+    /// e.g. `200 Alright`. Reason phrases are not sent over HTTP/2.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the reason phrase contains invalid characters.
+    pub fn reason(self, reason: impl Into<String>) -> Self {
+        let reason = reason.into();
+        hyper::ext::ReasonPhrase::try_from(reason.as_bytes()).expect("invalid reason phrase");
+        self.update(|r| {
+            r.reason = Some(reason);
+        });
+        self
+    }
+
+    /// Informational (1xx) response sent before the response, e.g. `103 Early Hints`.
+    ///
+    /// Informational responses are only sent for unary requests over HTTP/1.1,
+    /// as hyper does not support sending them over HTTP/2.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the status is not informational.
+    pub fn informational<T, U>(
+        self,
+        status: impl Into<StatusCode>,
+        headers: impl IntoIterator<Item = (T, U)>,
+    ) -> Self
+    where
+        T: Into<HeaderName>,
+        U: Into<HeaderValue>,
+    {
+        let status = status.into();
+        assert!(
+            (100..200).contains(&status.as_u16()),
+            "invalid informational status: {}",
+            status.as_u16()
+        );
+        self.update(|r| {
+            r.informational.push((status, Headers::from_iter(headers)));
+        });
+        self
+    }
+
+    /// `103 Early Hints` informational response with a `Link` header
+    /// for each link, e.g. `</style.css>; rel=preload; as=style`.
+    pub fn early_hints(self, links: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        let links = links.into_iter().map(|link| ("link", link.into()));
+        self.informational(StatusCode::EARLY_HINTS, links)
+    }
+
+    /// `Retry-After` header, in seconds.
+    pub fn retry_after(self, delay: Duration) -> Self {
+        self.update(|r| {
+            r.headers.insert("retry-after", delay.as_secs().to_string());
+        });
+        self
+    }
+
+    /// `Retry-After` header, as the HTTP date `delay` after the response
+    /// is sent according to the server clock.
+    pub fn retry_after_date(self, delay: Duration) -> Self {
+        self.update(|r| {
+            r.retry_after_date = Some(delay);
+        });
+        self
+    }
+
+    /// Caching headers set by a cache policy, answering conditional
+    /// requests with `304 Not Modified`, see [`CachePolicy`].
+    pub fn cache(self, policy: CachePolicy) -> Self {
+        self.update(|r| {
+            r.cache = Some(policy);
+        });
+        self
+    }
+
     /// Body.
     pub fn body(self, body: Body) -> Self {
         self.update(|r| {
@@ -66,6 +154,259 @@ impl Then {
         });
         self
     }
+
+    /// Increments a server counter each time the mock is matched.
+    pub fn increment(self, counter: impl Into<String>) -> Self {
+        self.add(counter, 1)
+    }
+
+    /// Adds `delta` to a server counter each time the mock is matched.
+    pub fn add(self, counter: impl Into<String>, delta: i64) -> Self {
+        self.update(|r| {
+            r.counters.push((counter.into(), delta));
+        });
+        self
+    }
+
+    /// Adds a response variant, chosen at random in proportion to its weight
+    /// relative to the other variants.
+    ///
+    /// If a mock has variants, one of them is returned instead of the response.
+    /// Use [`MockServerConfigBuilder::seed`](crate::server::MockServerConfigBuilder::seed)
+    /// for a deterministic choice.
+    pub fn variant<F>(self, weight: u32, f: F) -> Self
+    where
+        F: FnOnce(Then),
+    {
+        let then = Then::new();
+        f(then.clone());
+        let variant = then.into_inner();
+        self.update(|r| {
+            r.variants.push((weight, variant));
+        });
+        self
+    }
+
+    /// Adds a response variant for a language tag, e.g. `fr` or `en-US`,
+    /// negotiated with the request `Accept-Language` header.
+    ///
+    /// The response is the default, returned if no variant is accepted.
+    pub fn language<F>(self, tag: impl Into<String>, f: F) -> Self
+    where
+        F: FnOnce(Then),
+    {
+        let then = Then::new();
+        f(then.clone());
+        let variant = then.into_inner();
+        self.update(|r| {
+            r.languages.push((tag.into(), variant));
+        });
+        self
+    }
+
+    /// Adds a response to a sequence of responses returned in order,
+    /// one for each match of the mock.
+    ///
+    /// If a mock has a sequence, its responses are returned instead of the
+    /// response; see [`Then::on_exhausted`] for the behavior once every
+    /// response has been returned.
+    pub fn sequence<F>(self, f: F) -> Self
+    where
+        F: FnOnce(Then),
+    {
+        let then = Then::new();
+        f(then.clone());
+        let response = then.into_inner();
+        self.update(|r| {
+            r.sequence.push(response);
+        });
+        self
+    }
+
+    /// Behavior once the response sequence is exhausted,
+    /// repeating the last response by default.
+    pub fn on_exhausted(self, exhausted: Exhausted) -> Self {
+        self.update(|r| {
+            r.exhausted = exhausted;
+        });
+        self
+    }
+
+    /// Reflects the request method, path, query, headers and body
+    /// back as a JSON body.
+    pub fn echo(self) -> Self {
+        self.echo_with(Echo::default())
+    }
+
+    /// Reflects the parts of the request included by `echo` back as a JSON body.
+    pub fn echo_with(self, echo: Echo) -> Self {
+        self.update(|r| {
+            r.echo = Some(echo);
+        });
+        self
+    }
+
+    /// Handles the request with an in-memory [`Resource`].
+    pub fn resource(self, resource: Resource) -> Self {
+        self.update(|r| {
+            r.resource = Some(resource);
+        });
+        self
+    }
+
+    /// Delays the response.
+    pub fn delay(self, delay: Duration) -> Self {
+        self.delay_with_jitter(delay, Jitter::None)
+    }
+
+    /// Delays the response by the base duration plus random jitter, so
+    /// repeated matches do not all have identical latency.
+    ///
+    /// Use [`MockServerConfigBuilder::seed`](crate::server::MockServerConfigBuilder::seed)
+    /// for deterministic jitter.
+    pub fn delay_with_jitter(self, base: Duration, jitter: Jitter) -> Self {
+        self.update(|r| {
+            r.delay = Some(Delay::with_jitter(base, jitter));
+        });
+        self
+    }
+
+    /// Splits the body into data frames of at most `size` bytes,
+    /// e.g. to split one JSON object across frames.
+    ///
+    /// For HTTP/1, chunked bodies are sent with chunked transfer encoding.
+    pub fn chunk_size(self, size: usize) -> Self {
+        self.update(|r| {
+            r.chunking.get_or_insert_default().size = Some(size);
+        });
+        self
+    }
+
+    /// Waits `interval` between body data frames, so each frame is flushed
+    /// separately, e.g. as its own TCP segment.
+    pub fn chunk_interval(self, interval: Duration) -> Self {
+        self.update(|r| {
+            r.chunking.get_or_insert_default().interval = interval;
+        });
+        self
+    }
+
+    /// Overrides the server compression settings, e.g. with
+    /// [`Compression::none`] to send the response uncompressed.
+    #[cfg(feature = "compression")]
+    pub fn compression(self, compression: Compression) -> Self {
+        self.update(|r| {
+            r.compression = Some(compression);
+        });
+        self
+    }
+
+    /// Resets the HTTP/2 stream with the error code after sending the headers,
+    /// e.g. [`Reason::REFUSED_STREAM`](crate::Reason::REFUSED_STREAM).
+    ///
+    /// HTTP/1 connections are closed instead.
+    pub fn reset_stream(self, reason: impl Into<u32>) -> Self {
+        self.reset_stream_after(0, reason)
+    }
+
+    /// Resets the HTTP/2 stream with the error code after sending
+    /// the headers and the first `bytes` of the body.
+    ///
+    /// HTTP/1 connections are closed instead.
+    pub fn reset_stream_after(self, bytes: usize, reason: impl Into<u32>) -> Self {
+        let reason = reason.into();
+        self.update(|r| {
+            r.reset = Some(Reset {
+                after: bytes,
+                reason,
+            });
+        });
+        self
+    }
+
+    /// Sends a webhook when the mock is matched, e.g. a delayed
+    /// "processing complete" notification.
+    #[cfg(feature = "client")]
+    pub fn webhook(self, webhook: Webhook) -> Self {
+        self.update(|r| {
+            r.webhooks.push(webhook);
+        });
+        self
+    }
+
+    /// Hands off the request to a server, joining the request path and query
+    /// to the url and sending its response. The request is still recorded in
+    /// the journal.
+    ///
+    /// Supported for HTTP mocks only.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the url is invalid.
+    #[cfg(feature = "client")]
+    pub fn delegate_to(self, url: impl AsRef<str>) -> Self {
+        let url = url.as_ref().parse().expect("invalid delegate url");
+        self.delegate(Delegate::url(url))
+    }
+
+    /// Hands off the request to a tower service, sending its response.
+    /// The request is still recorded in the journal.
+    ///
+    /// Supported for HTTP mocks only.
+    #[cfg(feature = "tower")]
+    pub fn delegate_to_service<S, B>(self, service: S) -> Self
+    where
+        S: tower_service::Service<
+                http::Request<http_body_util::Full<Bytes>>,
+                Response = http::Response<B>,
+            > + Clone
+            + Send
+            + Sync
+            + 'static,
+        S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+        S::Future: Send + 'static,
+        B: http_body::Body<Data = Bytes> + Send + 'static,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        self.delegate(Delegate::service(service))
+    }
+
+    /// Hands off the request to a [`Delegate`], sending its response.
+    ///
+    /// Supported for HTTP mocks only.
+    pub fn delegate(self, delegate: Delegate) -> Self {
+        self.update(|r| {
+            r.delegate = Some(delegate);
+        });
+        self
+    }
+
+    /// Issues a new session with the response, which subsequent requests
+    /// sending back the session ID match with [`When::same_session`](super::When::same_session).
+    ///
+    /// The session ID is issued in a cookie by default, see
+    /// [`MockServerConfigBuilder::session_mode`](crate::server::MockServerConfigBuilder::session_mode).
+    pub fn start_session(self) -> Self {
+        self.update(|r| {
+            r.start_session = true;
+        });
+        self
+    }
+
+    /// Fails the first `n` matches with the status code, returning
+    /// the response afterwards.
+    ///
+    /// For gRPC, the status code is mapped to the equivalent gRPC code,
+    /// e.g. `503 Service Unavailable` to `UNAVAILABLE`.
+    pub fn fail_times(self, n: usize, status: impl Into<StatusCode>) -> Self {
+        self.update(|r| {
+            r.failure = Some(Failure {
+                times: n,
+                status: status.into(),
+            });
+        });
+        self
+    }
 }
 
 /// Body convenience methods.
@@ -115,6 +456,123 @@ impl Then {
         self
     }
 
+    /// Template body, rendered with the matched request.
+    ///
+    /// See [`template`](crate::template) for the template syntax.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the template is invalid.
+    pub fn template(self, template: impl Into<String>) -> Self {
+        let template = Template::parse(template).expect("invalid template");
+        self.update(|r| {
+            r.template = Some(template);
+        });
+        self
+    }
+
+    /// Header with a template value, rendered with the matched request,
+    /// e.g. `Location: /orders/{{state.next_id}}`.
+    ///
+    /// See [`template`](crate::template) for the template syntax.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the template is invalid.
+    pub fn header_template(self, name: impl Into<String>, template: impl Into<String>) -> Self {
+        let template = Template::parse(template).expect("invalid template");
+        self.update(|r| {
+            r.header_templates.push((name.into().into(), template));
+        });
+        self
+    }
+
+    /// Sets the error message to a template rendered with the matched request,
+    /// e.g. `"method not implemented: {{ request.path }}"`.
+    ///
+    /// See [`template`](crate::template) for the template syntax.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the template is invalid.
+    pub fn message_template(self, template: impl Into<String>) -> Self {
+        let template = Template::parse(template).expect("invalid template");
+        self.update(|r| {
+            r.message_template = Some(template);
+        });
+        self
+    }
+
+    /// Reflects the request gRPC messages back as the response messages.
+    ///
+    /// For streaming requests, each message is reflected as it is received.
+    pub fn grpc_echo(self) -> Self {
+        self.update(|r| {
+            r.grpc_echo = true;
+        });
+        self
+    }
+
+    /// Builds the response with a script evaluated for each matched request.
+    ///
+    /// See [`script`](crate::script) for the script inputs and outputs.
+    /// Script errors are answered with `500 Internal Server Error`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the script fails to compile.
+    #[cfg(feature = "scripting")]
+    pub fn script(self, source: impl Into<String>) -> Self {
+        let script = crate::script::Script::compile(source).expect("invalid script");
+        self.update(|r| {
+            r.script = Some(script);
+        });
+        self
+    }
+
+    /// Multipart body, with a `content-type` header including the boundary.
+    pub fn multipart(self, multipart: Multipart) -> Self {
+        self.update(|r| {
+            r.headers.insert("content-type", multipart.content_type());
+            r.body = Body::bytes(multipart.to_bytes());
+        });
+        self
+    }
+
+    /// `206 Partial Content` with a `multipart/byteranges` body of the
+    /// ranges of the content. See [`Multipart::byteranges`].
+    pub fn byteranges(
+        self,
+        content: impl Into<Bytes>,
+        content_type: &str,
+        ranges: impl IntoIterator<Item = std::ops::Range<usize>>,
+    ) -> Self {
+        self.status(StatusCode::PARTIAL_CONTENT)
+            .multipart(Multipart::byteranges(content, content_type, ranges))
+    }
+
+    /// SOAP envelope body wrapping the XML payload.
+    #[cfg(feature = "soap")]
+    pub fn soap(self, version: SoapVersion, payload: impl AsRef<str>) -> Self {
+        self.update(|r| {
+            r.headers.insert("content-type", version.content_type());
+            r.body = Body::bytes(soap::envelope(version, payload.as_ref()));
+        });
+        self
+    }
+
+    /// SOAP fault envelope body, with the fault code without a prefix,
+    /// e.g. `Server`, and the HTTP status for the fault.
+    #[cfg(feature = "soap")]
+    pub fn soap_fault(self, version: SoapVersion, code: &str, reason: &str) -> Self {
+        self.update(|r| {
+            r.status = version.fault_status(code);
+            r.headers.insert("content-type", version.content_type());
+            r.body = Body::bytes(soap::fault(version, code, reason));
+        });
+        self
+    }
+
     /// Json body.
     pub fn json(self, body: impl serde::Serialize) -> Self {
         self.update(|r| {
@@ -151,6 +609,26 @@ impl Then {
         });
         self
     }
+
+    /// gRPC error status code and message with typed details, sent as a
+    /// `google.rpc.Status` in the `grpc-status-details-bin` trailer.
+    pub fn pb_error_with_details(
+        self,
+        status: StatusCode,
+        message: impl Into<String>,
+        details: impl IntoIterator<Item = ErrorDetail>,
+    ) -> Self {
+        let message = message.into();
+        let details = status::status_details(
+            status.as_grpc(),
+            message.clone(),
+            details.into_iter().collect(),
+        );
+        self.update(|r| {
+            r.headers.insert("grpc-status-details-bin", details);
+        });
+        self.error(status, message)
+    }
 }
 
 /// Status convenience methods.
@@ -172,6 +650,30 @@ impl Then {
         self
     }
 
+    /// 201 Created
+    pub fn created(self) -> Self {
+        self.update(|r| {
+            r.status = StatusCode::CREATED;
+        });
+        self
+    }
+
+    /// 202 Accepted
+    pub fn accepted(self) -> Self {
+        self.update(|r| {
+            r.status = StatusCode::ACCEPTED;
+        });
+        self
+    }
+
+    /// 204 No Content
+    pub fn no_content(self) -> Self {
+        self.update(|r| {
+            r.status = StatusCode::NO_CONTENT;
+        });
+        self
+    }
+
     // Common client errors
 
     /// 400 Bad Request
@@ -214,6 +716,13 @@ impl Then {
         self
     }
 
+    /// 409 Conflict
+    pub fn conflict(self) -> Self {
+        self.update(|r| {
+            r.status = StatusCode::CONFLICT;
+        });
+        self
+    }
     /// 422 Unprocessable Content
     pub fn unprocessable_content(self) -> Self {
         self.update(|r| {
@@ -222,6 +731,14 @@ impl Then {
         self
     }
 
+    /// 429 Too Many Requests, with a `Retry-After` header in seconds.
+    pub fn too_many_requests(self, retry_after: Duration) -> Self {
+        self.update(|r| {
+            r.status = StatusCode::TOO_MANY_REQUESTS;
+        });
+        self.retry_after(retry_after)
+    }
+
     // Common server errors
 
     /// 500 Internal Server Error
@@ -264,3 +781,82 @@ impl Then {
         self
     }
 }
+
+/// Status and body convenience methods.
+impl Then {
+    /// 400 Bad Request with a JSON body.
+    pub fn bad_request_json(self, body: impl serde::Serialize) -> Self {
+        self.bad_request().json(body)
+    }
+
+    /// 401 Unauthorized with a JSON body.
+    pub fn unauthorized_json(self, body: impl serde::Serialize) -> Self {
+        self.unauthorized().json(body)
+    }
+
+    /// 403 Forbidden with a JSON body.
+    pub fn forbidden_json(self, body: impl serde::Serialize) -> Self {
+        self.forbidden().json(body)
+    }
+
+    /// 404 Not Found with a JSON body.
+    pub fn not_found_json(self, body: impl serde::Serialize) -> Self {
+        self.not_found().json(body)
+    }
+
+    /// 409 Conflict with a JSON body.
+    pub fn conflict_json(self, body: impl serde::Serialize) -> Self {
+        self.conflict().json(body)
+    }
+
+    /// 422 Unprocessable Content with a JSON body.
+    pub fn unprocessable_content_json(self, body: impl serde::Serialize) -> Self {
+        self.unprocessable_content().json(body)
+    }
+
+    /// 500 Internal Server Error with a JSON body.
+    pub fn internal_server_error_json(self, body: impl serde::Serialize) -> Self {
+        self.internal_server_error().json(body)
+    }
+}
+
+/// gRPC status convenience methods.
+///
+/// gRPC status codes are mapped from HTTP status codes,
+/// see [`Code::from_http`](crate::Code::from_http).
+impl Then {
+    /// `InvalidArgument` status and message.
+    pub fn grpc_invalid_argument(self, message: impl Into<String>) -> Self {
+        self.error(StatusCode::UNPROCESSABLE_ENTITY, message)
+    }
+
+    /// `Unauthenticated` status and message.
+    pub fn grpc_unauthenticated(self, message: impl Into<String>) -> Self {
+        self.error(StatusCode::UNAUTHORIZED, message)
+    }
+
+    /// `PermissionDenied` status and message.
+    pub fn grpc_permission_denied(self, message: impl Into<String>) -> Self {
+        self.error(StatusCode::FORBIDDEN, message)
+    }
+
+    /// `NotFound` status and message.
+    pub fn grpc_not_found(self, message: impl Into<String>) -> Self {
+        self.error(StatusCode::NOT_FOUND, message)
+    }
+
+    /// `Internal` status and message.
+    pub fn grpc_internal(self, message: impl Into<String>) -> Self {
+        self.error(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+
+    /// `Unimplemented` status and message.
+    pub fn grpc_unimplemented(self, message: impl Into<String>) -> Self {
+        self.error(StatusCode::NOT_IMPLEMENTED, message)
+    }
+
+    /// `Unavailable` status and message.
+    pub fn grpc_unavailable(self, message: impl Into<String>) -> Self {
+        self.error(StatusCode::SERVICE_UNAVAILABLE, message)
+    }
+}
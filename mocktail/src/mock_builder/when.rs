@@ -1,13 +1,17 @@
 //! When
-use std::{cell::Cell, rc::Rc, sync::Arc};
+use std::{cell::Cell, cmp::Ordering, rc::Rc, sync::Arc};
 
 use bytes::Bytes;
+use uuid::Uuid;
 
+#[cfg(feature = "sigv4")]
+use crate::auth::SigV4;
 use crate::{
+    auth::Credentials,
     body::Body,
     headers::{HeaderName, HeaderValue, Headers},
     matchers,
-    matchers::Matcher,
+    matchers::{HeaderCompare, Matcher, PbCompare},
     request::Method,
 };
 
@@ -42,12 +46,32 @@ impl When {
         self
     }
 
+    /// Any HTTP method.
+    /// Can be combined with other matchers, e.g. a path.
+    pub fn any_method(self) -> Self {
+        self.push(matchers::any_method());
+        self
+    }
+
+    /// Any path.
+    /// Can be combined with other matchers, e.g. a method.
+    pub fn any_path(self) -> Self {
+        self.push(matchers::any_path());
+        self
+    }
+
     /// HTTP method.
     pub fn method(self, method: impl Into<Method>) -> Self {
         self.push(matchers::method(method.into()));
         self
     }
 
+    /// HTTP method any, matches any of the methods.
+    pub fn method_any(self, methods: impl IntoIterator<Item = impl Into<Method>>) -> Self {
+        self.push(matchers::method_any(methods));
+        self
+    }
+
     /// Path.
     pub fn path(self, path: impl Into<String>) -> Self {
         self.push(matchers::path(path));
@@ -60,12 +84,39 @@ impl When {
         self
     }
 
+    /// Path template with `{name}` parameter segments, e.g. `/orders/{id}`.
+    ///
+    /// The parameters are recorded with journal entries,
+    /// see [`JournalEntry::path_param`](crate::JournalEntry::path_param).
+    pub fn path_template(self, template: impl Into<String>) -> Self {
+        self.push(matchers::path_template(template));
+        self
+    }
+
     /// Body.
     pub fn body(self, body: Body) -> Self {
         self.push(matchers::body(body));
         self
     }
 
+    /// Body prefix.
+    ///
+    /// Streaming requests are matched as soon as the prefix is received,
+    /// and the response is sent while the rest of the request is received,
+    /// e.g. for full-duplex protocols.
+    pub fn body_prefix(self, prefix: Body) -> Self {
+        self.push(matchers::body_prefix(prefix));
+        self
+    }
+
+    /// Body SHA-256 digest, hex-encoded.
+    ///
+    /// Matches large bodies without embedding the expected bytes.
+    pub fn body_sha256(self, hex: impl Into<String>) -> Self {
+        self.push(matchers::body_sha256(hex));
+        self
+    }
+
     /// Headers.
     ///
     /// Cannonicalizes passed in header name and values to ensure matching is done in a
@@ -97,12 +148,100 @@ impl When {
         self
     }
 
+    /// Authorization header with credentials.
+    pub fn authorization(self, credentials: Credentials) -> Self {
+        self.push(matchers::authorization(credentials));
+        self
+    }
+
+    /// Authorization header with `Basic` credentials.
+    pub fn basic_auth(self, user: impl Into<String>, password: impl Into<String>) -> Self {
+        self.authorization(Credentials::basic(user, password))
+    }
+
+    /// Authorization header with a `Bearer` token.
+    pub fn bearer_token(self, token: impl Into<String>) -> Self {
+        self.authorization(Credentials::bearer(token))
+    }
+
+    /// AWS Signature Version 4 signature, in the `Authorization` header or a presigned URL.
+    #[cfg(feature = "sigv4")]
+    pub fn aws_sigv4(self, sigv4: SigV4) -> Self {
+        self.push(matchers::aws_sigv4(sigv4));
+        self
+    }
+
+    /// Header, comparing values with options, e.g. case-insensitively.
+    pub fn header_with(
+        self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+        compare: HeaderCompare,
+    ) -> Self {
+        self.push(matchers::header_with(name, value, compare));
+        self
+    }
+
     /// Header exists.
     pub fn header_exists(self, name: impl Into<String>) -> Self {
         self.push(matchers::header_exists(name));
         self
     }
 
+    /// SOAP action, from the SOAP 1.1 `SOAPAction` header or
+    /// the SOAP 1.2 `action` content type parameter.
+    #[cfg(feature = "soap")]
+    pub fn soap_action(self, action: impl Into<String>) -> Self {
+        self.push(matchers::soap_action(action));
+        self
+    }
+
+    /// XML body with a node at the XPath.
+    ///
+    /// See [`soap::select`](crate::soap::select) for the supported XPath subset.
+    #[cfg(feature = "soap")]
+    pub fn xpath(self, path: impl Into<String>) -> Self {
+        self.push(matchers::xpath(path));
+        self
+    }
+
+    /// XML body with a node at the XPath with the text value.
+    #[cfg(feature = "soap")]
+    pub fn xpath_eq(self, path: impl Into<String>, value: impl Into<String>) -> Self {
+        self.push(matchers::xpath_eq(path, value));
+        self
+    }
+
+    /// Well-formed W3C `traceparent` header.
+    pub fn traceparent(self) -> Self {
+        self.push(matchers::traceparent());
+        self
+    }
+
+    /// Well-formed W3C `traceparent` header with trace ID.
+    pub fn trace_id(self, trace_id: impl Into<String>) -> Self {
+        self.push(matchers::trace_id(trace_id));
+        self
+    }
+
+    /// W3C `baggage` header member.
+    pub fn baggage(self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.push(matchers::baggage(key, value));
+        self
+    }
+
+    /// Well-formed `x-request-id` header.
+    pub fn request_id(self) -> Self {
+        self.push(matchers::request_id());
+        self
+    }
+
+    /// Well-formed `x-request-id` header with value.
+    pub fn request_id_eq(self, id: impl Into<String>) -> Self {
+        self.push(matchers::request_id_eq(id));
+        self
+    }
+
     /// Query params.
     pub fn query_params(
         self,
@@ -124,6 +263,60 @@ impl When {
         self
     }
 
+    /// Matched after another mock has been matched at least once.
+    pub fn after(self, mock_id: Uuid) -> Self {
+        self.push(matchers::after(mock_id, 1));
+        self
+    }
+
+    /// Matched after another mock has been matched at least `n` times.
+    pub fn after_times(self, mock_id: Uuid, n: usize) -> Self {
+        self.push(matchers::after(mock_id, n));
+        self
+    }
+
+    /// Received on the nth connection accepted by the server, starting at 1,
+    /// e.g. to fail the first connection and succeed on reconnect.
+    pub fn connection(self, n: usize) -> Self {
+        self.push(matchers::connection(n));
+        self
+    }
+
+    /// Sent from a client address in a CIDR block, e.g. `10.0.0.0/8`,
+    /// or from an address, e.g. `127.0.0.1`.
+    ///
+    /// Panics if the CIDR block is invalid.
+    pub fn peer_ip(self, cidr: &str) -> Self {
+        self.push(matchers::peer_ip(cidr));
+        self
+    }
+
+    /// Sent in a session issued by the server with
+    /// [`Then::start_session`](super::Then::start_session),
+    /// e.g. to test clients maintaining sticky sessions.
+    pub fn same_session(self) -> Self {
+        self.push(matchers::same_session());
+        self
+    }
+
+    /// Server counter is equal to the value.
+    pub fn counter_eq(self, name: impl Into<String>, value: i64) -> Self {
+        self.push(matchers::counter(name, Ordering::Equal, value));
+        self
+    }
+
+    /// Server counter is greater than the value.
+    pub fn counter_gt(self, name: impl Into<String>, value: i64) -> Self {
+        self.push(matchers::counter(name, Ordering::Greater, value));
+        self
+    }
+
+    /// Server counter is less than the value.
+    pub fn counter_lt(self, name: impl Into<String>, value: i64) -> Self {
+        self.push(matchers::counter(name, Ordering::Less, value));
+        self
+    }
+
     /// Custom matcher.
     pub fn matcher(self, matcher: impl Matcher) -> Self {
         self.push(matcher);
@@ -145,6 +338,33 @@ impl When {
         self
     }
 
+    /// Raw bytes body, read from a file, e.g. a firmware or image fixture.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file cannot be read.
+    pub fn bytes_file(self, path: impl AsRef<std::path::Path>) -> Self {
+        let body = std::fs::read(path).expect("failed to read body file");
+        self.bytes(body)
+    }
+
+    /// Raw bytes body prefix, see [`When::body_prefix`].
+    pub fn bytes_prefix(self, prefix: impl Into<Bytes>) -> Self {
+        self.body_prefix(Body::bytes(prefix.into()))
+    }
+
+    /// Raw bytes body suffix.
+    pub fn bytes_suffix(self, suffix: impl Into<Bytes>) -> Self {
+        self.push(matchers::body_suffix(suffix));
+        self
+    }
+
+    /// Raw bytes at an offset of the body, e.g. a magic number or header field.
+    pub fn bytes_at(self, offset: usize, bytes: impl Into<Bytes>) -> Self {
+        self.push(matchers::body_at(offset, bytes));
+        self
+    }
+
     /// Raw bytes stream body.
     pub fn bytes_stream(self, messages: impl IntoIterator<Item = impl Into<Bytes>>) -> Self {
         self.push(matchers::body(Body::bytes_stream(messages)));
@@ -189,11 +409,50 @@ impl When {
         self
     }
 
+    /// Protobuf body, compared semantically with options.
+    ///
+    /// See [`PbCompareMatcher`](matchers::PbCompareMatcher) for how messages are compared.
+    pub fn pb_with(self, body: impl prost::Message, compare: PbCompare) -> Self {
+        self.push(matchers::pb_with(body, compare));
+        self
+    }
+
+    /// gRPC service, matching calls of any of its methods, e.g. `pkg.Service`.
+    pub fn grpc_service(self, service: impl Into<String>) -> Self {
+        self.push(matchers::grpc_service(service));
+        self
+    }
+
+    /// gRPC full method name regex, e.g. `^pkg\.Service/(Get|List)`.
+    ///
+    /// Panics if the regex is invalid.
+    pub fn grpc_method_regex(self, regex: &str) -> Self {
+        self.push(matchers::grpc_method_regex(regex));
+        self
+    }
+
+    /// Protobuf message of a streaming body at an index, starting at 0,
+    /// e.g. `grpc_message_at(2, matchers::body(Body::pb(message)))`.
+    ///
+    /// Other messages of the stream are not matched.
+    pub fn grpc_message_at(self, index: usize, matcher: impl Matcher) -> Self {
+        self.push(matchers::grpc_message_at(index, matcher));
+        self
+    }
+
     /// Protobuf streaming body.
     pub fn pb_stream(self, messages: impl IntoIterator<Item = impl prost::Message>) -> Self {
         self.push(matchers::body(Body::pb_stream(messages)));
         self
     }
+
+    /// Protobuf streaming body starting with the messages.
+    ///
+    /// See [`When::body_prefix`].
+    pub fn pb_stream_prefix(self, messages: impl IntoIterator<Item = impl prost::Message>) -> Self {
+        self.push(matchers::body_prefix(Body::pb_stream(messages)));
+        self
+    }
 }
 
 /// Method convenience methods.
@@ -0,0 +1,617 @@
+//! Mock definition files
+//!
+//! Mocks can be defined in JSON or YAML files, e.g.
+//!
+//! ```json
+//! {
+//!   "mocks": [
+//!     {
+//!       "request": { "method": "GET", "path": "/tenants/${vars.tenant}" },
+//!       "response": {
+//!         "status": 200,
+//!         "headers": { "x-api-key": "${env.API_KEY}" },
+//!         "body": { "json": { "name": "${vars.tenant}" } }
+//!       }
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! With the `yaml` feature, files with a `.yaml` or `.yml` extension are
+//! loaded as YAML, e.g.
+//!
+//! ```yaml
+//! mocks:
+//!   - request: { method: GET, path: "/tenants/${vars.tenant}" }
+//!     response: { status: 200, body: { text: ok } }
+//! ```
+//!
+//! Settings shared by the mocks of a file, e.g. response headers, can be
+//! defined once as `defaults`, see [`DefaultsDefinition`].
+//!
+//! Files can include other files of either format, e.g. shared per-service
+//! fixtures, with paths relative to the including file:
+//!
+//! ```json
+//! { "include": ["../shared/auth.json"], "mocks": [] }
+//! ```
+//!
+//! Included mocks inherit the defaults of the including file.
+//!
+//! Strings may reference variables, resolved when the file is loaded:
+//! - `${env.NAME}`: the `NAME` environment variable
+//! - `${vars.name}`: the `name` variable provided to the [`Loader`]
+//!
+//! Use `$${` for a literal `${`.
+//!
+//! Mocks built in code can be exported with [`MockSet::to_file`], e.g. to share
+//! fixtures with non-Rust tools, as long as they only use the matchers and
+//! response settings of definition files.
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    body::Body,
+    matchers,
+    mock::{Mock, DEFAULT_PRIORITY},
+    mock_builder::{Then, When},
+    mock_set::{MockDefaults, MockSet},
+    openapi,
+    request::Method,
+    status::StatusCode,
+    Error,
+};
+
+/// A mock definition file format.
+#[non_exhaustive]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockFileFormat {
+    /// Pretty-printed JSON.
+    #[default]
+    Json,
+    /// YAML, with the `yaml` feature.
+    Yaml,
+}
+
+impl MockFileFormat {
+    /// Returns the format of a file by its extension: YAML for `.yaml` and
+    /// `.yml` files, JSON otherwise.
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("yaml" | "yml") => Self::Yaml,
+            _ => Self::Json,
+        }
+    }
+}
+
+/// A mock definition file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MockFile {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub defaults: Option<DefaultsDefinition>,
+    #[serde(default)]
+    pub mocks: Vec<MockDefinition>,
+}
+
+/// Defaults inherited by the mocks of a mock definition file, unless
+/// overridden by a mock, see [`MockDefaults`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DefaultsDefinition {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u8>,
+}
+
+impl DefaultsDefinition {
+    /// Creates the mock set defaults.
+    pub fn into_defaults(self) -> MockDefaults {
+        let mut defaults = MockDefaults::new().headers(self.headers);
+        if let Some(content_type) = self.content_type {
+            defaults = defaults.content_type(content_type);
+        }
+        if let Some(delay) = self.delay_ms {
+            defaults = defaults.delay(Duration::from_millis(delay));
+        }
+        if let Some(priority) = self.priority {
+            defaults = defaults.priority(priority);
+        }
+        defaults
+    }
+}
+
+/// A mock definition.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MockDefinition {
+    #[serde(default)]
+    pub request: RequestDefinition,
+    #[serde(default)]
+    pub response: ResponseDefinition,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+/// Request match conditions of a mock definition.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RequestDefinition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_prefix: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub query: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<BodyDefinition>,
+}
+
+/// Response of a mock definition.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseDefinition {
+    #[serde(default = "default_status")]
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<BodyDefinition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl Default for ResponseDefinition {
+    fn default() -> Self {
+        Self {
+            status: default_status(),
+            headers: BTreeMap::new(),
+            body: None,
+            message: None,
+        }
+    }
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+/// A request or response body of a mock definition.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyDefinition {
+    Json(Value),
+    Text(String),
+}
+
+impl BodyDefinition {
+    /// Creates a definition from a body, text if it is UTF-8.
+    ///
+    /// Response bodies are JSON if their content type is JSON. Request bodies,
+    /// matched by their bytes either way, are JSON if they are serialized
+    /// as a JSON value would be.
+    pub(crate) fn from_body(
+        body: &Body,
+        content_type: Option<&str>,
+    ) -> Result<Option<Self>, String> {
+        let bytes = body.clone().as_bytes();
+        if bytes.is_empty() {
+            return Ok(None);
+        }
+        if let Ok(value) = serde_json::from_slice::<Value>(&bytes) {
+            let json = match content_type {
+                Some(content_type) => openapi::is_json(content_type),
+                None => serde_json::to_vec(&value).is_ok_and(|json| json == bytes),
+            };
+            if json {
+                return Ok(Some(Self::Json(value)));
+            }
+        }
+        match std::str::from_utf8(&bytes) {
+            Ok(text) => Ok(Some(Self::Text(text.into()))),
+            Err(_) => Err("binary bodies are not supported".into()),
+        }
+    }
+}
+
+impl MockDefinition {
+    /// Creates a definition from a mock.
+    ///
+    /// Only the status, headers, body and message of the response are exported.
+    /// Fails if a matcher can't be expressed in a definition file.
+    pub fn from_mock(mock: &Mock) -> Result<Self, Error> {
+        let invalid = |err: String| Error::Invalid(format!("mock {}: {err}", mock.id));
+        let mut request = RequestDefinition::default();
+        for matcher in &mock.matchers {
+            matchers::define(matcher.as_ref(), &mut request).map_err(invalid)?;
+        }
+        let response = mock.response();
+        let mut headers = BTreeMap::new();
+        for (name, value) in response.headers().iter() {
+            if headers
+                .insert(name.to_string(), value.to_string())
+                .is_some()
+            {
+                return Err(invalid(format!(
+                    "response header `{}` has multiple values",
+                    name.as_str()
+                )));
+            }
+        }
+        Ok(Self {
+            request,
+            response: ResponseDefinition {
+                status: response.status().as_u16(),
+                headers,
+                body: BodyDefinition::from_body(
+                    response.body(),
+                    Some(
+                        response
+                            .headers()
+                            .get("content-type")
+                            .map_or("", |value| value.as_str()),
+                    ),
+                )
+                .map_err(invalid)?,
+                message: response.message().map(Into::into),
+            },
+            priority: (mock.priority != DEFAULT_PRIORITY).then_some(mock.priority),
+            limit: mock.limit,
+        })
+    }
+
+    /// Builds the mock.
+    pub fn into_mock(self) -> Result<Mock, Error> {
+        let request = self.request;
+        let method = request
+            .method
+            .map(|method| method.parse::<Method>())
+            .transpose()
+            .map_err(Error::Invalid)?;
+        let response = self.response;
+        let status = StatusCode::from_u16(response.status)?;
+        let mut mock = Mock::new(|mut when: When, mut then: Then| {
+            if let Some(method) = method {
+                when = when.method(method);
+            }
+            if let Some(path) = request.path {
+                when = when.path(path);
+            }
+            if let Some(prefix) = request.path_prefix {
+                when = when.path_prefix(prefix);
+            }
+            if !request.headers.is_empty() {
+                when = when.headers(request.headers);
+            }
+            for (key, value) in request.query {
+                when = when.query_param(key, value);
+            }
+            match request.body {
+                Some(BodyDefinition::Json(body)) => {
+                    when.json(body);
+                }
+                Some(BodyDefinition::Text(body)) => {
+                    when.text(body);
+                }
+                None => {}
+            }
+            then = then.status(status).headers(response.headers);
+            if let Some(message) = response.message {
+                then = then.message(message);
+            }
+            match response.body {
+                Some(BodyDefinition::Json(body)) => {
+                    then.json(body);
+                }
+                Some(BodyDefinition::Text(body)) => {
+                    then.text(body);
+                }
+                None => {}
+            }
+        });
+        if let Some(priority) = self.priority {
+            mock = mock.with_priority(priority);
+        }
+        if let Some(limit) = self.limit {
+            mock = mock.with_limit(limit);
+        }
+        Ok(mock)
+    }
+}
+
+/// Loads mock definition files, resolving variables.
+#[derive(Debug, Clone, Default)]
+pub struct Loader {
+    vars: BTreeMap<String, String>,
+}
+
+impl Loader {
+    /// Creates a loader without variables.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a variable, referenced as `${vars.name}`.
+    pub fn var(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.vars.insert(name.into(), value.into());
+        self
+    }
+
+    /// Adds variables, referenced as `${vars.name}`.
+    pub fn vars(
+        mut self,
+        vars: impl IntoIterator<Item = (impl Into<String>, impl Into<String>)>,
+    ) -> Self {
+        self.vars
+            .extend(vars.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    /// Loads mocks from a file, with the files it includes, in the format
+    /// given by its extension, see [`MockFileFormat::from_path`].
+    pub fn load_file(&self, path: impl AsRef<Path>) -> Result<MockSet, Error> {
+        self.load_path(path.as_ref(), &mut Vec::new())
+    }
+
+    /// Loads mocks from JSON, with the files it includes, relative to the
+    /// current directory.
+    pub fn load_json(&self, json: &str) -> Result<MockSet, Error> {
+        self.load(json, MockFileFormat::Json, Path::new(""), &mut Vec::new())
+    }
+
+    /// Loads mocks from YAML, with the files it includes, relative to the
+    /// current directory.
+    pub fn load_yaml(&self, yaml: &str) -> Result<MockSet, Error> {
+        self.load(yaml, MockFileFormat::Yaml, Path::new(""), &mut Vec::new())
+    }
+
+    /// Loads a file, given the files including it.
+    fn load_path(&self, path: &Path, including: &mut Vec<PathBuf>) -> Result<MockSet, Error> {
+        let canonical = path.canonicalize()?;
+        if including.contains(&canonical) {
+            return Err(Error::Invalid(format!(
+                "include cycle: {} includes itself",
+                path.display()
+            )));
+        }
+        let contents = std::fs::read_to_string(path)?;
+        including.push(canonical);
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let mocks = self.load(&contents, MockFileFormat::from_path(path), dir, including);
+        including.pop();
+        mocks
+    }
+
+    /// Loads mocks in a format, resolving includes relative to `dir`.
+    fn load(
+        &self,
+        contents: &str,
+        format: MockFileFormat,
+        dir: &Path,
+        including: &mut Vec<PathBuf>,
+    ) -> Result<MockSet, Error> {
+        let mut value: Value = match format {
+            MockFileFormat::Json => serde_json::from_str(contents)?,
+            MockFileFormat::Yaml => from_yaml(contents)?,
+        };
+        self.resolve(&mut value)?;
+        let file: MockFile = serde_json::from_value(value)?;
+        let mut mocks = MockSet::new().with_defaults(
+            file.defaults
+                .map(DefaultsDefinition::into_defaults)
+                .unwrap_or_default(),
+        );
+        for definition in file.mocks {
+            mocks.insert(definition.into_mock()?);
+        }
+        for include in file.include {
+            let path = dir.join(&include);
+            let included = self.load_path(&path, including).map_err(|err| {
+                let err = match err {
+                    Error::Invalid(err) => err,
+                    err => err.to_string(),
+                };
+                Error::Invalid(format!("failed to include {}: {err}", path.display()))
+            })?;
+            mocks.insert_set(included);
+        }
+        Ok(mocks)
+    }
+
+    /// Resolves variables in the strings of a JSON value.
+    fn resolve(&self, value: &mut Value) -> Result<(), Error> {
+        match value {
+            Value::String(s) => *s = self.interpolate(s)?,
+            Value::Array(values) => {
+                for value in values {
+                    self.resolve(value)?;
+                }
+            }
+            Value::Object(map) => {
+                for value in map.values_mut() {
+                    self.resolve(value)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Replaces the variable references in a string.
+    fn interpolate(&self, s: &str) -> Result<String, Error> {
+        let mut out = String::with_capacity(s.len());
+        let mut rest = s;
+        while let Some(start) = rest.find("${") {
+            if rest[..start].ends_with('$') {
+                // Escaped literal
+                out.push_str(&rest[..start - 1]);
+                out.push_str("${");
+                rest = &rest[start + 2..];
+                continue;
+            }
+            out.push_str(&rest[..start]);
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| Error::Invalid(format!("unterminated variable in `{s}`")))?;
+            let name = &rest[start + 2..start + end];
+            let value = match name.split_once('.') {
+                Some(("env", key)) => std::env::var(key).ok(),
+                Some(("vars", key)) => self.vars.get(key).cloned(),
+                _ => None,
+            };
+            let value =
+                value.ok_or_else(|| Error::Invalid(format!("undefined variable: {name}")))?;
+            out.push_str(&value);
+            rest = &rest[start + end + 1..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+}
+
+impl MockSet {
+    /// Loads mocks from a JSON or YAML mock definition file, see [`mock_file`](crate::mock_file).
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Loader::new().load_file(path)
+    }
+
+    /// Creates a mock definition file from the mocks, see [`MockDefinition::from_mock`].
+    ///
+    /// The mocks are exported with the defaults they inherited applied.
+    pub fn to_mock_file(&self) -> Result<MockFile, Error> {
+        Ok(MockFile {
+            include: Vec::new(),
+            defaults: None,
+            mocks: self
+                .iter()
+                .map(MockDefinition::from_mock)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Writes the mocks to a mock definition file, see [`MockSet::to_mock_file`].
+    pub fn to_file(&self, path: impl AsRef<Path>, format: MockFileFormat) -> Result<(), Error> {
+        let file = self.to_mock_file()?;
+        let contents = match format {
+            MockFileFormat::Json => serde_json::to_string_pretty(&file)? + "\n",
+            MockFileFormat::Yaml => to_yaml(&file)?,
+        };
+        Ok(std::fs::write(path, contents)?)
+    }
+}
+
+/// Parses a YAML mock definition file.
+#[cfg(feature = "yaml")]
+fn from_yaml(contents: &str) -> Result<Value, Error> {
+    Ok(serde_norway::from_str(contents)?)
+}
+
+#[cfg(not(feature = "yaml"))]
+fn from_yaml(_contents: &str) -> Result<Value, Error> {
+    Err(yaml_unsupported())
+}
+
+/// Serializes a YAML mock definition file.
+///
+/// The file is serialized as a JSON value, so that bodies are written as
+/// maps, e.g. `json: ...`, rather than YAML tags.
+#[cfg(feature = "yaml")]
+fn to_yaml(file: &MockFile) -> Result<String, Error> {
+    Ok(serde_norway::to_string(&serde_json::to_value(file)?)?)
+}
+
+#[cfg(not(feature = "yaml"))]
+fn to_yaml(_file: &MockFile) -> Result<String, Error> {
+    Err(yaml_unsupported())
+}
+
+#[cfg(not(feature = "yaml"))]
+fn yaml_unsupported() -> Error {
+    Error::Invalid("YAML mock files require the `yaml` feature".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolate() {
+        let loader = Loader::new().var("tenant", "acme");
+        std::env::set_var("MOCKTAIL_TEST_API_KEY", "secret");
+        assert_eq!(
+            loader
+                .interpolate("/tenants/${vars.tenant}?key=${env.MOCKTAIL_TEST_API_KEY}")
+                .unwrap(),
+            "/tenants/acme?key=secret"
+        );
+        assert_eq!(
+            loader.interpolate("$${vars.tenant}").unwrap(),
+            "${vars.tenant}"
+        );
+        assert!(loader.interpolate("${vars.missing}").is_err());
+        assert!(loader.interpolate("${vars.tenant").is_err());
+    }
+
+    #[test]
+    fn test_body_definition() {
+        let mut mocks = MockSet::new();
+        mocks.mock(|when, then| {
+            when.path("/count").json(42);
+            then.text("42");
+        });
+        mocks.mock(|when, then| {
+            when.path("/total");
+            then.json(42);
+        });
+        let file = mocks.to_mock_file().unwrap();
+        let bodies = file
+            .mocks
+            .iter()
+            .map(|mock| (mock.request.body.clone(), mock.response.body.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            bodies,
+            [
+                (
+                    Some(BodyDefinition::Json(42.into())),
+                    Some(BodyDefinition::Text("42".into()))
+                ),
+                (None, Some(BodyDefinition::Json(42.into()))),
+            ]
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_to_file_yaml() {
+        let mut mocks = MockSet::new();
+        mocks.mock(|when, then| {
+            when.get().path("/orders");
+            then.json(serde_json::json!([{"id": 1}]));
+        });
+        let path =
+            std::env::temp_dir().join(format!("mocktail-export-{}.yaml", std::process::id()));
+        mocks.to_file(&path, MockFileFormat::Yaml).unwrap();
+        let yaml = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(yaml.starts_with("mocks:\n"));
+        assert!(yaml.contains("json:") && !yaml.contains('!'));
+        let value = from_yaml(&yaml).unwrap();
+        let file: MockFile = serde_json::from_value(value).unwrap();
+        assert_eq!(file, mocks.to_mock_file().unwrap());
+    }
+}
@@ -1,13 +1,34 @@
 //! Mock set
+use std::{
+    fmt,
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, SystemTime},
+};
+
+use uuid::Uuid;
+
 use crate::{
-    mock::Mock,
+    auth::Challenge,
+    clock::Clock,
+    headers::{HeaderName, HeaderValue, Headers},
+    matchers::subsumes,
+    mock::{Mock, DEFAULT_PRIORITY},
     mock_builder::{Then, When},
     request::Request,
+    response::{Delay, Response},
 };
 
 /// A set of mocks.
+///
+/// Mocks are evaluated in a deterministic order: by priority, highest
+/// (lowest value) first, then in insertion order. The first mock matching
+/// a request is matched.
 #[derive(Default, Debug, Clone)]
-pub struct MockSet(Vec<Mock>);
+pub struct MockSet {
+    mocks: Vec<Mock>,
+    clock: Option<Arc<dyn Clock>>,
+    defaults: MockDefaults,
+}
 
 impl MockSet {
     /// Creates an empty mockset.
@@ -17,38 +38,93 @@ impl MockSet {
 
     /// Returns the number of mocks.
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.mocks.len()
     }
 
     /// Returns true if empty.
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.mocks.is_empty()
+    }
+
+    /// Sets the defaults inherited by the mocks of the set, see [`MockDefaults`].
+    ///
+    /// The defaults are applied to the mocks already inserted and to the mocks
+    /// inserted later.
+    pub fn with_defaults(mut self, defaults: MockDefaults) -> Self {
+        for mock in &mut self.mocks {
+            defaults.apply(mock);
+        }
+        self.mocks.sort_by_key(|mock| mock.priority());
+        self.defaults = defaults;
+        self
+    }
+
+    /// Returns the defaults inherited by the mocks of the set.
+    pub fn defaults(&self) -> &MockDefaults {
+        &self.defaults
     }
 
-    /// Inserts a mock.
-    pub fn insert(&mut self, mock: Mock) {
+    /// Inserts a mock, applying the defaults of the set.
+    ///
+    /// The insertion time, the start of an [`expires_after`](Mock::expires_after)
+    /// expiration, is taken from the clock of the server the set belongs to.
+    pub fn insert(&mut self, mut mock: Mock) {
+        self.defaults.apply(&mut mock);
         if !self.contains(&mock) {
-            self.0.push(mock);
-            self.0.sort_by_key(|mock| mock.priority());
+            mock.inserted_at.get_or_insert_with(|| self.now());
+            self.mocks.push(mock);
+            self.mocks.sort_by_key(|mock| mock.priority());
+        }
+    }
+
+    /// Inserts the mocks of another set, e.g. the fixtures of a feature.
+    ///
+    /// The mocks keep the defaults of their set, and inherit the defaults
+    /// of this set for the settings neither they nor their set define.
+    pub fn insert_set(&mut self, set: MockSet) {
+        for mock in set {
+            self.insert(mock);
         }
     }
 
+    /// Sets the clock, restarting the expirations of the mocks from its current time.
+    pub(crate) fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        let now = clock.now();
+        for mock in &mut self.mocks {
+            mock.inserted_at = Some(now);
+        }
+        self.clock = Some(clock);
+    }
+
+    pub(crate) fn clock(&self) -> Option<&Arc<dyn Clock>> {
+        self.clock.as_ref()
+    }
+
+    fn now(&self) -> SystemTime {
+        self.clock
+            .as_ref()
+            .map(|clock| clock.now())
+            .unwrap_or_else(SystemTime::now)
+    }
+
     // Returns `true` if the mockset contains the mock.
     pub fn contains(&self, mock: &Mock) -> bool {
-        self.0.contains(mock)
+        self.mocks.contains(mock)
     }
 
-    /// Builds and inserts a mock with default options.
-    pub fn mock<F>(&mut self, f: F)
+    /// Builds and inserts a mock with default options, returning its ID.
+    pub fn mock<F>(&mut self, f: F) -> Uuid
     where
         F: FnOnce(When, Then),
     {
         let mock = Mock::new(f);
+        let id = mock.id;
         self.insert(mock);
+        id
     }
 
-    /// Builds and inserts a mock with options.
-    pub fn mock_with_options<F>(&mut self, priority: u8, limit: Option<usize>, f: F)
+    /// Builds and inserts a mock with options, returning its ID.
+    pub fn mock_with_options<F>(&mut self, priority: u8, limit: Option<usize>, f: F) -> Uuid
     where
         F: FnOnce(When, Then),
     {
@@ -56,7 +132,28 @@ impl MockSet {
         if let Some(limit) = limit {
             mock = mock.with_limit(limit);
         }
+        let id = mock.id;
+        self.insert(mock);
+        id
+    }
+
+    /// Builds and inserts a mock requiring the credentials of a challenge,
+    /// returning its ID.
+    ///
+    /// Requests matching the mock without the credentials are answered with
+    /// `401 Unauthorized` and the `WWW-Authenticate` headers of the challenge.
+    pub fn mock_with_challenge<F>(&mut self, challenge: Challenge, f: F) -> Uuid
+    where
+        F: FnOnce(When, Then),
+    {
+        // The challenge mock is matched after the mock with its defaults
+        let mut mock = Mock::new(f);
+        self.defaults.apply(&mut mock);
+        let (mock, challenge) = challenge.mocks(mock);
+        let id = mock.id;
         self.insert(mock);
+        self.insert(challenge);
+        id
     }
 
     /// Finds a mock by predicate.
@@ -64,27 +161,175 @@ impl MockSet {
     where
         P: FnMut(&&Mock) -> bool,
     {
-        self.0.iter().find(predicate)
+        self.mocks.iter().find(predicate)
     }
 
     /// Removes a mock by index.
     pub fn remove(&mut self, index: usize) -> Mock {
-        self.0.remove(index)
+        self.mocks.remove(index)
     }
 
     /// Clears the mockset.
     pub fn clear(&mut self) {
-        self.0.clear()
+        self.mocks.clear()
+    }
+
+    /// Retains only the mocks specified by the predicate.
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&Mock) -> bool,
+    {
+        self.mocks.retain(f)
     }
 
     /// Returns an iterator over the mockset.
     pub fn iter(&self) -> std::slice::Iter<'_, Mock> {
-        self.0.iter()
+        self.mocks.iter()
+    }
+
+    /// Returns the evaluation of a request against each mock, in the order
+    /// the mocks are evaluated, without incrementing match counters.
+    ///
+    /// The first mock evaluated as matched is the mock the request would match.
+    pub fn evaluation_order(&self, request: &Request) -> Vec<Evaluation> {
+        self.mocks
+            .iter()
+            .map(|mock| Evaluation {
+                mock_id: mock.id,
+                priority: mock.priority,
+                matched: mock.would_match(request),
+            })
+            .collect()
     }
 
     /// Matches a request to a mock.
     pub fn match_by_request(&self, request: &Request) -> Option<Mock> {
-        self.0.iter().find(|&mock| mock.matches(request)).cloned()
+        self.match_by_request_if(request, || true)
+    }
+
+    /// Matches a request to a mock like [`MockSet::match_by_request`],
+    /// incrementing its match counter only if `commit` returns true.
+    pub fn match_by_request_if(
+        &self,
+        request: &Request,
+        commit: impl FnOnce() -> bool,
+    ) -> Option<Mock> {
+        let mock = self.mocks.iter().find(|mock| mock.would_match(request))?;
+        if !commit() {
+            return None;
+        }
+        mock.match_count.fetch_add(1, Ordering::Relaxed);
+        Some(mock.clone())
+    }
+
+    /// Increments the match counter of a mock matched in a snapshot of the set,
+    /// unless it was removed or reached its limit since.
+    pub(crate) fn record_match(&self, id: &Uuid) -> Option<Mock> {
+        let mock = self.mocks.iter().find(|mock| mock.id == *id)?;
+        let limit = mock.limit.or(mock.response.sequence_limit());
+        mock.match_count
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+                limit.is_none_or(|limit| count < limit).then_some(count + 1)
+            })
+            .ok()?;
+        Some(mock.clone())
+    }
+
+    /// Decrements the match counter of a mock incremented by [`MockSet::record_match`].
+    pub(crate) fn withdraw_match(&self, id: &Uuid) {
+        if let Some(mock) = self.mocks.iter().find(|mock| mock.id == *id) {
+            mock.match_count.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the mocks that can never be matched because an earlier mock,
+    /// given priorities, matches every request they would match.
+    ///
+    /// Mocks with a limit, or a response sequence falling through once exhausted,
+    /// are not reported as shadowing later mocks, as the later mocks are matched
+    /// once the limit is reached.
+    pub fn check_conflicts(&self) -> Vec<Conflict> {
+        let mut conflicts = Vec::new();
+        for (i, mock) in self.mocks.iter().enumerate() {
+            let shadowed_by = self.mocks[..i]
+                .iter()
+                .filter(|other| {
+                    other.limit.is_none()
+                        && other.response.sequence_limit().is_none()
+                        && other.expiry().is_none()
+                })
+                .find(|other| shadows(other, mock));
+            if let Some(other) = shadowed_by {
+                let kind = if shadows(mock, other) {
+                    ConflictKind::Duplicate
+                } else {
+                    ConflictKind::Shadowed
+                };
+                conflicts.push(Conflict {
+                    mock_id: mock.id,
+                    shadowed_by: other.id,
+                    kind,
+                });
+            }
+        }
+        conflicts
+    }
+}
+
+/// Returns true if every request matched by `b` is also matched by `a`.
+fn shadows(a: &Mock, b: &Mock) -> bool {
+    (a.namespace.is_none() || a.namespace == b.namespace)
+        && a.matchers.iter().all(|matcher| {
+            b.matchers
+                .iter()
+                .any(|other| subsumes(matcher.as_ref(), other.as_ref()))
+        })
+}
+
+/// The evaluation of a request against a mock, reported by [`MockSet::evaluation_order`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Evaluation {
+    /// ID of the mock.
+    pub mock_id: Uuid,
+    /// Priority of the mock.
+    pub priority: u8,
+    /// Whether the request matches the mock.
+    pub matched: bool,
+}
+
+/// A mock that can never be matched, reported by [`MockSet::check_conflicts`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conflict {
+    /// ID of the mock that is never matched.
+    pub mock_id: Uuid,
+    /// ID of the earlier mock matching its requests.
+    pub shadowed_by: Uuid,
+    pub kind: ConflictKind,
+}
+
+/// The kind of [`Conflict`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    /// The mocks have the same match conditions.
+    Duplicate,
+    /// The earlier mock has broader match conditions.
+    Shadowed,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ConflictKind::Duplicate => write!(
+                f,
+                "mock {} duplicates the match conditions of mock {}",
+                self.mock_id, self.shadowed_by
+            ),
+            ConflictKind::Shadowed => write!(
+                f,
+                "mock {} is shadowed by mock {}",
+                self.mock_id, self.shadowed_by
+            ),
+        }
     }
 }
 
@@ -94,19 +339,128 @@ impl IntoIterator for MockSet {
     type IntoIter = std::vec::IntoIter<Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+        self.mocks.into_iter()
     }
 }
 
 impl FromIterator<Mock> for MockSet {
     fn from_iter<I: IntoIterator<Item = Mock>>(iter: I) -> Self {
-        Self(iter.into_iter().collect())
+        let mut mocks = iter.into_iter().collect::<Vec<_>>();
+        mocks.sort_by_key(|mock| mock.priority());
+        Self {
+            mocks,
+            clock: None,
+            defaults: MockDefaults::default(),
+        }
+    }
+}
+
+/// Defaults inherited by the mocks of a [`MockSet`], e.g. to share response
+/// headers across the fixtures of an API.
+///
+/// A mock overrides a default by setting it: response headers it sets, e.g.
+/// the `content-type` of a JSON body, its response delay, or a priority
+/// other than the default priority.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MockDefaults {
+    headers: Headers,
+    delay: Option<Delay>,
+    priority: Option<u8>,
+}
+
+impl MockDefaults {
+    /// Creates empty defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a default response header.
+    pub fn header(mut self, name: impl Into<HeaderName>, value: impl Into<HeaderValue>) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Adds default response headers.
+    pub fn headers<T, U>(mut self, headers: impl IntoIterator<Item = (T, U)>) -> Self
+    where
+        T: Into<HeaderName>,
+        U: Into<HeaderValue>,
+    {
+        for (name, value) in headers {
+            self.headers.insert(name, value);
+        }
+        self
+    }
+
+    /// Sets the default `content-type` response header.
+    pub fn content_type(mut self, content_type: impl Into<HeaderValue>) -> Self {
+        self.headers.remove("content-type");
+        self.header("content-type", content_type)
+    }
+
+    /// Sets the default response delay.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(Delay::fixed(delay));
+        self
+    }
+
+    /// Sets the default priority.
+    pub fn priority(mut self, priority: u8) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Returns the default response headers.
+    pub fn default_headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    /// Returns the default response delay.
+    pub fn default_delay(&self) -> Option<Delay> {
+        self.delay
+    }
+
+    /// Returns the default priority.
+    pub fn default_priority(&self) -> Option<u8> {
+        self.priority
+    }
+
+    /// Applies the defaults a mock does not override.
+    fn apply(&self, mock: &mut Mock) {
+        if let Some(priority) = self.priority {
+            if mock.priority == DEFAULT_PRIORITY {
+                mock.priority = priority;
+            }
+        }
+        self.apply_response(&mut mock.response);
+    }
+
+    /// Applies the defaults to a response and its alternative responses.
+    fn apply_response(&self, response: &mut Response) {
+        for (name, value) in self.headers.iter() {
+            if !response.headers.contains_name(name.as_str()) {
+                response.headers.insert(name.clone(), value.clone());
+            }
+        }
+        if response.delay.is_none() {
+            response.delay = self.delay;
+        }
+        let alternatives = response
+            .variants
+            .iter_mut()
+            .map(|(_, response)| response)
+            .chain(response.languages.iter_mut().map(|(_, response)| response))
+            .chain(response.sequence.iter_mut());
+        for response in alternatives {
+            self.apply_response(response);
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::request::Method;
 
     #[test]
     fn test_builder() {
@@ -120,5 +474,166 @@ mod test {
             then.text("hello!");
         });
         assert_eq!(mocks.len(), 2);
+        assert!(mocks.check_conflicts().is_empty());
+    }
+
+    #[test]
+    fn test_defaults() {
+        let mut features = MockSet::new().with_defaults(
+            MockDefaults::new()
+                .header("x-feature", "orders")
+                .delay(Duration::from_millis(10)),
+        );
+        let orders = features.mock(|when, then| {
+            when.get().path("/orders");
+            then.text("orders");
+        });
+        let slow = features.mock(|when, then| {
+            when.get().path("/orders/slow");
+            then.text("slow").delay(Duration::from_secs(1));
+        });
+
+        let mut mocks = MockSet::new().with_defaults(
+            MockDefaults::new()
+                .content_type("text/plain")
+                .header("x-feature", "api")
+                .priority(2),
+        );
+        let created = mocks.mock(|when, then| {
+            when.post().path("/orders");
+            then.json("created");
+        });
+        let urgent = mocks.mock_with_options(1, None, |when, then| {
+            when.post().path("/orders/urgent");
+            then.text("urgent");
+        });
+        mocks.insert_set(features);
+
+        let mock = |id| mocks.find(|mock| mock.id == id).unwrap();
+        // Set by the mock
+        assert!(mock(created)
+            .response()
+            .headers()
+            .contains("content-type", "application/json"));
+        assert_eq!(mock(urgent).priority(), 1);
+        assert_eq!(
+            mock(slow).response().delay,
+            Some(Delay::fixed(Duration::from_secs(1)))
+        );
+        // Inherited from the set of the mock, then from the set it is inserted into
+        let headers = mock(orders).response().headers();
+        assert!(headers.contains("x-feature", "orders"));
+        assert!(!headers.contains("x-feature", "api"));
+        assert!(headers.contains("content-type", "text/plain"));
+        assert_eq!(
+            mock(orders).response().delay,
+            Some(Delay::fixed(Duration::from_millis(10)))
+        );
+        assert_eq!(mock(orders).priority(), 2);
+        assert!(mock(created)
+            .response()
+            .headers()
+            .contains("x-feature", "api"));
+    }
+
+    #[test]
+    fn test_evaluation_order() {
+        let mut mocks = MockSet::new();
+        let a = mocks.mock(|when, then| {
+            when.get().path_prefix("/users");
+            then.text("users");
+        });
+        let b = mocks.mock(|when, then| {
+            when.get().path("/users/1");
+            then.text("user");
+        });
+        let c = mocks.mock_with_options(1, None, |when, then| {
+            when.post();
+            then.text("created");
+        });
+        let d = mocks.mock(|when, then| {
+            when.get().path_prefix("/users");
+            then.text("users again");
+        });
+
+        let request = Request::new(Method::GET, "http://localhost/users/1".parse().unwrap());
+        let order = mocks.evaluation_order(&request);
+        assert_eq!(
+            order
+                .iter()
+                .map(|evaluation| (evaluation.mock_id, evaluation.matched))
+                .collect::<Vec<_>>(),
+            vec![(c, false), (a, true), (b, true), (d, true)]
+        );
+        // Evaluation does not count as a match
+        assert!(mocks.iter().all(|mock| mock.match_count() == 0));
+        assert_eq!(mocks.match_by_request(&request).unwrap().id, a);
+
+        // Collected mocks are ordered the same way
+        let collected = mocks.clone().into_iter().rev().collect::<MockSet>();
+        assert_eq!(
+            collected.iter().map(|mock| mock.id).collect::<Vec<_>>(),
+            vec![c, d, b, a]
+        );
+    }
+
+    #[test]
+    fn test_check_conflicts() {
+        let mut mocks = MockSet::new();
+        let a = mocks.mock(|when, then| {
+            when.get().path_prefix("/users");
+            then.text("users");
+        });
+        let b = mocks.mock(|when, then| {
+            when.get().path("/users/1").header("x-id", "1");
+            then.text("user");
+        });
+        let c = mocks.mock(|when, then| {
+            when.path_prefix("/users").get();
+            then.text("users");
+        });
+        mocks.mock(|when, then| {
+            when.post().path("/users");
+            then.text("created");
+        });
+        assert_eq!(
+            mocks.check_conflicts(),
+            vec![
+                Conflict {
+                    mock_id: b,
+                    shadowed_by: a,
+                    kind: ConflictKind::Shadowed,
+                },
+                Conflict {
+                    mock_id: c,
+                    shadowed_by: a,
+                    kind: ConflictKind::Duplicate,
+                },
+            ]
+        );
+
+        // A higher priority mock is matched first
+        let mut mocks = MockSet::new();
+        mocks.mock(|when, then| {
+            when.get().path_prefix("/users");
+            then.text("users");
+        });
+        mocks.mock_with_options(1, None, |when, then| {
+            when.get().path("/users/1");
+            then.text("user");
+        });
+        assert!(mocks.check_conflicts().is_empty());
+
+        // A limited mock only shadows until its limit is reached
+        let mut mocks = MockSet::new();
+        mocks.mock_with_options(5, Some(1), |when, then| {
+            when.get().path("/users");
+            then.internal_server_error();
+        });
+        mocks.mock(|when, then| {
+            when.get().path("/users");
+            then.text("users");
+        });
+        assert!(mocks.check_conflicts().is_empty());
     }
 }
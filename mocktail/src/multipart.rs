@@ -0,0 +1,160 @@
+//! Multipart bodies
+use std::ops::Range;
+
+use bytes::{BufMut, Bytes, BytesMut};
+use uuid::Uuid;
+
+use crate::headers::Headers;
+
+/// A part of a [`Multipart`] body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Part {
+    pub headers: Headers,
+    pub body: Bytes,
+}
+
+impl Part {
+    /// Creates a part without headers.
+    pub fn new(body: impl Into<Bytes>) -> Self {
+        Self {
+            headers: Headers::new(),
+            body: body.into(),
+        }
+    }
+
+    /// Creates a `text/plain` part.
+    pub fn text(body: impl Into<String>) -> Self {
+        Self::new(body.into()).with_header("content-type", "text/plain; charset=utf-8")
+    }
+
+    /// Creates an `application/json` part.
+    pub fn json(body: impl serde::Serialize) -> Self {
+        Self::new(serde_json::to_vec(&body).unwrap())
+            .with_header("content-type", "application/json")
+    }
+
+    /// Adds a header.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+}
+
+/// A multipart body, e.g. `multipart/mixed` for batch responses, or
+/// `multipart/byteranges` for multi-range downloads.
+///
+/// A random boundary is generated unless set with [`Multipart::with_boundary`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Multipart {
+    subtype: String,
+    boundary: String,
+    parts: Vec<Part>,
+}
+
+impl Multipart {
+    /// Creates an empty multipart body with the subtype, e.g. `related`.
+    pub fn new(subtype: impl Into<String>) -> Self {
+        Self {
+            subtype: subtype.into(),
+            boundary: Uuid::now_v7().simple().to_string(),
+            parts: Vec::new(),
+        }
+    }
+
+    /// Creates an empty `multipart/mixed` body.
+    pub fn mixed() -> Self {
+        Self::new("mixed")
+    }
+
+    /// Creates a `multipart/byteranges` body with a part for each range
+    /// of the content, with `content-type` and `content-range` headers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a range is empty or out of bounds.
+    pub fn byteranges(
+        content: impl Into<Bytes>,
+        content_type: &str,
+        ranges: impl IntoIterator<Item = Range<usize>>,
+    ) -> Self {
+        let content: Bytes = content.into();
+        let mut multipart = Self::new("byteranges");
+        for range in ranges {
+            assert!(
+                !range.is_empty() && range.end <= content.len(),
+                "invalid range {range:?} for content length {}",
+                content.len()
+            );
+            let content_range =
+                format!("bytes {}-{}/{}", range.start, range.end - 1, content.len());
+            multipart = multipart.part(
+                Part::new(content.slice(range))
+                    .with_header("content-type", content_type)
+                    .with_header("content-range", content_range),
+            );
+        }
+        multipart
+    }
+
+    /// Sets the boundary.
+    pub fn with_boundary(mut self, boundary: impl Into<String>) -> Self {
+        self.boundary = boundary.into();
+        self
+    }
+
+    /// Adds a part.
+    pub fn part(mut self, part: Part) -> Self {
+        self.parts.push(part);
+        self
+    }
+
+    /// Returns the boundary.
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Returns the parts.
+    pub fn parts(&self) -> &[Part] {
+        &self.parts
+    }
+
+    /// Returns the content type, including the boundary.
+    pub fn content_type(&self) -> String {
+        format!("multipart/{}; boundary={}", self.subtype, self.boundary)
+    }
+
+    /// Encodes the body.
+    pub fn to_bytes(&self) -> Bytes {
+        let mut buf = BytesMut::new();
+        for part in &self.parts {
+            buf.put_slice(format!("--{}\r\n", self.boundary).as_bytes());
+            for (name, value) in part.headers.iter() {
+                buf.put_slice(format!("{}: {}\r\n", name.as_str(), value.as_str()).as_bytes());
+            }
+            buf.put_slice(b"\r\n");
+            buf.put_slice(&part.body);
+            buf.put_slice(b"\r\n");
+        }
+        buf.put_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+        buf.freeze()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byteranges() {
+        let multipart =
+            Multipart::byteranges("hello world", "text/plain", [0..5, 6..11]).with_boundary("b");
+        assert_eq!(multipart.content_type(), "multipart/byteranges; boundary=b");
+        assert_eq!(
+            multipart.to_bytes(),
+            "--b\r\ncontent-type: text/plain\r\ncontent-range: bytes 0-4/11\r\n\r\nhello\r\n\
+             --b\r\ncontent-type: text/plain\r\ncontent-range: bytes 6-10/11\r\n\r\nworld\r\n\
+             --b--\r\n"
+        );
+        assert_ne!(Multipart::mixed().boundary(), Multipart::mixed().boundary());
+    }
+}
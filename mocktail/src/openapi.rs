@@ -0,0 +1,514 @@
+//! OpenAPI mocks
+//!
+//! Mocks can be generated from the response examples of an OpenAPI 3
+//! document in JSON, one mock per operation, e.g.
+//!
+//! ```no_run
+//! # use mocktail::openapi::{ExampleSelection, OpenApi};
+//! # fn f() -> Result<(), mocktail::Error> {
+//! let mocks = OpenApi::from_file("openapi.json")?
+//!     .selection(ExampleSelection::Header("x-example".into()))
+//!     .select("getUser", ExampleSelection::Status(404))
+//!     .to_mocks()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Path parameters are matched with [`When::path_template`]. Examples are
+//! taken from the `example` or `examples` of the media type of a response,
+//! `application/json` if there are several. Named examples are ordered by name.
+//!
+//! Documents are also a [`Spec`] checking mocks defined in code for drift,
+//! see [`drift`](crate::drift). Request and response bodies are checked
+//! against the schemas of the `application/json` media types.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use serde_json::Value;
+
+use crate::{
+    body::Body,
+    drift::{DriftKind, Spec},
+    matchers::{self, RoutePath},
+    mock::{Mock, DEFAULT_PRIORITY},
+    mock_builder::{Then, When},
+    mock_set::MockSet,
+    request::Method,
+    status::StatusCode,
+    Error,
+};
+
+/// The methods of an OpenAPI path item.
+const METHODS: &[&str] = &[
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// The maximum number of `$ref` followed to resolve a value.
+const MAX_REFS: usize = 16;
+
+/// The name of the example defined with `example` rather than `examples`.
+pub const DEFAULT_EXAMPLE: &str = "default";
+
+/// How the example served for an operation is chosen.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub enum ExampleSelection {
+    /// The first example of the first `2xx` response.
+    #[default]
+    FirstSuccess,
+    /// The first example of the response with a status, or the
+    /// first success if the operation has no such response.
+    Status(u16),
+    /// The example with a name, or the first success if the
+    /// operation has no such example.
+    Named(String),
+    /// Chosen per request by a header, with an example name or a status, e.g.
+    /// `x-example: not_found` or `x-example: 404`, or the first success
+    /// without the header.
+    Header(String),
+}
+
+/// An OpenAPI document.
+#[derive(Debug, Clone)]
+pub struct OpenApi {
+    operations: Vec<Operation>,
+    selection: ExampleSelection,
+    selections: BTreeMap<String, ExampleSelection>,
+    /// The components of the document, resolving `$ref` of schemas.
+    refs: Value,
+}
+
+/// An operation of an OpenAPI document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operation {
+    pub method: Method,
+    /// The path template, e.g. `/users/{id}`.
+    pub path: String,
+    pub operation_id: Option<String>,
+    /// The schema of the JSON request body.
+    pub request_schema: Option<Value>,
+    /// The responses with a status, ordered by status.
+    pub responses: Vec<ApiResponse>,
+}
+
+/// A response of an OpenAPI operation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    /// The schema of the media type.
+    pub schema: Option<Value>,
+    /// The named examples, ordered by name.
+    pub examples: Vec<(String, Value)>,
+}
+
+impl OpenApi {
+    /// Loads a document from a JSON file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json(&json)
+    }
+
+    /// Loads a document from JSON.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Self::from_value(serde_json::from_str(json)?)
+    }
+
+    /// Loads a document from a JSON value.
+    pub fn from_value(spec: Value) -> Result<Self, Error> {
+        let invalid = |err: &str| Error::Invalid(format!("openapi: {err}"));
+        let paths = spec
+            .get("paths")
+            .and_then(Value::as_object)
+            .ok_or_else(|| invalid("missing `paths` object"))?;
+        let mut operations = Vec::new();
+        for (path, item) in paths {
+            for &method in METHODS {
+                let Some(operation) = item.get(method) else {
+                    continue;
+                };
+                operations.push(Operation {
+                    method: method.parse().map_err(|err: String| invalid(&err))?,
+                    path: path.clone(),
+                    operation_id: operation
+                        .get("operationId")
+                        .and_then(Value::as_str)
+                        .map(Into::into),
+                    request_schema: request_schema(&spec, operation),
+                    responses: responses(&spec, operation),
+                });
+            }
+        }
+        let components = spec.get("components").cloned().unwrap_or_default();
+        Ok(Self {
+            operations,
+            selection: ExampleSelection::default(),
+            selections: BTreeMap::new(),
+            refs: serde_json::json!({ "components": components }),
+        })
+    }
+
+    /// Returns the operations.
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    /// Sets how examples are chosen for the operations, see [`ExampleSelection`].
+    pub fn selection(mut self, selection: ExampleSelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Sets how examples are chosen for the operation with an `operationId`,
+    /// overriding [`OpenApi::selection`].
+    pub fn select(mut self, operation_id: impl Into<String>, selection: ExampleSelection) -> Self {
+        self.selections.insert(operation_id.into(), selection);
+        self
+    }
+
+    /// Builds the mocks of the operations.
+    pub fn to_mocks(&self) -> Result<MockSet, Error> {
+        let mut mocks = MockSet::new();
+        for operation in &self.operations {
+            let selection = operation
+                .operation_id
+                .as_ref()
+                .and_then(|id| self.selections.get(id))
+                .unwrap_or(&self.selection);
+            for mock in operation.mocks(selection)? {
+                mocks.insert(mock);
+            }
+        }
+        Ok(mocks)
+    }
+
+    /// Adds the fields of a JSON value not defined by a schema to `unknown`,
+    /// as dotted paths from `path`.
+    fn unknown_fields(
+        &self,
+        value: &Value,
+        schema: &Value,
+        path: &str,
+        unknown: &mut BTreeSet<String>,
+    ) {
+        let schemas = self.subschemas(schema);
+        match value {
+            Value::Object(object) => {
+                let properties = schemas
+                    .iter()
+                    .filter_map(|schema| schema.get("properties")?.as_object())
+                    .collect::<Vec<_>>();
+                let additional = schemas
+                    .iter()
+                    .find_map(|schema| schema.get("additionalProperties"));
+                // Objects without properties are free-form
+                let open = properties.is_empty() || additional == Some(&Value::Bool(true));
+                for (key, value) in object {
+                    let path = format!("{path}.{key}");
+                    let property = properties
+                        .iter()
+                        .find_map(|properties| properties.get(key))
+                        .or(additional.filter(|additional| additional.is_object()));
+                    match property {
+                        Some(schema) => self.unknown_fields(value, schema, &path, unknown),
+                        None if !open => {
+                            unknown.insert(path);
+                        }
+                        None => {}
+                    }
+                }
+            }
+            Value::Array(values) => {
+                if let Some(items) = schemas.iter().find_map(|schema| schema.get("items")) {
+                    let path = format!("{path}[]");
+                    for value in values {
+                        self.unknown_fields(value, items, &path, unknown);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns a schema and the schemas it is composed of with
+    /// `allOf`, `oneOf` or `anyOf`, with `$ref` resolved.
+    fn subschemas<'a>(&'a self, schema: &'a Value) -> Vec<&'a Value> {
+        let mut schemas = vec![resolve(&self.refs, schema)];
+        let mut i = 0;
+        while i < schemas.len() && schemas.len() < MAX_REFS {
+            for key in ["allOf", "oneOf", "anyOf"] {
+                if let Some(composed) = schemas[i].get(key).and_then(Value::as_array) {
+                    schemas.extend(composed.iter().map(|schema| resolve(&self.refs, schema)));
+                }
+            }
+            i += 1;
+        }
+        schemas
+    }
+}
+
+impl Spec for OpenApi {
+    /// Checks the path, methods and JSON bodies of a mock against the
+    /// operations it may match.
+    ///
+    /// Mocks without a path are not checked. Fields are reported if no
+    /// operation the mock may match defines them.
+    fn check(&self, mock: &Mock) -> Vec<DriftKind> {
+        let route = matchers::route(&mock.matchers);
+        let Some(path) = &route.path else {
+            return Vec::new();
+        };
+        let operations = self
+            .operations
+            .iter()
+            .filter(|operation| path_matches(path, &operation.path))
+            .collect::<Vec<_>>();
+        if operations.is_empty() {
+            return vec![DriftKind::UnknownPath(path.as_str().to_string())];
+        }
+        let mut drift = route
+            .methods
+            .iter()
+            .filter(|method| !operations.iter().any(|op| op.method == **method))
+            .map(|method| DriftKind::UnknownMethod(method.clone(), path.as_str().to_string()))
+            .collect::<Vec<_>>();
+        let request = route.bodies.iter().filter_map(json).collect::<Vec<_>>();
+        let response = json(&mock.response.body);
+        let status = mock.response.status.as_u16();
+        let mut unknown: Option<BTreeSet<String>> = None;
+        for operation in operations
+            .into_iter()
+            .filter(|op| route.methods.is_empty() || route.methods.contains(&op.method))
+        {
+            let mut fields = BTreeSet::new();
+            if let Some(schema) = &operation.request_schema {
+                for body in &request {
+                    self.unknown_fields(body, schema, "request", &mut fields);
+                }
+            }
+            let schema = operation
+                .responses
+                .iter()
+                .find(|response| response.status == status)
+                .and_then(|response| response.schema.as_ref());
+            if let (Some(body), Some(schema)) = (&response, schema) {
+                self.unknown_fields(body, schema, "response", &mut fields);
+            }
+            unknown = Some(match unknown {
+                Some(unknown) => unknown.intersection(&fields).cloned().collect(),
+                None => fields,
+            });
+        }
+        drift.extend(
+            unknown
+                .unwrap_or_default()
+                .into_iter()
+                .map(DriftKind::UnknownField),
+        );
+        drift
+    }
+}
+
+impl Operation {
+    /// Returns the response and example chosen by a selection, given
+    /// as the index of the response and the index of its example.
+    pub fn choose(&self, selection: &ExampleSelection) -> Option<(usize, Option<usize>)> {
+        let chosen = match selection {
+            ExampleSelection::Status(status) => self
+                .responses
+                .iter()
+                .position(|response| response.status == *status)
+                .map(|i| (i, (!self.responses[i].examples.is_empty()).then_some(0))),
+            ExampleSelection::Named(name) => {
+                self.responses.iter().enumerate().find_map(|(i, response)| {
+                    let j = response.examples.iter().position(|(n, _)| n == name)?;
+                    Some((i, Some(j)))
+                })
+            }
+            ExampleSelection::FirstSuccess | ExampleSelection::Header(_) => None,
+        };
+        chosen.or_else(|| self.first_success())
+    }
+
+    /// Returns the first `2xx` response with an example, or without one,
+    /// or the first response.
+    fn first_success(&self) -> Option<(usize, Option<usize>)> {
+        let success = |response: &ApiResponse| (200..300).contains(&response.status);
+        let i = self
+            .responses
+            .iter()
+            .position(|response| success(response) && !response.examples.is_empty())
+            .or_else(|| self.responses.iter().position(success))
+            .or((!self.responses.is_empty()).then_some(0))?;
+        Some((i, (!self.responses[i].examples.is_empty()).then_some(0)))
+    }
+
+    /// Builds the mocks of the operation.
+    fn mocks(&self, selection: &ExampleSelection) -> Result<Vec<Mock>, Error> {
+        let Some((i, example)) = self.choose(selection) else {
+            return Ok(Vec::new());
+        };
+        let mut mocks = vec![self.mock(None, &self.responses[i], example)?];
+        if let ExampleSelection::Header(header) = selection {
+            // Mocks selected by the header are evaluated first
+            for response in &self.responses {
+                let value = response.status.to_string();
+                let example = (!response.examples.is_empty()).then_some(0);
+                mocks.push(self.mock(Some((header, &value)), response, example)?);
+                for (j, (name, _)) in response.examples.iter().enumerate() {
+                    mocks.push(self.mock(Some((header, name)), response, Some(j))?);
+                }
+            }
+        }
+        Ok(mocks)
+    }
+
+    /// Builds a mock serving an example of a response.
+    fn mock(
+        &self,
+        header: Option<(&str, &str)>,
+        response: &ApiResponse,
+        example: Option<usize>,
+    ) -> Result<Mock, Error> {
+        let status = StatusCode::from_u16(response.status)?;
+        let body = example.map(|j| &response.examples[j].1).map(|example| {
+            match (example, response.content_type.as_deref()) {
+                (Value::String(text), Some(content_type)) if !is_json(content_type) => {
+                    Body::bytes(text.clone())
+                }
+                (example, _) => Body::json(example),
+            }
+        });
+        let mock = Mock::new(|mut when: When, mut then: Then| {
+            when = when
+                .method(self.method.clone())
+                .path_template(self.path.clone());
+            if let Some((name, value)) = header {
+                when.header(name, value);
+            }
+            then = then.status(status);
+            if let Some(content_type) = &response.content_type {
+                then = then.headers([("content-type", content_type.as_str())]);
+            }
+            if let Some(body) = body {
+                then.body(body);
+            }
+        });
+        Ok(match header {
+            Some(_) => mock.with_priority(DEFAULT_PRIORITY - 1),
+            None => mock,
+        })
+    }
+}
+
+/// Returns the schema of the JSON request body of an operation.
+fn request_schema(spec: &Value, operation: &Value) -> Option<Value> {
+    let body = resolve(spec, operation.get("requestBody")?);
+    let content = body.get("content")?.as_object()?;
+    content
+        .iter()
+        .find(|(content_type, _)| is_json(content_type))
+        .and_then(|(_, media)| media.get("schema").cloned())
+}
+
+/// Returns the responses of an operation with a status, ordered by status.
+fn responses(spec: &Value, operation: &Value) -> Vec<ApiResponse> {
+    let Some(responses) = operation.get("responses").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    let mut responses = responses
+        .iter()
+        .filter_map(|(status, response)| {
+            let status = status.parse::<u16>().ok()?;
+            let response = resolve(spec, response);
+            let content = response.get("content").and_then(Value::as_object);
+            let media = content.and_then(|content| {
+                content
+                    .get_key_value("application/json")
+                    .or_else(|| content.iter().next())
+            });
+            let mut examples = Vec::new();
+            if let Some((_, media)) = media {
+                if let Some(example) = media.get("example") {
+                    examples.push((DEFAULT_EXAMPLE.to_string(), example.clone()));
+                }
+                if let Some(named) = media.get("examples").and_then(Value::as_object) {
+                    examples.extend(named.iter().filter_map(|(name, example)| {
+                        Some((name.clone(), example.get("value")?.clone()))
+                    }));
+                }
+            }
+            Some(ApiResponse {
+                status,
+                content_type: media.map(|(content_type, _)| content_type.clone()),
+                schema: media.and_then(|(_, media)| media.get("schema").cloned()),
+                examples,
+            })
+        })
+        .collect::<Vec<_>>();
+    responses.sort_by_key(|response| response.status);
+    responses
+}
+
+/// Follows the `$ref` of a value within a document, e.g. `#/components/schemas/User`.
+fn resolve<'a>(root: &'a Value, mut value: &'a Value) -> &'a Value {
+    for _ in 0..MAX_REFS {
+        let target = value
+            .get("$ref")
+            .and_then(Value::as_str)
+            .and_then(|pointer| root.pointer(pointer.strip_prefix('#')?));
+        match target {
+            Some(target) => value = target,
+            None => break,
+        }
+    }
+    value
+}
+
+/// Returns true if requests matching a mock path may match an operation path template.
+fn path_matches(path: &RoutePath, template: &str) -> bool {
+    let is_param = |segment: &str| segment.starts_with('{') && segment.ends_with('}');
+    let template = template.split('/').collect::<Vec<_>>();
+    let is_template = matches!(path, RoutePath::Template(_));
+    match path {
+        RoutePath::Exact(path) | RoutePath::Template(path) => {
+            let segments = path.split('/').collect::<Vec<_>>();
+            segments.len() == template.len()
+                && segments.iter().zip(&template).all(|(segment, other)| {
+                    segment == other || is_param(other) || is_template && is_param(segment)
+                })
+        }
+        RoutePath::Prefix(prefix) => {
+            let segments = prefix.split('/').collect::<Vec<_>>();
+            let Some((last, init)) = segments.split_last() else {
+                return true;
+            };
+            segments.len() <= template.len()
+                && init
+                    .iter()
+                    .zip(&template)
+                    .all(|(segment, other)| segment == other || is_param(other))
+                && (is_param(template[init.len()]) || template[init.len()].starts_with(last))
+        }
+    }
+}
+
+/// Parses a JSON body.
+fn json(body: &Body) -> Option<Value> {
+    serde_json::from_slice(&body.clone().as_bytes()).ok()
+}
+
+/// Returns true if a media type is JSON, e.g. `application/problem+json`.
+pub(crate) fn is_json(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    essence == "application/json" || essence.ends_with("+json")
+}
+
+impl MockSet {
+    /// Builds mocks from the examples of an OpenAPI document in JSON,
+    /// see [`openapi`](crate::openapi).
+    pub fn from_openapi(path: impl AsRef<Path>) -> Result<Self, Error> {
+        OpenApi::from_file(path)?.to_mocks()
+    }
+}
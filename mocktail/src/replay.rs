@@ -0,0 +1,220 @@
+//! Journal replay
+//!
+//! Replays journaled requests against a real server and compares its
+//! responses to the responses of the mocks they matched, to validate
+//! that mocks still reflect reality.
+use std::{fmt, time::Duration};
+
+use bytes::Bytes;
+#[cfg(feature = "client")]
+use url::Url;
+
+use crate::{delegate::Delegate, journal::Journal, mock_set::MockSet, response::Response};
+
+/// How requests are paced when replayed.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum Pacing {
+    /// Requests are sent back to back.
+    #[default]
+    None,
+    /// Requests are sent with a fixed interval between them.
+    Interval(Duration),
+    /// Requests are sent with the intervals they were recorded with,
+    /// scaled by a factor, e.g. `0.5` to replay twice as fast.
+    Recorded(f64),
+}
+
+/// Replays journaled requests against a server.
+///
+/// Only requests that matched a mock are replayed, as the others have
+/// no mocked response to compare to. Status codes and bodies are compared;
+/// JSON bodies are compared as values, ignoring formatting and key order.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    delegate: Delegate,
+    pacing: Pacing,
+}
+
+impl Replay {
+    /// Creates a replay sending requests with a delegate.
+    pub fn new(delegate: Delegate) -> Self {
+        Self {
+            delegate,
+            pacing: Pacing::default(),
+        }
+    }
+
+    /// Creates a replay sending requests to a server, joining
+    /// the request path and query to the base url, see [`Delegate::url`].
+    #[cfg(feature = "client")]
+    pub fn to_url(base_url: Url) -> Self {
+        Self::new(Delegate::url(base_url))
+    }
+
+    /// Sets how requests are paced.
+    pub fn pacing(mut self, pacing: Pacing) -> Self {
+        self.pacing = pacing;
+        self
+    }
+
+    /// Replays the requests of the journal that matched a mock of the set.
+    pub async fn run(&self, journal: &Journal, mocks: &MockSet) -> ReplayReport {
+        let mut results = Vec::new();
+        let mut previous = None;
+        for (index, entry) in journal.iter().enumerate() {
+            let Some(mock) = entry
+                .mock_id
+                .and_then(|id| mocks.find(|mock| mock.id == id))
+            else {
+                continue;
+            };
+            if let Some(previous) = previous {
+                let delay = match self.pacing {
+                    Pacing::None => Duration::ZERO,
+                    Pacing::Interval(interval) => interval,
+                    Pacing::Recorded(factor) => entry
+                        .timestamp
+                        .duration_since(previous)
+                        .unwrap_or_default()
+                        .mul_f64(factor),
+                };
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+            }
+            previous = Some(entry.timestamp);
+
+            let request = &entry.request;
+            let mut actual = self.delegate.call(request).await;
+            let expected = mock.response();
+            let actual_body = body_bytes(&mut actual);
+            let expected_body = body_bytes(&mut expected.clone());
+            results.push(ReplayResult {
+                index,
+                method: request.method().to_string(),
+                path: request.path().to_string(),
+                expected_status: expected.status().as_u16(),
+                actual_status: actual.status().as_u16(),
+                body_matches: bodies_eq(&expected_body, &actual_body),
+                expected_body,
+                actual_body,
+            });
+        }
+        ReplayReport { results }
+    }
+}
+
+/// Returns the body of a response, or its error message.
+fn body_bytes(response: &mut Response) -> Bytes {
+    let body = response.body().clone().as_bytes();
+    match response.message() {
+        Some(message) if body.is_empty() && response.is_error() => {
+            Bytes::copy_from_slice(message.as_bytes())
+        }
+        _ => body,
+    }
+}
+
+fn bodies_eq(expected: &Bytes, actual: &Bytes) -> bool {
+    match (
+        serde_json::from_slice::<serde_json::Value>(expected),
+        serde_json::from_slice::<serde_json::Value>(actual),
+    ) {
+        (Ok(expected), Ok(actual)) => expected == actual,
+        _ => expected == actual,
+    }
+}
+
+/// The result of a replayed request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayResult {
+    /// Index of the entry in the journal.
+    pub index: usize,
+    pub method: String,
+    pub path: String,
+    /// Status of the mocked response.
+    pub expected_status: u16,
+    /// Status of the server response.
+    pub actual_status: u16,
+    /// Body of the mocked response.
+    pub expected_body: Bytes,
+    /// Body of the server response.
+    pub actual_body: Bytes,
+    /// Whether the bodies are equal.
+    pub body_matches: bool,
+}
+
+impl ReplayResult {
+    /// Returns true if the server response matches the mocked response.
+    pub fn is_match(&self) -> bool {
+        self.expected_status == self.actual_status && self.body_matches
+    }
+}
+
+impl fmt::Display for ReplayResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} {}", self.index, self.method, self.path)?;
+        if self.expected_status != self.actual_status {
+            write!(
+                f,
+                "\n  status: expected {}, got {}",
+                self.expected_status, self.actual_status
+            )?;
+        }
+        if !self.body_matches {
+            write!(
+                f,
+                "\n  body: expected {}, got {}",
+                display_body(&self.expected_body),
+                display_body(&self.actual_body)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn display_body(body: &Bytes) -> String {
+    match std::str::from_utf8(body) {
+        Ok(text) => format!("{text:?}"),
+        Err(_) => format!("{} bytes", body.len()),
+    }
+}
+
+/// The results of a replay.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayReport {
+    results: Vec<ReplayResult>,
+}
+
+impl ReplayReport {
+    /// Returns the results of all replayed requests.
+    pub fn results(&self) -> &[ReplayResult] {
+        &self.results
+    }
+
+    /// Returns the results where the server response differs from the mocked response.
+    pub fn diffs(&self) -> impl Iterator<Item = &ReplayResult> {
+        self.results.iter().filter(|result| !result.is_match())
+    }
+
+    /// Returns true if all server responses match the mocked responses.
+    pub fn is_ok(&self) -> bool {
+        self.diffs().next().is_none()
+    }
+}
+
+impl fmt::Display for ReplayReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let diffs = self.diffs().collect::<Vec<_>>();
+        write!(
+            f,
+            "replayed {} requests, {} differ",
+            self.results.len(),
+            diffs.len()
+        )?;
+        for diff in diffs {
+            write!(f, "\n{diff}")?;
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,17 @@
 //! Mock request
+use std::{borrow::Cow, net::SocketAddr, time::Duration};
+
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
 use url::Url;
 
-use crate::{body::Body, headers::Headers};
+use crate::{
+    body::Body,
+    headers::Headers,
+    interactions::Interactions,
+    trace::{self, TraceContext},
+    Error,
+};
 
 /// Represents a HTTP request.
 #[derive(Debug, Clone, PartialEq)]
@@ -10,6 +20,72 @@ pub struct Request {
     pub url: Url,
     pub headers: Headers,
     pub body: Body,
+    pub(crate) namespace: Option<String>,
+    pub(crate) path_normalization: PathNormalization,
+    /// SHA-256 digest of the body, if computed while it was received.
+    pub(crate) body_sha256: Option<[u8; 32]>,
+    /// Prior interactions with the server.
+    pub(crate) interactions: Interactions,
+    /// ID of the session issued by the server the request was sent in.
+    pub(crate) session: Option<String>,
+    /// Transfer metrics of the body, measured while it was received.
+    pub(crate) transfer: Option<Transfer>,
+    /// HTTP version the request was received with, e.g. `HTTP/2.0`.
+    pub(crate) protocol: Option<String>,
+    /// Details of the connection the request was received on.
+    pub(crate) connection_info: Option<ConnectionInfo>,
+}
+
+/// Transfer metrics of a request body received by a server.
+///
+/// Metrics cover the body received up to the time the request was recorded,
+/// e.g. up to the match of a streaming request.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transfer {
+    /// Number of body bytes received.
+    pub bytes: usize,
+    /// Number of non-empty data frames received.
+    pub chunks: usize,
+    /// Number of complete length-prefixed gRPC messages received.
+    pub messages: usize,
+    /// Time from receiving the request head to receiving the last data frame.
+    pub duration: Duration,
+}
+
+/// Details of the connection a request was received on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectionInfo {
+    /// Number of the connection, starting at 1.
+    pub number: usize,
+    /// Address of the client, for TCP connections.
+    pub peer_addr: Option<SocketAddr>,
+    /// Protocol negotiated with ALPN, e.g. `h2`, for TLS connections.
+    pub alpn: Option<String>,
+    /// TLS session details, for TLS connections.
+    pub tls: Option<TlsInfo>,
+}
+
+impl ConnectionInfo {
+    /// Creates the details of a connection without a peer address or TLS.
+    pub(crate) fn new(number: usize) -> Self {
+        Self {
+            number,
+            peer_addr: None,
+            alpn: None,
+            tls: None,
+        }
+    }
+}
+
+/// TLS session details of a connection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsInfo {
+    /// Protocol version, e.g. `TLSv1_3`.
+    pub version: String,
+    /// Negotiated cipher suite, e.g. `TLS13_AES_256_GCM_SHA384`.
+    pub cipher_suite: String,
+    /// Server name sent by the client with SNI, if any.
+    pub server_name: Option<String>,
 }
 
 impl Request {
@@ -19,6 +95,14 @@ impl Request {
             url,
             headers: Headers::default(),
             body: Body::default(),
+            namespace: None,
+            path_normalization: PathNormalization::default(),
+            body_sha256: None,
+            interactions: Interactions::default(),
+            session: None,
+            transfer: None,
+            protocol: None,
+            connection_info: None,
         }
     }
 
@@ -30,11 +114,21 @@ impl Request {
         }
         .parse()
         .unwrap();
+        let connection_info = parts.extensions.get::<ConnectionInfo>().cloned();
+        let protocol = format!("{:?}", parts.version);
         Self {
             method: parts.method.into(),
             url,
             headers: parts.headers.into(),
             body: Body::default(),
+            namespace: None,
+            path_normalization: PathNormalization::default(),
+            body_sha256: None,
+            interactions: Interactions::default(),
+            session: None,
+            transfer: None,
+            protocol: Some(protocol),
+            connection_info,
         }
     }
 
@@ -45,6 +139,22 @@ impl Request {
 
     pub fn with_body(mut self, body: impl Into<Body>) -> Self {
         self.body = body.into();
+        self.body_sha256 = None;
+        self
+    }
+
+    pub fn with_body_sha256(mut self, digest: [u8; 32]) -> Self {
+        self.body_sha256 = Some(digest);
+        self
+    }
+
+    pub fn with_transfer(mut self, transfer: Transfer) -> Self {
+        self.transfer = Some(transfer);
+        self
+    }
+
+    pub fn with_path_normalization(mut self, normalization: PathNormalization) -> Self {
+        self.path_normalization = normalization;
         self
     }
 
@@ -60,6 +170,16 @@ impl Request {
         self.url.path()
     }
 
+    /// Returns the path normalization options of the request.
+    pub fn path_normalization(&self) -> &PathNormalization {
+        &self.path_normalization
+    }
+
+    /// Returns the path normalized with the request path normalization options.
+    pub fn normalized_path(&self) -> Cow<'_, str> {
+        self.path_normalization.normalize(self.path())
+    }
+
     pub fn query(&self) -> Option<&str> {
         self.url.query()
     }
@@ -75,11 +195,177 @@ impl Request {
     pub fn body(&self) -> &Body {
         &self.body
     }
+
+    /// Deserializes the JSON body.
+    pub fn body_json<T: serde::de::DeserializeOwned>(&self) -> Result<T, Error> {
+        Ok(serde_json::from_slice(&self.body.clone().as_bytes())?)
+    }
+
+    /// Decodes the protobuf body, the first message of a gRPC request.
+    pub fn body_pb<M: prost::Message + Default>(&self) -> Result<M, Error> {
+        let is_grpc = self
+            .headers
+            .get("content-type")
+            .is_some_and(|value| value.starts_with("application/grpc"));
+        let bytes = if is_grpc {
+            let message = self
+                .body
+                .grpc_messages()
+                .into_iter()
+                .next()
+                .ok_or_else(|| Error::Invalid("no grpc message in body".into()))?;
+            if message[0] != 0 {
+                return Err(Error::Invalid("compressed grpc message".into()));
+            }
+            message.slice(5..)
+        } else {
+            self.body.clone().as_bytes()
+        };
+        M::decode(bytes).map_err(|err| Error::Invalid(format!("invalid protobuf body: {err}")))
+    }
+
+    /// Returns the SHA-256 digest of the body.
+    pub fn body_sha256(&self) -> [u8; 32] {
+        self.body_sha256.unwrap_or_else(|| self.body.sha256())
+    }
+
+    /// Returns the parsed `traceparent` header, if present and well-formed.
+    pub fn traceparent(&self) -> Option<TraceContext> {
+        self.headers
+            .get(trace::TRACEPARENT_HEADER)
+            .and_then(|value| TraceContext::parse(value))
+    }
+
+    /// Returns the parsed `baggage` header members.
+    pub fn baggage(&self) -> Vec<(String, String)> {
+        self.headers
+            .get(trace::BAGGAGE_HEADER)
+            .map(|value| trace::parse_baggage(value))
+            .unwrap_or_default()
+    }
+
+    /// Returns the `x-request-id` header, if present and well-formed.
+    pub fn request_id(&self) -> Option<&str> {
+        self.headers
+            .get(trace::REQUEST_ID_HEADER)
+            .map(|value| value.as_ref())
+            .filter(|value| trace::is_valid_request_id(value))
+    }
+
+    /// Returns the number of the connection the request was received on, starting at 1.
+    pub fn connection(&self) -> Option<usize> {
+        self.connection_info.as_ref().map(|info| info.number)
+    }
+
+    /// Returns the prior interactions with the server, taken when the request was matched.
+    pub fn interactions(&self) -> &Interactions {
+        &self.interactions
+    }
+
+    /// Returns the details of the connection the request was received on.
+    pub fn connection_info(&self) -> Option<&ConnectionInfo> {
+        self.connection_info.as_ref()
+    }
+
+    /// Returns the address of the client, for requests received on TCP connections.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.connection_info.as_ref()?.peer_addr
+    }
+
+    /// Returns the HTTP version the request was received with, e.g. `HTTP/2.0`.
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    /// Returns the ID of the session issued by the server the request was sent in.
+    pub fn session(&self) -> Option<&str> {
+        self.session.as_deref()
+    }
+
+    /// Returns the transfer metrics of the body, if received by a server.
+    pub fn transfer(&self) -> Option<&Transfer> {
+        self.transfer.as_ref()
+    }
+
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+}
+
+/// Path normalization options applied before matching.
+///
+/// Normalization is applied to both the request path and the mock path.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathNormalization {
+    trailing_slash: bool,
+    percent_decode: bool,
+    case_insensitive: bool,
+}
+
+impl PathNormalization {
+    /// Creates path normalization options with all options disabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates path normalization options with all options enabled.
+    pub fn all() -> Self {
+        Self {
+            trailing_slash: true,
+            percent_decode: true,
+            case_insensitive: true,
+        }
+    }
+
+    /// Treats paths with and without a trailing slash as equivalent.
+    pub fn trailing_slash(mut self, enabled: bool) -> Self {
+        self.trailing_slash = enabled;
+        self
+    }
+
+    /// Percent-decodes paths.
+    pub fn percent_decode(mut self, enabled: bool) -> Self {
+        self.percent_decode = enabled;
+        self
+    }
+
+    /// Compares paths case-insensitively.
+    pub fn case_insensitive(mut self, enabled: bool) -> Self {
+        self.case_insensitive = enabled;
+        self
+    }
+
+    /// Returns true if no options are enabled.
+    pub fn is_none(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Normalizes a path.
+    pub fn normalize<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        let mut path = Cow::Borrowed(path);
+        if self.percent_decode {
+            if let Cow::Owned(decoded) = percent_decode_str(&path).decode_utf8_lossy() {
+                path = Cow::Owned(decoded);
+            }
+        }
+        if self.case_insensitive && path.chars().any(|c| c.is_uppercase()) {
+            path = Cow::Owned(path.to_lowercase());
+        }
+        if self.trailing_slash && path.len() > 1 && path.ends_with('/') {
+            // The root path keeps its slash, e.g. `//` is normalized to `/`
+            let trimmed = match path.trim_end_matches('/') {
+                "" => "/",
+                trimmed => trimmed,
+            };
+            path = Cow::Owned(trimmed.to_string());
+        }
+        path
+    }
 }
 
 /// Represents a HTTP method.
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Method {
     #[default]
     GET,
@@ -91,30 +377,61 @@ pub enum Method {
     OPTIONS,
     TRACE,
     PATCH,
+    /// A non-standard method, e.g. WebDAV `PROPFIND`, created by parsing its name.
+    Other(ExtensionMethod),
+}
+
+impl Method {
+    /// Returns the method as a string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Method::GET => "GET",
+            Method::HEAD => "HEAD",
+            Method::POST => "POST",
+            Method::PUT => "PUT",
+            Method::DELETE => "DELETE",
+            Method::CONNECT => "CONNECT",
+            Method::OPTIONS => "OPTIONS",
+            Method::TRACE => "TRACE",
+            Method::PATCH => "PATCH",
+            Method::Other(method) => method.as_str(),
+        }
+    }
+
+    /// Returns the standard method of a name, ignoring case.
+    fn standard(value: &str) -> Option<Self> {
+        match value.to_uppercase().as_str() {
+            "GET" => Some(Method::GET),
+            "HEAD" => Some(Method::HEAD),
+            "POST" => Some(Method::POST),
+            "PUT" => Some(Method::PUT),
+            "DELETE" => Some(Method::DELETE),
+            "CONNECT" => Some(Method::CONNECT),
+            "OPTIONS" => Some(Method::OPTIONS),
+            "TRACE" => Some(Method::TRACE),
+            "PATCH" => Some(Method::PATCH),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Display for Method {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        std::fmt::Debug::fmt(self, f)
+        f.write_str(self.as_str())
     }
 }
 
 impl std::str::FromStr for Method {
     type Err = String;
 
+    /// Parses a method, standard methods ignoring case.
     fn from_str(value: &str) -> Result<Self, Self::Err> {
-        match value.to_uppercase().as_str() {
-            "GET" => Ok(Method::GET),
-            "HEAD" => Ok(Method::HEAD),
-            "POST" => Ok(Method::POST),
-            "PUT" => Ok(Method::PUT),
-            "DELETE" => Ok(Method::DELETE),
-            "CONNECT" => Ok(Method::CONNECT),
-            "OPTIONS" => Ok(Method::OPTIONS),
-            "TRACE" => Ok(Method::TRACE),
-            "PATCH" => Ok(Method::PATCH),
-            _ => Err(format!("Invalid HTTP method {value}")),
+        if let Some(method) = Method::standard(value) {
+            return Ok(method);
         }
+        http::Method::from_bytes(value.as_bytes())
+            .map(|method| Method::Other(ExtensionMethod(method)))
+            .map_err(|_| format!("Invalid HTTP method {value}"))
     }
 }
 
@@ -122,34 +439,94 @@ impl TryFrom<&str> for Method {
     type Error = String;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        match value {
-            "POST" => Ok(Self::POST),
-            "GET" => Ok(Self::GET),
-            "HEAD" => Ok(Self::HEAD),
-            "PUT" => Ok(Self::PUT),
-            "DELETE" => Ok(Self::DELETE),
-            "CONNECT" => Ok(Self::CONNECT),
-            "OPTIONS" => Ok(Self::OPTIONS),
-            "TRACE" => Ok(Self::TRACE),
-            "PATCH" => Ok(Self::PATCH),
-            _ => Err(format!("Invalid HTTP method {value}")),
-        }
+        value.parse()
     }
 }
 
 impl From<http::Method> for Method {
     fn from(value: http::Method) -> Self {
-        match value.as_str() {
-            "GET" => Self::GET,
-            "HEAD" => Self::HEAD,
-            "POST" => Self::POST,
-            "PUT" => Self::PUT,
-            "DELETE" => Self::DELETE,
-            "CONNECT" => Self::CONNECT,
-            "OPTIONS" => Self::OPTIONS,
-            "TRACE" => Self::TRACE,
-            "PATCH" => Self::PATCH,
-            _ => unimplemented!(),
+        match Method::standard(value.as_str()) {
+            Some(method) => method,
+            None => Method::Other(ExtensionMethod(value)),
+        }
+    }
+}
+
+impl From<Method> for http::Method {
+    fn from(value: Method) -> Self {
+        match value {
+            Method::GET => http::Method::GET,
+            Method::HEAD => http::Method::HEAD,
+            Method::POST => http::Method::POST,
+            Method::PUT => http::Method::PUT,
+            Method::DELETE => http::Method::DELETE,
+            Method::CONNECT => http::Method::CONNECT,
+            Method::OPTIONS => http::Method::OPTIONS,
+            Method::TRACE => http::Method::TRACE,
+            Method::PATCH => http::Method::PATCH,
+            Method::Other(method) => method.0,
         }
     }
 }
+
+/// A non-standard HTTP method, validated as a token when parsed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExtensionMethod(http::Method);
+
+impl ExtensionMethod {
+    /// Returns the method as a string.
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl PartialOrd for ExtensionMethod {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ExtensionMethod {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_normalization() {
+        let normalization = PathNormalization::new();
+        assert_eq!(
+            normalization.normalize("/Hello%20World/"),
+            "/Hello%20World/"
+        );
+        let normalization = PathNormalization::all();
+        assert_eq!(normalization.normalize("/Hello%20World/"), "/hello world");
+        assert_eq!(normalization.normalize("/"), "/");
+        let normalization = PathNormalization::new().trailing_slash(true);
+        assert_eq!(normalization.normalize("/Hello//"), "/Hello");
+        assert_eq!(normalization.normalize("//"), "/");
+        assert_eq!(normalization.normalize("/"), "/");
+    }
+
+    #[test]
+    fn test_method_canonical() {
+        assert_eq!("get".parse::<Method>().unwrap(), Method::GET);
+        assert_eq!(Method::try_from("get").unwrap(), Method::GET);
+        assert_eq!(
+            Method::from(http::Method::from_bytes(b"get").unwrap()),
+            Method::GET
+        );
+        let propfind = Method::try_from("PROPFIND").unwrap();
+        assert_eq!(propfind.as_str(), "PROPFIND");
+        assert_eq!(
+            Method::from(http::Method::from_bytes(b"PROPFIND").unwrap()),
+            propfind
+        );
+        assert_eq!(http::Method::from(propfind).as_str(), "PROPFIND");
+        assert!(Method::try_from("bad method").is_err());
+    }
+}
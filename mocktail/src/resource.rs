@@ -0,0 +1,266 @@
+//! In-memory REST resource
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    body::Body,
+    headers::Headers,
+    request::{Method, Request},
+    response::Response,
+    status::StatusCode,
+};
+
+/// Generates IDs for items created in a [`Resource`].
+#[derive(Default, Clone)]
+pub enum IdGenerator {
+    /// Sequential integers, starting at 1.
+    #[default]
+    Sequential,
+    /// UUID v7 strings.
+    Uuid,
+    /// A custom generator.
+    Custom(Arc<dyn Fn() -> String + Send + Sync>),
+}
+
+impl fmt::Debug for IdGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sequential => write!(f, "Sequential"),
+            Self::Uuid => write!(f, "Uuid"),
+            Self::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+/// A REST resource backed by an in-memory collection of JSON items.
+///
+/// Serves `{path}` and `{path}/{id}`:
+/// - `POST {path}` creates an item, returning `201 Created` with the item.
+/// - `GET {path}` returns all items.
+/// - `GET {path}/{id}` returns an item.
+/// - `PUT {path}/{id}` replaces an item.
+/// - `DELETE {path}/{id}` removes an item, returning `204 No Content`.
+///
+/// The item ID is set as the `id` field of object items. Requests for
+/// missing items receive `404 Not Found`.
+///
+/// Clones share the same collection.
+#[derive(Debug, Clone)]
+pub struct Resource {
+    path: String,
+    id_generator: IdGenerator,
+    next_id: Arc<AtomicU64>,
+    items: Arc<Mutex<Vec<(String, Value)>>>,
+}
+
+impl Resource {
+    /// Creates an empty resource served at the path.
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into().trim_end_matches('/').to_string(),
+            id_generator: IdGenerator::default(),
+            next_id: Arc::new(AtomicU64::new(1)),
+            items: Arc::default(),
+        }
+    }
+
+    /// Sets the ID generator.
+    pub fn with_id_generator(mut self, id_generator: IdGenerator) -> Self {
+        self.id_generator = id_generator;
+        self
+    }
+
+    /// Returns the path.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the number of items.
+    pub fn len(&self) -> usize {
+        self.items.lock().unwrap().len()
+    }
+
+    /// Returns true if empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an item by ID.
+    pub fn get(&self, id: &str) -> Option<Value> {
+        self.items
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(item_id, _)| item_id == id)
+            .map(|(_, item)| item.clone())
+    }
+
+    /// Returns all items, in order of creation.
+    pub fn items(&self) -> Vec<Value> {
+        self.items
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(_, item)| item.clone())
+            .collect()
+    }
+
+    /// Inserts an item, returning its generated ID.
+    pub fn insert(&self, item: Value) -> String {
+        let id = self.generate_id();
+        let item = with_id(item, &id);
+        self.items.lock().unwrap().push((id.clone(), item));
+        id
+    }
+
+    /// Removes all items.
+    pub fn clear(&self) {
+        self.items.lock().unwrap().clear()
+    }
+
+    fn generate_id(&self) -> String {
+        match &self.id_generator {
+            IdGenerator::Sequential => self.next_id.fetch_add(1, Ordering::Relaxed).to_string(),
+            IdGenerator::Uuid => Uuid::now_v7().to_string(),
+            IdGenerator::Custom(f) => f(),
+        }
+    }
+
+    /// Handles a request, returning the response.
+    pub fn handle(&self, request: &Request) -> Response {
+        let Some(rest) = request.path().strip_prefix(&self.path) else {
+            return not_found();
+        };
+        let id = match rest.trim_end_matches('/') {
+            "" => None,
+            rest => match rest.strip_prefix('/') {
+                Some(id) if !id.contains('/') => Some(id),
+                _ => return not_found(),
+            },
+        };
+        match (request.method(), id) {
+            (Method::GET, None) => json(StatusCode::OK, Value::Array(self.items())),
+            (Method::POST, None) => {
+                let Some(item) = parse(request) else {
+                    return bad_request();
+                };
+                let id = self.insert(item);
+                let mut response = json(StatusCode::CREATED, self.get(&id).unwrap());
+                response
+                    .headers
+                    .insert("location", format!("{}/{id}", self.path));
+                response
+            }
+            (Method::GET, Some(id)) => match self.get(id) {
+                Some(item) => json(StatusCode::OK, item),
+                None => not_found(),
+            },
+            (Method::PUT, Some(id)) => {
+                let Some(item) = parse(request) else {
+                    return bad_request();
+                };
+                let item = with_id(item, id);
+                let mut items = self.items.lock().unwrap();
+                match items.iter_mut().find(|(item_id, _)| item_id == id) {
+                    Some((_, existing)) => {
+                        *existing = item.clone();
+                        json(StatusCode::OK, item)
+                    }
+                    None => not_found(),
+                }
+            }
+            (Method::DELETE, Some(id)) => {
+                let mut items = self.items.lock().unwrap();
+                match items.iter().position(|(item_id, _)| item_id == id) {
+                    Some(index) => {
+                        items.remove(index);
+                        Response::default().with_status(StatusCode::NO_CONTENT)
+                    }
+                    None => not_found(),
+                }
+            }
+            _ => Response::default()
+                .with_status(StatusCode::METHOD_NOT_ALLOWED)
+                .with_message("method not allowed"),
+        }
+    }
+}
+
+impl PartialEq for Resource {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.items, &other.items)
+    }
+}
+
+fn with_id(mut item: Value, id: &str) -> Value {
+    if let Value::Object(object) = &mut item {
+        object.insert("id".into(), id.into());
+    }
+    item
+}
+
+fn parse(request: &Request) -> Option<Value> {
+    serde_json::from_slice(&request.body().clone().as_bytes()).ok()
+}
+
+fn json(status: StatusCode, body: Value) -> Response {
+    let mut headers = Headers::new();
+    headers.insert("content-type", "application/json");
+    Response::new(Body::json(body))
+        .with_status(status)
+        .with_headers(headers)
+}
+
+fn not_found() -> Response {
+    Response::default()
+        .with_status(StatusCode::NOT_FOUND)
+        .with_message("not found")
+}
+
+fn bad_request() -> Response {
+    Response::default()
+        .with_status(StatusCode::BAD_REQUEST)
+        .with_message("invalid json body")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: Method, path: &str, body: &str) -> Request {
+        Request::new(method, format!("http://localhost{path}").parse().unwrap())
+            .with_body(Body::bytes(body.to_string()))
+    }
+
+    #[test]
+    fn test_crud() {
+        let resource = Resource::new("/things");
+        let response = resource.handle(&request(Method::POST, "/things", r#"{"name":"a"}"#));
+        assert_eq!(response.status(), &StatusCode::CREATED);
+        assert_eq!(response.headers().get("location").unwrap(), "/things/1");
+        assert_eq!(
+            resource.get("1").unwrap(),
+            serde_json::json!({"id": "1", "name": "a"})
+        );
+
+        let response = resource.handle(&request(Method::PUT, "/things/1", r#"{"name":"b"}"#));
+        assert_eq!(response.status(), &StatusCode::OK);
+        assert_eq!(resource.get("1").unwrap()["name"], "b");
+
+        let response = resource.handle(&request(Method::DELETE, "/things/1", ""));
+        assert_eq!(response.status(), &StatusCode::NO_CONTENT);
+        assert!(resource.is_empty());
+        let response = resource.handle(&request(Method::GET, "/things/1", ""));
+        assert_eq!(response.status(), &StatusCode::NOT_FOUND);
+        let response = resource.handle(&request(Method::POST, "/things", "nope"));
+        assert_eq!(response.status(), &StatusCode::BAD_REQUEST);
+    }
+}
@@ -1,5 +1,27 @@
 //! Mock response
-use super::{body::Body, headers::Headers, status::StatusCode};
+use std::time::{Duration, SystemTime};
+
+use bytes::Bytes;
+use rand::Rng;
+
+use super::{
+    body::Body,
+    cache::CachePolicy,
+    delegate::Delegate,
+    headers::{HeaderName, Headers},
+    request::Request,
+    resource::Resource,
+    status::StatusCode,
+    template::{Context, Template},
+};
+#[cfg(feature = "digest")]
+use crate::auth::Digest;
+#[cfg(feature = "compression")]
+use crate::compression::Compression;
+#[cfg(feature = "scripting")]
+use crate::script::Script;
+#[cfg(feature = "client")]
+use crate::webhook::Webhook;
 
 /// Represents a HTTP response.
 #[derive(Debug, Clone, PartialEq)]
@@ -8,6 +30,173 @@ pub struct Response {
     pub headers: Headers,
     pub body: Body,
     pub message: Option<String>,
+    pub failure: Option<Failure>,
+    pub counters: Vec<(String, i64)>,
+    pub variants: Vec<(u32, Response)>,
+    /// Response variants by language tag, negotiated with `Accept-Language`.
+    pub languages: Vec<(String, Response)>,
+    /// Delay after the response time sent as a `Retry-After` HTTP date.
+    pub retry_after_date: Option<Duration>,
+    /// Parts of the request reflected in the body.
+    pub echo: Option<Echo>,
+    /// In-memory resource handling the request.
+    pub resource: Option<Resource>,
+    /// Template rendered as the body.
+    pub template: Option<Template>,
+    /// Templates rendered as header values.
+    pub header_templates: Vec<(HeaderName, Template)>,
+    /// Template rendered as the error message.
+    pub message_template: Option<Template>,
+    /// Whether the request gRPC messages are reflected as the response messages.
+    pub grpc_echo: bool,
+    /// Whether a session is issued with the response.
+    pub start_session: bool,
+    /// A stream reset sent instead of completing the response.
+    pub reset: Option<Reset>,
+    /// A responder the request is handed off to.
+    pub delegate: Option<Delegate>,
+    /// Informational (1xx) responses sent before the response.
+    pub informational: Vec<(StatusCode, Headers)>,
+    /// HTTP/1.1 reason phrase sent in place of the canonical reason.
+    pub reason: Option<String>,
+    /// Callbacks sent when the response is rendered.
+    #[cfg(feature = "client")]
+    pub webhooks: Vec<Webhook>,
+    /// Delay before the response is sent.
+    pub delay: Option<Delay>,
+    /// How the body is split into data frames and paced.
+    pub chunking: Option<Chunking>,
+    /// Compression settings overriding the server settings.
+    #[cfg(feature = "compression")]
+    pub compression: Option<Compression>,
+    /// Cache policy setting the caching headers and answering conditional requests.
+    pub cache: Option<CachePolicy>,
+    /// Digest authentication issuing a nonce in the challenge of the response.
+    #[cfg(feature = "digest")]
+    pub digest: Option<Digest>,
+    /// Responses returned in order for each match.
+    pub sequence: Vec<Response>,
+    /// Behavior once the sequence is exhausted.
+    pub exhausted: Exhausted,
+    /// Script evaluated to build the response.
+    #[cfg(feature = "scripting")]
+    pub script: Option<Script>,
+}
+
+/// An HTTP/2 `RST_STREAM` sent mid-response.
+///
+/// HTTP/1 connections are closed instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reset {
+    /// Number of body bytes sent before the reset.
+    pub after: usize,
+    /// The HTTP/2 error code.
+    pub reason: u32,
+}
+
+/// How a response body is split into data frames and paced,
+/// e.g. to split a JSON object across TCP segments.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Chunking {
+    /// The maximum size of data frames, or the body chunks as-is if unset.
+    pub size: Option<usize>,
+    /// The interval between data frames, flushing each frame separately.
+    pub interval: Duration,
+}
+
+impl Chunking {
+    /// Splits a body chunk into data frames of the chunk size.
+    pub(crate) fn split(&self, mut chunk: Bytes) -> Vec<Bytes> {
+        match self.size {
+            Some(size) if size > 0 => {
+                let mut frames = Vec::with_capacity(chunk.len().div_ceil(size));
+                while chunk.len() > size {
+                    frames.push(chunk.split_to(size));
+                }
+                frames.push(chunk);
+                frames
+            }
+            _ => vec![chunk],
+        }
+    }
+}
+
+/// A delay before a response is sent, with a base duration and random jitter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Delay {
+    /// The base duration.
+    pub base: Duration,
+    /// Random jitter added to the base duration.
+    pub jitter: Jitter,
+}
+
+/// Random jitter added to a [`Delay`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Jitter {
+    /// No jitter.
+    #[default]
+    None,
+    /// Up to a duration.
+    Absolute(Duration),
+    /// Up to a percentage of the base duration, e.g. `25.0` for 25%.
+    Percent(f64),
+}
+
+impl Delay {
+    /// Creates a delay without jitter.
+    pub fn fixed(base: Duration) -> Self {
+        Self {
+            base,
+            jitter: Jitter::None,
+        }
+    }
+
+    /// Creates a delay with jitter.
+    pub fn with_jitter(base: Duration, jitter: Jitter) -> Self {
+        Self { base, jitter }
+    }
+
+    /// Returns the maximum jitter.
+    pub fn max_jitter(&self) -> Duration {
+        match self.jitter {
+            Jitter::None => Duration::ZERO,
+            Jitter::Absolute(jitter) => jitter,
+            Jitter::Percent(percent) => self.base.mul_f64(percent.max(0.0) / 100.0),
+        }
+    }
+
+    /// Returns the base duration plus jitter chosen at random, uniformly
+    /// between zero and the maximum jitter.
+    pub fn sample(&self, rng: &mut impl Rng) -> Duration {
+        let max = self.max_jitter();
+        if max.is_zero() {
+            return self.base;
+        }
+        self.base + max.mul_f64(rng.random_range(0.0..=1.0))
+    }
+}
+
+/// The behavior of a response sequence once every response has been returned.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Exhausted {
+    /// Repeat the last response.
+    #[default]
+    RepeatLast,
+    /// Start the sequence over.
+    Cycle,
+    /// Stop matching, so requests fall through to lower priority mocks.
+    FallThrough,
+    /// Return a `500 Internal Server Error` with a `sequence exhausted` message.
+    Error,
+}
+
+/// A failure returned for the first matches of a mock.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    /// Number of matches to fail.
+    pub times: usize,
+    /// Failure status code.
+    pub status: StatusCode,
 }
 
 impl Response {
@@ -17,6 +206,35 @@ impl Response {
             headers: Headers::default(),
             body: body.into(),
             message: None,
+            failure: None,
+            counters: Vec::new(),
+            variants: Vec::new(),
+            languages: Vec::new(),
+            retry_after_date: None,
+            echo: None,
+            resource: None,
+            template: None,
+            header_templates: Vec::new(),
+            message_template: None,
+            grpc_echo: false,
+            start_session: false,
+            reset: None,
+            delegate: None,
+            informational: Vec::new(),
+            reason: None,
+            #[cfg(feature = "client")]
+            webhooks: Vec::new(),
+            delay: None,
+            chunking: None,
+            #[cfg(feature = "compression")]
+            compression: None,
+            cache: None,
+            #[cfg(feature = "digest")]
+            digest: None,
+            sequence: Vec::new(),
+            exhausted: Exhausted::default(),
+            #[cfg(feature = "scripting")]
+            script: None,
         }
     }
 
@@ -58,6 +276,197 @@ impl Response {
     pub fn is_error(&self) -> bool {
         self.status.is_error()
     }
+
+    pub fn failure(&self) -> Option<&Failure> {
+        self.failure.as_ref()
+    }
+
+    /// Returns the counter updates applied when the mock is matched.
+    pub fn counters(&self) -> &[(String, i64)] {
+        &self.counters
+    }
+
+    /// Returns the response sequence.
+    pub fn sequence(&self) -> &[Response] {
+        &self.sequence
+    }
+
+    /// Returns the number of matches after which the mock stops matching,
+    /// if the sequence falls through once exhausted.
+    pub fn sequence_limit(&self) -> Option<usize> {
+        (self.exhausted == Exhausted::FallThrough && !self.sequence.is_empty())
+            .then_some(self.sequence.len())
+    }
+
+    /// Returns the weighted response variants.
+    pub fn variants(&self) -> &[(u32, Response)] {
+        &self.variants
+    }
+
+    /// Returns a variant chosen at random by weight,
+    /// or the response itself if it has no variants.
+    pub fn into_variant(self, rng: &mut impl Rng) -> Self {
+        match self.choose_variant(rng) {
+            Some(i) => self.into_variant_at(i),
+            None => self,
+        }
+    }
+
+    /// Returns the index of a variant chosen at random by weight,
+    /// or `None` if the response has no variants.
+    pub fn choose_variant(&self, rng: &mut impl Rng) -> Option<usize> {
+        let total = self.variants.iter().map(|(weight, _)| *weight).sum::<u32>();
+        if total == 0 {
+            return None;
+        }
+        let mut n = rng.random_range(0..total);
+        for (i, (weight, _)) in self.variants.iter().enumerate() {
+            if n < *weight {
+                return Some(i);
+            }
+            n -= weight;
+        }
+        unreachable!()
+    }
+
+    /// Returns the variant at an index, or the response itself if there is none.
+    pub fn into_variant_at(mut self, i: usize) -> Self {
+        if i < self.variants.len() {
+            self.variants.swap_remove(i).1
+        } else {
+            self
+        }
+    }
+
+    /// Returns the language variant negotiated with an `Accept-Language`
+    /// header value, or the response itself as the default.
+    ///
+    /// Language ranges are tried in order of quality; a range matches a
+    /// variant with the same tag, a more specific tag, e.g. `en` matches `en-US`,
+    /// or a less specific tag, e.g. `en-US` matches `en`. The variant is sent
+    /// with a `Content-Language` header.
+    pub fn into_language(mut self, accept_language: Option<&str>) -> Self {
+        if self.languages.is_empty() {
+            return self;
+        }
+        self.headers.insert("vary", "accept-language");
+        let mut ranges = accept_language
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|value| {
+                let mut parts = value.split(';');
+                let range = parts.next()?.trim().to_lowercase();
+                let q = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map(|q| q.parse::<f32>().unwrap_or(0.0))
+                    .unwrap_or(1.0);
+                (!range.is_empty() && q > 0.0).then_some((range, q))
+            })
+            .collect::<Vec<_>>();
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let chosen = ranges.iter().find_map(|(range, _)| {
+            if range == "*" {
+                return None;
+            }
+            self.languages.iter().position(|(tag, _)| {
+                let tag = tag.to_lowercase();
+                tag == *range
+                    || tag.starts_with(&format!("{range}-"))
+                    || range.starts_with(&format!("{tag}-"))
+            })
+        });
+        match chosen {
+            Some(i) => {
+                let (tag, mut variant) = self.languages.swap_remove(i);
+                if variant.headers.get("content-language").is_none() {
+                    variant.headers.insert("content-language", tag);
+                }
+                variant.headers.insert("vary", "accept-language");
+                variant
+            }
+            None => self,
+        }
+    }
+
+    /// Renders the response for a matched request.
+    ///
+    /// If echo is configured, the body is replaced with the reflected request,
+    /// and if a template is configured, with the rendered template.
+    /// Header templates are rendered into headers, replacing headers of the same name.
+    /// If a resource or script is configured, it handles the request instead.
+    pub fn render(mut self, request: &Request, now: SystemTime, rng: &mut impl Rng) -> Self {
+        if let Some(resource) = &self.resource {
+            return resource.handle(request);
+        }
+        #[cfg(feature = "scripting")]
+        if let Some(script) = &self.script {
+            return script.respond(request);
+        }
+        if let Some(echo) = self.echo.take() {
+            self.headers.insert("content-type", "application/json");
+            self.body = Body::json(echo.reflect(request));
+        }
+        if self.grpc_echo {
+            self.body = Body::bytes_stream(request.body().grpc_messages());
+        }
+        let mut ctx = Context { request, now, rng };
+        if let Some(template) = self.template.take() {
+            self.body = Body::bytes(template.render(&mut ctx));
+        }
+        if let Some(template) = self.message_template.take() {
+            self.message = Some(template.render(&mut ctx));
+        }
+        for (name, template) in std::mem::take(&mut self.header_templates) {
+            let value = template.render(&mut ctx);
+            self.headers.remove(name.as_str());
+            self.headers.insert(name, value);
+        }
+        self
+    }
+
+    /// Resolves the response, handing off the request if a delegate is configured.
+    pub async fn resolve(self, request: &Request) -> Self {
+        match &self.delegate {
+            Some(delegate) => delegate.call(request).await,
+            None => self,
+        }
+    }
+
+    /// Returns the response for the nth match of a mock, starting at 1.
+    ///
+    /// If a failure is configured, its status is returned
+    /// for the first `times` matches. If a sequence is configured,
+    /// its nth response is returned, or once it is exhausted,
+    /// the response given by [`Exhausted`].
+    pub fn into_nth(self, n: usize) -> Self {
+        match &self.failure {
+            Some(failure) if n <= failure.times => Self::default()
+                .with_status(failure.status.clone())
+                .with_message(
+                    failure
+                        .status
+                        .as_http()
+                        .canonical_reason()
+                        .unwrap_or("failure"),
+                ),
+            _ if !self.sequence.is_empty() => {
+                let len = self.sequence.len();
+                let i = n.saturating_sub(1);
+                let i = match self.exhausted {
+                    _ if i < len => i,
+                    Exhausted::RepeatLast | Exhausted::FallThrough => len - 1,
+                    Exhausted::Cycle => i % len,
+                    Exhausted::Error => {
+                        return Self::default()
+                            .with_status(StatusCode::INTERNAL_SERVER_ERROR)
+                            .with_message("sequence exhausted")
+                    }
+                };
+                self.sequence.into_iter().nth(i).unwrap()
+            }
+            _ => self,
+        }
+    }
 }
 
 impl Default for Response {
@@ -67,6 +476,202 @@ impl Default for Response {
             headers: Headers::default(),
             body: Body::default(),
             message: None,
+            failure: None,
+            counters: Vec::new(),
+            variants: Vec::new(),
+            languages: Vec::new(),
+            retry_after_date: None,
+            echo: None,
+            resource: None,
+            template: None,
+            header_templates: Vec::new(),
+            message_template: None,
+            grpc_echo: false,
+            start_session: false,
+            reset: None,
+            delegate: None,
+            informational: Vec::new(),
+            reason: None,
+            #[cfg(feature = "client")]
+            webhooks: Vec::new(),
+            delay: None,
+            chunking: None,
+            #[cfg(feature = "compression")]
+            compression: None,
+            cache: None,
+            #[cfg(feature = "digest")]
+            digest: None,
+            sequence: Vec::new(),
+            exhausted: Exhausted::default(),
+            #[cfg(feature = "scripting")]
+            script: None,
+        }
+    }
+}
+
+/// The parts of a request reflected in an echo response body.
+///
+/// The body is a JSON object with the included fields, e.g.
+/// `{"method": "POST", "path": "/hello", "query": {}, "headers": {}, "body": {}}`.
+/// JSON request bodies are embedded as JSON, others as text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Echo {
+    pub method: bool,
+    pub path: bool,
+    pub query: bool,
+    pub headers: bool,
+    pub body: bool,
+}
+
+impl Echo {
+    /// Creates an echo including all request parts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Includes the method.
+    pub fn method(mut self, enabled: bool) -> Self {
+        self.method = enabled;
+        self
+    }
+
+    /// Includes the path.
+    pub fn path(mut self, enabled: bool) -> Self {
+        self.path = enabled;
+        self
+    }
+
+    /// Includes the query parameters.
+    pub fn query(mut self, enabled: bool) -> Self {
+        self.query = enabled;
+        self
+    }
+
+    /// Includes the headers.
+    pub fn headers(mut self, enabled: bool) -> Self {
+        self.headers = enabled;
+        self
+    }
+
+    /// Includes the body.
+    pub fn body(mut self, enabled: bool) -> Self {
+        self.body = enabled;
+        self
+    }
+
+    /// Reflects the request as a JSON object.
+    pub fn reflect(&self, request: &Request) -> serde_json::Value {
+        use serde_json::{Map, Value};
+        let mut object = Map::new();
+        if self.method {
+            object.insert("method".into(), request.method().as_str().into());
         }
+        if self.path {
+            object.insert("path".into(), request.path().into());
+        }
+        if self.query {
+            let query = request
+                .query_pairs()
+                .map(|(key, value)| (key.into_owned(), Value::String(value.into_owned())))
+                .collect::<Map<_, _>>();
+            object.insert("query".into(), query.into());
+        }
+        if self.headers {
+            let headers = request
+                .headers()
+                .iter()
+                .map(|(name, value)| (name.to_string(), Value::String(value.to_string())))
+                .collect::<Map<_, _>>();
+            object.insert("headers".into(), headers.into());
+        }
+        if self.body {
+            let body = request.body().clone().as_bytes();
+            let body = serde_json::from_slice(&body)
+                .unwrap_or_else(|_| String::from_utf8_lossy(&body).into_owned().into());
+            object.insert("body".into(), body);
+        }
+        object.into()
+    }
+}
+
+impl Default for Echo {
+    fn default() -> Self {
+        Self {
+            method: true,
+            path: true,
+            query: true,
+            headers: true,
+            body: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_delay_sample() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let base = Duration::from_millis(100);
+        assert_eq!(Delay::fixed(base).sample(&mut rng), base);
+
+        for jitter in [
+            Jitter::Absolute(Duration::from_millis(50)),
+            Jitter::Percent(50.0),
+        ] {
+            let delay = Delay::with_jitter(base, jitter);
+            assert_eq!(delay.max_jitter(), Duration::from_millis(50));
+            let samples = (0..20).map(|_| delay.sample(&mut rng)).collect::<Vec<_>>();
+            assert!(samples
+                .iter()
+                .all(|d| (base..=base + Duration::from_millis(50)).contains(d)));
+            assert!(samples.iter().any(|d| *d != samples[0]));
+        }
+    }
+
+    #[test]
+    fn test_into_language() {
+        let mut response = Response::new(Body::bytes("hello"));
+        response
+            .languages
+            .push(("fr".into(), Response::new(Body::bytes("bonjour"))));
+        response
+            .languages
+            .push(("en-GB".into(), Response::new(Body::bytes("hello, mate"))));
+        let negotiate = |accept: Option<&str>| {
+            let mut response = response.clone().into_language(accept);
+            (
+                String::from_utf8(response.body.as_bytes().to_vec()).unwrap(),
+                response
+                    .headers
+                    .get("content-language")
+                    .map(|value| value.to_string()),
+            )
+        };
+        assert_eq!(negotiate(None), ("hello".into(), None));
+        assert_eq!(negotiate(Some("de, *;q=0.1")), ("hello".into(), None));
+        assert_eq!(
+            negotiate(Some("de;q=0.9, fr-CA;q=0.8, en;q=0.5")),
+            ("bonjour".into(), Some("fr".into()))
+        );
+        assert_eq!(
+            negotiate(Some("fr;q=0.4, en")),
+            ("hello, mate".into(), Some("en-GB".into()))
+        );
+        assert_eq!(negotiate(Some("fr;q=0")), ("hello".into(), None));
+    }
+
+    #[test]
+    fn test_chunking_split() {
+        let body = Bytes::from_static(b"0123456789ab");
+        let chunking = Chunking {
+            size: Some(5),
+            ..Default::default()
+        };
+        assert_eq!(chunking.split(body.clone()), ["01234", "56789", "ab"]);
+        assert_eq!(Chunking::default().split(body.clone()), [body]);
     }
 }
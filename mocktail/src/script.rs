@@ -0,0 +1,221 @@
+//! Scripted responses
+//!
+//! Scripts are written in [Rhai](https://rhai.rs) and evaluated with the
+//! matched request when a response is sent. The request is available as the
+//! `request` map, with `method`, `path`, `query` and `headers` fields, the
+//! `body` as text, and `json`, the body parsed as JSON if valid. The details
+//! of the connection are available as `peer_addr`, `protocol` and `alpn`,
+//! and `tls`, a map with `version`, `cipher_suite` and `server_name` fields
+//! for TLS connections.
+//!
+//! A script returns either the response body, e.g. a string, or a map with
+//! optional `status`, `headers`, `body` and `json` fields:
+//!
+//! ```rhai
+//! if request.json.name == "" {
+//!     #{ status: 400, body: "name required" }
+//! } else {
+//!     #{ status: 201, json: #{ id: 1, name: request.json.name } }
+//! }
+//! ```
+use std::{fmt, sync::Arc, sync::OnceLock};
+
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+use tracing::debug;
+
+use crate::{
+    body::Body, headers::Headers, request::Request, response::Response, status::StatusCode,
+};
+
+fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(Engine::new)
+}
+
+/// A compiled response script.
+#[derive(Clone)]
+pub struct Script {
+    source: String,
+    ast: Arc<AST>,
+}
+
+impl Script {
+    /// Compiles a script.
+    pub fn compile(source: impl Into<String>) -> Result<Self, ScriptError> {
+        let source = source.into();
+        let ast = engine()
+            .compile(&source)
+            .map_err(|err| ScriptError::Compile(err.to_string()))?;
+        Ok(Self {
+            source,
+            ast: Arc::new(ast),
+        })
+    }
+
+    /// Returns the script source.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Evaluates the script with the request, returning the response.
+    pub fn eval(&self, request: &Request) -> Result<Response, ScriptError> {
+        let mut scope = Scope::new();
+        scope.push_constant("request", request_map(request));
+        let result = engine()
+            .eval_ast_with_scope::<Dynamic>(&mut scope, &self.ast)
+            .map_err(|err| ScriptError::Eval(err.to_string()))?;
+        into_response(result)
+    }
+
+    /// Evaluates the script, responding with `500 Internal Server Error` if it fails.
+    pub(crate) fn respond(&self, request: &Request) -> Response {
+        self.eval(request).unwrap_or_else(|err| {
+            debug!(%err, "script failed");
+            Response::default()
+                .with_status(StatusCode::INTERNAL_SERVER_ERROR)
+                .with_message(err.to_string())
+        })
+    }
+}
+
+impl fmt::Debug for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Script").field(&self.source).finish()
+    }
+}
+
+impl PartialEq for Script {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+/// Converts a request to a script map.
+fn request_map(request: &Request) -> Map {
+    let body = request.body().clone().as_bytes();
+    let json = serde_json::from_slice::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|value| rhai::serde::to_dynamic(value).ok())
+        .unwrap_or(Dynamic::UNIT);
+    let query = request
+        .query_pairs()
+        .map(|(key, value)| (key.as_ref().into(), value.into_owned().into()))
+        .collect::<Map>();
+    let headers = request
+        .headers()
+        .iter()
+        .map(|(name, value)| (name.as_str().into(), value.to_string().into()))
+        .collect::<Map>();
+    let mut map = Map::new();
+    map.insert("method".into(), request.method().to_string().into());
+    map.insert("path".into(), request.path().to_string().into());
+    map.insert("query".into(), query.into());
+    map.insert("headers".into(), headers.into());
+    map.insert(
+        "body".into(),
+        String::from_utf8_lossy(&body).into_owned().into(),
+    );
+    map.insert("json".into(), json);
+    let info = request.connection_info();
+    let optional = |value: Option<String>| value.map(Dynamic::from).unwrap_or(Dynamic::UNIT);
+    map.insert(
+        "peer_addr".into(),
+        optional(request.peer_addr().map(|addr| addr.to_string())),
+    );
+    map.insert(
+        "protocol".into(),
+        optional(request.protocol().map(Into::into)),
+    );
+    map.insert(
+        "alpn".into(),
+        optional(info.and_then(|info| info.alpn.clone())),
+    );
+    let tls = info.and_then(|info| info.tls.as_ref()).map(|tls| {
+        let mut map = Map::new();
+        map.insert("version".into(), tls.version.clone().into());
+        map.insert("cipher_suite".into(), tls.cipher_suite.clone().into());
+        map.insert("server_name".into(), optional(tls.server_name.clone()));
+        Dynamic::from(map)
+    });
+    map.insert("tls".into(), tls.unwrap_or(Dynamic::UNIT));
+    map
+}
+
+/// Converts a script result to a response.
+fn into_response(result: Dynamic) -> Result<Response, ScriptError> {
+    let Some(mut map) = result.clone().try_cast::<Map>() else {
+        return Ok(Response::new(Body::bytes(result.to_string())));
+    };
+    let mut response = Response::default();
+    if let Some(status) = map.remove("status") {
+        let status = status
+            .as_int()
+            .ok()
+            .and_then(|status| u16::try_from(status).ok())
+            .and_then(|status| StatusCode::from_u16(status).ok())
+            .ok_or_else(|| ScriptError::Eval(format!("invalid status: {status}")))?;
+        response.status = status;
+    }
+    if let Some(headers) = map.remove("headers").and_then(|v| v.try_cast::<Map>()) {
+        response.headers = Headers::from_iter(
+            headers
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string())),
+        );
+    }
+    if let Some(json) = map.remove("json") {
+        let json = rhai::serde::from_dynamic::<serde_json::Value>(&json)
+            .map_err(|err| ScriptError::Eval(err.to_string()))?;
+        response.headers.insert("content-type", "application/json");
+        response.body = Body::json(json);
+    } else if let Some(body) = map.remove("body") {
+        response.body = Body::bytes(body.to_string());
+    }
+    if response.is_error() {
+        let message = response.body.clone().as_bytes();
+        if !message.is_empty() {
+            response.message = Some(String::from_utf8_lossy(&message).into_owned());
+        }
+    }
+    Ok(response)
+}
+
+/// Represents errors that can occur while compiling or evaluating a [`Script`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ScriptError {
+    #[error("script compile error: {0}")]
+    Compile(String),
+    #[error("script error: {0}")]
+    Eval(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::Method;
+
+    #[test]
+    fn test_eval() {
+        let request = Request::new(Method::POST, "http://localhost/users".parse().unwrap())
+            .with_body(Body::bytes(r#"{"name":"dan"}"#));
+
+        let script = Script::compile(r#"`hello ${request.json.name}!`"#).unwrap();
+        let response = script.eval(&request).unwrap();
+        assert_eq!(response.body().clone().as_bytes(), "hello dan!");
+
+        let script = Script::compile(
+            r#"#{ status: 201, headers: #{ "x-id": "1" }, json: #{ id: 1, path: request.path } }"#,
+        )
+        .unwrap();
+        let response = script.eval(&request).unwrap();
+        assert_eq!(response.status(), &StatusCode::CREATED);
+        assert_eq!(response.headers().get("x-id").unwrap(), "1");
+        assert_eq!(
+            response.body().clone().as_bytes(),
+            r#"{"id":1,"path":"/users"}"#
+        );
+
+        assert!(Script::compile("let x = ").is_err());
+        assert!(Script::compile("throw 1").unwrap().eval(&request).is_err());
+    }
+}
@@ -1,38 +1,90 @@
 //! Mock server
 use std::{
-    net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream},
-    sync::{Arc, OnceLock, RwLock, RwLockReadGuard, RwLockWriteGuard},
-    time::Duration,
+    collections::{BTreeMap, HashMap, HashSet},
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock, RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
+    time::{Duration, SystemTime},
 };
 
+use futures::{stream, StreamExt};
 use http_body::Body;
 use hyper::{body::Incoming, service::Service};
 use hyper_util::{
-    rt::{TokioExecutor, TokioIo},
+    rt::{TokioExecutor, TokioIo, TokioTimer},
     server::conn,
 };
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
-use tokio::net::TcpListener;
-use tracing::{debug, error, info};
+#[cfg(feature = "client")]
+use tokio::task::AbortHandle;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    runtime::{self, Handle, Runtime},
+    sync::{mpsc, oneshot, watch, Notify},
+    task::{JoinHandle, JoinSet},
+};
+use tracing::{debug, error, info, warn};
 use url::Url;
+use uuid::Uuid;
 
+#[cfg(feature = "digest")]
+use crate::matchers;
 use crate::{
+    auth::Challenge,
+    clock::Clock,
+    interactions::Interactions,
+    journal::{Journal, JournalEntry},
     mock::Mock,
     mock_builder::{Then, When},
     mock_set::MockSet,
-    service::{GrpcMockService, HttpMockService},
+    replay::{Replay, ReplayReport},
+    request::{ConnectionInfo, Method, Request},
+    resource::Resource,
+    response::{Delay, Response},
+    service::{GrpcMockService, HttpMockService, InformationalWriter, SharedIo},
+    snapshot::Snapshot,
+    status::StatusCode,
+    verify::{Attempts, Expectation, Sequence, VerificationError},
     Error,
 };
 
+mod config;
+pub use config::{
+    ConfigError, Http2Settings, MockServerConfig, MockServerConfigBuilder, PortStrategy,
+    ProtocolErrors, ReadStall, UnusedMocks, DEFAULT_IDEMPOTENCY_HEADER,
+};
+mod degraded;
+pub use degraded::{DegradedMode, DEGRADED_MODE_LABEL};
+mod namespace;
+pub use namespace::{Namespace, NamespaceMode, DEFAULT_NAMESPACE_HEADER};
+mod listener;
+use listener::{bind_tcp, BoundListener, Connection};
+pub use listener::{Listener, ListenerAddr};
+mod ping;
+use ping::PingFilter;
+mod pool;
+pub use pool::{MockServerPool, PooledMockServer};
+mod scoped;
+pub use scoped::ScopedMockServer;
+mod session;
+pub use session::{SessionMode, DEFAULT_SESSION_COOKIE};
+
+/// The priority of catch-all preset mocks, matched after all other mocks.
+const CATCH_ALL_PRIORITY: u8 = u8::MAX;
+
 /// A mock server.
 pub struct MockServer {
     name: &'static str,
     kind: ServerKind,
     addr: OnceLock<SocketAddr>,
     base_url: OnceLock<Url>,
+    listeners: OnceLock<Vec<ListenerAddr>>,
     state: Arc<MockServerState>,
     config: MockServerConfig,
+    task: Mutex<Option<JoinHandle<()>>>,
 }
 
 impl MockServer {
@@ -43,8 +95,10 @@ impl MockServer {
             kind: ServerKind::Http,
             addr: OnceLock::new(),
             base_url: OnceLock::new(),
+            listeners: OnceLock::new(),
             state: Arc::new(MockServerState::default()),
             config: MockServerConfig::default(),
+            task: Mutex::new(None),
         }
     }
 
@@ -55,8 +109,10 @@ impl MockServer {
             kind: ServerKind::Http,
             addr: OnceLock::new(),
             base_url: OnceLock::new(),
+            listeners: OnceLock::new(),
             state: Arc::new(MockServerState::default()),
             config: MockServerConfig::default(),
+            task: Mutex::new(None),
         }
     }
 
@@ -67,8 +123,10 @@ impl MockServer {
             kind: ServerKind::Grpc,
             addr: OnceLock::new(),
             base_url: OnceLock::new(),
+            listeners: OnceLock::new(),
             state: Arc::new(MockServerState::default()),
             config: MockServerConfig::default(),
+            task: Mutex::new(None),
         }
     }
 
@@ -80,77 +138,140 @@ impl MockServer {
     }
 
     /// Sets the server mocks.
-    pub fn with_mocks(self, mocks: MockSet) -> Self {
-        *self.state.mocks.write().unwrap() = mocks;
+    pub fn with_mocks(self, mut mocks: MockSet) -> Self {
+        let mut current = self.state.mocks.write().unwrap();
+        if let Some(clock) = current.clock() {
+            mocks.set_clock(clock.clone());
+        }
+        *current = mocks;
+        drop(current);
         self
     }
 
     /// Sets the server configuration.
     pub fn with_config(mut self, config: MockServerConfig) -> Self {
+        self.state.configure(&config);
         self.config = config;
         self
     }
 
+    /// Starts the server, waiting up to the configured ready timeout
+    /// for it to begin accepting connections.
     pub async fn start(&self) -> Result<(), Error> {
+        self.start_with_timeout(self.config.ready_timeout()).await
+    }
+
+    /// Starts the server, waiting up to `timeout` for it to begin
+    /// accepting connections.
+    ///
+    /// The server runs on the current tokio runtime, or on a shared background
+    /// runtime if called outside of one, e.g. from another async executor.
+    pub async fn start_with_timeout(&self, timeout: Duration) -> Result<(), Error> {
+        self.start_on(runtime(), timeout).await
+    }
+
+    /// Starts the server on a runtime.
+    async fn start_on(&self, runtime: Handle, timeout: Duration) -> Result<(), Error> {
         if self.addr().is_some() {
             return Err(Error::ServerError("already running".into()));
         }
-
-        let mut counter = 0;
-        let mut rng = SmallRng::from_os_rng();
-
-        let listener = loop {
-            let port: u16 =
-                rng.random_range(self.config.port_range_start..self.config.port_range_end);
-            let addr = SocketAddr::from((self.config.listen_addr, port));
-            if let Ok(listener) = TcpListener::bind(&addr).await {
-                break listener;
-            }
-
-            if counter == self.config.bind_max_retries {
-                return Err(Error::ServerError("server failed to bind to port".into()));
-            }
-            counter += 1;
-        };
-
-        let addr = listener.local_addr()?;
-        info!("started {} [{}] server on {addr}", self.name(), &self.kind);
-        let base_url = Url::parse(&format!("http://{}", &addr)).unwrap();
-
-        match self.kind {
-            ServerKind::Http => {
-                let service = HttpMockService::new(self.state.clone());
-                tokio::spawn(run_server(listener, self.kind, service));
-            }
-            ServerKind::Grpc => {
-                let service = GrpcMockService::new(self.state.clone());
-                tokio::spawn(run_server(listener, self.kind, service));
-            }
-        };
-        // Wait for server to become ready
-        let mut counter = 0;
-        loop {
-            if TcpStream::connect_timeout(&addr, self.config.ready_connect_timeout).is_ok() {
-                break;
-            }
-            if counter == self.config.ready_connect_max_retries {
-                return Err(Error::ServerError("server failed to become ready".into()));
-            }
-            counter += 1;
-            tokio::time::sleep(Duration::from_millis(10)).await;
+        if let Some(path) = self.config.journal_file() {
+            self.state.journal.write().unwrap().persist(path)?;
         }
+
+        let name = self.name;
+        let kind = self.kind;
+        let state = self.state.clone();
+        let config = Arc::new(self.config.clone());
+        let (addr, listeners, task) = runtime
+            .spawn(async move { bind_and_serve(name, kind, state, config, timeout).await })
+            .await
+            .map_err(|err| Error::ServerError(format!("server failed to start: {err}")))??;
         info!("{} server ready", self.name());
 
+        let base_url = Url::parse(&format!("{}://{}", self.scheme(), &addr)).unwrap();
         self.addr.set(addr).unwrap();
         self.base_url.set(base_url).unwrap();
+        self.listeners.set(listeners).unwrap();
+        *self.task.lock().unwrap() = Some(task);
 
         Ok(())
     }
 
+    /// Starts the server, returning a handle that stops it when dropped,
+    /// e.g. at the end of a test fixture.
+    ///
+    /// On drop, unmatched requests are printed and verification failures
+    /// panic, see [`ScopedMockServer`].
+    pub async fn start_scoped(self) -> Result<ScopedMockServer, Error> {
+        ScopedMockServer::start(self).await
+    }
+
+    /// Starts the server, blocking the current thread until it is ready.
+    ///
+    /// For use in tests without an async runtime, e.g. plain `#[test]` functions;
+    /// the server runs on a shared background runtime. Must not be called from
+    /// within an async context.
+    pub fn start_blocking(&self) -> Result<(), Error> {
+        futures::executor::block_on(self.start())
+    }
+
+    /// Stops the server, blocking the current thread until it has stopped.
+    ///
+    /// See [`MockServer::stop`]. Must not be called from within an async context.
+    pub fn stop_blocking(&self) {
+        futures::executor::block_on(self.stop())
+    }
+
+    /// Returns a service handling HTTP requests in-process, without a running server.
+    #[cfg(feature = "tower")]
+    pub fn http_service(&self) -> HttpMockService {
+        HttpMockService::new(self.state.clone(), Arc::new(self.config.clone()))
+    }
+
+    /// Returns a service handling gRPC requests in-process, without a running server.
+    #[cfg(feature = "tower")]
+    pub fn grpc_service(&self) -> GrpcMockService {
+        GrpcMockService::new(self.state.clone(), Arc::new(self.config.clone()))
+    }
+
+    /// Returns a connector serving the server mocks in-process,
+    /// without a running server.
+    #[cfg(feature = "tower")]
+    pub fn connector(&self) -> crate::service::MockConnector {
+        crate::service::MockConnector::new(
+            self.kind,
+            self.state.clone(),
+            Arc::new(self.config.clone()),
+        )
+    }
+
     pub fn name(&self) -> &str {
         self.name
     }
 
+    /// Returns the url scheme, `https` if TLS is configured.
+    fn scheme(&self) -> &'static str {
+        #[cfg(feature = "tls")]
+        if self.config.tls().is_some() {
+            return "https";
+        }
+        "http"
+    }
+
+    /// Returns the PEM-encoded CA certificate of the generated TLS certificate,
+    /// to configure clients to trust the server.
+    #[cfg(feature = "tls")]
+    pub fn tls_ca_pem(&self) -> Option<&str> {
+        self.config.tls().and_then(|tls| tls.ca_pem())
+    }
+
+    /// Returns the DER-encoded TLS server certificate.
+    #[cfg(feature = "tls")]
+    pub fn tls_cert_der(&self) -> Option<&[u8]> {
+        self.config.tls().map(|tls| tls.cert_der())
+    }
+
     pub fn addr(&self) -> Option<&SocketAddr> {
         self.addr.get()
     }
@@ -167,6 +288,11 @@ impl MockServer {
         self.base_url.get()
     }
 
+    /// Returns the addresses of the additional listeners, see [`Listener`].
+    pub fn listener_addrs(&self) -> &[ListenerAddr] {
+        self.listeners.get().map(Vec::as_slice).unwrap_or_default()
+    }
+
     pub fn url(&self, path: &str) -> Url {
         if let Some(url) = self.base_url() {
             url.join(path).unwrap()
@@ -176,24 +302,178 @@ impl MockServer {
     }
 
     pub fn is_running(&self) -> bool {
-        self.addr().is_some()
+        self.addr().is_some() && !*self.state.shutdown.borrow()
+    }
+
+    /// Returns the number of connections accepted.
+    pub fn connection_count(&self) -> usize {
+        self.state.connections.load(Ordering::Relaxed)
+    }
+
+    /// Stops the server.
+    ///
+    /// New connections are no longer accepted, and in-flight requests are given
+    /// the configured shutdown grace period to complete before their connections
+    /// are aborted. A stopped server cannot be restarted.
+    pub async fn stop(&self) {
+        self.state.shutdown.send_replace(true);
+        let task = self.task.lock().unwrap().take();
+        if let Some(task) = task {
+            let _ = task.await;
+            info!("{} server stopped", self.name());
+        }
     }
 
     pub fn mocks(&self) -> RwLockWriteGuard<'_, MockSet> {
         self.state.mocks.write().unwrap()
     }
 
-    /// Builds and inserts a mock with default options.
-    pub fn mock<F>(&mut self, f: F)
+    /// Returns the request journal.
+    pub fn journal(&self) -> RwLockReadGuard<'_, Journal> {
+        self.state.journal()
+    }
+
+    /// Returns the last request received.
+    pub fn last_request(&self) -> Option<Request> {
+        self.journal()
+            .iter()
+            .next_back()
+            .map(|entry| entry.request().clone())
+    }
+
+    /// Deserializes the JSON body of the last request received.
+    pub fn last_request_json<T: serde::de::DeserializeOwned>(&self) -> Result<T, Error> {
+        self.last_request()
+            .ok_or_else(|| Error::Invalid("no requests received".into()))?
+            .body_json()
+    }
+
+    /// Decodes the protobuf body of the last request received,
+    /// the first message of a gRPC request.
+    pub fn last_request_pb<M: prost::Message + Default>(&self) -> Result<M, Error> {
+        self.last_request()
+            .ok_or_else(|| Error::Invalid("no requests received".into()))?
+            .body_pb()
+    }
+
+    /// Waits for the client to cancel a request matched to a mock, returning
+    /// its journal entry, see [`JournalEntry::cancelled_after`].
+    ///
+    /// Returns immediately if a request matched to the mock was already cancelled.
+    pub async fn wait_for_cancellation(&self, mock_id: Uuid) -> JournalEntry {
+        let mut cancellations = self.state.cancellations.subscribe();
+        loop {
+            let entry = self
+                .journal()
+                .iter()
+                .rev()
+                .find(|entry| entry.mock_id == Some(mock_id) && entry.is_cancelled())
+                .cloned();
+            if let Some(entry) = entry {
+                return entry;
+            }
+            // The sender is owned by the state, it is never dropped while waiting
+            let _ = cancellations.changed().await;
+        }
+    }
+
+    /// Replays the journaled requests that matched a mock against a server,
+    /// reporting where its responses differ from the mocked responses.
+    pub async fn replay(&self, replay: &Replay) -> ReplayReport {
+        let journal = self.journal().clone();
+        let mocks = self.mocks().clone();
+        replay.run(&journal, &mocks).await
+    }
+
+    /// Clears the request journal.
+    pub fn clear_journal(&self) {
+        self.state.journal.write().unwrap().clear();
+    }
+
+    /// Pauses the server.
+    ///
+    /// While paused, open connections are closed and new connections are
+    /// closed as soon as they are accepted, simulating an unreachable upstream.
+    /// The port remains bound so that it can be resumed on the same address.
+    pub fn pause(&self) {
+        self.state.paused.send_replace(true);
+        info!("{} server paused", self.name());
+    }
+
+    /// Resumes a paused server.
+    pub fn resume(&self) {
+        self.state.paused.send_replace(false);
+        info!("{} server resumed", self.name());
+    }
+
+    /// Returns true if the server is paused.
+    pub fn is_paused(&self) -> bool {
+        self.state.is_paused()
+    }
+
+    /// Sets a degraded mode, answering all requests with the response
+    /// of the mode instead of matching mocks until it is cleared.
+    ///
+    /// Journal entries of requests answered in the mode are labeled
+    /// with [`DEGRADED_MODE_LABEL`] and the mode name.
+    pub fn set_degraded(&self, mode: DegradedMode) {
+        info!("{} server degraded: {}", self.name(), mode.name());
+        *self.state.degraded.write().unwrap() = Some(mode);
+    }
+
+    /// Clears the degraded mode, matching requests to mocks again.
+    pub fn clear_degraded(&self) {
+        *self.state.degraded.write().unwrap() = None;
+        info!("{} server restored", self.name());
+    }
+
+    /// Returns the name of the current degraded mode, if any.
+    pub fn degraded(&self) -> Option<String> {
+        self.state
+            .degraded
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|mode| mode.name().to_string())
+    }
+
+    /// Resets the server, clearing mocks, expectations and the request journal.
+    ///
+    /// A reset server behaves like a freshly started one: it is resumed,
+    /// its hooks are removed, scheduled webhooks are cancelled and its
+    /// random number generator is re-seeded from the configuration.
+    /// Connections accepted after a reset are numbered from 1 again.
+    /// Registered namespaces, the configuration and open connections are kept.
+    /// This allows a server to be started once and reused between tests.
+    pub fn reset(&self) {
+        self.state.reset(&self.config);
+    }
+
+    /// Creates a new [`Namespace`] with a unique token.
+    ///
+    /// Namespaces allow many tests to share a single server without
+    /// interfering with each other's mocks and journal entries.
+    pub fn namespace(&self) -> Namespace {
+        Namespace::new(
+            self.config.namespace_mode().clone(),
+            self.base_url().cloned(),
+            self.state.clone(),
+        )
+    }
+
+    /// Builds and inserts a mock with default options, returning its ID.
+    pub fn mock<F>(&mut self, f: F) -> Uuid
     where
         F: FnOnce(When, Then),
     {
         let mock = Mock::new(f);
+        let id = mock.id;
         self.state.mocks.write().unwrap().insert(mock);
+        id
     }
 
-    /// Builds and inserts a mock with options.
-    pub fn mock_with_options<F>(&mut self, priority: u8, limit: Option<usize>, f: F)
+    /// Builds and inserts a mock with options, returning its ID.
+    pub fn mock_with_options<F>(&mut self, priority: u8, limit: Option<usize>, f: F) -> Uuid
     where
         F: FnOnce(When, Then),
     {
@@ -201,7 +481,301 @@ impl MockServer {
         if let Some(limit) = limit {
             mock = mock.with_limit(limit);
         }
+        let id = mock.id;
         self.state.mocks.write().unwrap().insert(mock);
+        id
+    }
+
+    /// Builds and inserts a mock requiring the credentials of a challenge,
+    /// returning its ID.
+    ///
+    /// See [`MockSet::mock_with_challenge`].
+    pub fn mock_with_challenge<F>(&mut self, challenge: Challenge, f: F) -> Uuid
+    where
+        F: FnOnce(When, Then),
+    {
+        self.state
+            .mocks
+            .write()
+            .unwrap()
+            .mock_with_challenge(challenge, f)
+    }
+
+    /// Adds a mock responding to `GET` requests for the path with a JSON body,
+    /// returning its ID.
+    ///
+    /// Use [`MockServer::mock`] for mocks with more than a method, path and body.
+    pub fn stub_get_json(&mut self, path: impl Into<String>, body: impl serde::Serialize) -> Uuid {
+        let path = path.into();
+        self.mock(|when, then| {
+            when.get().path(path);
+            then.json(body);
+        })
+    }
+
+    /// Adds a mock responding to `GET` requests for the path with a text body,
+    /// returning its ID.
+    pub fn stub_get_text(&mut self, path: impl Into<String>, body: impl Into<String>) -> Uuid {
+        let path = path.into();
+        self.mock(|when, then| {
+            when.get().path(path);
+            then.text(body);
+        })
+    }
+
+    /// Adds a mock responding to `POST` requests for the path and JSON body
+    /// with a JSON body, returning its ID.
+    pub fn stub_post_json(
+        &mut self,
+        path: impl Into<String>,
+        request: impl serde::Serialize,
+        response: impl serde::Serialize,
+    ) -> Uuid {
+        let path = path.into();
+        self.mock(|when, then| {
+            when.post().path(path).json(request);
+            then.json(response);
+        })
+    }
+
+    /// Adds a mock responding to requests with the method and path with
+    /// the status and an empty body, returning its ID.
+    pub fn stub_status(
+        &mut self,
+        method: Method,
+        path: impl Into<String>,
+        status: impl Into<StatusCode>,
+    ) -> Uuid {
+        let path = path.into();
+        self.mock(|when, then| {
+            when.method(method).path(path);
+            then.status(status);
+        })
+    }
+
+    /// Adds a mock responding to calls of a unary gRPC method, e.g. `pkg.Service/Method`,
+    /// with the request message with the response message, returning its ID.
+    pub fn stub_grpc_unary(
+        &mut self,
+        method: impl Into<String>,
+        request: impl prost::Message,
+        response: impl prost::Message,
+    ) -> Uuid {
+        let method = method.into();
+        let path = format!("/{}", method.trim_start_matches('/'));
+        self.mock(|when, then| {
+            when.path(path).pb(request);
+            then.pb(response);
+        })
+    }
+
+    /// Adds a catch-all mock answering calls of any method of a gRPC service,
+    /// e.g. `pkg.Service`, by reflecting the request messages, returning its ID.
+    ///
+    /// The mock has the lowest priority, so other mocks are matched first.
+    pub fn grpc_echo_service(&mut self, service: impl Into<String>) -> Uuid {
+        self.mock_with_options(CATCH_ALL_PRIORITY, None, |when, then| {
+            when.grpc_service(service);
+            then.grpc_echo();
+        })
+    }
+
+    /// Adds a catch-all mock answering calls of any method of a gRPC service,
+    /// e.g. `pkg.Service`, with `UNIMPLEMENTED` and the method name, returning its ID.
+    ///
+    /// The mock has the lowest priority, so other mocks are matched first.
+    pub fn grpc_unimplemented_service(&mut self, service: impl Into<String>) -> Uuid {
+        self.mock_with_options(CATCH_ALL_PRIORITY, None, |when, then| {
+            when.grpc_service(service);
+            then.grpc_unimplemented("")
+                .message_template("method not implemented: {{ request.path }}");
+        })
+    }
+
+    /// Adds a mock serving an in-memory [`Resource`] at the path, returning the resource.
+    ///
+    /// The resource can be used to seed and inspect items.
+    pub fn resource(&self, path: impl Into<String>) -> Resource {
+        self.resource_with(Resource::new(path))
+    }
+
+    /// Adds a mock serving the [`Resource`], returning it.
+    pub fn resource_with(&self, resource: Resource) -> Resource {
+        self.mocks().mock(|when, then| {
+            when.path_prefix(resource.path());
+            then.resource(resource.clone());
+        });
+        resource
+    }
+
+    /// Sets a hook called with each request that does not match a mock.
+    ///
+    /// The hook is called on the server task serving the request.
+    pub fn on_unmatched(&self, f: impl Fn(&Request) + Send + Sync + 'static) {
+        *self.state.unmatched_hook.write().unwrap() = Some(Hook::new(f));
+    }
+
+    /// Adds a hook called with each request rejected with a protocol error,
+    /// e.g. a body that failed to be received or a malformed gRPC message frame.
+    ///
+    /// The journal entry contains the raw body bytes received and the error,
+    /// see [`JournalEntry::protocol_error`]. The hook is called on the server
+    /// task serving the request.
+    pub fn on_protocol_error(&self, f: impl Fn(&JournalEntry) + Send + Sync + 'static) {
+        self.state
+            .protocol_error_hooks
+            .write()
+            .unwrap()
+            .push(Hook::new(f));
+    }
+
+    /// Adds a hook called with the journal entry of each request cancelled by
+    /// the client, see [`JournalEntry::cancelled_after`].
+    ///
+    /// The hook is called on the server task serving the request.
+    pub fn on_cancellation(&self, f: impl Fn(&JournalEntry) + Send + Sync + 'static) {
+        self.state
+            .cancellation_hooks
+            .write()
+            .unwrap()
+            .push(Hook::new(f));
+    }
+
+    /// Adds a hook called with each request as it is recorded in the journal,
+    /// whether or not it matched a mock.
+    ///
+    /// The hook is called on the server task serving the request.
+    pub fn on_request(&self, f: impl Fn(&JournalEntry) + Send + Sync + 'static) {
+        self.state.request_hooks.write().unwrap().push(Hook::new(f));
+    }
+
+    /// Adds a hook called with a mock when it is matched for the last time
+    /// before reaching its limit, e.g. to phase behavior.
+    ///
+    /// The hook is called on the server task serving the request.
+    pub fn on_limit_reached(&self, id: Uuid, f: impl Fn(&Mock) + Send + Sync + 'static) {
+        self.state
+            .limit_hooks
+            .write()
+            .unwrap()
+            .push((id, Hook::new(f)));
+    }
+
+    /// Returns the number of matches remaining until the limit of a mock is
+    /// reached, or `None` if the mock has no limit or is not found.
+    pub fn remaining_matches(&self, id: &Uuid) -> Option<usize> {
+        self.state
+            .mocks()
+            .find(|mock| mock.id == *id)?
+            .remaining_matches()
+    }
+
+    /// Resets the match count of a mock, so a limited mock matches again.
+    pub fn reset_match_count(&self, id: &Uuid) {
+        if let Some(mock) = self.state.mocks().find(|mock| mock.id == *id) {
+            mock.reset();
+        }
+    }
+
+    /// Returns a channel receiving each request as it is recorded in the journal.
+    pub fn mirror(&self) -> mpsc::UnboundedReceiver<JournalEntry> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.on_request(move |entry| {
+            let _ = tx.send(entry.clone());
+        });
+        rx
+    }
+
+    /// Returns the value of a counter, defaulting to zero.
+    pub fn counter(&self, name: &str) -> i64 {
+        self.state
+            .counters
+            .read()
+            .unwrap()
+            .get(name)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Sets the value of a counter.
+    pub fn set_counter(&self, name: impl Into<String>, value: i64) {
+        self.state
+            .counters
+            .write()
+            .unwrap()
+            .insert(name.into(), value);
+    }
+
+    /// Adds an expected order of calls to mocks, checked by [`MockServer::verify`].
+    pub fn expect_sequence(&self, sequence: Sequence) {
+        self.state.sequences.write().unwrap().push(sequence);
+    }
+
+    /// Verifies the server expectations against the request journal.
+    pub fn verify(&self) -> Result<(), VerificationError> {
+        self.state.verify()
+    }
+
+    /// Asserts the journal contains the expected number of requests matching
+    /// the matchers of an [`Expectation`], e.g.
+    /// `server.assert_received(request_matching(When::new().post().path("/orders")).times(2))`.
+    pub fn assert_received(&self, expectation: Expectation) -> Result<(), VerificationError> {
+        expectation
+            .verify(&self.state.journal())
+            .map_err(|failure| VerificationError {
+                failures: vec![failure],
+            })
+    }
+
+    /// Returns the recorded calls to a mock.
+    pub fn attempts(&self, mock_id: Uuid) -> Attempts {
+        Attempts::new(&self.state.journal(), mock_id)
+    }
+
+    /// Asserts a mock was retried between `min` and `max` times (inclusive),
+    /// with all calls made `within` the duration, returning the calls
+    /// for further inspection of the intervals between them.
+    pub fn assert_retried(
+        &self,
+        mock_id: Uuid,
+        min: usize,
+        max: usize,
+        within: Duration,
+    ) -> Result<Attempts, VerificationError> {
+        let attempts = self.attempts(mock_id);
+        attempts
+            .verify_retried(min, max, within)
+            .map_err(|failure| VerificationError {
+                failures: vec![failure],
+            })?;
+        Ok(attempts)
+    }
+
+    /// Returns the mocks that have never been matched, e.g. stale fixtures.
+    pub fn unused_mocks(&self) -> Vec<Mock> {
+        self.state
+            .mocks()
+            .iter()
+            .filter(|mock| mock.match_count() == 0)
+            .cloned()
+            .collect()
+    }
+
+    /// Returns a snapshot of the interactions recorded in the journal.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::new(&self.state.journal(), &self.state.mocks())
+    }
+
+    /// Asserts the interactions recorded in the journal match the golden file,
+    /// writing it if it does not exist.
+    ///
+    /// Set the `MOCKTAIL_UPDATE_SNAPSHOTS` environment variable to update it.
+    /// See [`Snapshot`] to exclude headers that vary between runs.
+    pub fn assert_snapshot(
+        &self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<(), VerificationError> {
+        self.snapshot().verify_file(path)
     }
 }
 
@@ -209,22 +783,401 @@ impl MockServer {
 #[derive(Debug, Default)]
 pub struct MockServerState {
     pub mocks: RwLock<MockSet>,
+    pub journal: RwLock<Journal>,
+    pub namespaces: RwLock<HashSet<String>>,
+    pub paused: watch::Sender<bool>,
+    pub sequences: RwLock<Vec<Sequence>>,
+    pub failures: RwLock<Vec<String>>,
+    pub unmatched_hook: RwLock<Option<Hook<Request>>>,
+    pub request_hooks: RwLock<Vec<Hook<JournalEntry>>>,
+    pub protocol_error_hooks: RwLock<Vec<Hook<JournalEntry>>>,
+    pub cancellation_hooks: RwLock<Vec<Hook<JournalEntry>>>,
+    pub limit_hooks: RwLock<Vec<(Uuid, Hook<Mock>)>>,
+    pub degraded: RwLock<Option<DegradedMode>>,
+    pub connections: AtomicUsize,
+    pub idempotent_responses: RwLock<HashMap<IdempotencyKey, (Mock, Response)>>,
+    pub counters: RwLock<HashMap<String, i64>>,
+    pub shutdown: watch::Sender<bool>,
+    pub cancellations: watch::Sender<usize>,
+    pub rng: Mutex<Option<SmallRng>>,
+    pub clock: RwLock<Option<Arc<dyn Clock>>>,
+    pub sessions: RwLock<HashSet<String>>,
+    pub session_mode: RwLock<SessionMode>,
+    #[cfg(feature = "client")]
+    pub webhooks: Mutex<Vec<AbortHandle>>,
 }
 
+/// An idempotency key and the namespace it was sent in.
+pub type IdempotencyKey = (Option<String>, String);
+
 impl MockServerState {
     pub fn new(mocks: MockSet) -> Self {
         Self {
             mocks: RwLock::new(mocks),
+            ..Default::default()
         }
     }
 
     pub fn mocks(&self) -> RwLockReadGuard<'_, MockSet> {
         self.mocks.read().unwrap()
     }
+
+    pub fn journal(&self) -> RwLockReadGuard<'_, Journal> {
+        self.journal.read().unwrap()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Returns the number of the next connection accepted, starting at 1.
+    pub fn next_connection(&self) -> usize {
+        self.connections.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Resolves the namespace of a request.
+    pub fn resolve_namespace(&self, mode: &NamespaceMode, request: &mut Request) {
+        mode.resolve(&self.namespaces.read().unwrap(), request);
+    }
+
+    /// Resolves the session of a request.
+    pub fn resolve_session(&self, request: &mut Request) {
+        self.session_mode
+            .read()
+            .unwrap()
+            .resolve(&self.sessions.read().unwrap(), request);
+    }
+
+    /// Applies the configured journal capacity, seed, clock and session mode.
+    pub fn configure(&self, config: &MockServerConfig) {
+        self.journal
+            .write()
+            .unwrap()
+            .set_capacity(config.journal_capacity());
+        *self.rng.lock().unwrap() = config.seed().map(SmallRng::seed_from_u64);
+        *self.clock.write().unwrap() = Some(config.clock());
+        self.mocks.write().unwrap().set_clock(config.clock());
+        *self.session_mode.write().unwrap() = config.session_mode().clone();
+    }
+
+    /// Restores the state of a freshly started server, given its configuration.
+    ///
+    /// Mocks, expectations, the journal, hooks and sessions are cleared,
+    /// scheduled webhooks are cancelled and the server is resumed.
+    /// Namespaces are kept.
+    pub fn reset(&self, config: &MockServerConfig) {
+        self.mocks.write().unwrap().clear();
+        self.journal.write().unwrap().clear();
+        self.sequences.write().unwrap().clear();
+        self.failures.write().unwrap().clear();
+        self.idempotent_responses.write().unwrap().clear();
+        self.counters.write().unwrap().clear();
+        self.sessions.write().unwrap().clear();
+        self.limit_hooks.write().unwrap().clear();
+        *self.degraded.write().unwrap() = None;
+        *self.unmatched_hook.write().unwrap() = None;
+        self.request_hooks.write().unwrap().clear();
+        self.protocol_error_hooks.write().unwrap().clear();
+        self.cancellation_hooks.write().unwrap().clear();
+        #[cfg(feature = "client")]
+        for webhook in self.webhooks.lock().unwrap().drain(..) {
+            webhook.abort();
+        }
+        self.connections.store(0, Ordering::Relaxed);
+        self.paused.send_replace(false);
+        self.configure(config);
+    }
+
+    /// Resets the server and restores its initial mocks, given its configuration.
+    pub fn restore(&self, config: &MockServerConfig, mocks: MockSet) {
+        self.reset(config);
+        let mut current = self.mocks.write().unwrap();
+        *current = mocks;
+        current.set_clock(config.clock());
+    }
+
+    /// Returns a snapshot of prior interactions.
+    pub fn interactions(&self) -> Interactions {
+        Interactions {
+            hits: self
+                .mocks()
+                .iter()
+                .map(|mock| (mock.id, mock.match_count()))
+                .collect(),
+            counters: self.counters.read().unwrap().clone(),
+            now: Some(self.now()),
+        }
+    }
+
+    /// Returns the response of a mock matched by the request,
+    /// choosing its delay, issuing its session and scheduling its webhooks.
+    ///
+    /// The nonce counts of the `Digest` credentials of the request are recorded.
+    pub fn response(&self, mock: Mock, request: &Request) -> Response {
+        let now = self.now();
+        #[cfg(feature = "digest")]
+        for digest in matchers::digests(&mock.matchers) {
+            digest.record(request, now);
+        }
+        // Seed a generator from the server generator, so that it is not locked
+        // while the response is rendered, e.g. if a template or script panics
+        let mut rng = {
+            let mut rng = self.rng.lock().unwrap();
+            SmallRng::from_rng(rng.get_or_insert_with(SmallRng::from_os_rng))
+        };
+        let rng = &mut rng;
+        let accept_language = request.headers.get("accept-language");
+        let response = mock
+            .into_response_with(rng)
+            .into_language(accept_language.map(|value| value.as_str()));
+        let delay = response.delay.map(|delay| delay.sample(rng));
+        let mut response = response.render(request, now, rng);
+        response.delay = delay.map(Delay::fixed);
+        if let Some(cache) = response.cache.clone() {
+            cache.apply(&mut response, request, now);
+        }
+        #[cfg(feature = "digest")]
+        if let Some(digest) = response.digest.clone() {
+            digest.challenge(&mut response, request, now);
+        }
+        if response.start_session {
+            let id = format!("{:032x}", rng.random::<u128>());
+            self.session_mode
+                .read()
+                .unwrap()
+                .issue(&id, &mut response.headers);
+            self.sessions.write().unwrap().insert(id);
+        }
+        #[cfg(feature = "client")]
+        if !response.webhooks.is_empty() {
+            let mut webhooks = self.webhooks.lock().unwrap();
+            webhooks.retain(|webhook| !webhook.is_finished());
+            for webhook in std::mem::take(&mut response.webhooks) {
+                webhooks.push(webhook.schedule(now));
+            }
+        }
+        response
+    }
+
+    /// Matches a request to a mock, given the prior interactions,
+    /// chooses its response variant and applies the mock counter updates.
+    ///
+    /// In a degraded mode, all requests are matched to the mock of the mode.
+    pub fn match_request(&self, request: &mut Request) -> Option<Mock> {
+        self.match_request_if(request, || true)
+    }
+
+    /// Matches a request to a mock like [`MockServerState::match_request`],
+    /// recording the match only if `commit` returns true once a mock is found.
+    pub fn match_request_if(
+        &self,
+        request: &mut Request,
+        commit: impl FnOnce() -> bool,
+    ) -> Option<Mock> {
+        if let Some(mode) = self.degraded.read().unwrap().as_ref() {
+            return Some(mode.mock().clone());
+        }
+        request.interactions = self.interactions();
+        // Matchers are evaluated against a snapshot of the mocks, so that
+        // a matcher that never returns does not hold the lock of the mocks
+        let snapshot = self.mocks().clone();
+        let mut mock = snapshot
+            .iter()
+            .filter(|mock| mock.would_match(request))
+            .find_map(|mock| self.mocks().record_match(&mock.id))?;
+        if !commit() {
+            self.mocks().withdraw_match(&mock.id);
+            return None;
+        }
+        if !mock.response().variants().is_empty() || !mock.response().sequence().is_empty() {
+            let mut rng = self.rng.lock().unwrap();
+            mock.choose_variant(rng.get_or_insert_with(SmallRng::from_os_rng));
+        }
+        let mut counters = self.counters.write().unwrap();
+        for (name, delta) in mock.response().counters() {
+            *counters.entry(name.clone()).or_default() += delta;
+        }
+        drop(counters);
+        if mock.remaining_matches() == Some(0) {
+            let hooks = self.limit_hooks.read().unwrap().clone();
+            for (_, hook) in hooks.iter().filter(|(id, _)| *id == mock.id) {
+                hook.call(&mock);
+            }
+        }
+        Some(mock)
+    }
+
+    /// Verifies expectations against the journal.
+    pub fn verify(&self) -> Result<(), VerificationError> {
+        let journal = self.journal();
+        let mut failures = self.failures.read().unwrap().clone();
+        failures.extend(
+            self.sequences
+                .read()
+                .unwrap()
+                .iter()
+                .filter_map(|sequence| sequence.verify(&journal).err()),
+        );
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(VerificationError { failures })
+        }
+    }
+
+    /// Records a verification failure.
+    pub fn add_failure(&self, failure: impl Into<String>) {
+        self.failures.write().unwrap().push(failure.into());
+    }
+
+    /// Records a request rejected with a protocol error in the journal.
+    pub fn record_protocol_error(&self, request: Request, error: impl Into<String>) {
+        let entry = JournalEntry {
+            request,
+            mock_id: None,
+            timestamp: self.now(),
+            labels: BTreeMap::new(),
+            path_params: BTreeMap::new(),
+            variant: None,
+            protocol_error: Some(error.into()),
+            cancelled: None,
+        };
+        let hooks = self.protocol_error_hooks.read().unwrap().clone();
+        for hook in hooks {
+            hook.call(&entry);
+        }
+        self.push(entry);
+    }
+
+    /// Records a request in the journal.
+    pub fn record(&self, request: Request, mock: Option<&Mock>) {
+        let path_params = mock
+            .map(|mock| mock.path_params(&request))
+            .unwrap_or_default();
+        let entry = JournalEntry {
+            request,
+            mock_id: mock.map(|mock| mock.id),
+            timestamp: self.now(),
+            labels: mock.map(|mock| mock.labels.clone()).unwrap_or_default(),
+            path_params,
+            variant: mock.and_then(|mock| mock.variant),
+            protocol_error: None,
+            cancelled: None,
+        };
+        self.push(entry);
+    }
+
+    /// Records the cancellation of a request by the client, `after` receiving
+    /// the request head.
+    ///
+    /// The entry of a request matched to a mock is marked as cancelled; a
+    /// request cancelled before it was matched is recorded as cancelled.
+    pub fn record_cancellation(&self, request: Request, mock_id: Option<Uuid>, after: Duration) {
+        let entry = match mock_id {
+            Some(mock_id) => self
+                .journal
+                .write()
+                .unwrap()
+                .cancel(mock_id, &request, after),
+            None => {
+                let entry = JournalEntry {
+                    request,
+                    mock_id: None,
+                    timestamp: self.now(),
+                    labels: BTreeMap::new(),
+                    path_params: BTreeMap::new(),
+                    variant: None,
+                    protocol_error: None,
+                    cancelled: Some(after),
+                };
+                self.push(entry.clone());
+                Some(entry)
+            }
+        };
+        let Some(entry) = entry else {
+            return;
+        };
+        let hooks = self.cancellation_hooks.read().unwrap().clone();
+        for hook in hooks {
+            hook.call(&entry);
+        }
+        self.cancellations.send_modify(|count| *count += 1);
+    }
+
+    fn push(&self, entry: JournalEntry) {
+        let hooks = self.request_hooks.read().unwrap().clone();
+        for hook in hooks {
+            hook.call(&entry);
+        }
+        self.journal.write().unwrap().push(entry);
+    }
+
+    /// Returns the current time of the server clock.
+    pub fn now(&self) -> SystemTime {
+        self.clock
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|clock| clock.now())
+            .unwrap_or_else(SystemTime::now)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        // Signal the server to shut down, draining connections in the background
+        self.state.shutdown.send_replace(true);
+        if self.config.unused_mocks() == UnusedMocks::Ignore {
+            return;
+        }
+        let unused = self.unused_mocks();
+        if unused.is_empty() {
+            return;
+        }
+        let ids = unused
+            .iter()
+            .map(|mock| mock.id.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        match self.config.unused_mocks() {
+            UnusedMocks::Warn => {
+                warn!(server = self.name, "{} unused mocks: {ids}", unused.len())
+            }
+            UnusedMocks::Panic if !std::thread::panicking() => {
+                panic!("{}: {} unused mocks: {ids}", self.name, unused.len())
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A callback invoked by the server.
+pub struct Hook<T: ?Sized>(Arc<dyn Fn(&T) + Send + Sync>);
+
+impl<T: ?Sized> Hook<T> {
+    pub fn new(f: impl Fn(&T) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    pub fn call(&self, value: &T) {
+        (self.0)(value)
+    }
+}
+
+impl<T: ?Sized> Clone for Hook<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: ?Sized> std::fmt::Debug for Hook<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Hook")
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
-enum ServerKind {
+pub(crate) enum ServerKind {
     Http,
     Grpc,
 }
@@ -238,13 +1191,189 @@ impl std::fmt::Display for ServerKind {
     }
 }
 
+/// Returns a handle to the current tokio runtime, or to a shared background runtime.
+fn runtime() -> Handle {
+    Handle::try_current().unwrap_or_else(|_| background_runtime())
+}
+
+/// Returns the shared background runtime.
+fn background_runtime() -> Handle {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME
+        .get_or_init(|| {
+            runtime::Builder::new_multi_thread()
+                .thread_name("mocktail")
+                .enable_all()
+                .build()
+                .expect("failed to build runtime")
+        })
+        .handle()
+        .clone()
+}
+
+/// Binds to a port and spawns the server, waiting up to `timeout`
+/// for it to become ready.
+async fn bind_and_serve(
+    name: &'static str,
+    kind: ServerKind,
+    state: Arc<MockServerState>,
+    config: Arc<MockServerConfig>,
+    timeout: Duration,
+) -> Result<(SocketAddr, Vec<ListenerAddr>, JoinHandle<()>), Error> {
+    let listener = bind_tcp(&config).await?;
+    let addr = listener.local_addr()?;
+    let mut listeners = vec![BoundListener::tcp(listener, &config, kind)];
+    let mut addrs = Vec::new();
+    for listener in config.listeners() {
+        let (listener, addr) = listener.bind(kind, &config).await?;
+        listeners.push(listener);
+        addrs.push(addr);
+    }
+    info!("started {name} [{kind}] server on {addr}");
+
+    let (ready_tx, ready_rx) = oneshot::channel();
+    let task = match kind {
+        ServerKind::Http => {
+            let service = HttpMockService::new(state.clone(), config.clone());
+            tokio::spawn(run_server(listeners, service, state, config, ready_tx))
+        }
+        ServerKind::Grpc => {
+            let service = GrpcMockService::new(state.clone(), config.clone());
+            tokio::spawn(run_server(listeners, service, state, config, ready_tx))
+        }
+    };
+    // Wait for server to become ready
+    match tokio::time::timeout(timeout, ready_rx).await {
+        Ok(Ok(())) => Ok((addr, addrs, task)),
+        Ok(Err(_)) => Err(Error::ServerError("server failed to start".into())),
+        Err(_) => Err(Error::ServerError("server failed to become ready".into())),
+    }
+}
+
+/// Creates a connection builder for the configuration.
+///
+/// gRPC servers also accept HTTP/1 connections, to answer HTTP/1 requests
+/// with a protocol error rather than failing the connection.
+pub(crate) fn connection_builder(config: &MockServerConfig) -> conn::auto::Builder<TokioExecutor> {
+    let mut builder = conn::auto::Builder::new(TokioExecutor::new());
+    if let Some(timeout) = config.header_read_timeout() {
+        builder
+            .http1()
+            .timer(TokioTimer::new())
+            .header_read_timeout(timeout);
+    }
+    if let Some(size) = config.max_header_size() {
+        builder.http1().max_buf_size(size);
+        builder
+            .http2()
+            .max_header_list_size(u32::try_from(size).unwrap_or(u32::MAX));
+    }
+    let http2 = config.http2();
+    if let Some(max) = http2.max_concurrent_streams {
+        builder.http2().max_concurrent_streams(max);
+    }
+    if let Some(size) = http2.initial_stream_window_size {
+        builder.http2().initial_stream_window_size(size);
+    }
+    if let Some(size) = http2.initial_connection_window_size {
+        builder.http2().initial_connection_window_size(size);
+    }
+    if let Some(size) = http2.max_frame_size {
+        builder.http2().max_frame_size(size);
+    }
+    if let Some(interval) = http2.keep_alive_interval {
+        builder
+            .http2()
+            .timer(TokioTimer::new())
+            .keep_alive_interval(interval);
+    }
+    if let Some(timeout) = http2.keep_alive_timeout {
+        builder
+            .http2()
+            .timer(TokioTimer::new())
+            .keep_alive_timeout(timeout);
+    }
+    builder
+}
+
+/// A per-connection service attaching the connection details to requests,
+/// and notifying when the connection has served its limit of streams.
+#[derive(Clone)]
+pub(crate) struct ConnectionService<S> {
+    inner: S,
+    info: ConnectionInfo,
+    writer: InformationalWriter,
+    limit: Option<usize>,
+    count: Arc<AtomicUsize>,
+    notify: Arc<Notify>,
+}
+
+impl<S> ConnectionService<S> {
+    pub(crate) fn new(
+        inner: S,
+        info: ConnectionInfo,
+        config: &MockServerConfig,
+        notify: Arc<Notify>,
+        writer: InformationalWriter,
+    ) -> Self {
+        Self {
+            inner,
+            info,
+            writer,
+            limit: config.goaway_after(),
+            count: Arc::default(),
+            notify,
+        }
+    }
+}
+
+impl<S, B> Service<http::Request<B>> for ConnectionService<S>
+where
+    S: Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn call(&self, mut req: http::Request<B>) -> Self::Future {
+        if let Some(limit) = self.limit {
+            if self.count.fetch_add(1, Ordering::Relaxed) + 1 == limit {
+                self.notify.notify_one();
+            }
+        }
+        req.extensions_mut().insert(self.info.clone());
+        req.extensions_mut().insert(self.writer.clone());
+        self.inner.call(req)
+    }
+}
+
+/// A connection stream, plain or TLS.
+pub(crate) trait Io: AsyncRead + AsyncWrite + Send + Unpin + 'static {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin + 'static> Io for T {}
+
+/// Accepts a connection, performing the TLS handshake and
+/// dropping `PING` acknowledgements if configured.
+async fn accept(
+    connection: Connection,
+    number: usize,
+    config: &MockServerConfig,
+) -> Option<(Box<dyn Io>, ConnectionInfo)> {
+    let (io, info) = connection.handshake(number).await?;
+    if config.http2().ignore_pings {
+        return Some((Box::new(PingFilter::new(io, false)), info));
+    }
+    Some((io, info))
+}
+
 /// Runs the main server loop to accept and serve connections.
 async fn run_server<S, B>(
-    listener: TcpListener,
-    server_kind: ServerKind,
+    listeners: Vec<BoundListener>,
     service: S,
-) -> Result<(), Error>
-where
+    state: Arc<MockServerState>,
+    config: Arc<MockServerConfig>,
+    ready_tx: oneshot::Sender<()>,
+) where
     S: Service<http::Request<Incoming>, Response = http::Response<B>> + Clone + Send + 'static,
     S::Future: Send + 'static,
     S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
@@ -252,62 +1381,93 @@ where
     B::Data: Send + 'static,
     B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
 {
-    // Spawn task to accept new connections
-    tokio::spawn(async move {
-        loop {
-            let (stream, addr) = match listener.accept().await {
-                Ok(conn) => conn,
-                Err(err) => {
+    let mut connections = JoinSet::new();
+    let mut shutdown = state.shutdown.subscribe();
+    let mut incoming = stream::select_all(listeners.into_iter().map(BoundListener::incoming));
+    // Signal that the server is ready, the listener is already bound
+    // so connections are queued until accepted below
+    let _ = ready_tx.send(());
+    loop {
+        let (stream, addr) = tokio::select! {
+            biased;
+            _ = shutdown.wait_for(|shutdown| *shutdown) => break,
+            result = incoming.next() => match result {
+                Some(Ok(conn)) => conn,
+                Some(Err(err)) => {
                     error!("connection accept error: {err}");
                     continue;
                 }
+                None => break,
+            },
+            // Reap completed connection tasks
+            Some(_) = connections.join_next(), if !connections.is_empty() => continue,
+        };
+        if state.is_paused() {
+            debug!("server paused, closing connection: {addr}");
+            drop(stream);
+            continue;
+        }
+        debug!("connection accepted: {addr}");
+        let number = state.next_connection();
+        let service = service.clone();
+        let mut paused = state.paused.subscribe();
+        let mut shutdown = state.shutdown.subscribe();
+        let config = config.clone();
+        let goaway = Arc::new(Notify::new());
+        // Spawn task to serve connection
+        connections.spawn(async move {
+            let Some((stream, info)) = accept(stream, number, &config).await else {
+                return;
             };
-            debug!("connection accepted: {addr}");
-            let io = TokioIo::new(stream);
-            let service = service.clone();
-            // Spawn task to serve connection
-            tokio::spawn(async move {
-                let builder = match server_kind {
-                    ServerKind::Http => conn::auto::Builder::new(TokioExecutor::new()),
-                    ServerKind::Grpc => conn::auto::Builder::new(TokioExecutor::new()).http2_only(),
-                };
-                if let Err(err) = builder.serve_connection(io, service).await {
-                    debug!("connection error: {err}");
+            let io = SharedIo::new(stream);
+            let writer = io.writer();
+            let io = TokioIo::new(io);
+            let service = ConnectionService::new(service, info, &config, goaway.clone(), writer);
+            let builder = connection_builder(&config);
+            let conn = builder.serve_connection(io, service);
+            tokio::pin!(conn);
+            tokio::select! {
+                biased;
+                _ = async { paused.wait_for(|paused| *paused).await.is_ok() } => {
+                    debug!("server paused, closing connection: {addr}");
                 }
-                debug!("connection dropped: {addr}");
-            });
-        }
-    });
-
-    Ok(())
-}
-
-#[derive(Debug)]
-pub struct MockServerConfig {
-    pub listen_addr: IpAddr,
-    pub port_range_start: u16,
-    pub port_range_end: u16,
-    pub bind_max_retries: usize,
-    pub ready_connect_max_retries: usize,
-    pub ready_connect_timeout: Duration,
-}
-
-impl MockServerConfig {
-    pub fn new() -> Self {
-        Self::default()
+                _ = async { shutdown.wait_for(|shutdown| *shutdown).await.is_ok() } => {
+                    debug!("server shutting down, draining connection: {addr}");
+                    conn.as_mut().graceful_shutdown();
+                    if let Err(err) = conn.await {
+                        debug!("connection error: {err}");
+                    }
+                }
+                _ = goaway.notified() => {
+                    debug!("stream limit reached, shutting down connection: {addr}");
+                    conn.as_mut().graceful_shutdown();
+                    if let Err(err) = conn.await {
+                        debug!("connection error: {err}");
+                    }
+                }
+                result = conn.as_mut() => {
+                    if let Err(err) = result {
+                        debug!("connection error: {err}");
+                    }
+                }
+            }
+            debug!("connection dropped: {addr}");
+        });
     }
-}
 
-impl Default for MockServerConfig {
-    fn default() -> Self {
-        Self {
-            listen_addr: IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
-            port_range_start: 10000,
-            port_range_end: 30000,
-            bind_max_retries: 10,
-            ready_connect_max_retries: 30,
-            ready_connect_timeout: Duration::from_millis(10),
-        }
+    // Stop accepting connections and drain in-flight requests
+    drop(incoming);
+    let grace_period = config.shutdown_grace_period();
+    let drained = tokio::time::timeout(grace_period, async {
+        while connections.join_next().await.is_some() {}
+    })
+    .await;
+    if drained.is_err() {
+        debug!(
+            "shutdown grace period elapsed, aborting {} connections",
+            connections.len()
+        );
+        connections.shutdown().await;
     }
 }
 
@@ -0,0 +1,961 @@
+//! Mock server configuration
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    ops::{Range, RangeInclusive},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use super::{Listener, NamespaceMode, SessionMode};
+#[cfg(feature = "compression")]
+use crate::compression::Compression;
+#[cfg(feature = "tls")]
+use crate::tls::TlsConfig;
+use crate::{
+    clock::{Clock, SystemClock},
+    request::PathNormalization,
+    status::{Code, StatusCode},
+};
+
+/// The default idempotency key header name.
+pub const DEFAULT_IDEMPOTENCY_HEADER: &str = "idempotency-key";
+
+/// The prefix of configuration environment variables.
+const ENV_PREFIX: &str = "MOCKTAIL_";
+
+/// The minimum max header size supported by HTTP/1.
+const MIN_MAX_HEADER_SIZE: usize = 8192;
+
+/// The range of HTTP/2 max frame sizes allowed by RFC 9113.
+const HTTP2_FRAME_SIZE_RANGE: RangeInclusive<u32> = 16_384..=16_777_215;
+
+/// The maximum HTTP/2 flow-control window size allowed by RFC 9113.
+const HTTP2_MAX_WINDOW_SIZE: u32 = (1 << 31) - 1;
+
+/// The maximum compression level.
+#[cfg(feature = "compression")]
+const MAX_COMPRESSION_LEVEL: u32 = 9;
+
+/// Mock server configuration.
+///
+/// Use [`MockServerConfig::builder`] to build a validated configuration.
+#[derive(Debug, Clone)]
+pub struct MockServerConfig {
+    listen_addr: IpAddr,
+    port_range: Range<u16>,
+    bind_max_retries: usize,
+    port_strategy: PortStrategy,
+    ready_timeout: Duration,
+    namespace_mode: NamespaceMode,
+    session_mode: SessionMode,
+    header_read_timeout: Option<Duration>,
+    handler_timeout: Option<Duration>,
+    max_header_size: Option<usize>,
+    max_body_size: Option<usize>,
+    path_normalization: PathNormalization,
+    strict: bool,
+    journal_capacity: Option<usize>,
+    journal_file: Option<PathBuf>,
+    idempotency_header: Option<String>,
+    seed: Option<u64>,
+    clock: Arc<dyn Clock>,
+    shutdown_grace_period: Duration,
+    http2: Http2Settings,
+    protocol_errors: ProtocolErrors,
+    goaway_after: Option<usize>,
+    unused_mocks: UnusedMocks,
+    read_stall: Option<ReadStall>,
+    #[cfg(feature = "compression")]
+    compression: Option<Compression>,
+    #[cfg(feature = "tls")]
+    tls: Option<TlsConfig>,
+    listeners: Vec<Listener>,
+}
+
+impl MockServerConfig {
+    /// Creates a default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a configuration builder.
+    pub fn builder() -> MockServerConfigBuilder {
+        MockServerConfigBuilder::default()
+    }
+
+    /// Creates a configuration from `MOCKTAIL_*` environment variables,
+    /// see [`MockServerConfigBuilder::env`].
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::builder().env()?.build()
+    }
+
+    /// Returns the address the server listens on.
+    pub fn listen_addr(&self) -> IpAddr {
+        self.listen_addr
+    }
+
+    /// Returns the range of ports the server may bind to.
+    pub fn port_range(&self) -> Range<u16> {
+        self.port_range.clone()
+    }
+
+    /// Returns the number of times binding to a port is retried.
+    pub fn bind_max_retries(&self) -> usize {
+        self.bind_max_retries
+    }
+
+    /// Returns how ports are chosen from the port range.
+    pub fn port_strategy(&self) -> PortStrategy {
+        self.port_strategy
+    }
+
+    /// Returns how long to wait for the server to become ready.
+    pub fn ready_timeout(&self) -> Duration {
+        self.ready_timeout
+    }
+
+    /// Returns how the namespace of a request is resolved.
+    pub fn namespace_mode(&self) -> &NamespaceMode {
+        &self.namespace_mode
+    }
+
+    /// Returns how sessions are issued and sent back.
+    pub fn session_mode(&self) -> &SessionMode {
+        &self.session_mode
+    }
+
+    /// Returns how long to wait for a client to send HTTP/1 request headers.
+    pub fn header_read_timeout(&self) -> Option<Duration> {
+        self.header_read_timeout
+    }
+
+    /// Returns how long matching and responders may take for a request.
+    pub fn handler_timeout(&self) -> Option<Duration> {
+        self.handler_timeout
+    }
+
+    /// Returns the maximum size of request headers in bytes.
+    pub fn max_header_size(&self) -> Option<usize> {
+        self.max_header_size
+    }
+
+    /// Returns the maximum size of request bodies in bytes.
+    pub fn max_body_size(&self) -> Option<usize> {
+        self.max_body_size
+    }
+
+    /// Returns the path normalization options applied before matching.
+    pub fn path_normalization(&self) -> PathNormalization {
+        self.path_normalization
+    }
+
+    /// Returns true if unmatched requests are reported as verification failures.
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Returns the maximum number of requests kept in the journal.
+    pub fn journal_capacity(&self) -> Option<usize> {
+        self.journal_capacity
+    }
+
+    /// Returns the file the journal is persisted to.
+    pub fn journal_file(&self) -> Option<&Path> {
+        self.journal_file.as_deref()
+    }
+
+    /// Returns the header used to cache responses by idempotency key, if enabled.
+    pub fn idempotency_header(&self) -> Option<&str> {
+        self.idempotency_header.as_deref()
+    }
+
+    /// Returns the seed of the random number generator used to choose response variants.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Returns the clock.
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    /// Returns how long in-flight requests are given to complete on shutdown.
+    pub fn shutdown_grace_period(&self) -> Duration {
+        self.shutdown_grace_period
+    }
+
+    /// Returns the number of streams served on a connection before it is shut down.
+    pub fn goaway_after(&self) -> Option<usize> {
+        self.goaway_after
+    }
+
+    /// Returns the HTTP/2 settings.
+    pub fn http2(&self) -> Http2Settings {
+        self.http2
+    }
+
+    /// Returns the responses to requests using the wrong protocol.
+    pub fn protocol_errors(&self) -> &ProtocolErrors {
+        &self.protocol_errors
+    }
+
+    /// Returns how mocks never matched are reported when the server is dropped.
+    pub fn unused_mocks(&self) -> UnusedMocks {
+        self.unused_mocks
+    }
+
+    /// Returns how request bodies are read slowly, if configured.
+    pub fn read_stall(&self) -> Option<ReadStall> {
+        self.read_stall
+    }
+
+    /// Returns the response compression settings.
+    #[cfg(feature = "compression")]
+    pub fn compression(&self) -> Option<&Compression> {
+        self.compression.as_ref()
+    }
+
+    /// Returns the TLS configuration.
+    #[cfg(feature = "tls")]
+    pub fn tls(&self) -> Option<&TlsConfig> {
+        self.tls.as_ref()
+    }
+
+    /// Returns the additional listeners.
+    pub fn listeners(&self) -> &[Listener] {
+        &self.listeners
+    }
+}
+
+impl Default for MockServerConfig {
+    fn default() -> Self {
+        Self {
+            listen_addr: IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
+            port_range: 10000..30000,
+            bind_max_retries: 10,
+            port_strategy: PortStrategy::default(),
+            ready_timeout: Duration::from_secs(5),
+            namespace_mode: NamespaceMode::default(),
+            session_mode: SessionMode::default(),
+            header_read_timeout: None,
+            handler_timeout: None,
+            max_header_size: None,
+            max_body_size: None,
+            path_normalization: PathNormalization::default(),
+            strict: false,
+            journal_capacity: None,
+            journal_file: None,
+            idempotency_header: None,
+            seed: None,
+            clock: Arc::new(SystemClock),
+            shutdown_grace_period: Duration::from_secs(5),
+            http2: Http2Settings::default(),
+            protocol_errors: ProtocolErrors::default(),
+            goaway_after: None,
+            unused_mocks: UnusedMocks::default(),
+            read_stall: None,
+            #[cfg(feature = "compression")]
+            compression: None,
+            #[cfg(feature = "tls")]
+            tls: None,
+            listeners: Vec::new(),
+        }
+    }
+}
+
+/// A [`MockServerConfig`] builder.
+#[derive(Debug, Default)]
+pub struct MockServerConfigBuilder {
+    config: MockServerConfig,
+}
+
+impl MockServerConfigBuilder {
+    /// Sets the address the server listens on.
+    pub fn listen_addr(mut self, addr: impl Into<IpAddr>) -> Self {
+        self.config.listen_addr = addr.into();
+        self
+    }
+
+    /// Sets the range of ports the server may bind to, `start` inclusive
+    /// and `end` exclusive.
+    pub fn port_range(mut self, start: u16, end: u16) -> Self {
+        self.config.port_range = start..end;
+        self
+    }
+
+    /// Sets the number of times binding to a port is retried.
+    pub fn bind_max_retries(mut self, retries: usize) -> Self {
+        self.config.bind_max_retries = retries;
+        self
+    }
+
+    /// Sets how ports are chosen from the port range.
+    pub fn port_strategy(mut self, strategy: PortStrategy) -> Self {
+        self.config.port_strategy = strategy;
+        self
+    }
+
+    /// Sets how long to wait for the server to become ready.
+    pub fn ready_timeout(mut self, timeout: Duration) -> Self {
+        self.config.ready_timeout = timeout;
+        self
+    }
+
+    /// Sets how the namespace of a request is resolved.
+    pub fn namespace_mode(mut self, mode: NamespaceMode) -> Self {
+        self.config.namespace_mode = mode;
+        self
+    }
+
+    /// Sets how sessions are issued and sent back.
+    pub fn session_mode(mut self, mode: SessionMode) -> Self {
+        self.config.session_mode = mode;
+        self
+    }
+
+    /// Sets how long to wait for a client to send HTTP/1 request headers.
+    pub fn header_read_timeout(mut self, timeout: Duration) -> Self {
+        self.config.header_read_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets how long matching a request and resolving its responder may take,
+    /// e.g. to fail fast when a custom matcher or delegate deadlocks.
+    ///
+    /// Requests exceeding it are answered with `504 Gateway Timeout` or the
+    /// `DEADLINE_EXCEEDED` gRPC status, and reported as verification failures.
+    /// Matchers and responses are then evaluated on separate threads, left
+    /// running if they never return, without holding up the server or the
+    /// shutdown of the runtime.
+    pub fn handler_timeout(mut self, timeout: Duration) -> Self {
+        self.config.handler_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum size of request headers in bytes.
+    ///
+    /// Requests exceeding it are rejected with `431 Request Header Fields Too Large`.
+    pub fn max_header_size(mut self, size: usize) -> Self {
+        self.config.max_header_size = Some(size);
+        self
+    }
+
+    /// Sets the maximum size of request bodies in bytes.
+    ///
+    /// HTTP requests exceeding it are rejected with `413 Payload Too Large`
+    /// and gRPC requests with `RESOURCE_EXHAUSTED`. When set, the response
+    /// head of a streaming HTTP request is held until a mock is matched, and
+    /// once sent, the rest of a request body exceeding it is not received.
+    pub fn max_body_size(mut self, size: usize) -> Self {
+        self.config.max_body_size = Some(size);
+        self
+    }
+
+    /// Sets the path normalization options applied before matching.
+    pub fn path_normalization(mut self, normalization: PathNormalization) -> Self {
+        self.config.path_normalization = normalization;
+        self
+    }
+
+    /// Enables strict mode, where each request that does not match a mock
+    /// is reported as a failure by [`MockServer::verify`](super::MockServer::verify).
+    pub fn strict(mut self, enabled: bool) -> Self {
+        self.config.strict = enabled;
+        self
+    }
+
+    /// Sets the maximum number of requests kept in the journal.
+    ///
+    /// Once full, the oldest entries are evicted. By default, the journal is unbounded.
+    pub fn journal_capacity(mut self, capacity: usize) -> Self {
+        self.config.journal_capacity = Some(capacity);
+        self
+    }
+
+    /// Persists the journal to a JSON lines file, see [`Journal::persist`](crate::Journal::persist).
+    ///
+    /// Entries in the file are loaded when the server starts, and each request
+    /// is appended to it, e.g. to keep a record of the traffic of a standalone
+    /// server between restarts.
+    pub fn journal_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.journal_file = Some(path.into());
+        self
+    }
+
+    /// Enables caching HTTP responses by the `Idempotency-Key` header.
+    ///
+    /// See [`MockServerConfigBuilder::idempotency_header`].
+    pub fn idempotency(self) -> Self {
+        self.idempotency_header(DEFAULT_IDEMPOTENCY_HEADER)
+    }
+
+    /// Enables caching HTTP responses by an idempotency key sent in the header.
+    ///
+    /// The first request with a key is matched as usual and its response is
+    /// recorded; subsequent requests with the same key are answered with the
+    /// identical response, without matching.
+    pub fn idempotency_header(mut self, name: impl Into<String>) -> Self {
+        self.config.idempotency_header = Some(name.into().to_lowercase());
+        self
+    }
+
+    /// Sets the seed of the random number generator used to choose response variants,
+    /// making the choice deterministic for a given order of requests.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+
+    /// Sets the clock used for journal timestamps and time-based
+    /// response headers, e.g. a [`MockClock`](crate::clock::MockClock).
+    pub fn clock(mut self, clock: impl Clock) -> Self {
+        self.config.clock = Arc::new(clock);
+        self
+    }
+
+    /// Sets how long in-flight requests are given to complete on shutdown,
+    /// before their connections are aborted.
+    pub fn shutdown_grace_period(mut self, period: Duration) -> Self {
+        self.config.shutdown_grace_period = period;
+        self
+    }
+
+    /// Sets the HTTP/2 settings and flow-control windows.
+    pub fn http2(mut self, settings: Http2Settings) -> Self {
+        self.config.http2 = settings;
+        self
+    }
+
+    /// Sets the responses to requests using the wrong protocol for the server.
+    pub fn protocol_errors(mut self, errors: ProtocolErrors) -> Self {
+        self.config.protocol_errors = errors;
+        self
+    }
+
+    /// Gracefully shuts down each connection after `n` streams, sending an
+    /// HTTP/2 `GOAWAY` frame, or closing HTTP/1 connections after `n` requests.
+    ///
+    /// In-flight streams are completed; clients must open a new connection
+    /// for subsequent requests.
+    pub fn goaway_after(mut self, n: usize) -> Self {
+        self.config.goaway_after = Some(n);
+        self
+    }
+
+    /// Sets how mocks never matched are reported when the server is dropped.
+    pub fn unused_mocks(mut self, policy: UnusedMocks) -> Self {
+        self.config.unused_mocks = policy;
+        self
+    }
+
+    /// Reads request bodies slowly, or stops reading them, after accepting
+    /// the request headers, to test client write timeouts and backpressure.
+    pub fn read_stall(mut self, stall: ReadStall) -> Self {
+        self.config.read_stall = Some(stall);
+        self
+    }
+
+    /// Compresses responses for clients accepting an enabled encoding,
+    /// unless overridden by a mock.
+    #[cfg(feature = "compression")]
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.config.compression = Some(compression);
+        self
+    }
+
+    /// Serves connections over TLS, e.g. with [`TlsConfig::generate`].
+    #[cfg(feature = "tls")]
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.config.tls = Some(tls);
+        self
+    }
+
+    /// Adds a listener, e.g. to serve `https` and `http` on different ports.
+    pub fn listener(mut self, listener: Listener) -> Self {
+        self.config.listeners.push(listener);
+        self
+    }
+
+    /// Applies the `MOCKTAIL_*` environment variables that are set, e.g. when the
+    /// server is embedded in a container and configured externally:
+    ///
+    /// - `MOCKTAIL_LISTEN_ADDR`: the address to listen on, e.g. `127.0.0.1`
+    /// - `MOCKTAIL_PORT`: a fixed port, e.g. `8080`
+    /// - `MOCKTAIL_PORT_RANGE`: a range of ports, e.g. `8000-9000` (end exclusive)
+    /// - `MOCKTAIL_READY_TIMEOUT_MS`: the ready timeout in milliseconds
+    /// - `MOCKTAIL_MAX_BODY_SIZE`: the maximum request body size in bytes
+    /// - `MOCKTAIL_STRICT`: `true` or `false`
+    /// - `MOCKTAIL_JOURNAL_CAPACITY`: the maximum number of journal entries
+    /// - `MOCKTAIL_JOURNAL_FILE`: the file the journal is persisted to
+    /// - `MOCKTAIL_SEED`: the random seed
+    /// - `MOCKTAIL_TLS_CERT` and `MOCKTAIL_TLS_KEY`: PEM certificate chain and
+    ///   private key files, with the `tls` feature
+    pub fn env(self) -> Result<Self, ConfigError> {
+        self.vars(|name| std::env::var(name).ok())
+    }
+
+    /// Applies configuration variables, see [`MockServerConfigBuilder::env`].
+    fn vars(mut self, var: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        fn parse<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, ConfigError>
+        where
+            T::Err: std::fmt::Display,
+        {
+            value
+                .trim()
+                .parse()
+                .map_err(|err: T::Err| ConfigError::InvalidEnv {
+                    name: name.into(),
+                    reason: err.to_string(),
+                })
+        }
+        let invalid = |name: &str, reason: &str| ConfigError::InvalidEnv {
+            name: name.into(),
+            reason: reason.into(),
+        };
+        let get = |name: &str| {
+            let name = format!("{ENV_PREFIX}{name}");
+            var(&name).map(|value| (name, value))
+        };
+
+        if let Some((name, value)) = get("LISTEN_ADDR") {
+            self = self.listen_addr(parse::<IpAddr>(&name, &value)?);
+        }
+        if let Some((name, value)) = get("PORT") {
+            let port = parse::<u16>(&name, &value)?;
+            let end = port
+                .checked_add(1)
+                .ok_or_else(|| invalid(&name, "port must be less than 65535"))?;
+            self = self.port_range(port, end);
+        }
+        if let Some((name, value)) = get("PORT_RANGE") {
+            let (start, end) = value
+                .split_once('-')
+                .ok_or_else(|| invalid(&name, "expected a range, e.g. `8000-9000`"))?;
+            self = self.port_range(parse(&name, start)?, parse(&name, end)?);
+        }
+        if let Some((name, value)) = get("READY_TIMEOUT_MS") {
+            self = self.ready_timeout(Duration::from_millis(parse(&name, &value)?));
+        }
+        if let Some((name, value)) = get("MAX_BODY_SIZE") {
+            self = self.max_body_size(parse(&name, &value)?);
+        }
+        if let Some((name, value)) = get("STRICT") {
+            self = self.strict(parse(&name, &value)?);
+        }
+        if let Some((name, value)) = get("JOURNAL_CAPACITY") {
+            self = self.journal_capacity(parse(&name, &value)?);
+        }
+        if let Some((_, value)) = get("JOURNAL_FILE") {
+            self = self.journal_file(value);
+        }
+        if let Some((name, value)) = get("SEED") {
+            self = self.seed(parse(&name, &value)?);
+        }
+        match (get("TLS_CERT"), get("TLS_KEY")) {
+            (None, None) => {}
+            #[cfg(feature = "tls")]
+            (Some((name, cert)), Some((_, key))) => {
+                let tls = TlsConfig::from_pem_files(cert, key)
+                    .map_err(|err| invalid(&name, &err.to_string()))?;
+                self = self.tls(tls);
+            }
+            #[cfg(not(feature = "tls"))]
+            (Some((name, _)), Some(_)) => {
+                return Err(invalid(&name, "the `tls` feature is not enabled"));
+            }
+            (Some((name, _)), None) | (None, Some((name, _))) => {
+                return Err(invalid(
+                    &name,
+                    "MOCKTAIL_TLS_CERT and MOCKTAIL_TLS_KEY must be set together",
+                ));
+            }
+        }
+        Ok(self)
+    }
+
+    /// Validates and builds the configuration.
+    pub fn build(self) -> Result<MockServerConfig, ConfigError> {
+        let config = self.config;
+        if config.port_range.is_empty() {
+            return Err(ConfigError::InvalidPortRange {
+                start: config.port_range.start,
+                end: config.port_range.end,
+            });
+        }
+        if config.bind_max_retries == 0 {
+            return Err(ConfigError::ZeroBindRetries);
+        }
+        if config.ready_timeout.is_zero() {
+            return Err(ConfigError::ZeroReadyTimeout);
+        }
+        if config.header_read_timeout.is_some_and(|v| v.is_zero()) {
+            return Err(ConfigError::ZeroHeaderReadTimeout);
+        }
+        if config.handler_timeout.is_some_and(|v| v.is_zero()) {
+            return Err(ConfigError::ZeroHandlerTimeout);
+        }
+        if let Some(size) = config.max_header_size {
+            if size < MIN_MAX_HEADER_SIZE {
+                return Err(ConfigError::MaxHeaderSizeTooSmall {
+                    size,
+                    min: MIN_MAX_HEADER_SIZE,
+                });
+            }
+        }
+        if config.goaway_after == Some(0) {
+            return Err(ConfigError::ZeroGoawayAfter);
+        }
+        if config.read_stall
+            == Some(ReadStall::Throttle {
+                bytes_per_second: 0,
+            })
+        {
+            return Err(ConfigError::ZeroReadStallRate);
+        }
+        #[cfg(feature = "compression")]
+        if let Some(level) = config
+            .compression
+            .as_ref()
+            .map(|c| c.level)
+            .filter(|level| *level > MAX_COMPRESSION_LEVEL)
+        {
+            return Err(ConfigError::InvalidCompressionLevel(level));
+        }
+        if let Some(size) = config.http2.max_frame_size {
+            if !HTTP2_FRAME_SIZE_RANGE.contains(&size) {
+                return Err(ConfigError::InvalidHttp2FrameSize(size));
+            }
+        }
+        if config.http2.keep_alive_interval == Some(Duration::ZERO) {
+            return Err(ConfigError::ZeroKeepAliveInterval);
+        }
+        for size in [
+            config.http2.initial_stream_window_size,
+            config.http2.initial_connection_window_size,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if size > HTTP2_MAX_WINDOW_SIZE {
+                return Err(ConfigError::InvalidHttp2WindowSize(size));
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// How ports are chosen from the port range when binding.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortStrategy {
+    /// Ports are chosen at random, avoiding collisions between servers
+    /// started concurrently.
+    #[default]
+    Random,
+    /// Ports are tried in order from the start of the range, e.g. on
+    /// machines where only a few ports may be bound.
+    Sequential,
+}
+
+/// How mocks never matched are reported when the server is dropped.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnusedMocks {
+    /// Unused mocks are not reported.
+    #[default]
+    Ignore,
+    /// Unused mocks are logged as a warning.
+    Warn,
+    /// The server panics if there are unused mocks, failing the test,
+    /// unless the thread is already panicking.
+    Panic,
+}
+
+/// How request bodies are read slowly, once the request headers are accepted.
+///
+/// Unread data is left to HTTP/2 flow control and TCP backpressure,
+/// so clients block once their send windows and buffers are full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadStall {
+    /// Stop reading after `after` bytes of the body have been read.
+    Stop { after: usize },
+    /// Read the body at a rate of `bytes_per_second`.
+    Throttle { bytes_per_second: u64 },
+}
+
+/// Responses to requests using the wrong protocol for the server,
+/// with bodies that fail to be received or decoded, or failed by a
+/// matcher or responder that panicked.
+///
+/// Protocol errors are recorded in the journal, see
+/// [`JournalEntry::protocol_error`](crate::JournalEntry::protocol_error).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolErrors {
+    pub(crate) http1_status: StatusCode,
+    pub(crate) grpc_code: Option<Code>,
+    pub(crate) decode_status: StatusCode,
+    pub(crate) decode_code: Code,
+    pub(crate) panic_status: StatusCode,
+    pub(crate) panic_code: Code,
+}
+
+impl ProtocolErrors {
+    /// Creates default responses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the status of responses to HTTP/1 requests sent to a gRPC server,
+    /// `505 HTTP Version Not Supported` by default.
+    pub fn http1_status(mut self, status: StatusCode) -> Self {
+        self.http1_status = status;
+        self
+    }
+
+    /// Answers gRPC requests sent to an HTTP server with a gRPC status, e.g.
+    /// [`Code::Unimplemented`], without matching mocks.
+    ///
+    /// By default, gRPC requests are matched like any other request.
+    pub fn grpc_code(mut self, code: Code) -> Self {
+        self.grpc_code = Some(code);
+        self
+    }
+
+    /// Sets the status of responses to HTTP requests with bodies that fail
+    /// to be received, `400 Bad Request` by default.
+    pub fn decode_status(mut self, status: StatusCode) -> Self {
+        self.decode_status = status;
+        self
+    }
+
+    /// Sets the code of responses to gRPC requests with bodies that fail to be
+    /// received or with malformed message frames, [`Code::Internal`] by default.
+    pub fn decode_code(mut self, code: Code) -> Self {
+        self.decode_code = code;
+        self
+    }
+
+    /// Sets the status of responses to HTTP requests failed by a matcher or
+    /// responder that panicked, `500 Internal Server Error` by default.
+    ///
+    /// Panics are reported as verification failures.
+    pub fn panic_status(mut self, status: StatusCode) -> Self {
+        self.panic_status = status;
+        self
+    }
+
+    /// Sets the code of responses to gRPC requests failed by a matcher or
+    /// responder that panicked, [`Code::Internal`] by default.
+    ///
+    /// Panics are reported as verification failures.
+    pub fn panic_code(mut self, code: Code) -> Self {
+        self.panic_code = code;
+        self
+    }
+}
+
+impl Default for ProtocolErrors {
+    fn default() -> Self {
+        Self {
+            http1_status: StatusCode::HTTP_VERSION_NOT_SUPPORTED,
+            grpc_code: None,
+            decode_status: StatusCode::BAD_REQUEST,
+            decode_code: Code::Internal,
+            panic_status: StatusCode::INTERNAL_SERVER_ERROR,
+            panic_code: Code::Internal,
+        }
+    }
+}
+
+/// HTTP/2 settings and flow-control windows.
+///
+/// Unset values use the hyper defaults.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Http2Settings {
+    pub(crate) max_concurrent_streams: Option<u32>,
+    pub(crate) initial_stream_window_size: Option<u32>,
+    pub(crate) initial_connection_window_size: Option<u32>,
+    pub(crate) max_frame_size: Option<u32>,
+    pub(crate) keep_alive_interval: Option<Duration>,
+    pub(crate) keep_alive_timeout: Option<Duration>,
+    pub(crate) ignore_pings: bool,
+}
+
+impl Http2Settings {
+    /// Creates default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the `SETTINGS_MAX_CONCURRENT_STREAMS` advertised to clients.
+    pub fn max_concurrent_streams(mut self, max: u32) -> Self {
+        self.max_concurrent_streams = Some(max);
+        self
+    }
+
+    /// Sets the `SETTINGS_INITIAL_WINDOW_SIZE` for stream-level flow control.
+    pub fn initial_stream_window_size(mut self, size: u32) -> Self {
+        self.initial_stream_window_size = Some(size);
+        self
+    }
+
+    /// Sets the connection-level flow-control window size.
+    pub fn initial_connection_window_size(mut self, size: u32) -> Self {
+        self.initial_connection_window_size = Some(size);
+        self
+    }
+
+    /// Sets the `SETTINGS_MAX_FRAME_SIZE` advertised to clients.
+    pub fn max_frame_size(mut self, size: u32) -> Self {
+        self.max_frame_size = Some(size);
+        self
+    }
+
+    /// Sends a keepalive `PING` to clients at the interval.
+    pub fn keep_alive_interval(mut self, interval: Duration) -> Self {
+        self.keep_alive_interval = Some(interval);
+        self
+    }
+
+    /// Closes the connection if a keepalive `PING` is not acknowledged
+    /// within the timeout.
+    pub fn keep_alive_timeout(mut self, timeout: Duration) -> Self {
+        self.keep_alive_timeout = Some(timeout);
+        self
+    }
+
+    /// Stops acknowledging `PING` frames from clients, simulating an
+    /// unresponsive server, e.g. to test client keepalive timeouts.
+    pub fn ignore_pings(mut self, ignore: bool) -> Self {
+        self.ignore_pings = ignore;
+        self
+    }
+}
+
+/// Represents errors that can occur while building a [`MockServerConfig`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    #[error("invalid port range: start ({start}) must be less than end ({end})")]
+    InvalidPortRange { start: u16, end: u16 },
+    #[error("bind max retries must be greater than zero")]
+    ZeroBindRetries,
+    #[error("ready timeout must be greater than zero")]
+    ZeroReadyTimeout,
+    #[error("header read timeout must be greater than zero")]
+    ZeroHeaderReadTimeout,
+    #[error("handler timeout must be greater than zero")]
+    ZeroHandlerTimeout,
+    #[error("max header size ({size}) must be at least {min} bytes")]
+    MaxHeaderSizeTooSmall { size: usize, min: usize },
+    #[error("goaway after must be greater than zero")]
+    ZeroGoawayAfter,
+    #[error("http2 max frame size ({0}) must be between 16384 and 16777215 bytes")]
+    InvalidHttp2FrameSize(u32),
+    #[error("http2 window size ({0}) must be at most 2147483647 bytes")]
+    InvalidHttp2WindowSize(u32),
+    #[error("http2 keep alive interval must be greater than zero")]
+    ZeroKeepAliveInterval,
+    #[error("read stall rate must be greater than zero")]
+    ZeroReadStallRate,
+    #[error("compression level ({0}) must be at most 9")]
+    InvalidCompressionLevel(u32),
+    #[error("invalid environment variable {name}: {reason}")]
+    InvalidEnv { name: String, reason: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_validation() {
+        assert!(MockServerConfig::builder().build().is_ok());
+        assert_eq!(
+            MockServerConfig::builder()
+                .port_range(20000, 10000)
+                .build()
+                .unwrap_err(),
+            ConfigError::InvalidPortRange {
+                start: 20000,
+                end: 10000
+            }
+        );
+        assert_eq!(
+            MockServerConfig::builder()
+                .bind_max_retries(0)
+                .build()
+                .unwrap_err(),
+            ConfigError::ZeroBindRetries
+        );
+        assert!(MockServerConfig::builder()
+            .max_header_size(1024)
+            .build()
+            .is_err());
+        assert_eq!(
+            MockServerConfig::builder()
+                .http2(Http2Settings::new().max_frame_size(1024))
+                .build()
+                .unwrap_err(),
+            ConfigError::InvalidHttp2FrameSize(1024)
+        );
+        assert_eq!(
+            MockServerConfig::builder()
+                .read_stall(ReadStall::Throttle {
+                    bytes_per_second: 0
+                })
+                .build()
+                .unwrap_err(),
+            ConfigError::ZeroReadStallRate
+        );
+        #[cfg(feature = "compression")]
+        assert_eq!(
+            MockServerConfig::builder()
+                .compression(Compression::new().level(10))
+                .build()
+                .unwrap_err(),
+            ConfigError::InvalidCompressionLevel(10)
+        );
+    }
+
+    #[test]
+    fn test_vars() {
+        let vars = |vars: &'static [(&str, &str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(k, _)| *k == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        let config = MockServerConfig::builder()
+            .vars(vars(&[
+                ("MOCKTAIL_LISTEN_ADDR", "0.0.0.0"),
+                ("MOCKTAIL_PORT", "8080"),
+                ("MOCKTAIL_STRICT", "true"),
+                ("MOCKTAIL_JOURNAL_CAPACITY", "100"),
+                ("MOCKTAIL_SEED", "42"),
+            ]))
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(config.listen_addr(), IpAddr::from([0, 0, 0, 0]));
+        assert_eq!(config.port_range(), 8080..8081);
+        assert!(config.strict());
+        assert_eq!(config.journal_capacity(), Some(100));
+        assert_eq!(config.seed(), Some(42));
+
+        let config = MockServerConfig::builder()
+            .vars(vars(&[("MOCKTAIL_PORT_RANGE", "9000-9100")]))
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(config.port_range(), 9000..9100);
+
+        let err = MockServerConfig::builder()
+            .vars(vars(&[("MOCKTAIL_PORT", "http")]))
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidEnv { name, .. } if name == "MOCKTAIL_PORT"));
+        let err = MockServerConfig::builder()
+            .vars(vars(&[("MOCKTAIL_TLS_CERT", "cert.pem")]))
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidEnv { name, .. } if name == "MOCKTAIL_TLS_CERT"));
+    }
+}
@@ -0,0 +1,48 @@
+//! Degraded modes
+use std::time::Duration;
+
+use crate::{mock::Mock, mock_builder::Then};
+
+/// The label identifying journal entries served in a degraded mode.
+pub const DEGRADED_MODE_LABEL: &str = "degraded-mode";
+
+/// A named mode overriding all mocks of a server with a response,
+/// e.g. to test client behavior during an outage.
+///
+/// Set with [`MockServer::set_degraded`](crate::server::MockServer::set_degraded).
+#[derive(Debug, Clone)]
+pub struct DegradedMode {
+    name: String,
+    mock: Mock,
+}
+
+impl DegradedMode {
+    /// Creates a mode answering all requests with the response built by `f`.
+    pub fn new<F>(name: impl Into<String>, f: F) -> Self
+    where
+        F: FnOnce(Then),
+    {
+        let name = name.into();
+        let mock = Mock::new(|_, then| f(then)).with_label(DEGRADED_MODE_LABEL, name.clone());
+        Self { name, mock }
+    }
+
+    /// Creates a mode answering all requests with `503 Service Unavailable`
+    /// and a `Retry-After` header, or `UNAVAILABLE` for gRPC.
+    pub fn unavailable(name: impl Into<String>, retry_after: Duration) -> Self {
+        Self::new(name, |then| {
+            then.grpc_unavailable("service unavailable")
+                .retry_after(retry_after);
+        })
+    }
+
+    /// Returns the mode name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the mock answering requests in this mode.
+    pub(crate) fn mock(&self) -> &Mock {
+        &self.mock
+    }
+}
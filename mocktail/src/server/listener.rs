@@ -0,0 +1,272 @@
+//! Listeners
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use futures::{stream, stream::BoxStream, StreamExt};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+#[cfg(feature = "tls")]
+use tokio_rustls::TlsAcceptor;
+use tracing::debug;
+use url::Url;
+
+use super::{Io, MockServerConfig, PortStrategy, ServerKind};
+#[cfg(feature = "tls")]
+use crate::tls::TlsConfig;
+use crate::{request::ConnectionInfo, Error};
+
+/// An additional listener of a server.
+///
+/// Connections accepted by all listeners of a server are served by the same
+/// mocks and recorded in the same journal, e.g. for clients switching between
+/// `http` and `https` mid-flow.
+#[derive(Debug, Clone)]
+pub struct Listener {
+    kind: ListenerKind,
+    #[cfg(feature = "tls")]
+    tls: Option<TlsConfig>,
+}
+
+#[derive(Debug, Clone)]
+enum ListenerKind {
+    Tcp,
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl Listener {
+    /// Creates a plain TCP listener, bound to a port of the configured port range.
+    pub fn tcp() -> Self {
+        Self {
+            kind: ListenerKind::Tcp,
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+
+    /// Creates a Unix domain socket listener, bound to `path`.
+    #[cfg(unix)]
+    pub fn unix(path: impl Into<PathBuf>) -> Self {
+        Self {
+            kind: ListenerKind::Unix(path.into()),
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+
+    /// Serves connections of the listener over TLS.
+    #[cfg(feature = "tls")]
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Binds the listener.
+    pub(crate) async fn bind(
+        &self,
+        kind: ServerKind,
+        config: &MockServerConfig,
+    ) -> Result<(BoundListener, ListenerAddr), Error> {
+        #[cfg(feature = "tls")]
+        let acceptor = self.tls.as_ref().map(|tls| tls.acceptor(kind));
+        #[cfg(not(feature = "tls"))]
+        let _ = kind;
+        let (socket, addr) = match &self.kind {
+            ListenerKind::Tcp => {
+                let listener = bind_tcp(config).await?;
+                let addr = listener.local_addr()?;
+                #[cfg(feature = "tls")]
+                let scheme = if acceptor.is_some() { "https" } else { "http" };
+                #[cfg(not(feature = "tls"))]
+                let scheme = "http";
+                let url = Url::parse(&format!("{scheme}://{addr}")).unwrap();
+                (Socket::Tcp(listener), ListenerAddr::Tcp(addr, url))
+            }
+            #[cfg(unix)]
+            ListenerKind::Unix(path) => {
+                let listener = UnixListener::bind(path)?;
+                (
+                    Socket::Unix(listener, path.clone()),
+                    ListenerAddr::Unix(path.clone()),
+                )
+            }
+        };
+        Ok((
+            BoundListener {
+                socket,
+                #[cfg(feature = "tls")]
+                acceptor,
+            },
+            addr,
+        ))
+    }
+}
+
+/// The address of a bound listener.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenerAddr {
+    /// A TCP listener, with its base url.
+    Tcp(SocketAddr, Url),
+    /// A Unix domain socket listener.
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl ListenerAddr {
+    /// Returns the url of a path, for TCP listeners.
+    pub fn url(&self, path: &str) -> Option<Url> {
+        match self {
+            ListenerAddr::Tcp(_, url) => Some(url.join(path).unwrap()),
+            #[cfg(unix)]
+            ListenerAddr::Unix(_) => None,
+        }
+    }
+
+    /// Returns the socket path, for Unix domain socket listeners.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            ListenerAddr::Tcp(..) => None,
+            #[cfg(unix)]
+            ListenerAddr::Unix(path) => Some(path),
+        }
+    }
+}
+
+/// Binds a TCP listener to a port of the configured port range.
+pub(crate) async fn bind_tcp(config: &MockServerConfig) -> Result<TcpListener, Error> {
+    let mut rng = SmallRng::from_os_rng();
+    let range = config.port_range();
+    let mut attempts = Vec::new();
+
+    loop {
+        let port = match config.port_strategy() {
+            PortStrategy::Random => rng.random_range(range.clone()),
+            PortStrategy::Sequential => match range.clone().nth(attempts.len()) {
+                Some(port) => port,
+                None => break,
+            },
+        };
+        let addr = SocketAddr::from((config.listen_addr(), port));
+        match TcpListener::bind(&addr).await {
+            Ok(listener) => return Ok(listener),
+            Err(err) => {
+                debug!(%addr, "failed to bind: {err}");
+                attempts.push(format!("{port} ({err})"));
+            }
+        }
+        if attempts.len() > config.bind_max_retries() {
+            break;
+        }
+    }
+    Err(Error::ServerError(format!(
+        "server failed to bind to {} in ports {}..{} after {} attempts: {}",
+        config.listen_addr(),
+        range.start,
+        range.end,
+        attempts.len(),
+        attempts.join(", ")
+    )))
+}
+
+/// A bound listener accepting connections.
+pub(crate) struct BoundListener {
+    socket: Socket,
+    #[cfg(feature = "tls")]
+    acceptor: Option<TlsAcceptor>,
+}
+
+enum Socket {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+impl BoundListener {
+    /// Creates a listener from a bound TCP listener.
+    pub(crate) fn tcp(listener: TcpListener, config: &MockServerConfig, kind: ServerKind) -> Self {
+        #[cfg(not(feature = "tls"))]
+        let _ = (config, kind);
+        Self {
+            socket: Socket::Tcp(listener),
+            #[cfg(feature = "tls")]
+            acceptor: config.tls().map(|tls| tls.acceptor(kind)),
+        }
+    }
+
+    /// Accepts a connection, returning it with the peer address.
+    async fn accept(&self) -> std::io::Result<(Connection, String)> {
+        let (io, peer, peer_addr): (Box<dyn Io>, String, _) = match &self.socket {
+            Socket::Tcp(listener) => {
+                let (stream, addr) = listener.accept().await?;
+                (Box::new(stream), addr.to_string(), Some(addr))
+            }
+            #[cfg(unix)]
+            Socket::Unix(listener, path) => {
+                let (stream, _) = listener.accept().await?;
+                (Box::new(stream), path.display().to_string(), None)
+            }
+        };
+        let connection = Connection {
+            io,
+            peer_addr,
+            #[cfg(feature = "tls")]
+            acceptor: self.acceptor.clone(),
+        };
+        Ok((connection, peer))
+    }
+
+    /// Returns a stream of accepted connections.
+    pub(crate) fn incoming(self) -> BoxStream<'static, std::io::Result<(Connection, String)>> {
+        stream::unfold(self, |listener| async move {
+            let result = listener.accept().await;
+            Some((result, listener))
+        })
+        .boxed()
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Socket::Unix(_, path) = self {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// An accepted connection, before the TLS handshake.
+pub(crate) struct Connection {
+    io: Box<dyn Io>,
+    peer_addr: Option<SocketAddr>,
+    #[cfg(feature = "tls")]
+    acceptor: Option<TlsAcceptor>,
+}
+
+impl Connection {
+    /// Performs the TLS handshake if the listener serves TLS, returning
+    /// the stream with the details of the connection.
+    pub(crate) async fn handshake(self, number: usize) -> Option<(Box<dyn Io>, ConnectionInfo)> {
+        let mut info = ConnectionInfo::new(number);
+        info.peer_addr = self.peer_addr;
+        #[cfg(feature = "tls")]
+        if let Some(acceptor) = self.acceptor {
+            return match acceptor.accept(self.io).await {
+                Ok(stream) => {
+                    let (alpn, tls) = crate::tls::session_info(stream.get_ref().1);
+                    info.alpn = alpn;
+                    info.tls = Some(tls);
+                    Some((Box::new(stream), info))
+                }
+                Err(err) => {
+                    debug!("tls handshake error: {err}");
+                    None
+                }
+            };
+        }
+        Some((self.io, info))
+    }
+}
@@ -0,0 +1,175 @@
+//! Namespaces
+use std::{collections::HashSet, sync::Arc};
+
+use url::Url;
+use uuid::Uuid;
+
+use super::MockServerState;
+use crate::{
+    journal::Journal,
+    mock::Mock,
+    mock_builder::{Then, When},
+    request::Request,
+};
+
+/// The default namespace header name.
+pub const DEFAULT_NAMESPACE_HEADER: &str = "x-mocktail-namespace";
+
+/// Determines how the namespace of a request is resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NamespaceMode {
+    /// The namespace token is sent in a request header.
+    Header(String),
+    /// The namespace token is sent as the first path segment,
+    /// e.g. `/{token}/hello`.
+    PathPrefix,
+}
+
+impl NamespaceMode {
+    /// Resolves the namespace of a request, removing the namespace token
+    /// so that it does not affect matching.
+    pub(crate) fn resolve(&self, namespaces: &HashSet<String>, request: &mut Request) {
+        match self {
+            NamespaceMode::Header(name) => {
+                if let Some(value) = request.headers.get(name) {
+                    request.namespace = Some(value.to_string());
+                    request.headers.remove(name);
+                }
+            }
+            NamespaceMode::PathPrefix => {
+                let path = request.path().trim_start_matches('/');
+                let (token, rest) = path.split_once('/').unwrap_or((path, ""));
+                if namespaces.contains(token) {
+                    let token = token.to_string();
+                    let rest = format!("/{rest}");
+                    request.url.set_path(&rest);
+                    request.namespace = Some(token);
+                }
+            }
+        }
+    }
+}
+
+impl Default for NamespaceMode {
+    fn default() -> Self {
+        Self::Header(DEFAULT_NAMESPACE_HEADER.into())
+    }
+}
+
+/// An isolated partition of a shared [`MockServer`](super::MockServer).
+///
+/// Mocks registered via a namespace only match requests sent with its token,
+/// and its journal only contains those requests. Mocks registered directly on
+/// the server are shared by all namespaces.
+///
+/// The namespace mocks and journal entries are removed when it is dropped.
+#[derive(Debug)]
+pub struct Namespace {
+    token: String,
+    mode: NamespaceMode,
+    base_url: Option<Url>,
+    state: Arc<MockServerState>,
+}
+
+impl Namespace {
+    pub(crate) fn new(
+        mode: NamespaceMode,
+        base_url: Option<Url>,
+        state: Arc<MockServerState>,
+    ) -> Self {
+        let token = Uuid::now_v7().simple().to_string();
+        state.namespaces.write().unwrap().insert(token.clone());
+        Self {
+            token,
+            mode,
+            base_url,
+            state,
+        }
+    }
+
+    /// Returns the namespace token.
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// Returns the header that must be sent with requests,
+    /// if the server resolves namespaces by header.
+    pub fn header(&self) -> Option<(&str, &str)> {
+        match &self.mode {
+            NamespaceMode::Header(name) => Some((name, &self.token)),
+            NamespaceMode::PathPrefix => None,
+        }
+    }
+
+    /// Returns a url for the path, including the namespace token
+    /// if the server resolves namespaces by path prefix.
+    pub fn url(&self, path: &str) -> Url {
+        let Some(base_url) = &self.base_url else {
+            panic!("server not running");
+        };
+        match &self.mode {
+            NamespaceMode::Header(_) => base_url.join(path).unwrap(),
+            NamespaceMode::PathPrefix => base_url
+                .join(&format!("/{}/{}", self.token, path.trim_start_matches('/')))
+                .unwrap(),
+        }
+    }
+
+    /// Builds and inserts a mock with default options, returning its ID.
+    pub fn mock<F>(&self, f: F) -> Uuid
+    where
+        F: FnOnce(When, Then),
+    {
+        let mock = Mock::new(f).with_namespace(&self.token);
+        let id = mock.id;
+        self.state.mocks.write().unwrap().insert(mock);
+        id
+    }
+
+    /// Builds and inserts a mock with options, returning its ID.
+    pub fn mock_with_options<F>(&self, priority: u8, limit: Option<usize>, f: F) -> Uuid
+    where
+        F: FnOnce(When, Then),
+    {
+        let mut mock = Mock::new(f)
+            .with_priority(priority)
+            .with_namespace(&self.token);
+        if let Some(limit) = limit {
+            mock = mock.with_limit(limit);
+        }
+        let id = mock.id;
+        self.state.mocks.write().unwrap().insert(mock);
+        id
+    }
+
+    /// Returns the journal entries for this namespace.
+    pub fn journal(&self) -> Journal {
+        self.state
+            .journal()
+            .iter()
+            .filter(|entry| entry.request.namespace() == Some(&self.token))
+            .cloned()
+            .collect()
+    }
+
+    /// Removes the namespace mocks and journal entries.
+    pub fn clear(&self) {
+        self.state
+            .mocks
+            .write()
+            .unwrap()
+            .retain(|mock| mock.namespace() != Some(&self.token));
+        self.state
+            .journal
+            .write()
+            .unwrap()
+            .retain(|entry| entry.request.namespace() != Some(&self.token));
+    }
+}
+
+impl Drop for Namespace {
+    fn drop(&mut self) {
+        self.clear();
+        self.state.namespaces.write().unwrap().remove(&self.token);
+    }
+}
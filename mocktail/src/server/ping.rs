@@ -0,0 +1,169 @@
+//! `PING` acknowledgement filtering
+use std::{
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use bytes::{Buf, BytesMut};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// The HTTP/2 client connection preface prefix.
+const PREFACE: &[u8] = b"PRI * HTTP/2.0";
+/// The HTTP/2 frame header size.
+const FRAME_HEADER_SIZE: usize = 9;
+/// The HTTP/2 `PING` frame type.
+const PING: u8 = 0x6;
+/// The `ACK` flag.
+const ACK: u8 = 0x1;
+/// The size of buffered output after which writes wait for it to be written.
+const MAX_PENDING: usize = 64 * 1024;
+
+/// A connection dropping the HTTP/2 `PING` acknowledgements written by the
+/// server, so client `PING`s appear unanswered.
+///
+/// HTTP/2 is detected from the client connection preface; other
+/// connections are passed through.
+pub(crate) struct PingFilter<T> {
+    inner: T,
+    http2: Option<bool>,
+    preface: Vec<u8>,
+    pending: BytesMut,
+    header: Vec<u8>,
+    copy: usize,
+    skip: usize,
+}
+
+impl<T> PingFilter<T> {
+    /// Creates a filter, with HTTP/2 known up front or detected.
+    pub(crate) fn new(inner: T, http2: bool) -> Self {
+        Self {
+            inner,
+            http2: http2.then_some(true),
+            preface: Vec::new(),
+            pending: BytesMut::new(),
+            header: Vec::with_capacity(FRAME_HEADER_SIZE),
+            copy: 0,
+            skip: 0,
+        }
+    }
+
+    /// Buffers written data, dropping `PING` acknowledgement frames.
+    fn filter(&mut self, mut buf: &[u8]) {
+        while !buf.is_empty() {
+            if self.skip > 0 {
+                let n = self.skip.min(buf.len());
+                self.skip -= n;
+                buf = &buf[n..];
+            } else if self.copy > 0 {
+                let n = self.copy.min(buf.len());
+                self.pending.extend_from_slice(&buf[..n]);
+                self.copy -= n;
+                buf = &buf[n..];
+            } else {
+                let n = (FRAME_HEADER_SIZE - self.header.len()).min(buf.len());
+                self.header.extend_from_slice(&buf[..n]);
+                buf = &buf[n..];
+                if self.header.len() == FRAME_HEADER_SIZE {
+                    let len =
+                        u32::from_be_bytes([0, self.header[0], self.header[1], self.header[2]])
+                            as usize;
+                    if self.header[3] == PING && self.header[4] & ACK != 0 {
+                        self.skip = len;
+                    } else {
+                        self.pending.extend_from_slice(&self.header);
+                        self.copy = len;
+                    }
+                    self.header.clear();
+                }
+            }
+        }
+    }
+}
+
+impl<T: AsyncWrite + Unpin> PingFilter<T> {
+    /// Writes buffered output until it is empty or the connection is not ready.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.pending.is_empty() {
+            let n = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.pending))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.pending.advance(n);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for PingFilter<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        ready!(Pin::new(&mut self.inner).poll_read(cx, buf))?;
+        if self.http2.is_none() {
+            let n = (PREFACE.len() - self.preface.len()).min(buf.filled().len() - filled);
+            let read = &buf.filled()[filled..filled + n];
+            self.preface.extend_from_slice(read);
+            if !PREFACE.starts_with(&self.preface) {
+                self.http2 = Some(false);
+            } else if self.preface.len() == PREFACE.len() {
+                self.http2 = Some(true);
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for PingFilter<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.http2 != Some(true) && self.pending.is_empty() {
+            return Pin::new(&mut self.inner).poll_write(cx, buf);
+        }
+        if self.poll_drain(cx)?.is_pending() && self.pending.len() >= MAX_PENDING {
+            return Poll::Pending;
+        }
+        if self.http2 == Some(true) {
+            self.filter(buf);
+        } else {
+            self.pending.extend_from_slice(buf);
+        }
+        let _ = self.poll_drain(cx)?;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_drain(cx))?;
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_drain(cx))?;
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        let mut filter = PingFilter::new((), true);
+        let settings = [0, 0, 0, 0x4, 0, 0, 0, 0, 0];
+        let ping_ack = [0, 0, 8, PING, ACK, 0, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8];
+        let ping = [0, 0, 8, PING, 0, 0, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8];
+        let frames = [&settings[..], &ping_ack, &ping].concat();
+        // Written in pieces splitting frame headers and payloads
+        for chunk in frames.chunks(5) {
+            filter.filter(chunk);
+        }
+        assert_eq!(&filter.pending[..], [&settings[..], &ping].concat());
+    }
+}
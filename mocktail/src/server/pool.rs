@@ -0,0 +1,171 @@
+//! Server pools
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::{background_runtime, MockServer};
+use crate::{Error, MockSet};
+
+/// A pool of started servers leased to tests.
+///
+/// Starting a server for each test of a large suite adds up; a pool starts
+/// its servers once and resets them between leases, so that each lease
+/// behaves like a fresh server with the mocks it was created with.
+/// A server stopped during a lease is removed from the pool. Servers run on a shared background runtime,
+/// so a pool can be shared by tests running on different runtimes, e.g.
+/// each `#[tokio::test]`:
+///
+/// ```no_run
+/// use mocktail::server::{MockServer, MockServerPool, PooledMockServer};
+///
+/// static POOL: tokio::sync::OnceCell<MockServerPool> = tokio::sync::OnceCell::const_new();
+///
+/// async fn server() -> PooledMockServer {
+///     POOL.get_or_try_init(|| MockServerPool::start(4, |_| MockServer::new_http("pool")))
+///         .await
+///         .unwrap()
+///         .lease()
+///         .await
+/// }
+/// ```
+#[derive(Clone)]
+pub struct MockServerPool {
+    inner: Arc<PoolInner>,
+}
+
+struct PoolInner {
+    servers: Mutex<Vec<(MockServer, MockSet)>>,
+    permits: Arc<Semaphore>,
+    size: AtomicUsize,
+}
+
+impl MockServerPool {
+    /// Creates and starts `size` servers, created by `f` with their index.
+    pub async fn start(size: usize, f: impl Fn(usize) -> MockServer) -> Result<Self, Error> {
+        let mut servers = Vec::with_capacity(size);
+        for index in 0..size {
+            let server = f(index);
+            server
+                .start_on(background_runtime(), server.config.ready_timeout())
+                .await?;
+            let mocks = server.state.mocks().clone();
+            servers.push((server, mocks));
+        }
+        Ok(Self {
+            inner: Arc::new(PoolInner {
+                servers: Mutex::new(servers),
+                permits: Arc::new(Semaphore::new(size)),
+                size: AtomicUsize::new(size),
+            }),
+        })
+    }
+
+    /// Returns the number of servers in the pool.
+    pub fn size(&self) -> usize {
+        self.inner.size.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of servers available to lease.
+    pub fn available(&self) -> usize {
+        self.inner.permits.available_permits()
+    }
+
+    /// Leases a server, waiting for one to become available.
+    ///
+    /// # Panics
+    ///
+    /// Panics if all servers of the pool were stopped.
+    pub async fn lease(&self) -> PooledMockServer {
+        let permit = self
+            .inner
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("all servers of the pool were stopped");
+        self.leased(permit)
+    }
+
+    /// Leases a server if one is available.
+    pub fn try_lease(&self) -> Option<PooledMockServer> {
+        let permit = self.inner.permits.clone().try_acquire_owned().ok()?;
+        Some(self.leased(permit))
+    }
+
+    fn leased(&self, permit: OwnedSemaphorePermit) -> PooledMockServer {
+        let (server, mocks) = self
+            .inner
+            .servers
+            .lock()
+            .unwrap()
+            .pop()
+            .expect("a permit is held for each pooled server");
+        PooledMockServer {
+            server: Some(server),
+            mocks,
+            pool: self.inner.clone(),
+            permit: Some(permit),
+        }
+    }
+}
+
+impl std::fmt::Debug for MockServerPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockServerPool")
+            .field("size", &self.size())
+            .field("available", &self.available())
+            .finish()
+    }
+}
+
+/// A server leased from a [`MockServerPool`], reset and returned to the pool when dropped.
+///
+/// A server stopped during the lease is not returned, shrinking the pool.
+pub struct PooledMockServer {
+    server: Option<MockServer>,
+    mocks: MockSet,
+    pool: Arc<PoolInner>,
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Deref for PooledMockServer {
+    type Target = MockServer;
+
+    fn deref(&self) -> &Self::Target {
+        self.server.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledMockServer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.server.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledMockServer {
+    fn drop(&mut self) {
+        let (Some(server), Some(permit)) = (self.server.take(), self.permit.take()) else {
+            return;
+        };
+        if !server.is_running() {
+            // Remove the server and its permit from the pool
+            permit.forget();
+            if self.pool.size.fetch_sub(1, Ordering::Relaxed) == 1 {
+                self.pool.permits.close();
+            }
+            return;
+        }
+        // Restore the state the server was created with
+        let mocks = std::mem::take(&mut self.mocks);
+        server.state.restore(&server.config, mocks.clone());
+        // Return the server before the permit is released
+        self.pool.servers.lock().unwrap().push((server, mocks));
+        drop(permit);
+    }
+}
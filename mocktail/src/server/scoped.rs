@@ -0,0 +1,68 @@
+//! Scoped servers
+use std::ops::{Deref, DerefMut};
+
+use super::MockServer;
+use crate::Error;
+
+/// A running [`MockServer`] stopped and verified when dropped.
+///
+/// Returned by [`MockServer::start_scoped`] for use in test fixtures. On drop,
+/// unmatched requests are printed and the test panics if verification fails,
+/// unless it is already panicking.
+pub struct ScopedMockServer {
+    server: MockServer,
+}
+
+impl ScopedMockServer {
+    pub(crate) async fn start(server: MockServer) -> Result<Self, Error> {
+        server.start().await?;
+        Ok(Self { server })
+    }
+
+    /// Returns the diagnostics printed on drop, if any.
+    pub fn diagnostics(&self) -> Option<String> {
+        let journal = self.server.journal();
+        let unmatched = journal
+            .unmatched()
+            .map(|entry| format!("  {} {}", entry.request.method(), entry.request.path()))
+            .collect::<Vec<_>>();
+        if unmatched.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "{}: {} unmatched requests:\n{}",
+            self.server.name(),
+            unmatched.len(),
+            unmatched.join("\n")
+        ))
+    }
+}
+
+impl Deref for ScopedMockServer {
+    type Target = MockServer;
+
+    fn deref(&self) -> &Self::Target {
+        &self.server
+    }
+}
+
+impl DerefMut for ScopedMockServer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.server
+    }
+}
+
+impl Drop for ScopedMockServer {
+    fn drop(&mut self) {
+        // The server is stopped when dropped after this
+        if let Some(diagnostics) = self.diagnostics() {
+            eprintln!("{diagnostics}");
+        }
+        if std::thread::panicking() {
+            return;
+        }
+        if let Err(err) = self.server.verify() {
+            panic!("{}: {err}", self.server.name());
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Sessions
+use std::collections::HashSet;
+
+use crate::{headers::Headers, request::Request};
+
+/// The default session cookie name.
+pub const DEFAULT_SESSION_COOKIE: &str = "mocktail-session";
+
+/// Determines how sessions are issued to clients and sent back by them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionMode {
+    /// The session ID is issued with a `Set-Cookie` header
+    /// and sent back in the `Cookie` header.
+    Cookie(String),
+    /// The session ID is issued and sent back in a header.
+    Header(String),
+}
+
+impl SessionMode {
+    /// Resolves the session of a request, if it sends back a session ID issued by the server.
+    pub(crate) fn resolve(&self, sessions: &HashSet<String>, request: &mut Request) {
+        let id = match self {
+            SessionMode::Cookie(name) => request.headers.get("cookie").and_then(|value| {
+                value.split(';').find_map(|cookie| {
+                    let (key, value) = cookie.trim().split_once('=')?;
+                    (key == name).then(|| value.to_string())
+                })
+            }),
+            SessionMode::Header(name) => request.headers.get(name).map(|value| value.to_string()),
+        };
+        request.session = id.filter(|id| sessions.contains(id));
+    }
+
+    /// Adds the header issuing a session ID to response headers.
+    pub(crate) fn issue(&self, id: &str, headers: &mut Headers) {
+        match self {
+            SessionMode::Cookie(name) => {
+                headers.insert("set-cookie", format!("{name}={id}; Path=/; HttpOnly"));
+            }
+            SessionMode::Header(name) => {
+                headers.insert(name.clone(), id.to_string());
+            }
+        }
+    }
+}
+
+impl Default for SessionMode {
+    fn default() -> Self {
+        Self::Cookie(DEFAULT_SESSION_COOKIE.into())
+    }
+}
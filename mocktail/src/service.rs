@@ -1,5 +1,593 @@
 //! Mock services
+use std::{
+    any::Any,
+    future::Future,
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use futures::{FutureExt, StreamExt};
+use http_body::{Body as _, Frame};
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::Sleep,
+};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::{
+    mock::Mock,
+    request::{Request, Transfer},
+    response::Response,
+    server::{MockServerConfig, MockServerState, ReadStall},
+};
+
+#[cfg(feature = "tower")]
+mod connector;
+#[cfg(feature = "tower")]
+pub use connector::MockConnector;
 pub mod grpc;
 pub use grpc::GrpcMockService;
 pub mod http;
 pub use http::HttpMockService;
+mod informational;
+pub(crate) use informational::{InformationalWriter, SharedIo};
+
+/// A request body supported by the mock services.
+pub trait RequestBody:
+    http_body::Body<Data = Bytes, Error: Into<BoxError> + std::fmt::Debug + Send>
+    + std::fmt::Debug
+    + Send
+    + Unpin
+    + 'static
+{
+}
+
+impl<B> RequestBody for B where
+    B: http_body::Body<Data = Bytes, Error: Into<BoxError> + std::fmt::Debug + Send>
+        + std::fmt::Debug
+        + Send
+        + Unpin
+        + 'static
+{
+}
+
+pub(crate) type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A request body read slowly, or not at all, according to a [`ReadStall`].
+///
+/// Without a stall, frames are passed through as is.
+#[derive(Debug)]
+struct StalledBody<B> {
+    inner: B,
+    stall: Option<ReadStall>,
+    read: usize,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl<B> StalledBody<B> {
+    fn new(inner: B, stall: Option<ReadStall>) -> Self {
+        Self {
+            inner,
+            stall,
+            read: 0,
+            sleep: None,
+        }
+    }
+
+    fn is_stopped(&self) -> bool {
+        matches!(self.stall, Some(ReadStall::Stop { after }) if self.read >= after)
+    }
+}
+
+impl<B: RequestBody> http_body::Body for StalledBody<B> {
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        if let Some(sleep) = &mut this.sleep {
+            ready!(sleep.as_mut().poll(cx));
+            this.sleep = None;
+        }
+        if this.is_stopped() {
+            // Never woken, the rest of the body is not read
+            return Poll::Pending;
+        }
+        let frame = ready!(Pin::new(&mut this.inner).poll_frame(cx));
+        if let Some(data) = frame
+            .as_ref()
+            .and_then(|frame| frame.as_ref().ok())
+            .and_then(|frame| frame.data_ref())
+        {
+            this.read += data.len();
+            if let Some(ReadStall::Throttle { bytes_per_second }) = this.stall {
+                let delay = Duration::from_secs_f64(data.len() as f64 / bytes_per_second as f64);
+                this.sleep = Some(Box::pin(tokio::time::sleep(delay)));
+            }
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        !self.is_stopped() && self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// Measures the transfer of a request body while it is received.
+#[derive(Debug, Clone, Copy)]
+struct TransferMeter {
+    start: Instant,
+    transfer: Transfer,
+}
+
+impl TransferMeter {
+    /// Starts measuring when the request head is received.
+    fn start() -> Self {
+        Self {
+            start: Instant::now(),
+            transfer: Transfer::default(),
+        }
+    }
+
+    /// Records a received data frame.
+    fn chunk(&mut self, chunk: &[u8]) {
+        if chunk.is_empty() {
+            return;
+        }
+        self.transfer.bytes += chunk.len();
+        self.transfer.chunks += 1;
+        self.transfer.duration = self.start.elapsed();
+    }
+
+    /// Sets the number of complete gRPC messages received.
+    fn messages(&mut self, messages: usize) {
+        self.transfer.messages = messages;
+    }
+
+    /// Returns the transfer metrics so far.
+    fn transfer(&self) -> Transfer {
+        self.transfer
+    }
+
+    /// Returns the time since the request head was received.
+    fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// Returns true if a request body error is a cancellation by the client,
+/// a stream reset or a connection closed before the body was received.
+fn is_cancellation(err: &BoxError) -> bool {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(err.as_ref());
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<h2::Error>() {
+            return err.is_reset() && err.is_remote();
+        }
+        if let Some(err) = err.downcast_ref::<std::io::Error>() {
+            return matches!(
+                err.kind(),
+                std::io::ErrorKind::UnexpectedEof | std::io::ErrorKind::ConnectionReset
+            );
+        }
+        if err
+            .downcast_ref::<hyper::Error>()
+            .is_some_and(|err| err.is_incomplete_message())
+        {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
+/// Records the cancellation of a matched request by the client if dropped
+/// before the response is complete, e.g. when the client disconnects while
+/// the response is delayed.
+struct CancellationGuard {
+    state: Arc<MockServerState>,
+    request: Option<(Request, Uuid)>,
+    meter: TransferMeter,
+}
+
+impl CancellationGuard {
+    fn new(
+        state: Arc<MockServerState>,
+        request: Request,
+        mock_id: Uuid,
+        meter: TransferMeter,
+    ) -> Self {
+        Self {
+            state,
+            request: Some((request, mock_id)),
+            meter,
+        }
+    }
+
+    /// Disarms the guard when the response is complete.
+    fn disarm(&mut self) {
+        self.request = None;
+    }
+}
+
+impl Drop for CancellationGuard {
+    fn drop(&mut self) {
+        if let Some((request, mock_id)) = self.request.take() {
+            debug!("response dropped before completion, the client disconnected");
+            self.state
+                .record_cancellation(request, Some(mock_id), self.meter.elapsed());
+        }
+    }
+}
+
+/// A response body disarming a [`CancellationGuard`] when complete.
+struct GuardedBody {
+    inner: http::BoxBody,
+    guard: CancellationGuard,
+}
+
+impl http_body::Body for GuardedBody {
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        let frame = ready!(Pin::new(&mut this.inner).poll_frame(cx));
+        // Errors end the response on purpose, e.g. to reset the stream
+        if !matches!(frame, Some(Ok(_))) || this.inner.is_end_stream() {
+            this.guard.disarm();
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for GuardedBody {
+    fn drop(&mut self) {
+        // Bodies ending without being polled, e.g. empty bodies
+        if self.inner.is_end_stream() {
+            self.guard.disarm();
+        }
+    }
+}
+
+/// Returns a body error resetting the HTTP/2 stream with the error code.
+fn reset_error(reason: u32) -> BoxError {
+    Box::new(h2::Error::from(h2::Reason::from(reason)))
+}
+
+/// A sender of response body frames.
+type FrameSender = mpsc::Sender<Result<Frame<Bytes>, BoxError>>;
+
+/// Waits for the response delay, if configured.
+async fn delay(response: &Response) {
+    if let Some(delay) = response.delay {
+        debug!(delay = ?delay.base, "delaying response");
+        tokio::time::sleep(delay.base).await;
+    }
+}
+
+/// Sends the response body as data frames, split and paced by the chunking
+/// and resetting the stream if configured. Returns false if the stream was reset.
+async fn send_body(tx: &FrameSender, response: &mut Response) -> bool {
+    let mut remaining = response.reset.map(|reset| reset.after);
+    let mut sent = false;
+    while let Some(mut chunk) = response.body.next().await {
+        if let Some(remaining) = &mut remaining {
+            chunk.truncate(*remaining);
+            *remaining -= chunk.len();
+        }
+        if !chunk.is_empty() {
+            match response.chunking {
+                Some(chunking) => {
+                    for frame in chunking.split(chunk) {
+                        if sent && !chunking.interval.is_zero() {
+                            tokio::time::sleep(chunking.interval).await;
+                        }
+                        let _ = tx.send(Ok(Frame::data(frame))).await;
+                        sent = true;
+                    }
+                }
+                None => {
+                    let _ = tx.send(Ok(Frame::data(chunk))).await;
+                }
+            }
+        }
+        if remaining == Some(0) {
+            break;
+        }
+    }
+    if let Some(reset) = response.reset {
+        debug!(?reset, "resetting stream");
+        let _ = tx.send(Err(reset_error(reset.reason))).await;
+        return false;
+    }
+    true
+}
+
+/// Prepares a request for matching.
+fn prepare_request(state: &MockServerState, config: &MockServerConfig, request: &mut Request) {
+    state.resolve_namespace(config.namespace_mode(), request);
+    state.resolve_session(request);
+    request.path_normalization = config.path_normalization();
+}
+
+/// Records a request rejected with a protocol error in the journal,
+/// reporting it in strict mode.
+fn record_protocol_error(
+    state: &MockServerState,
+    config: &MockServerConfig,
+    parts: ::http::request::Parts,
+    error: String,
+) {
+    let mut request = Request::from_parts(parts);
+    prepare_request(state, config, &mut request);
+    record_decode_error(state, config, request, error);
+}
+
+/// Records a prepared request rejected with a protocol error, e.g. a body
+/// that failed to be received or decoded, with the raw body bytes received.
+fn record_decode_error(
+    state: &MockServerState,
+    config: &MockServerConfig,
+    request: Request,
+    error: String,
+) {
+    warn!(
+        error,
+        "protocol error: {} {}",
+        request.method(),
+        request.path()
+    );
+    if config.strict() {
+        state.add_failure(format!(
+            "protocol error: {} {}: {error}",
+            request.method(),
+            request.path()
+        ));
+    }
+    state.record_protocol_error(request, error);
+}
+
+/// Records a panic of a matcher or responder while handling a request
+/// as a verification failure, returning the panic message.
+fn record_panic(state: &MockServerState, request: &Request, panic: Box<dyn Any + Send>) -> String {
+    let message = panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".into());
+    warn!(message, "panic: {} {}", request.method(), request.path());
+    state.add_failure(format!(
+        "panic: {} {}: {message}",
+        request.method(),
+        request.path()
+    ));
+    message
+}
+
+/// A failure of a matcher or responder, reported as a verification failure.
+enum HandlerError {
+    /// A matcher or responder panicked, with the panic message.
+    Panic(String),
+    /// A matcher or responder exceeded the handler timeout, with the diagnostics.
+    Timeout(String),
+}
+
+impl HandlerError {
+    fn message(&self) -> &str {
+        match self {
+            HandlerError::Panic(message) | HandlerError::Timeout(message) => message,
+        }
+    }
+}
+
+/// Records a matcher or responder exceeding the handler timeout
+/// as a verification failure, returning the diagnostics.
+fn record_timeout(
+    state: &MockServerState,
+    request: &Request,
+    stage: &str,
+    timeout: Duration,
+) -> String {
+    let message = format!("{stage} did not complete within {timeout:?}");
+    warn!(message, "timeout: {} {}", request.method(), request.path());
+    state.add_failure(format!(
+        "timeout: {} {}: {message}",
+        request.method(),
+        request.path()
+    ));
+    message
+}
+
+/// Runs a matcher or responder on a detached thread, returning the payload of a panic.
+///
+/// A handler that never returns, e.g. on a deadlock, leaks its thread but
+/// neither holds up the runtime nor its shutdown. The thread enters the
+/// runtime, e.g. to schedule webhooks.
+async fn spawn_handler<T: Send + 'static>(
+    name: &str,
+    f: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, Box<dyn Any + Send>> {
+    let (tx, rx) = oneshot::channel();
+    let runtime = tokio::runtime::Handle::current();
+    std::thread::Builder::new()
+        .name(name.into())
+        .spawn(move || {
+            let _runtime = runtime.enter();
+            let _ = tx.send(std::panic::catch_unwind(AssertUnwindSafe(f)));
+        })
+        .expect("failed to spawn handler thread");
+    // The thread sends its result, unless it exits abnormally
+    rx.await
+        .unwrap_or_else(|_| Err(Box::new(format!("{name} thread exited"))))
+}
+
+/// Matches a request to a mock, catching panics of matchers.
+///
+/// With a handler timeout, matchers are evaluated on a separate thread,
+/// so that a matcher that never returns, e.g. on a deadlock, fails the request.
+/// A match found after the timeout is not recorded.
+async fn match_mock(
+    state: &Arc<MockServerState>,
+    config: &MockServerConfig,
+    request: &mut Request,
+) -> Result<Option<Mock>, HandlerError> {
+    let Some(timeout) = config.handler_timeout() else {
+        return std::panic::catch_unwind(AssertUnwindSafe(|| state.match_request(request)))
+            .map_err(|panic| HandlerError::Panic(record_panic(state, request, panic)));
+    };
+    // Claimed by the matching task to record a match, or on timeout to
+    // abandon the request, whichever comes first
+    let claimed = Arc::new(AtomicBool::new(false));
+    let task_claimed = claimed.clone();
+    let task_state = state.clone();
+    let mut task_request = request.clone();
+    let mut task = Box::pin(spawn_handler("mocktail-matcher", move || {
+        let mock = task_state.match_request_if(&mut task_request, || {
+            !task_claimed.swap(true, Ordering::AcqRel)
+        });
+        (task_request, mock)
+    }));
+    let result = match tokio::time::timeout(timeout, &mut task).await {
+        Ok(result) => result,
+        // The match is being recorded, the request is served
+        Err(_) if claimed.swap(true, Ordering::AcqRel) => task.await,
+        Err(_) => {
+            return Err(HandlerError::Timeout(record_timeout(
+                state, request, "matching", timeout,
+            )))
+        }
+    };
+    match result {
+        Ok((matched, mock)) => {
+            *request = matched;
+            Ok(mock)
+        }
+        Err(panic) => Err(HandlerError::Panic(record_panic(state, request, panic))),
+    }
+}
+
+/// Builds the response of a matched mock, resolving its delegate, catching
+/// panics and failing responders exceeding the handler timeout.
+async fn respond(
+    state: &Arc<MockServerState>,
+    config: &MockServerConfig,
+    mock: Mock,
+    request: &Request,
+) -> Result<Response, HandlerError> {
+    let id = mock.id;
+    let response = async {
+        let response = build_response(state, config, mock, request).await?;
+        AssertUnwindSafe(response.resolve(request))
+            .catch_unwind()
+            .await
+    };
+    guard_responder(state, config, id, request, response).await
+}
+
+/// Builds the response of a matched mock, without resolving its delegate,
+/// catching panics and failing responders exceeding the handler timeout.
+async fn render(
+    state: &Arc<MockServerState>,
+    config: &MockServerConfig,
+    mock: Mock,
+    request: &Request,
+) -> Result<Response, HandlerError> {
+    let id = mock.id;
+    let response = build_response(state, config, mock, request);
+    guard_responder(state, config, id, request, response).await
+}
+
+/// Builds the response of a matched mock, returning the payload of a panic.
+///
+/// With a handler timeout, the response is built on a separate thread,
+/// so that the timeout covers rendering that never returns, e.g. a template
+/// helper.
+async fn build_response(
+    state: &Arc<MockServerState>,
+    config: &MockServerConfig,
+    mock: Mock,
+    request: &Request,
+) -> Result<Response, Box<dyn Any + Send>> {
+    if config.handler_timeout().is_none() {
+        return std::panic::catch_unwind(AssertUnwindSafe(|| state.response(mock, request)));
+    }
+    let task_state = state.clone();
+    let task_request = request.clone();
+    spawn_handler("mocktail-responder", move || {
+        task_state.response(mock, &task_request)
+    })
+    .await
+}
+
+/// Awaits the response of a mock, recording a panic and failing it once
+/// the handler timeout is exceeded.
+async fn guard_responder(
+    state: &MockServerState,
+    config: &MockServerConfig,
+    id: Uuid,
+    request: &Request,
+    response: impl Future<Output = Result<Response, Box<dyn Any + Send>>>,
+) -> Result<Response, HandlerError> {
+    let response = match config.handler_timeout() {
+        Some(timeout) => match tokio::time::timeout(timeout, response).await {
+            Ok(response) => response,
+            Err(_) => {
+                let stage = format!("responder of mock {id}");
+                return Err(HandlerError::Timeout(record_timeout(
+                    state, request, &stage, timeout,
+                )));
+            }
+        },
+        None => response.await,
+    };
+    response.map_err(|panic| HandlerError::Panic(record_panic(state, request, panic)))
+}
+
+/// Records a request in the journal, reporting it if it did not match a mock.
+fn record_request(
+    state: &MockServerState,
+    config: &MockServerConfig,
+    request: Request,
+    mock: Option<&Mock>,
+) {
+    if mock.is_none() {
+        if config.strict() {
+            state.add_failure(format!(
+                "unexpected request: {} {}",
+                request.method(),
+                request.path()
+            ));
+        }
+        let hook = state.unmatched_hook.read().unwrap().clone();
+        if let Some(hook) = hook {
+            hook.call(&request);
+        }
+    }
+    state.record(request, mock);
+}
@@ -0,0 +1,100 @@
+//! In-process connector
+use std::{
+    convert::Infallible,
+    future::{ready, Ready},
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use hyper_util::rt::TokioIo;
+use tokio::io::DuplexStream;
+use tracing::debug;
+
+use super::{GrpcMockService, HttpMockService, SharedIo};
+use crate::{
+    request::ConnectionInfo,
+    server::{
+        connection_builder, ConnectionService, MockServerConfig, MockServerState, ServerKind,
+    },
+};
+
+/// The buffer size of in-memory streams.
+const BUF_SIZE: usize = 64 * 1024;
+
+/// A connector serving mocks in-process over in-memory streams,
+/// without sockets or ports.
+///
+/// Can be used with clients accepting a custom connector, e.g.
+/// `tonic::transport::Endpoint::connect_with_connector`.
+#[derive(Debug, Clone)]
+pub struct MockConnector {
+    kind: ServerKind,
+    state: Arc<MockServerState>,
+    config: Arc<MockServerConfig>,
+}
+
+impl MockConnector {
+    pub(crate) fn new(
+        kind: ServerKind,
+        state: Arc<MockServerState>,
+        config: Arc<MockServerConfig>,
+    ) -> Self {
+        Self {
+            kind,
+            state,
+            config,
+        }
+    }
+}
+
+impl tower_service::Service<http::Uri> for MockConnector {
+    type Response = TokioIo<DuplexStream>;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _uri: http::Uri) -> Self::Future {
+        let (client, server) = tokio::io::duplex(BUF_SIZE);
+        let kind = self.kind;
+        let state = self.state.clone();
+        let config = self.config.clone();
+        // Spawn task to serve connection
+        tokio::spawn(async move {
+            let builder = connection_builder(&config);
+            let io = SharedIo::new(server);
+            let writer = io.writer();
+            let io = TokioIo::new(io);
+            let result = match kind {
+                ServerKind::Http => {
+                    let service = HttpMockService::new(state.clone(), config.clone());
+                    let service = ConnectionService::new(
+                        service,
+                        ConnectionInfo::new(state.next_connection()),
+                        &config,
+                        Arc::default(),
+                        writer,
+                    );
+                    builder.serve_connection(io, service).await
+                }
+                ServerKind::Grpc => {
+                    let service = GrpcMockService::new(state.clone(), config.clone());
+                    let service = ConnectionService::new(
+                        service,
+                        ConnectionInfo::new(state.next_connection()),
+                        &config,
+                        Arc::default(),
+                        writer,
+                    );
+                    builder.serve_connection(io, service).await
+                }
+            };
+            if let Err(err) = result {
+                debug!("connection error: {err}");
+            }
+        });
+        ready(Ok(TokioIo::new(client)))
+    }
+}
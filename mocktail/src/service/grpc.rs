@@ -6,50 +6,109 @@ use futures::{future::BoxFuture, StreamExt};
 use http::{HeaderMap, HeaderValue};
 use http_body::Frame;
 use http_body_util::{BodyExt, StreamBody};
-use hyper::{body::Incoming, service::Service};
+use hyper::service::Service;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
 use tracing::debug;
 
-use crate::{request::Request, server::MockServerState, service::http::empty, Code};
+#[cfg(feature = "compression")]
+use crate::{body::Body, compression::Encoding, response::Response};
+use crate::{
+    request::{ConnectionInfo, Request},
+    server::{MockServerConfig, MockServerState},
+    service::http::{empty, full},
+    Code,
+};
 
-use super::http::BoxBody;
+use super::{
+    delay, http::BoxBody, is_cancellation, match_mock, prepare_request, record_decode_error,
+    record_protocol_error, record_request, render, send_body, BoxError, HandlerError, RequestBody,
+    StalledBody, TransferMeter,
+};
 
 /// Mock gRPC service.
 #[derive(Debug, Clone)]
 pub struct GrpcMockService {
     state: Arc<MockServerState>,
+    config: Arc<MockServerConfig>,
 }
 
 impl GrpcMockService {
-    pub fn new(state: Arc<MockServerState>) -> Self {
-        Self { state }
+    pub fn new(state: Arc<MockServerState>, config: Arc<MockServerConfig>) -> Self {
+        Self { state, config }
     }
 }
 
-impl Service<http::Request<Incoming>> for GrpcMockService {
+#[cfg(feature = "tower")]
+impl<B> tower_service::Service<http::Request<B>> for GrpcMockService
+where
+    B: RequestBody,
+{
     type Response = http::Response<BoxBody>;
     type Error = Infallible;
     type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
 
-    fn call(&self, req: http::Request<Incoming>) -> Self::Future {
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        Service::call(self, req)
+    }
+}
+
+impl<B> Service<http::Request<B>> for GrpcMockService
+where
+    B: RequestBody,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn call(&self, req: http::Request<B>) -> Self::Future {
         let state = self.state.clone();
+        let config = self.config.clone();
         let fut = async move {
             debug!(?req, "handling request");
+            let mut meter = TransferMeter::start();
 
+            // Requests served in-process, without a connection, may have any version
+            let connection = req.extensions().get::<ConnectionInfo>().is_some();
+            if connection && req.version() < http::Version::HTTP_2 {
+                let error = format!("gRPC requires HTTP/2, received {:?}", req.version());
+                let (parts, _) = req.into_parts();
+                record_protocol_error(&state, &config, parts, error.clone());
+                return Ok(http::Response::builder()
+                    .status(config.protocol_errors().http1_status.as_http())
+                    .header("content-type", "text/plain")
+                    .body(full(Bytes::from(error)))
+                    .unwrap());
+            }
             if req.method() != http::Method::POST {
+                let error = format!("gRPC requires POST, received {}", req.method());
+                let (parts, _) = req.into_parts();
+                record_protocol_error(&state, &config, parts, error);
                 return Ok(http::Response::builder()
                     .status(http::StatusCode::METHOD_NOT_ALLOWED)
                     .header("Allow", "POST")
                     .body(empty())
                     .unwrap());
             }
-            let content_type = req.headers().get("content-type");
-            if !content_type.is_some_and(|v| {
-                v.to_str()
-                    .unwrap_or_default()
-                    .starts_with("application/grpc")
-            }) {
+            let content_type = req
+                .headers()
+                .get("content-type")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default();
+            if !content_type.starts_with("application/grpc") {
+                let error = format!(
+                    "gRPC requires an application/grpc content type, received {content_type:?}"
+                );
+                let (parts, _) = req.into_parts();
+                record_protocol_error(&state, &config, parts, error);
                 return Ok(http::Response::builder()
                     .status(http::StatusCode::UNSUPPORTED_MEDIA_TYPE)
                     .header("Accept-Post", "application/grpc")
@@ -57,59 +116,191 @@ impl Service<http::Request<Incoming>> for GrpcMockService {
                     .unwrap());
             }
 
+            // Negotiate message compression, headers are sent before a mock is matched
+            #[cfg(feature = "compression")]
+            let encoding = config
+                .compression()
+                .zip(
+                    req.headers()
+                        .get("grpc-accept-encoding")
+                        .and_then(|v| v.to_str().ok()),
+                )
+                .and_then(|(compression, accept)| compression.negotiate(accept));
+
+            let compressed = req
+                .headers()
+                .get("grpc-encoding")
+                .is_some_and(|v| v != "identity");
             let (parts, body) = req.into_parts();
+            let body = StalledBody::new(body, config.read_stall());
             let mut stream = body.into_data_stream();
 
             // Create response stream
-            let (response_tx, response_rx) =
-                mpsc::channel::<Result<Frame<Bytes>, hyper::Error>>(32);
+            let (response_tx, response_rx) = mpsc::channel::<Result<Frame<Bytes>, BoxError>>(32);
             let response_stream = ReceiverStream::new(response_rx);
             let response_body = BoxBody::new(StreamBody::new(response_stream));
-            let response = http::Response::builder()
+            #[cfg_attr(not(feature = "compression"), allow(unused_mut))]
+            let mut response = http::Response::builder()
                 .header("content-type", "application/grpc")
                 .body(response_body)
                 .unwrap();
+            #[cfg(feature = "compression")]
+            if let Some(encoding) = encoding {
+                response
+                    .headers_mut()
+                    .insert("grpc-encoding", HeaderValue::from_static(encoding.as_str()));
+            }
 
             // Spawn task to handle request
             tokio::spawn(async move {
-                let mut request = Request::from_parts(parts);
+                let mut request = Request::from_parts(parts).with_transfer(meter.transfer());
+                prepare_request(&state, &config, &mut request);
                 let mut matched = false;
+                // The last request matched, and the ID of its mock
+                let mut last_match = None;
                 let mut buf = BytesMut::new();
+                // Messages of the body buffers cleared after a match
+                let mut messages = 0;
 
-                while let Some(Ok(chunk)) = stream.next().await {
+                while let Some(chunk) = stream.next().await {
+                    let chunk = match chunk {
+                        Ok(chunk) => chunk,
+                        Err(err) => {
+                            let err: BoxError = err.into();
+                            if is_cancellation(&err) {
+                                debug!("request stream reset by the client");
+                                let (request, mock_id) = last_match.unwrap_or((request, None));
+                                state.record_cancellation(request, mock_id, meter.elapsed());
+                                return;
+                            }
+                            let error = format!("failed to receive request body: {err}");
+                            record_decode_error(&state, &config, request, error.clone());
+                            if !matched {
+                                let code = config.protocol_errors().decode_code;
+                                let _ = response_tx
+                                    .send(Ok(Frame::trailers(error_trailer(code, &error))))
+                                    .await;
+                            }
+                            return;
+                        }
+                    };
                     debug!(?chunk, "received chunk");
+                    if chunk.is_empty() {
+                        // Clients may end the stream with an empty data frame
+                        continue;
+                    }
+                    meter.chunk(&chunk);
+                    if config
+                        .max_body_size()
+                        .is_some_and(|limit| meter.transfer().bytes > limit)
+                    {
+                        debug!("request body exceeds max body size, sending error");
+                        // Requests matched before were recorded with their matches
+                        if !matched {
+                            let request = request.with_transfer(meter.transfer());
+                            record_request(&state, &config, request, None);
+                        }
+                        let _ = response_tx
+                            .send(Ok(Frame::trailers(body_too_large_trailer())))
+                            .await;
+                        return;
+                    }
                     // Add chunk to body buffer
                     buf.extend(chunk);
+                    meter.messages(messages + complete_messages(&buf));
 
                     // Match request to mock
-                    request = request.with_body(buf.clone().freeze());
-                    let mock = state.mocks().match_by_request(&request);
+                    request = request
+                        .with_body(buf.clone().freeze())
+                        .with_transfer(meter.transfer());
+                    let mock = match match_mock(&state, &config, &mut request).await {
+                        Ok(mock) => mock,
+                        Err(err) => {
+                            send_handler_error(&config, &response_tx, err).await;
+                            return;
+                        }
+                    };
                     if let Some(mock) = mock {
                         matched = true;
-                        debug!("mock found, sending response");
-                        let mut response = mock.response;
-                        // Send data frames
-                        if !response.body().is_empty() {
-                            while let Some(chunk) = response.body.next().await {
-                                let _ = response_tx.send(Ok(Frame::data(chunk))).await;
+                        debug!(mock_id = %mock.id, labels = ?mock.labels, "mock found, sending response");
+                        record_request(&state, &config, request.clone(), Some(&mock));
+                        last_match = Some((request.clone(), Some(mock.id)));
+                        let prefix = mock.matches_prefix();
+                        let mut response = match render(&state, &config, mock, &request).await {
+                            Ok(response) => response,
+                            Err(err) => {
+                                send_handler_error(&config, &response_tx, err).await;
+                                return;
                             }
+                        };
+                        if response.delegate.is_some() {
+                            let _ = response_tx
+                                .send(Ok(Frame::trailers(delegate_unsupported_trailer())))
+                                .await;
+                            return;
                         }
-                        // Send trailers frame
-                        let mut trailers = HeaderMap::from(response.headers().clone());
-                        trailers
-                            .insert("grpc-status", response.status().as_grpc().to_header_value());
-                        if let Some(message) = response.message() {
-                            trailers
-                                .insert("grpc-message", HeaderValue::from_str(message).unwrap());
+                        let send = async {
+                            delay(&response).await;
+                            #[cfg(feature = "compression")]
+                            if let Some(encoding) = encoding {
+                                compress_messages(&config, &mut response, encoding);
+                            }
+                            // Send data frames
+                            if !send_body(&response_tx, &mut response).await {
+                                return false;
+                            }
+                            // Send trailers frame
+                            let mut trailers = HeaderMap::from(response.headers().clone());
+                            trailers.insert(
+                                "grpc-status",
+                                response.status().as_grpc().to_header_value(),
+                            );
+                            if let Some(message) = response.message() {
+                                trailers.insert("grpc-message", grpc_message(message));
+                            }
+                            let _ = response_tx.send(Ok(Frame::trailers(trailers))).await;
+                            true
+                        };
+                        // The response body is dropped when the client resets the stream
+                        let sent = tokio::select! {
+                            biased;
+                            _ = response_tx.closed() => None,
+                            sent = send => Some(sent),
+                        };
+                        match sent {
+                            Some(true) => {}
+                            Some(false) => return,
+                            None => {
+                                debug!("response stream closed by the client");
+                                let (request, mock_id) = last_match.unwrap();
+                                state.record_cancellation(request, mock_id, meter.elapsed());
+                                return;
+                            }
+                        }
+                        if prefix {
+                            // Receive the rest of the request without matching
+                            while stream.next().await.is_some() {}
+                            return;
                         }
-                        let _ = response_tx.send(Ok(Frame::trailers(trailers))).await;
                         // Clear body buffer
+                        messages = meter.transfer().messages;
                         buf.clear();
                     }
                 }
                 debug!("request stream closed");
+                if let Some(error) = frame_error(&buf, compressed) {
+                    record_decode_error(&state, &config, request, error.clone());
+                    if !matched {
+                        let code = config.protocol_errors().decode_code;
+                        let _ = response_tx
+                            .send(Ok(Frame::trailers(error_trailer(code, &error))))
+                            .await;
+                    }
+                    return;
+                }
                 if !matched {
                     debug!(?request, "no mocks found, sending error");
+                    record_request(&state, &config, request, None);
                     let _ = response_tx
                         .send(Ok(Frame::trailers(mock_not_found_trailer())))
                         .await;
@@ -122,6 +313,120 @@ impl Service<http::Request<Incoming>> for GrpcMockService {
     }
 }
 
+/// Responds to a request failed by a matcher or responder: a panic with the
+/// configured code, a timeout with [`Code::DeadlineExceeded`].
+async fn send_handler_error(
+    config: &MockServerConfig,
+    response_tx: &mpsc::Sender<Result<Frame<Bytes>, BoxError>>,
+    err: HandlerError,
+) {
+    let code = match &err {
+        HandlerError::Panic(_) => config.protocol_errors().panic_code,
+        HandlerError::Timeout(_) => Code::DeadlineExceeded,
+    };
+    let _ = response_tx
+        .send(Ok(Frame::trailers(error_trailer(code, err.message()))))
+        .await;
+}
+
+/// Compresses the response messages with the negotiated encoding,
+/// unless disabled by the mock compression settings.
+#[cfg(feature = "compression")]
+fn compress_messages(config: &MockServerConfig, response: &mut Response, encoding: Encoding) {
+    let Some(compression) = response
+        .compression
+        .clone()
+        .or(config.compression().cloned())
+        .filter(|compression| compression.encodings.contains(&encoding))
+    else {
+        return;
+    };
+    let messages = response.body.grpc_messages();
+    response.body = Body::bytes_stream(
+        messages
+            .into_iter()
+            .map(|message| compression.compress_grpc_message(encoding, message)),
+    );
+}
+
+/// Returns the number of complete length-prefixed messages at the start of a buffer.
+fn complete_messages(mut buf: &[u8]) -> usize {
+    let mut count = 0;
+    while buf.len() >= 5 {
+        let len = u32::from_be_bytes(buf[1..5].try_into().unwrap()) as usize;
+        if buf.len() - 5 < len {
+            break;
+        }
+        buf = &buf[5 + len..];
+        count += 1;
+    }
+    count
+}
+
+/// Returns the error of malformed length-prefixed message frames, if any.
+fn frame_error(mut buf: &[u8], compressed: bool) -> Option<String> {
+    while !buf.is_empty() {
+        if buf.len() < 5 {
+            return Some(format!(
+                "incomplete message prefix: expected 5 bytes, received {}",
+                buf.len()
+            ));
+        }
+        match buf[0] {
+            0 => {}
+            1 if compressed => {}
+            1 => return Some("compressed message without grpc-encoding".into()),
+            flag => return Some(format!("invalid compressed flag: {flag}")),
+        }
+        let len = u32::from_be_bytes(buf[1..5].try_into().unwrap()) as usize;
+        if buf.len() - 5 < len {
+            return Some(format!(
+                "incomplete message: expected {len} bytes, received {}",
+                buf.len() - 5
+            ));
+        }
+        buf = &buf[5 + len..];
+    }
+    None
+}
+
+fn error_trailer(code: Code, message: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("grpc-status", code.to_header_value());
+    headers.insert("grpc-message", grpc_message(message));
+    headers
+}
+
+/// Bytes percent-encoded in a `grpc-message`, besides non-ASCII bytes.
+const GRPC_MESSAGE: &AsciiSet = &CONTROLS.add(b'%');
+
+/// Returns a `grpc-message` value, percent-encoded as the gRPC spec requires,
+/// e.g. for a message rendered with request data.
+fn grpc_message(message: &str) -> HeaderValue {
+    let encoded = utf8_percent_encode(message, GRPC_MESSAGE).to_string();
+    HeaderValue::from_str(&encoded).unwrap_or_else(|_| HeaderValue::from_static("invalid message"))
+}
+
+fn body_too_large_trailer() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("grpc-status", Code::ResourceExhausted.to_header_value());
+    headers.insert(
+        "grpc-message",
+        HeaderValue::from_static("request body too large"),
+    );
+    headers
+}
+
+fn delegate_unsupported_trailer() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("grpc-status", Code::Unimplemented.to_header_value());
+    headers.insert(
+        "grpc-message",
+        HeaderValue::from_static("delegation is not supported for grpc"),
+    );
+    headers
+}
+
 fn mock_not_found_trailer() -> HeaderMap {
     let mut headers = HeaderMap::new();
     headers.insert("grpc-status", Code::NotFound.to_header_value());
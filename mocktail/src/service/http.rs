@@ -2,63 +2,127 @@
 use std::{convert::Infallible, sync::Arc};
 
 use bytes::{Bytes, BytesMut};
-use futures::{future::BoxFuture, StreamExt};
+use futures::{future::BoxFuture, stream, StreamExt};
 use http::HeaderMap;
 use http_body::{Body as _, Frame};
 use http_body_util::{BodyExt, Empty, Full, StreamBody};
-use hyper::{body::Incoming, service::Service};
-use tokio::sync::mpsc;
+use hyper::service::Service;
+use sha2::{Digest, Sha256};
+use tokio::sync::{mpsc, oneshot};
 use tokio_stream::wrappers::ReceiverStream;
-use tracing::debug;
+use tracing::{debug, warn};
 
-use crate::{request::Request, server::MockServerState};
+use super::{
+    delay, is_cancellation, match_mock, prepare_request, record_decode_error,
+    record_protocol_error, record_request, reset_error, respond, send_body, BoxError,
+    CancellationGuard, GuardedBody, HandlerError, InformationalWriter, RequestBody, StalledBody,
+    TransferMeter,
+};
+#[cfg(feature = "compression")]
+use crate::compression::Encoding;
+use crate::{
+    request::Request,
+    response::{Chunking, Response},
+    server::{MockServerConfig, MockServerState},
+};
 
 /// A type-erased HTTP body.
-pub type BoxBody = http_body_util::combinators::BoxBody<Bytes, hyper::Error>;
-
-const ALLOWED_METHODS: [http::Method; 5] = [
-    http::Method::GET,
-    http::Method::POST,
-    http::Method::PUT,
-    http::Method::HEAD,
-    http::Method::DELETE,
-];
+pub type BoxBody = http_body_util::combinators::BoxBody<Bytes, BoxError>;
 
 /// Mock HTTP service.
 #[derive(Debug, Clone)]
 pub struct HttpMockService {
     state: Arc<MockServerState>,
+    config: Arc<MockServerConfig>,
 }
 
 impl HttpMockService {
-    pub fn new(state: Arc<MockServerState>) -> Self {
-        Self { state }
+    pub fn new(state: Arc<MockServerState>, config: Arc<MockServerConfig>) -> Self {
+        Self { state, config }
+    }
+}
+
+#[cfg(feature = "tower")]
+impl<B> tower_service::Service<http::Request<B>> for HttpMockService
+where
+    B: RequestBody,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        Service::call(self, req)
     }
 }
 
-impl Service<http::Request<Incoming>> for HttpMockService {
+impl<B> Service<http::Request<B>> for HttpMockService
+where
+    B: RequestBody,
+{
     type Response = http::Response<BoxBody>;
     type Error = Infallible;
     type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
 
-    fn call(&self, req: http::Request<Incoming>) -> Self::Future {
+    fn call(&self, req: http::Request<B>) -> Self::Future {
         let state = self.state.clone();
+        let config = self.config.clone();
         let fut = async move {
             debug!(?req, "handling request");
+            let mut meter = TransferMeter::start();
 
-            if !ALLOWED_METHODS.contains(req.method()) {
-                return Ok(http::Response::builder()
-                    .status(http::StatusCode::METHOD_NOT_ALLOWED)
-                    .header("Allow", "GET, POST, PUT, HEAD, DELETE")
-                    .body(empty())
-                    .unwrap());
+            if let Some(code) = config.protocol_errors().grpc_code {
+                if is_grpc(req.headers()) {
+                    let (parts, _) = req.into_parts();
+                    let error = "gRPC request sent to an HTTP server".to_string();
+                    record_protocol_error(&state, &config, parts, error.clone());
+                    return Ok(http::Response::builder()
+                        .header("content-type", "application/grpc")
+                        .header("grpc-status", code.to_header_value())
+                        .header("grpc-message", error)
+                        .body(empty())
+                        .unwrap());
+                }
             }
 
-            let (parts, mut body) = req.into_parts();
+            let (parts, body) = req.into_parts();
+            let mut body = StalledBody::new(body, config.read_stall());
+
+            let limit = config.max_body_size();
+            let content_length = parts
+                .headers
+                .get(http::header::CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<usize>().ok());
+            if limit.is_some_and(|limit| content_length.is_some_and(|len| len > limit)) {
+                let mut request = Request::from_parts(parts).with_transfer(meter.transfer());
+                prepare_request(&state, &config, &mut request);
+                return Ok(payload_too_large(&state, &config, request));
+            }
 
             // Get initial data frame
             let chunk = if !body.is_end_stream() {
-                body.frame().await.unwrap().unwrap().into_data().unwrap() // TODO: handle errors
+                match body.frame().await {
+                    Some(Ok(frame)) => frame.into_data().unwrap_or_default(),
+                    Some(Err(err)) => {
+                        return Ok(decode_error_response(
+                            &state,
+                            &config,
+                            parts,
+                            Bytes::new(),
+                            meter,
+                            err.into(),
+                        ));
+                    }
+                    None => Bytes::default(),
+                }
             } else {
                 Bytes::default()
             };
@@ -66,91 +130,189 @@ impl Service<http::Request<Incoming>> for HttpMockService {
 
             if body.is_end_stream() {
                 // Process as unary
-                // Match request to mock
-                let request = Request::from_parts(parts).with_body(chunk);
-                let mock = state.mocks().match_by_request(&request);
-                if let Some(mock) = mock {
-                    debug!("mock found, sending response");
-                    let response = mock.response;
-                    let mut body = response.body().clone().as_bytes();
-                    if response.is_error() {
-                        if let Some(message) = response.message() {
-                            body = Bytes::copy_from_slice(message.as_bytes());
-                        }
-                    }
-                    let status = response.status().as_http();
-                    let mut res = http::Response::builder()
-                        .status(status)
-                        .body(full(body))
-                        .unwrap();
-                    *res.headers_mut() = response.headers.into();
-                    Ok(res)
-                } else {
-                    debug!(?request, "no mocks found, sending error");
-                    Ok(http::Response::builder()
-                        .status(http::StatusCode::NOT_FOUND)
-                        .body(full(Bytes::from("mock not found")))
-                        .unwrap())
+                meter.chunk(&chunk);
+                if limit.is_some_and(|limit| chunk.len() > limit) {
+                    let mut request = Request::from_parts(parts).with_transfer(meter.transfer());
+                    prepare_request(&state, &config, &mut request);
+                    return Ok(payload_too_large(&state, &config, request));
                 }
+                Ok(unary_response(&state, &config, parts, chunk, meter).await)
             } else {
-                // Process as streaming
-                let mut stream = body.into_data_stream();
+                // Process as streaming, matching from the initial data frame
+                let mut stream = stream::iter([Ok(chunk)]).chain(body.into_data_stream());
 
                 // Create response stream
                 let (response_tx, response_rx) =
-                    mpsc::channel::<Result<Frame<Bytes>, hyper::Error>>(32);
+                    mpsc::channel::<Result<Frame<Bytes>, BoxError>>(32);
                 let response_stream = ReceiverStream::new(response_rx);
                 let response_body = BoxBody::new(StreamBody::new(response_stream));
-                let response = http::Response::builder().body(response_body).unwrap();
+                let response = http::Response::builder()
+                    .header(http::header::DATE, httpdate::fmt_http_date(state.now()))
+                    .body(response_body)
+                    .unwrap();
+                // With a body size limit, the response head is held until a mock
+                // is matched, so that a request failing before is answered with
+                // an error status, e.g. `413 Payload Too Large`
+                let (mut head, head_rx) = match limit {
+                    Some(_) => {
+                        let (tx, rx) = oneshot::channel::<http::Response<BoxBody>>();
+                        (Some(tx), Some(rx))
+                    }
+                    None => (None, None),
+                };
 
                 // Spawn task to handle request
                 tokio::spawn(async move {
-                    let mut request = Request::from_parts(parts);
+                    let mut request = Request::from_parts(parts).with_transfer(meter.transfer());
+                    prepare_request(&state, &config, &mut request);
                     let mut matched = false;
+                    // The last request matched, and the ID of its mock
+                    let mut last_match = None;
                     let mut buf = BytesMut::new();
-                    buf.extend(chunk);
+                    let mut hasher = Sha256::new();
 
-                    while let Some(Ok(chunk)) = stream.next().await {
+                    while let Some(chunk) = stream.next().await {
+                        let chunk = match chunk {
+                            Ok(chunk) => chunk,
+                            Err(err) => {
+                                // The response status was sent, the response body ends
+                                let err: BoxError = err.into();
+                                if is_cancellation(&err) {
+                                    debug!("request stream cancelled by the client");
+                                    let (request, mock_id) = last_match.unwrap_or((request, None));
+                                    state.record_cancellation(request, mock_id, meter.elapsed());
+                                    return;
+                                }
+                                let error = format!("failed to receive request body: {err}");
+                                record_decode_error(&state, &config, request, error.clone());
+                                if let Some(head) = head.take() {
+                                    let _ = head.send(decode_error(&config, error));
+                                }
+                                return;
+                            }
+                        };
                         debug!(?chunk, "received chunk");
+                        meter.chunk(&chunk);
+                        if limit.is_some_and(|limit| meter.transfer().bytes > limit) {
+                            match head.take() {
+                                Some(head) => {
+                                    let request = request.with_transfer(meter.transfer());
+                                    let _ = head.send(payload_too_large(&state, &config, request));
+                                }
+                                None => {
+                                    // The responses were sent and recorded with their
+                                    // matches, the rest of the body is not received
+                                    debug!("request body exceeds max body size, closing stream");
+                                }
+                            }
+                            return;
+                        }
                         // Add chunk to body buffer
+                        hasher.update(&chunk);
                         buf.extend(chunk);
 
                         // Match request to mock
-                        request = request.with_body(buf.clone().freeze());
-                        let mock = state.mocks().match_by_request(&request);
+                        request = request
+                            .with_body(buf.clone().freeze())
+                            .with_body_sha256(hasher.clone().finalize().into())
+                            .with_transfer(meter.transfer());
+                        let mock = match match_mock(&state, &config, &mut request).await {
+                            Ok(mock) => mock,
+                            Err(err) => {
+                                // The response status was sent, the message is sent as the body
+                                let message = Bytes::copy_from_slice(err.message().as_bytes());
+                                let _ = response_tx.send(Ok(Frame::data(message))).await;
+                                return;
+                            }
+                        };
                         if let Some(mock) = mock {
                             matched = true;
-                            debug!("mock found, sending response");
-                            let mut response = mock.response;
-                            // Send data frames
-                            if !response.body().is_empty() {
-                                while let Some(chunk) = response.body.next().await {
-                                    let _ = response_tx.send(Ok(Frame::data(chunk))).await;
+                            // Release the response head
+                            head = None;
+                            debug!(mock_id = %mock.id, labels = ?mock.labels, "mock found, sending response");
+                            record_request(&state, &config, request.clone(), Some(&mock));
+                            last_match = Some((request.clone(), Some(mock.id)));
+                            let prefix = mock.matches_prefix();
+                            let send = async {
+                                let mut response = match respond(&state, &config, mock, &request)
+                                    .await
+                                {
+                                    Ok(response) => response,
+                                    Err(err) => {
+                                        let message =
+                                            Bytes::copy_from_slice(err.message().as_bytes());
+                                        let _ = response_tx.send(Ok(Frame::data(message))).await;
+                                        return false;
+                                    }
+                                };
+                                delay(&response).await;
+                                // Send data frames
+                                if !send_body(&response_tx, &mut response).await {
+                                    return false;
+                                }
+                                if response.is_error() {
+                                    let message = response
+                                        .message()
+                                        .map(|s| Bytes::copy_from_slice(s.as_bytes()))
+                                        .unwrap_or_default();
+                                    let _ = response_tx.send(Ok(Frame::data(message))).await;
+                                }
+                                // Send trailers frame
+                                let trailers = HeaderMap::from(response.headers().clone());
+                                let _ = response_tx.send(Ok(Frame::trailers(trailers))).await;
+                                true
+                            };
+                            // The response body is dropped when the client disconnects
+                            let sent = tokio::select! {
+                                biased;
+                                _ = response_tx.closed() => None,
+                                sent = send => Some(sent),
+                            };
+                            match sent {
+                                Some(true) => {}
+                                Some(false) => return,
+                                None => {
+                                    debug!("response stream closed by the client");
+                                    let (request, mock_id) = last_match.unwrap();
+                                    state.record_cancellation(request, mock_id, meter.elapsed());
+                                    return;
                                 }
                             }
-                            if response.is_error() {
-                                let message = response
-                                    .message()
-                                    .map(|s| Bytes::copy_from_slice(s.as_bytes()))
-                                    .unwrap_or_default();
-                                let _ = response_tx.send(Ok(Frame::data(message))).await;
+                            if prefix {
+                                // Receive the rest of the request without matching
+                                while stream.next().await.is_some() {}
+                                return;
                             }
-                            // Send trailers frame
-                            let trailers = HeaderMap::from(response.headers().clone());
-                            let _ = response_tx.send(Ok(Frame::trailers(trailers))).await;
                             // Clear body buffer
                             buf.clear();
+                            hasher = Sha256::new();
                         }
                     }
                     debug!("request stream closed");
                     if !matched {
                         debug!(?request, "no mocks found, sending error");
+                        record_request(&state, &config, request, None);
+                        if let Some(head) = head.take() {
+                            let _ = head.send(
+                                http::Response::builder()
+                                    .status(http::StatusCode::NOT_FOUND)
+                                    .body(full(Bytes::from("mock not found")))
+                                    .unwrap(),
+                            );
+                            return;
+                        }
                         // Send data frame with message
                         let _ = response_tx
                             .send(Ok(Frame::data("mock not found".into())))
                             .await;
                     }
                 });
+                if let Some(head_rx) = head_rx {
+                    // The request failed or was not matched before the head was released
+                    if let Ok(response) = head_rx.await {
+                        return Ok(response);
+                    }
+                }
                 Ok(response)
             }
         };
@@ -158,6 +320,288 @@ impl Service<http::Request<Incoming>> for HttpMockService {
     }
 }
 
+/// Compresses a response body with the first enabled encoding accepted by
+/// the client, unless overridden by the mock compression settings.
+#[cfg(feature = "compression")]
+fn compress(
+    config: &MockServerConfig,
+    response: &Response,
+    body: Bytes,
+    accept_encoding: Option<&str>,
+) -> (Bytes, Option<Encoding>) {
+    let compression = response
+        .compression
+        .as_ref()
+        .or(config.compression())
+        .filter(|compression| {
+            body.len() >= compression.min_size && response.headers.get("content-encoding").is_none()
+        });
+    let encoding = compression
+        .zip(accept_encoding)
+        .and_then(|(compression, accept)| compression.negotiate(accept));
+    match compression.zip(encoding) {
+        Some((compression, encoding)) => (compression.compress(encoding, &body), Some(encoding)),
+        None => (body, None),
+    }
+}
+
+/// Records a request with a body exceeding the max body size as unmatched,
+/// responding with `413 Payload Too Large`.
+fn payload_too_large(
+    state: &MockServerState,
+    config: &MockServerConfig,
+    request: Request,
+) -> http::Response<BoxBody> {
+    debug!("request body exceeds max body size, sending error");
+    record_request(state, config, request, None);
+    http::Response::builder()
+        .status(http::StatusCode::PAYLOAD_TOO_LARGE)
+        .body(full(Bytes::from("payload too large")))
+        .unwrap()
+}
+
+/// Records a request with a body that failed to be received,
+/// responding with the configured status.
+///
+/// Requests cancelled by the client are recorded as cancelled instead.
+fn decode_error_response(
+    state: &MockServerState,
+    config: &MockServerConfig,
+    parts: http::request::Parts,
+    body: Bytes,
+    meter: TransferMeter,
+    err: BoxError,
+) -> http::Response<BoxBody> {
+    let error = format!("failed to receive request body: {err}");
+    let mut request = Request::from_parts(parts)
+        .with_body(body)
+        .with_transfer(meter.transfer());
+    prepare_request(state, config, &mut request);
+    if is_cancellation(&err) {
+        debug!("request body cancelled by the client");
+        state.record_cancellation(request, None, meter.elapsed());
+    } else {
+        record_decode_error(state, config, request, error.clone());
+    }
+    decode_error(config, error)
+}
+
+/// Responds to a request with a body that failed to be received.
+fn decode_error(config: &MockServerConfig, error: String) -> http::Response<BoxBody> {
+    http::Response::builder()
+        .status(config.protocol_errors().decode_status.as_http())
+        .body(full(Bytes::from(error)))
+        .unwrap()
+}
+
+/// Returns true if the request is a gRPC request.
+fn is_grpc(headers: &HeaderMap) -> bool {
+    headers
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/grpc"))
+}
+
+/// Matches a unary request to a mock and builds the response.
+async fn unary_response(
+    state: &Arc<MockServerState>,
+    config: &MockServerConfig,
+    parts: http::request::Parts,
+    body: Bytes,
+    meter: TransferMeter,
+) -> http::Response<BoxBody> {
+    let writer = parts.extensions.get::<InformationalWriter>().cloned();
+    let version = parts.version;
+    let mut request = Request::from_parts(parts)
+        .with_body(body)
+        .with_transfer(meter.transfer());
+    prepare_request(state, config, &mut request);
+    let accept_encoding = request
+        .headers
+        .get("accept-encoding")
+        .map(|value| value.to_string());
+
+    // Replay the response recorded for an idempotency key
+    let key = config
+        .idempotency_header()
+        .and_then(|name| request.headers.get(name))
+        .map(|value| (request.namespace.clone(), value.to_string()));
+    if let Some(key) = &key {
+        let cached = state.idempotent_responses.read().unwrap().get(key).cloned();
+        if let Some((mock, response)) = cached {
+            debug!(?key, "idempotency key found, replaying response");
+            record_request(state, config, request.clone(), Some(&mock));
+            let guard = CancellationGuard::new(state.clone(), request, mock.id, meter);
+            send_informational(writer.as_ref(), version, &response).await;
+            delay(&response).await;
+            return guarded(
+                http_response(state, config, response, accept_encoding.as_deref()),
+                guard,
+            );
+        }
+    }
+
+    // Match request to mock
+    let mock = match match_mock(state, config, &mut request).await {
+        Ok(mock) => mock,
+        Err(err) => return handler_error_response(config, err),
+    };
+    record_request(state, config, request.clone(), mock.as_ref());
+    if let Some(mock) = mock {
+        debug!(mock_id = %mock.id, labels = ?mock.labels, "mock found, sending response");
+        let mut guard = CancellationGuard::new(state.clone(), request.clone(), mock.id, meter);
+        let response = match respond(state, config, mock.clone(), &request).await {
+            Ok(response) => response,
+            Err(err) => {
+                guard.disarm();
+                return handler_error_response(config, err);
+            }
+        };
+        if let Some(key) = key {
+            state
+                .idempotent_responses
+                .write()
+                .unwrap()
+                .insert(key, (mock, response.clone()));
+        }
+        send_informational(writer.as_ref(), version, &response).await;
+        delay(&response).await;
+        guarded(
+            http_response(state, config, response, accept_encoding.as_deref()),
+            guard,
+        )
+    } else {
+        debug!(?request, "no mocks found, sending error");
+        http::Response::builder()
+            .status(http::StatusCode::NOT_FOUND)
+            .body(full(Bytes::from("mock not found")))
+            .unwrap()
+    }
+}
+
+/// Responds to a request failed by a matcher or responder: a panic with the
+/// configured status, a timeout with `504 Gateway Timeout`.
+fn handler_error_response(config: &MockServerConfig, err: HandlerError) -> http::Response<BoxBody> {
+    let status = match &err {
+        HandlerError::Panic(_) => config.protocol_errors().panic_status.as_http(),
+        HandlerError::Timeout(_) => http::StatusCode::GATEWAY_TIMEOUT,
+    };
+    http::Response::builder()
+        .status(status)
+        .body(full(Bytes::copy_from_slice(err.message().as_bytes())))
+        .unwrap()
+}
+
+/// Records the cancellation of the request if the response body is dropped
+/// before it is complete.
+fn guarded(response: http::Response<BoxBody>, guard: CancellationGuard) -> http::Response<BoxBody> {
+    response.map(|inner| BoxBody::new(GuardedBody { inner, guard }))
+}
+
+/// Sends the informational responses of a mock response.
+///
+/// Informational responses are only supported on HTTP/1.1 connections.
+async fn send_informational(
+    writer: Option<&InformationalWriter>,
+    version: http::Version,
+    response: &Response,
+) {
+    if response.informational.is_empty() {
+        return;
+    }
+    let Some(writer) = writer.filter(|_| version == http::Version::HTTP_11) else {
+        debug!(?version, "informational responses not supported, skipping");
+        return;
+    };
+    for (status, headers) in &response.informational {
+        debug!(?status, "sending informational response");
+        if let Err(err) = writer.write(status, headers).await {
+            debug!("informational response error: {err}");
+            return;
+        }
+    }
+}
+
+/// Builds a unary HTTP response from a mock response, compressing the body
+/// with an encoding accepted by the client if configured.
+#[cfg_attr(not(feature = "compression"), allow(unused_variables))]
+fn http_response(
+    state: &MockServerState,
+    config: &MockServerConfig,
+    response: Response,
+    accept_encoding: Option<&str>,
+) -> http::Response<BoxBody> {
+    let mut body = response.body().clone().as_bytes();
+    if response.is_error() {
+        if let Some(message) = response.message() {
+            body = Bytes::copy_from_slice(message.as_bytes());
+        }
+    }
+    #[cfg(feature = "compression")]
+    let (mut body, encoding) = compress(config, &response, body, accept_encoding);
+    let status = response.status().as_http();
+    let body = match (response.reset, response.chunking) {
+        (Some(reset), chunking) => {
+            debug!(?reset, "resetting stream");
+            body.truncate(reset.after);
+            let frames = paced(body, chunking.unwrap_or_default())
+                .chain(stream::iter([Err(reset_error(reset.reason))]));
+            BoxBody::new(StreamBody::new(frames))
+        }
+        (None, Some(chunking)) => BoxBody::new(StreamBody::new(paced(body, chunking))),
+        (None, None) => full(body),
+    };
+    let mut res = http::Response::builder().status(status).body(body).unwrap();
+    if let Some(reason) = response.reason {
+        match hyper::ext::ReasonPhrase::try_from(reason) {
+            Ok(reason) => {
+                res.extensions_mut().insert(reason);
+            }
+            Err(err) => warn!("invalid reason phrase, sending the canonical reason: {err}"),
+        }
+    }
+    let now = state.now();
+    let headers = res.headers_mut();
+    *headers = response.headers.into();
+    if !headers.contains_key(http::header::DATE) {
+        headers.insert(
+            http::header::DATE,
+            httpdate::fmt_http_date(now).parse().unwrap(),
+        );
+    }
+    #[cfg(feature = "compression")]
+    if let Some(encoding) = encoding {
+        headers.insert(
+            http::header::CONTENT_ENCODING,
+            http::HeaderValue::from_static(encoding.as_str()),
+        );
+        headers.append(
+            http::header::VARY,
+            http::HeaderValue::from_static("accept-encoding"),
+        );
+    }
+    if let Some(delay) = response.retry_after_date {
+        headers.insert(
+            http::header::RETRY_AFTER,
+            httpdate::fmt_http_date(now + delay).parse().unwrap(),
+        );
+    }
+    res
+}
+
+/// Splits a body into data frames paced by the chunking.
+fn paced(
+    body: Bytes,
+    chunking: Chunking,
+) -> impl futures::Stream<Item = Result<Frame<Bytes>, BoxError>> + Send + Sync + 'static {
+    stream::iter(chunking.split(body).into_iter().enumerate()).then(move |(i, frame)| async move {
+        if i > 0 && !chunking.interval.is_zero() {
+            tokio::time::sleep(chunking.interval).await;
+        }
+        Ok(Frame::data(frame))
+    })
+}
+
 pub fn full(data: Bytes) -> BoxBody {
     Full::new(data).map_err(|err| match err {}).boxed()
 }
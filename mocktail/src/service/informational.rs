@@ -0,0 +1,91 @@
+//! Informational responses
+use std::{
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::{headers::Headers, status::StatusCode};
+
+/// A connection stream shared with the service, so that informational
+/// responses can be written ahead of the final response.
+///
+/// hyper does not support sending informational responses from a service,
+/// so they are written directly to HTTP/1 connections while the final
+/// response is pending.
+pub(crate) struct SharedIo<T>(Arc<Mutex<T>>);
+
+impl<T> SharedIo<T>
+where
+    T: AsyncWrite + Send + Unpin + 'static,
+{
+    pub(crate) fn new(io: T) -> Self {
+        Self(Arc::new(Mutex::new(io)))
+    }
+
+    /// Returns a writer for informational responses.
+    pub(crate) fn writer(&self) -> InformationalWriter {
+        InformationalWriter(self.0.clone())
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for SharedIo<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.0.lock().unwrap()).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for SharedIo<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut *self.0.lock().unwrap()).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.0.lock().unwrap()).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.0.lock().unwrap()).poll_shutdown(cx)
+    }
+}
+
+/// Writes informational responses to an HTTP/1 connection,
+/// passed to the service as a request extension.
+#[derive(Clone)]
+pub(crate) struct InformationalWriter(Arc<Mutex<dyn AsyncWrite + Send + Unpin>>);
+
+impl InformationalWriter {
+    /// Writes an informational response.
+    pub(crate) async fn write(&self, status: &StatusCode, headers: &Headers) -> io::Result<()> {
+        let reason = status.as_http().canonical_reason().unwrap_or_default();
+        let mut head = format!("HTTP/1.1 {} {reason}\r\n", status.as_u16());
+        for (name, value) in headers.iter() {
+            head.push_str(&format!("{}: {}\r\n", name.as_str(), value.as_str()));
+        }
+        head.push_str("\r\n");
+        let buf = head.as_bytes();
+        let mut written = 0;
+        while written < buf.len() {
+            let n = std::future::poll_fn(|cx| {
+                Pin::new(&mut *self.0.lock().unwrap()).poll_write(cx, &buf[written..])
+            })
+            .await?;
+            if n == 0 {
+                return Err(io::ErrorKind::WriteZero.into());
+            }
+            written += n;
+        }
+        std::future::poll_fn(|cx| Pin::new(&mut *self.0.lock().unwrap()).poll_flush(cx)).await
+    }
+}
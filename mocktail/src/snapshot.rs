@@ -0,0 +1,247 @@
+//! Interaction snapshots
+use std::{collections::BTreeMap, path::Path};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{body::Body, journal::Journal, mock_set::MockSet, verify::VerificationError};
+
+/// Environment variable that, when set, overwrites snapshot files
+/// with the current interactions instead of comparing them.
+pub const UPDATE_SNAPSHOTS_ENV: &str = "MOCKTAIL_UPDATE_SNAPSHOTS";
+
+/// Headers excluded from snapshots by default, as they vary between
+/// runs or client versions.
+pub const DEFAULT_IGNORED_HEADERS: &[&str] = &[
+    "accept-encoding",
+    "content-length",
+    "date",
+    "host",
+    "te",
+    "traceparent",
+    "tracestate",
+    "user-agent",
+];
+
+/// A recorded interaction: a request and the response of the mock it matched.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    pub method: String,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub body: Value,
+    /// The matched mock response, if any.
+    #[serde(default)]
+    pub response: Option<SnapshotResponse>,
+}
+
+/// A recorded mock response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotResponse {
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub body: Value,
+}
+
+/// A snapshot of the interactions with a mock server, compared
+/// against a golden file to catch unintended client changes.
+///
+/// Bodies are stored as JSON if valid, otherwise as text, or as
+/// `{"base64": ...}` for binary bodies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    entries: Vec<SnapshotEntry>,
+}
+
+impl Snapshot {
+    /// Creates a snapshot of the journal, with the responses of the matched mocks.
+    pub fn new(journal: &Journal, mocks: &MockSet) -> Self {
+        let entries = journal
+            .iter()
+            .map(|entry| {
+                let request = &entry.request;
+                let headers = request
+                    .headers()
+                    .iter()
+                    .filter(|(name, _)| !DEFAULT_IGNORED_HEADERS.contains(&name.as_str()))
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect();
+                let response = entry
+                    .mock_id
+                    .and_then(|id| mocks.find(|mock| mock.id == id))
+                    .map(|mock| SnapshotResponse {
+                        status: mock.response().status().as_u16(),
+                        body: body_value(mock.response().body()),
+                    });
+                SnapshotEntry {
+                    method: request.method().to_string(),
+                    path: request.path().to_string(),
+                    query: request.query().map(Into::into),
+                    headers,
+                    body: body_value(request.body()),
+                    response,
+                }
+            })
+            .collect();
+        Self { entries }
+    }
+
+    /// Returns the entries.
+    pub fn entries(&self) -> &[SnapshotEntry] {
+        &self.entries
+    }
+
+    /// Excludes a header from all entries.
+    pub fn ignore_header(mut self, name: &str) -> Self {
+        let name = name.to_lowercase();
+        for entry in &mut self.entries {
+            entry.headers.remove(&name);
+        }
+        self
+    }
+
+    /// Excludes headers from all entries.
+    pub fn without_headers(mut self) -> Self {
+        for entry in &mut self.entries {
+            entry.headers.clear();
+        }
+        self
+    }
+
+    /// Serializes the snapshot as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.entries).unwrap()
+    }
+
+    /// Deserializes a snapshot from JSON.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        Ok(Self {
+            entries: serde_json::from_str(json)?,
+        })
+    }
+
+    /// Compares the snapshot to the golden file.
+    ///
+    /// The file is written if it does not exist, or if the
+    /// [`UPDATE_SNAPSHOTS_ENV`] environment variable is set.
+    pub fn verify_file(&self, path: impl AsRef<Path>) -> Result<(), VerificationError> {
+        let path = path.as_ref();
+        let fail = |failure: String| VerificationError {
+            failures: vec![failure],
+        };
+        if std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some() || !path.exists() {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|err| fail(format!("snapshot {}: {err}", path.display())))?;
+            }
+            return std::fs::write(path, self.to_json() + "\n")
+                .map_err(|err| fail(format!("snapshot {}: {err}", path.display())));
+        }
+        let expected = std::fs::read_to_string(path)
+            .map_err(|err| fail(format!("snapshot {}: {err}", path.display())))
+            .and_then(|json| {
+                Self::from_json(&json)
+                    .map_err(|err| fail(format!("snapshot {}: {err}", path.display())))
+            })?;
+        self.verify(&expected)
+    }
+
+    /// Compares the snapshot to an expected snapshot.
+    pub fn verify(&self, expected: &Snapshot) -> Result<(), VerificationError> {
+        let mut failures = Vec::new();
+        if self.entries.len() != expected.entries.len() {
+            failures.push(format!(
+                "expected {} interactions, got {}",
+                expected.entries.len(),
+                self.entries.len()
+            ));
+        }
+        for (i, (actual, expected)) in self.entries.iter().zip(&expected.entries).enumerate() {
+            if actual != expected {
+                failures.push(format!(
+                    "interaction {i}:\n  expected: {}\n  actual:   {}",
+                    serde_json::to_string(expected).unwrap(),
+                    serde_json::to_string(actual).unwrap()
+                ));
+            }
+        }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(VerificationError { failures })
+        }
+    }
+}
+
+/// Returns a body as stored in snapshots.
+pub(crate) fn body_value(body: &Body) -> Value {
+    let bytes = body.clone().as_bytes();
+    if bytes.is_empty() {
+        return Value::Null;
+    }
+    if let Ok(value) = serde_json::from_slice(&bytes) {
+        return value;
+    }
+    match std::str::from_utf8(&bytes) {
+        Ok(text) => Value::String(text.into()),
+        Err(_) => {
+            use base64::Engine;
+            serde_json::json!({ "base64": base64::engine::general_purpose::STANDARD.encode(&bytes) })
+        }
+    }
+}
+
+/// Returns the bytes of a body stored by [`body_value`].
+pub(crate) fn value_bytes(value: &Value) -> Bytes {
+    use base64::Engine;
+    match value {
+        Value::Null => Bytes::new(),
+        Value::String(text) => Bytes::copy_from_slice(text.as_bytes()),
+        Value::Object(object) if object.len() == 1 => {
+            match object
+                .get("base64")
+                .and_then(Value::as_str)
+                .map(|encoded| base64::engine::general_purpose::STANDARD.decode(encoded))
+            {
+                Some(Ok(bytes)) => bytes.into(),
+                _ => serde_json::to_vec(value).unwrap().into(),
+            }
+        }
+        value => serde_json::to_vec(value).unwrap().into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Method, Request};
+
+    #[test]
+    fn test_verify() {
+        let mut journal = Journal::new();
+        let request = Request::new(Method::POST, "http://localhost/a?x=1".parse().unwrap())
+            .with_body(Body::bytes(r#"{"name":"dan"}"#));
+        journal.record(request, None);
+        let snapshot = Snapshot::new(&journal, &MockSet::new());
+        let json = snapshot.to_json();
+        assert!(json.contains(r#""name": "dan""#));
+        let expected = Snapshot::from_json(&json).unwrap();
+        assert!(snapshot.verify(&expected).is_ok());
+
+        let mut journal = Journal::new();
+        let request = Request::new(Method::POST, "http://localhost/a?x=1".parse().unwrap())
+            .with_body(Body::bytes(r#"{"name":"sam"}"#));
+        journal.record(request, None);
+        let changed = Snapshot::new(&journal, &MockSet::new());
+        let err = changed.verify(&expected).unwrap_err();
+        assert!(err.failures()[0].starts_with("interaction 0"));
+        assert!(Snapshot::new(&Journal::new(), &MockSet::new())
+            .verify(&expected)
+            .is_err());
+    }
+}
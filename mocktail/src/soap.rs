@@ -0,0 +1,186 @@
+//! SOAP
+//!
+//! Helpers for mocking SOAP 1.1 and 1.2 services.
+use crate::{request::Request, status::StatusCode};
+
+/// A SOAP version.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd)]
+pub enum SoapVersion {
+    /// SOAP 1.1, sent as `text/xml`.
+    #[default]
+    V11,
+    /// SOAP 1.2, sent as `application/soap+xml`.
+    V12,
+}
+
+impl SoapVersion {
+    /// Returns the envelope namespace.
+    pub fn namespace(&self) -> &'static str {
+        match self {
+            SoapVersion::V11 => "http://schemas.xmlsoap.org/soap/envelope/",
+            SoapVersion::V12 => "http://www.w3.org/2003/05/soap-envelope",
+        }
+    }
+
+    /// Returns the HTTP status of a fault with the code.
+    ///
+    /// SOAP 1.2 `Sender` faults are sent with `400 Bad Request`,
+    /// other faults with `500 Internal Server Error`.
+    pub fn fault_status(&self, code: &str) -> StatusCode {
+        match (self, code) {
+            (SoapVersion::V12, "Sender") => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Returns the content type.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            SoapVersion::V11 => "text/xml; charset=utf-8",
+            SoapVersion::V12 => "application/soap+xml; charset=utf-8",
+        }
+    }
+}
+
+/// Wraps a payload in a SOAP envelope body.
+pub fn envelope(version: SoapVersion, payload: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?><soap:Envelope xmlns:soap="{}"><soap:Body>{payload}</soap:Body></soap:Envelope>"#,
+        version.namespace()
+    )
+}
+
+/// Returns a SOAP envelope with a fault.
+///
+/// The code is a fault code without a prefix, e.g. `Server` or `Client`
+/// for SOAP 1.1, and `Receiver` or `Sender` for SOAP 1.2.
+pub fn fault(version: SoapVersion, code: &str, reason: &str) -> String {
+    let reason = escape(reason);
+    let fault = match version {
+        SoapVersion::V11 => format!(
+            "<soap:Fault><faultcode>soap:{code}</faultcode><faultstring>{reason}</faultstring></soap:Fault>"
+        ),
+        SoapVersion::V12 => format!(
+            r#"<soap:Fault><soap:Code><soap:Value>soap:{code}</soap:Value></soap:Code><soap:Reason><soap:Text xml:lang="en">{reason}</soap:Text></soap:Reason></soap:Fault>"#
+        ),
+    };
+    envelope(version, &fault)
+}
+
+/// Returns the SOAP action of a request, from the SOAP 1.1 `SOAPAction`
+/// header or the SOAP 1.2 `action` content type parameter.
+pub fn action(request: &Request) -> Option<String> {
+    if let Some(value) = request.headers().get("soapaction") {
+        return Some(value.to_string().trim_matches('"').to_string());
+    }
+    let content_type = request.headers().get("content-type")?.to_string();
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        (name.trim().eq_ignore_ascii_case("action"))
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// Selects the text of the nodes at an XPath in an XML document.
+///
+/// Supports a subset of XPath: absolute (`/Envelope/Body/GetUser`) and
+/// descendant (`//GetUser/id`) steps, `*` wildcards, and a trailing
+/// `@attribute` or `text()` step. Element names are matched by local name,
+/// ignoring namespace prefixes. Returns `None` if the document or path is invalid.
+pub fn select(xml: &str, path: &str) -> Option<Vec<String>> {
+    let doc = roxmltree::Document::parse(xml).ok()?;
+    let mut nodes = vec![doc.root()];
+    let mut rest = path;
+    if !rest.starts_with('/') {
+        return None;
+    }
+    while !rest.is_empty() {
+        let descendant = rest.starts_with("//");
+        rest = rest.trim_start_matches('/');
+        let (step, next) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        rest = next;
+        if step.is_empty() {
+            return None;
+        }
+        if let Some(attribute) = step.strip_prefix('@') {
+            if !rest.is_empty() {
+                return None;
+            }
+            return Some(
+                nodes
+                    .iter()
+                    .filter_map(|node| node.attribute(attribute))
+                    .map(Into::into)
+                    .collect(),
+            );
+        }
+        if step == "text()" {
+            if !rest.is_empty() {
+                return None;
+            }
+            break;
+        }
+        let candidates = nodes
+            .iter()
+            .flat_map(|node| {
+                if descendant {
+                    node.descendants().skip(1).collect::<Vec<_>>()
+                } else {
+                    node.children().collect()
+                }
+            })
+            .filter(|node| node.is_element() && (step == "*" || node.tag_name().name() == step));
+        let mut next = Vec::new();
+        for node in candidates {
+            if !next.contains(&node) {
+                next.push(node);
+            }
+        }
+        nodes = next;
+    }
+    Some(
+        nodes
+            .iter()
+            .map(|node| {
+                node.descendants()
+                    .filter(|node| node.is_text())
+                    .filter_map(|node| node.text())
+                    .collect::<String>()
+                    .trim()
+                    .to_string()
+            })
+            .collect(),
+    )
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select() {
+        let xml = envelope(
+            SoapVersion::V11,
+            r#"<m:GetUser xmlns:m="urn:users"><m:id type="int">42</m:id><m:id>7</m:id></m:GetUser>"#,
+        );
+        assert_eq!(
+            select(&xml, "/Envelope/Body/GetUser/id").unwrap(),
+            ["42", "7"]
+        );
+        assert_eq!(select(&xml, "//id/text()").unwrap(), ["42", "7"]);
+        assert_eq!(select(&xml, "//GetUser/*/@type").unwrap(), ["int"]);
+        assert!(select(&xml, "//Missing").unwrap().is_empty());
+        assert!(select(&xml, "Envelope").is_none());
+        assert!(select("<nope", "//id").is_none());
+
+        let xml = fault(SoapVersion::V12, "Sender", "bad <id>");
+        assert_eq!(select(&xml, "//Fault/Reason/Text").unwrap(), ["bad <id>"]);
+    }
+}
@@ -1,5 +1,8 @@
 use std::num::NonZeroU16;
 
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use prost::Message;
+
 use crate::Error;
 
 /// Represents a HTTP status code.
@@ -308,3 +311,50 @@ impl std::fmt::Display for Code {
         std::fmt::Display::fmt(self.description(), f)
     }
 }
+
+/// A typed detail of a gRPC error, packed in a `google.protobuf.Any`,
+/// see [`Then::pb_error_with_details`](crate::mock_builder::Then::pb_error_with_details).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ErrorDetail {
+    #[prost(string, tag = "1")]
+    type_url: String,
+    #[prost(bytes = "vec", tag = "2")]
+    value: Vec<u8>,
+}
+
+impl ErrorDetail {
+    /// Creates a detail from a message, with the type URL of its name,
+    /// e.g. `type.googleapis.com/google.rpc.RetryInfo`.
+    pub fn new<M: prost::Name>(message: M) -> Self {
+        Self::with_type_url(M::type_url(), message)
+    }
+
+    /// Creates a detail from a message with a type URL.
+    pub fn with_type_url(type_url: impl Into<String>, message: impl prost::Message) -> Self {
+        Self {
+            type_url: type_url.into(),
+            value: message.encode_to_vec(),
+        }
+    }
+}
+
+/// A `google.rpc.Status` message.
+#[derive(Clone, PartialEq, prost::Message)]
+struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    code: i32,
+    #[prost(string, tag = "2")]
+    message: String,
+    #[prost(message, repeated, tag = "3")]
+    details: Vec<ErrorDetail>,
+}
+
+/// Returns the `grpc-status-details-bin` value of an error with details.
+pub(crate) fn status_details(code: Code, message: String, details: Vec<ErrorDetail>) -> String {
+    let status = RpcStatus {
+        code: code as i32,
+        message,
+        details,
+    };
+    STANDARD_NO_PAD.encode(status.encode_to_vec())
+}
@@ -0,0 +1,466 @@
+//! Response templates
+//!
+//! Templates are text with `{{ expression }}` placeholders, rendered
+//! with the matched request when a response is sent.
+//!
+//! Expressions are request fields, literals or helper calls:
+//! - `request.method`, `request.path`, `request.body`,
+//!   `request.query.<name>` and `request.headers.<name>`
+//! - `request.peer_addr`, `request.protocol`, e.g. `HTTP/2.0`, and
+//!   `request.alpn`: the details of the connection the request was received on
+//! - `state.<counter>`: the value of a counter when the request was matched,
+//!   before the updates of the matched mock
+//! - `'text'` or `"text"` strings and integers
+//! - `uuid()`: a random UUID
+//! - `now()` or `now(format)`: the server clock time, formatted with
+//!   `%Y`, `%m`, `%d`, `%H`, `%M`, `%S` and `%s` (seconds since the epoch),
+//!   defaulting to RFC 3339
+//! - `random_int(min, max)`: a random integer between `min` and `max` (inclusive)
+//! - `base64(value)`: the value encoded as base64
+//! - `jsonPath(value, path)`: the value at a path, e.g. `$.user.names[0]`,
+//!   of a JSON value
+//! - `fake(kind)`: a fake value, e.g. `fake('email')`; see [`fake::KINDS`]
+use std::{fmt, time::SystemTime};
+
+use base64::Engine;
+use rand::Rng;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    fake::{self, Faker},
+    request::Request,
+};
+
+const HELPERS: &[(&str, &[usize])] = &[
+    ("uuid", &[0]),
+    ("now", &[0, 1]),
+    ("random_int", &[2]),
+    ("base64", &[1]),
+    ("jsonPath", &[2]),
+    ("fake", &[1]),
+];
+
+/// A parsed response template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Literal(Value),
+    Field(Vec<String>),
+    Call(String, Vec<Expr>),
+}
+
+/// The values available while rendering a template.
+pub struct Context<'a, R: Rng> {
+    /// The matched request.
+    pub request: &'a Request,
+    /// The current time of the server clock.
+    pub now: SystemTime,
+    /// The server random number generator.
+    pub rng: &'a mut R,
+}
+
+impl Template {
+    /// Parses a template.
+    pub fn parse(source: impl Into<String>) -> Result<Self, TemplateError> {
+        let source = source.into();
+        let mut segments = Vec::new();
+        let mut rest = source.as_str();
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                segments.push(Segment::Text(rest[..start].to_string()));
+            }
+            let Some(end) = rest[start..].find("}}") else {
+                return Err(TemplateError::Unclosed);
+            };
+            let expr = &rest[start + 2..start + end];
+            segments.push(Segment::Expr(Parser::new(expr).parse()?));
+            rest = &rest[start + end + 2..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Text(rest.to_string()));
+        }
+        Ok(Self { source, segments })
+    }
+
+    /// Returns the template source.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Renders the template.
+    pub fn render<R: Rng>(&self, ctx: &mut Context<'_, R>) -> String {
+        let mut output = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => output.push_str(text),
+                Segment::Expr(expr) => output.push_str(&to_string(&expr.eval(ctx))),
+            }
+        }
+        output
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl Expr {
+    fn eval<R: Rng>(&self, ctx: &mut Context<'_, R>) -> Value {
+        match self {
+            Expr::Literal(value) => value.clone(),
+            Expr::Field(path) => field(ctx.request, path),
+            Expr::Call(name, args) => {
+                let args = args.iter().map(|arg| arg.eval(ctx)).collect::<Vec<_>>();
+                match (name.as_str(), args.as_slice()) {
+                    ("uuid", []) => Uuid::now_v7().to_string().into(),
+                    ("now", []) => format_time(ctx.now, "%Y-%m-%dT%H:%M:%SZ").into(),
+                    ("now", [format]) => format_time(ctx.now, &to_string(format)).into(),
+                    ("random_int", [min, max]) => {
+                        let min = min.as_i64().unwrap_or_default();
+                        let max = max.as_i64().unwrap_or_default().max(min);
+                        ctx.rng.random_range(min..=max).into()
+                    }
+                    ("base64", [value]) => base64::engine::general_purpose::STANDARD
+                        .encode(to_string(value))
+                        .into(),
+                    ("jsonPath", [value, path]) => {
+                        let value = match value {
+                            Value::String(s) => serde_json::from_str(s).unwrap_or(Value::Null),
+                            value => value.clone(),
+                        };
+                        json_path(&value, &to_string(path))
+                    }
+                    ("fake", [kind]) => Faker::with_rng(&mut *ctx.rng)
+                        .kind(&to_string(kind))
+                        .map_or(Value::Null, Value::from),
+                    _ => unreachable!("validated while parsing"),
+                }
+            }
+        }
+    }
+}
+
+/// Returns a request field.
+fn field(request: &Request, path: &[String]) -> Value {
+    let path = path.iter().map(String::as_str).collect::<Vec<_>>();
+    match path.as_slice() {
+        ["request", "method"] => request.method().as_str().into(),
+        ["request", "path"] => request.path().into(),
+        ["request", "body"] => {
+            let body = request.body().clone().as_bytes();
+            serde_json::from_slice(&body)
+                .unwrap_or_else(|_| String::from_utf8_lossy(&body).into_owned().into())
+        }
+        ["request", "query", name] => request
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned().into())
+            .unwrap_or(Value::Null),
+        ["request", "headers", name] => request
+            .headers()
+            .get(&name.to_lowercase())
+            .map(|value| value.to_string().into())
+            .unwrap_or(Value::Null),
+        ["request", "peer_addr"] => request
+            .peer_addr()
+            .map(|addr| addr.to_string().into())
+            .unwrap_or(Value::Null),
+        ["request", "protocol"] => request.protocol().map(Into::into).unwrap_or(Value::Null),
+        ["request", "alpn"] => request
+            .connection_info()
+            .and_then(|info| info.alpn.clone())
+            .map(Into::into)
+            .unwrap_or(Value::Null),
+        ["state", name] => request.interactions.counter(name).into(),
+        _ => Value::Null,
+    }
+}
+
+/// Returns the value at a JSON path, e.g. `$.a.b[0]`.
+fn json_path(value: &Value, path: &str) -> Value {
+    let path = path.trim_start_matches('$');
+    let mut current = value;
+    for part in path.split('.').filter(|part| !part.is_empty()) {
+        let (key, indexes) = part.split_once('[').unwrap_or((part, ""));
+        if !key.is_empty() {
+            match current.get(key) {
+                Some(value) => current = value,
+                None => return Value::Null,
+            }
+        }
+        for index in indexes.split('[').filter(|index| !index.is_empty()) {
+            match index
+                .trim_end_matches(']')
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| current.get(index))
+            {
+                Some(value) => current = value,
+                None => return Value::Null,
+            }
+        }
+    }
+    current.clone()
+}
+
+/// Formats a value for output; strings are written without quotes.
+fn to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Formats a UTC time with `strftime`-style specifiers.
+fn format_time(time: SystemTime, format: &str) -> String {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let (hour, minute, second) = (secs % 86400 / 3600, secs % 3600 / 60, secs % 60);
+    let mut output = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => output.push_str(&format!("{year:04}")),
+            Some('m') => output.push_str(&format!("{month:02}")),
+            Some('d') => output.push_str(&format!("{day:02}")),
+            Some('H') => output.push_str(&format!("{hour:02}")),
+            Some('M') => output.push_str(&format!("{minute:02}")),
+            Some('S') => output.push_str(&format!("{second:02}")),
+            Some('s') => output.push_str(&secs.to_string()),
+            Some(c) => {
+                output.push('%');
+                output.push(c);
+            }
+            None => output.push('%'),
+        }
+    }
+    output
+}
+
+/// Converts days since the Unix epoch to a (year, month, day) date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// A template expression parser.
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, pos: 0 }
+    }
+
+    fn parse(mut self) -> Result<Expr, TemplateError> {
+        let expr = self.expr()?;
+        self.skip_whitespace();
+        if self.pos < self.input.len() {
+            return Err(self.error());
+        }
+        Ok(expr)
+    }
+
+    fn expr(&mut self) -> Result<Expr, TemplateError> {
+        self.skip_whitespace();
+        let rest = &self.input[self.pos..];
+        match rest.chars().next() {
+            Some(quote @ ('\'' | '"')) => {
+                let end = rest[1..].find(quote).ok_or_else(|| self.error())?;
+                self.pos += end + 2;
+                Ok(Expr::Literal(rest[1..end + 1].into()))
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let len = rest[1..]
+                    .find(|c: char| !c.is_ascii_digit())
+                    .map_or(rest.len(), |i| i + 1);
+                let n = rest[..len].parse::<i64>().map_err(|_| self.error())?;
+                self.pos += len;
+                Ok(Expr::Literal(n.into()))
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let len = rest
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.' || c == '-'))
+                    .unwrap_or(rest.len());
+                let name = &rest[..len];
+                self.pos += len;
+                self.skip_whitespace();
+                if self.peek() == Some('(') {
+                    self.pos += 1;
+                    let args = self.args()?;
+                    let Some((_, arities)) = HELPERS.iter().find(|(helper, _)| *helper == name)
+                    else {
+                        return Err(TemplateError::UnknownHelper(name.into()));
+                    };
+                    if !arities.contains(&args.len()) {
+                        return Err(TemplateError::InvalidArguments(name.into()));
+                    }
+                    if let ("fake", [Expr::Literal(Value::String(kind))]) = (name, args.as_slice())
+                    {
+                        if !fake::KINDS.contains(&kind.as_str()) {
+                            return Err(TemplateError::InvalidArguments(name.into()));
+                        }
+                    }
+                    Ok(Expr::Call(name.into(), args))
+                } else {
+                    Ok(Expr::Field(name.split('.').map(String::from).collect()))
+                }
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    fn args(&mut self) -> Result<Vec<Expr>, TemplateError> {
+        let mut args = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(')') {
+            self.pos += 1;
+            return Ok(args);
+        }
+        loop {
+            args.push(self.expr()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(')') => {
+                    self.pos += 1;
+                    return Ok(args);
+                }
+                _ => return Err(self.error()),
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn error(&self) -> TemplateError {
+        TemplateError::InvalidExpression(self.input.trim().into())
+    }
+}
+
+/// Represents errors that can occur while parsing a [`Template`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    #[error("unclosed placeholder")]
+    Unclosed,
+    #[error("invalid expression: {0}")]
+    InvalidExpression(String),
+    #[error("unknown helper: {0}")]
+    UnknownHelper(String),
+    #[error("invalid arguments for helper: {0}")]
+    InvalidArguments(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    use super::*;
+    use crate::{body::Body, request::Method};
+
+    fn render(template: &str, request: &Request) -> String {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut ctx = Context {
+            request,
+            now: SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            rng: &mut rng,
+        };
+        Template::parse(template).unwrap().render(&mut ctx)
+    }
+
+    #[test]
+    fn test_render() {
+        let request = Request::new(
+            Method::POST,
+            "http://localhost/users?page=2".parse().unwrap(),
+        )
+        .with_body(Body::bytes(r#"{"user":{"names":["dan"]}}"#));
+        assert_eq!(
+            render("{{request.method}} {{ request.path }}", &request),
+            "POST /users"
+        );
+        assert_eq!(render("page {{request.query.page}}", &request), "page 2");
+        assert_eq!(
+            render("{{ jsonPath(request.body, '$.user.names[0]') }}", &request),
+            "dan"
+        );
+        assert_eq!(render("{{base64('hello')}}", &request), "aGVsbG8=");
+        assert_eq!(render("{{now()}}", &request), "2023-11-14T22:13:20Z");
+        assert_eq!(
+            render("{{now('%Y/%m/%d %s')}}", &request),
+            "2023/11/14 1700000000"
+        );
+        let n = render("{{random_int(1, 6)}}", &request)
+            .parse::<i64>()
+            .unwrap();
+        assert!((1..=6).contains(&n));
+        assert_eq!(render("{{uuid()}}", &request).len(), 36);
+        assert!(render("{{fake('email')}}", &request).contains('@'));
+
+        let mut request = request;
+        request.interactions.counters.insert("orders".into(), 3);
+        assert_eq!(
+            render("/orders/{{state.orders}}/{{state.missing}}", &request),
+            "/orders/3/0"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Template::parse("{{ a"), Err(TemplateError::Unclosed));
+        assert_eq!(
+            Template::parse("{{ nope() }}"),
+            Err(TemplateError::UnknownHelper("nope".into()))
+        );
+        assert_eq!(
+            Template::parse("{{ uuid(1) }}"),
+            Err(TemplateError::InvalidArguments("uuid".into()))
+        );
+        assert!(Template::parse("{{ base64('a' }}").is_err());
+        assert_eq!(
+            Template::parse("{{ fake('nope') }}"),
+            Err(TemplateError::InvalidArguments("fake".into()))
+        );
+    }
+}
@@ -0,0 +1,250 @@
+//! TLS
+use std::{path::Path, sync::Arc, time::SystemTime};
+
+use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair, SignatureAlgorithm};
+use tokio_rustls::{
+    rustls::{
+        self,
+        crypto::ring,
+        pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer},
+        ServerConfig, ServerConnection,
+    },
+    TlsAcceptor,
+};
+
+use crate::{request::TlsInfo, server::ServerKind, Error};
+
+/// The names the generated server certificate is valid for by default.
+const GENERATED_NAMES: [&str; 5] = ["localhost", "127.0.0.1", "::1", "0.0.0.0", "::"];
+
+/// TLS configuration of a mock server.
+///
+/// Use [`TlsConfig::generate`] for a certificate signed by a generated CA,
+/// which clients can trust with [`MockServer::tls_ca_pem`](crate::server::MockServer::tls_ca_pem)
+/// instead of disabling certificate verification, or [`TlsConfig::from_pem`]
+/// for a certificate chain of your own.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    chain: Vec<CertificateDer<'static>>,
+    key: Arc<PrivateKeyDer<'static>>,
+    ca_pem: Option<String>,
+}
+
+impl TlsConfig {
+    /// Generates a CA and a server certificate signed by it, valid for
+    /// `localhost` and the loopback and unspecified addresses.
+    pub fn generate() -> Self {
+        GeneratedCert::new()
+            .build()
+            .expect("default certificate is valid")
+    }
+
+    /// Creates a configuration from a PEM-encoded certificate chain,
+    /// starting with the server certificate, and private key.
+    pub fn from_pem(cert_pem: &str, key_pem: &str) -> Result<Self, Error> {
+        let chain = CertificateDer::pem_slice_iter(cert_pem.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| Error::Invalid(format!("invalid certificate pem: {err}")))?;
+        if chain.is_empty() {
+            return Err(Error::Invalid("no certificates found in pem".into()));
+        }
+        let key = PrivateKeyDer::from_pem_slice(key_pem.as_bytes())
+            .map_err(|err| Error::Invalid(format!("invalid private key pem: {err}")))?;
+        let config = Self {
+            chain,
+            key: Arc::new(key),
+            ca_pem: None,
+        };
+        config
+            .server_config()
+            .map_err(|err| Error::Invalid(format!("invalid certificate or key: {err}")))?;
+        Ok(config)
+    }
+
+    /// Creates a configuration from PEM files, see [`TlsConfig::from_pem`].
+    pub fn from_pem_files(
+        cert_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+    ) -> Result<Self, Error> {
+        let cert_pem = std::fs::read_to_string(cert_path)?;
+        let key_pem = std::fs::read_to_string(key_path)?;
+        Self::from_pem(&cert_pem, &key_pem)
+    }
+
+    /// Returns the PEM-encoded generated CA certificate.
+    pub fn ca_pem(&self) -> Option<&str> {
+        self.ca_pem.as_deref()
+    }
+
+    /// Returns the DER-encoded server certificate.
+    pub fn cert_der(&self) -> &[u8] {
+        &self.chain[0]
+    }
+
+    /// Creates an acceptor advertising the protocols of the server kind with ALPN.
+    pub(crate) fn acceptor(&self, kind: ServerKind) -> TlsAcceptor {
+        // Validated when the configuration is created
+        let mut config = self.server_config().expect("invalid certificate or key");
+        config.alpn_protocols = match kind {
+            ServerKind::Http => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+            ServerKind::Grpc => vec![b"h2".to_vec()],
+        };
+        TlsAcceptor::from(Arc::new(config))
+    }
+
+    fn server_config(&self) -> Result<ServerConfig, rustls::Error> {
+        ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(self.chain.clone(), self.key.clone_key())
+    }
+}
+
+/// Returns the protocol negotiated with ALPN and the session details of a connection.
+pub(crate) fn session_info(conn: &ServerConnection) -> (Option<String>, TlsInfo) {
+    let alpn = conn
+        .alpn_protocol()
+        .map(|protocol| String::from_utf8_lossy(protocol).into_owned());
+    let info = TlsInfo {
+        version: conn
+            .protocol_version()
+            .map(|version| format!("{version:?}"))
+            .unwrap_or_default(),
+        cipher_suite: conn
+            .negotiated_cipher_suite()
+            .map(|suite| format!("{:?}", suite.suite()))
+            .unwrap_or_default(),
+        server_name: conn.server_name().map(Into::into),
+    };
+    (alpn, info)
+}
+
+/// A builder for a generated server certificate, signed by a generated CA.
+///
+/// Customize the names and validity, e.g. to test client hostname
+/// verification or expired certificate errors.
+#[derive(Debug, Clone)]
+pub struct GeneratedCert {
+    common_name: String,
+    subject_alt_names: Vec<String>,
+    not_before: Option<SystemTime>,
+    not_after: Option<SystemTime>,
+    algorithm: KeyAlgorithm,
+}
+
+impl GeneratedCert {
+    /// Creates a builder for a certificate valid for `localhost`
+    /// and the loopback and unspecified addresses.
+    pub fn new() -> Self {
+        Self {
+            common_name: "localhost".into(),
+            subject_alt_names: GENERATED_NAMES.map(String::from).to_vec(),
+            not_before: None,
+            not_after: None,
+            algorithm: KeyAlgorithm::default(),
+        }
+    }
+
+    /// Sets the subject common name.
+    pub fn common_name(mut self, name: impl Into<String>) -> Self {
+        self.common_name = name.into();
+        self
+    }
+
+    /// Sets the subject alternative names, DNS names or IP addresses.
+    pub fn subject_alt_names(mut self, names: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.subject_alt_names = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the start of the validity period.
+    pub fn not_before(mut self, time: SystemTime) -> Self {
+        self.not_before = Some(time);
+        self
+    }
+
+    /// Sets the end of the validity period, e.g. in the past for an expired certificate.
+    pub fn not_after(mut self, time: SystemTime) -> Self {
+        self.not_after = Some(time);
+        self
+    }
+
+    /// Sets the key and signature algorithm of the server and CA certificates,
+    /// e.g. to test client algorithm support.
+    pub fn algorithm(mut self, algorithm: KeyAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Generates the CA and server certificate.
+    pub fn build(self) -> Result<TlsConfig, Error> {
+        let ca_key = KeyPair::generate_for(self.algorithm.as_rcgen())
+            .map_err(|err| Error::Invalid(format!("unsupported signature algorithm: {err}")))?;
+        let mut ca_params = CertificateParams::default();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        ca_params
+            .distinguished_name
+            .push(DnType::CommonName, "mocktail CA");
+        let ca = ca_params
+            .self_signed(&ca_key)
+            .map_err(|err| Error::Invalid(format!("failed to generate CA certificate: {err}")))?;
+
+        let key = KeyPair::generate_for(self.algorithm.as_rcgen())
+            .map_err(|err| Error::Invalid(format!("unsupported signature algorithm: {err}")))?;
+        let mut params = CertificateParams::new(self.subject_alt_names)
+            .map_err(|err| Error::Invalid(format!("invalid subject alternative name: {err}")))?;
+        params
+            .distinguished_name
+            .push(DnType::CommonName, self.common_name);
+        if let Some(time) = self.not_before {
+            params.not_before = time.into();
+        }
+        if let Some(time) = self.not_after {
+            params.not_after = time.into();
+        }
+        let cert = params.signed_by(&key, &ca, &ca_key).map_err(|err| {
+            Error::Invalid(format!("failed to generate server certificate: {err}"))
+        })?;
+
+        let config = TlsConfig {
+            chain: vec![cert.der().clone()],
+            key: Arc::new(PrivatePkcs8KeyDer::from(key.serialize_der()).into()),
+            ca_pem: Some(ca.pem()),
+        };
+        config
+            .server_config()
+            .map_err(|err| Error::Invalid(format!("invalid certificate or key: {err}")))?;
+        Ok(config)
+    }
+}
+
+impl Default for GeneratedCert {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The key and signature algorithm of generated certificates.
+///
+/// RSA keys and weak signature hashes, e.g. SHA-1, cannot be generated;
+/// use [`TlsConfig::from_pem`] with a certificate of your own to test those.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAlgorithm {
+    /// ECDSA with the P-256 curve and SHA-256.
+    #[default]
+    EcdsaP256,
+    /// ECDSA with the P-384 curve and SHA-384.
+    EcdsaP384,
+    /// Ed25519.
+    Ed25519,
+}
+
+impl KeyAlgorithm {
+    fn as_rcgen(self) -> &'static SignatureAlgorithm {
+        match self {
+            KeyAlgorithm::EcdsaP256 => &rcgen::PKCS_ECDSA_P256_SHA256,
+            KeyAlgorithm::EcdsaP384 => &rcgen::PKCS_ECDSA_P384_SHA384,
+            KeyAlgorithm::Ed25519 => &rcgen::PKCS_ED25519,
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Trace context and correlation headers
+use percent_encoding::percent_decode_str;
+
+/// The W3C trace context header name.
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+/// The W3C baggage header name.
+pub const BAGGAGE_HEADER: &str = "baggage";
+/// The request ID header name.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// A parsed W3C `traceparent` header.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd)]
+pub struct TraceContext {
+    pub version: u8,
+    pub trace_id: String,
+    pub parent_id: String,
+    pub flags: u8,
+}
+
+impl TraceContext {
+    /// Parses a `traceparent` header value, returning `None` if it is malformed.
+    ///
+    /// See <https://www.w3.org/TR/trace-context/#traceparent-header>.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let version = parts.next().filter(|v| is_lower_hex(v, 2))?;
+        let trace_id = parts.next().filter(|v| is_lower_hex(v, 32))?;
+        let parent_id = parts.next().filter(|v| is_lower_hex(v, 16))?;
+        let flags = parts.next().filter(|v| is_lower_hex(v, 2))?;
+        let version = u8::from_str_radix(version, 16).ok()?;
+        // Version 00 has exactly four fields; future versions may add more
+        if version == 0xff || (version == 0 && parts.next().is_some()) {
+            return None;
+        }
+        if is_zero(trace_id) || is_zero(parent_id) {
+            return None;
+        }
+        Some(Self {
+            version,
+            trace_id: trace_id.into(),
+            parent_id: parent_id.into(),
+            flags: u8::from_str_radix(flags, 16).ok()?,
+        })
+    }
+
+    /// Returns true if the sampled flag is set.
+    pub fn is_sampled(&self) -> bool {
+        self.flags & 0x01 != 0
+    }
+}
+
+/// Parses a W3C `baggage` header value into key-value pairs,
+/// skipping malformed members and member properties.
+///
+/// See <https://www.w3.org/TR/baggage/#baggage-http-header-format>.
+pub fn parse_baggage(value: &str) -> Vec<(String, String)> {
+    value
+        .split(',')
+        .filter_map(|member| {
+            let pair = member.split(';').next()?;
+            let (key, value) = pair.split_once('=')?;
+            let key = key.trim();
+            if key.is_empty() {
+                return None;
+            }
+            let value = percent_decode_str(value.trim()).decode_utf8().ok()?;
+            Some((key.to_string(), value.into_owned()))
+        })
+        .collect()
+}
+
+/// Returns true if the value is a well-formed request ID, i.e. 1 to 200
+/// visible ASCII characters.
+pub fn is_valid_request_id(value: &str) -> bool {
+    (1..=200).contains(&value.len()) && value.bytes().all(|b| b.is_ascii_graphic())
+}
+
+fn is_lower_hex(value: &str, len: usize) -> bool {
+    value.len() == len
+        && value
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn is_zero(value: &str) -> bool {
+    value.bytes().all(|b| b == b'0')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_context() {
+        let ctx =
+            TraceContext::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+        assert_eq!(ctx.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(ctx.parent_id, "00f067aa0ba902b7");
+        assert!(ctx.is_sampled());
+
+        for value in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+        ] {
+            assert!(TraceContext::parse(value).is_none(), "{value}");
+        }
+    }
+
+    #[test]
+    fn test_baggage() {
+        assert_eq!(
+            parse_baggage("userId=alice, isProduction=false;prop=1, name=a%20b, invalid"),
+            [
+                ("userId".to_string(), "alice".to_string()),
+                ("isProduction".to_string(), "false".to_string()),
+                ("name".to_string(), "a b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_request_id() {
+        assert!(is_valid_request_id("f058ebd6-02f7-4d3f-942e-904344e8cde5"));
+        assert!(!is_valid_request_id(""));
+        assert!(!is_valid_request_id("has space"));
+    }
+}
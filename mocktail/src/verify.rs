@@ -0,0 +1,375 @@
+//! Verification
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use uuid::Uuid;
+
+use crate::{journal::Journal, matchers::Matcher, mock_builder::When};
+
+/// An expected order of calls to mocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sequence {
+    mocks: Vec<Uuid>,
+    consecutive: bool,
+}
+
+impl Sequence {
+    /// Creates a sequence, expecting each mock to be first called
+    /// after the mock preceding it.
+    pub fn new(mocks: impl IntoIterator<Item = Uuid>) -> Self {
+        Self {
+            mocks: mocks.into_iter().collect(),
+            consecutive: false,
+        }
+    }
+
+    /// Expects the mocks to be called strictly consecutively,
+    /// with no other requests in between.
+    pub fn consecutive(mut self) -> Self {
+        self.consecutive = true;
+        self
+    }
+
+    /// Returns the mock IDs.
+    pub fn mocks(&self) -> &[Uuid] {
+        &self.mocks
+    }
+
+    /// Verifies the sequence against the journal.
+    pub fn verify(&self, journal: &Journal) -> Result<(), String> {
+        let calls = journal
+            .iter()
+            .map(|entry| entry.mock_id)
+            .collect::<Vec<_>>();
+        let ok = if self.consecutive {
+            self.mocks.is_empty()
+                || calls.windows(self.mocks.len()).any(|window| {
+                    window
+                        .iter()
+                        .zip(&self.mocks)
+                        .all(|(call, id)| call.as_ref() == Some(id))
+                })
+        } else {
+            let mut positions = Vec::with_capacity(self.mocks.len());
+            for id in &self.mocks {
+                match calls.iter().position(|call| call.as_ref() == Some(id)) {
+                    Some(position) => positions.push(position),
+                    None => break,
+                }
+            }
+            positions.len() == self.mocks.len() && positions.is_sorted()
+        };
+        if ok {
+            Ok(())
+        } else {
+            let expected = self
+                .mocks
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            let kind = if self.consecutive {
+                "consecutive sequence"
+            } else {
+                "sequence"
+            };
+            Err(format!(
+                "expected {kind} {expected}\ntimeline:\n{}",
+                timeline(journal)
+            ))
+        }
+    }
+}
+
+/// The recorded calls to a mock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attempts {
+    mock_id: Uuid,
+    labels: BTreeMap<String, String>,
+    timestamps: Vec<SystemTime>,
+}
+
+impl Attempts {
+    /// Collects the calls to a mock from the journal.
+    pub fn new(journal: &Journal, mock_id: Uuid) -> Self {
+        let entries = journal
+            .iter()
+            .filter(|entry| entry.mock_id == Some(mock_id))
+            .collect::<Vec<_>>();
+        Self {
+            mock_id,
+            labels: entries
+                .first()
+                .map(|entry| entry.labels.clone())
+                .unwrap_or_default(),
+            timestamps: entries.iter().map(|entry| entry.timestamp).collect(),
+        }
+    }
+
+    /// Returns the number of calls.
+    pub fn count(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    /// Returns the number of retries, i.e. calls after the first.
+    pub fn retries(&self) -> usize {
+        self.count().saturating_sub(1)
+    }
+
+    /// Returns the time of each call.
+    pub fn timestamps(&self) -> &[SystemTime] {
+        &self.timestamps
+    }
+
+    /// Returns the time between each call and the preceding call.
+    pub fn intervals(&self) -> Vec<Duration> {
+        self.timestamps
+            .windows(2)
+            .map(|pair| pair[1].duration_since(pair[0]).unwrap_or_default())
+            .collect()
+    }
+
+    /// Returns the time between the first and last call.
+    pub fn elapsed(&self) -> Duration {
+        match (self.timestamps.first(), self.timestamps.last()) {
+            (Some(first), Some(last)) => last.duration_since(*first).unwrap_or_default(),
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Verifies the mock was retried between `min` and `max` times (inclusive),
+    /// with all calls made `within` the duration.
+    pub fn verify_retried(&self, min: usize, max: usize, within: Duration) -> Result<(), String> {
+        let retries = self.retries();
+        let mut failures = Vec::new();
+        if !(min..=max).contains(&retries) {
+            failures.push(format!(
+                "expected between {min} and {max} retries, got {retries}"
+            ));
+        }
+        let elapsed = self.elapsed();
+        if elapsed > within {
+            failures.push(format!(
+                "expected calls within {within:?}, took {elapsed:?}"
+            ));
+        }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            let mut mock = self.mock_id.to_string();
+            if !self.labels.is_empty() {
+                mock.push_str(&format!(" {}", format_labels(&self.labels)));
+            }
+            Err(format!(
+                "mock {mock}: {}\nintervals: {:?}",
+                failures.join(", "),
+                self.intervals()
+            ))
+        }
+    }
+}
+
+/// An expected number of requests matching a set of matchers,
+/// verified with [`MockServer::assert_received`](crate::server::MockServer::assert_received).
+///
+/// Created with [`request_matching`], expecting at least one request by default.
+#[derive(Debug, Clone)]
+pub struct Expectation {
+    matchers: Vec<Arc<dyn Matcher>>,
+    min: usize,
+    max: Option<usize>,
+}
+
+/// Creates an expectation of requests matching the matchers of a [`When`],
+/// e.g. `request_matching(When::new().post().path("/orders")).times(2)`.
+pub fn request_matching(when: When) -> Expectation {
+    Expectation {
+        matchers: when.into_inner(),
+        min: 1,
+        max: None,
+    }
+}
+
+impl Expectation {
+    /// Expects exactly `n` matching requests.
+    pub fn times(mut self, n: usize) -> Self {
+        self.min = n;
+        self.max = Some(n);
+        self
+    }
+
+    /// Expects at least `n` matching requests.
+    pub fn at_least(mut self, n: usize) -> Self {
+        self.min = n;
+        self
+    }
+
+    /// Expects at most `n` matching requests.
+    pub fn at_most(mut self, n: usize) -> Self {
+        self.max = Some(n);
+        if self.min > n {
+            self.min = 0;
+        }
+        self
+    }
+
+    /// Expects no matching requests.
+    pub fn never(self) -> Self {
+        self.times(0)
+    }
+
+    /// Returns the number of requests in the journal matching the matchers.
+    pub fn count(&self, journal: &Journal) -> usize {
+        journal
+            .iter()
+            .filter(|entry| {
+                self.matchers
+                    .iter()
+                    .all(|matcher| matcher.matches(entry.request()))
+            })
+            .count()
+    }
+
+    /// Verifies the expectation against the journal.
+    pub fn verify(&self, journal: &Journal) -> Result<(), String> {
+        let count = self.count(journal);
+        if count >= self.min && self.max.is_none_or(|max| count <= max) {
+            return Ok(());
+        }
+        let expected = match self.max {
+            Some(max) if max == self.min => format!("{max}"),
+            Some(max) => format!("between {} and {max}", self.min),
+            None => format!("at least {}", self.min),
+        };
+        let matchers = self
+            .matchers
+            .iter()
+            .map(|matcher| matcher.name())
+            .collect::<Vec<_>>()
+            .join(", ");
+        Err(format!(
+            "expected {expected} requests matching [{matchers}], got {count}\ntimeline:\n{}",
+            timeline(journal)
+        ))
+    }
+}
+
+/// Formats the journal as a timeline of requests and matched mocks.
+pub fn timeline(journal: &Journal) -> String {
+    journal
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let mut mock = entry
+                .mock_id
+                .map(|id| id.to_string())
+                .unwrap_or_else(|| "unmatched".into());
+            if !entry.labels.is_empty() {
+                mock.push_str(&format!(" {}", format_labels(&entry.labels)));
+            }
+            format!(
+                "  {i}: {} {} -> {mock}",
+                entry.request.method(),
+                entry.request.path()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats mock labels, e.g. `[fixture=users, test=login]`.
+fn format_labels(labels: &BTreeMap<String, String>) -> String {
+    let labels = labels
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("[{labels}]")
+}
+
+/// Represents verification failures.
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+#[error("verification failed:\n{}", .failures.join("\n"))]
+pub struct VerificationError {
+    pub failures: Vec<String>,
+}
+
+impl VerificationError {
+    /// Returns the verification failures.
+    pub fn failures(&self) -> &[String] {
+        &self.failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Method, Request};
+
+    fn journal(calls: &[Option<Uuid>]) -> Journal {
+        let mut journal = Journal::new();
+        for call in calls {
+            let request = Request::new(Method::GET, "http://localhost/".parse().unwrap());
+            journal.record(request, *call);
+        }
+        journal
+    }
+
+    #[test]
+    fn test_sequence() {
+        let (a, b) = (Uuid::now_v7(), Uuid::now_v7());
+        let sequence = Sequence::new([a, b]);
+        assert!(sequence.verify(&journal(&[Some(a), None, Some(b)])).is_ok());
+        assert!(sequence
+            .verify(&journal(&[Some(a), Some(b), Some(a)]))
+            .is_ok());
+        assert!(sequence.verify(&journal(&[Some(b), Some(a)])).is_err());
+        assert!(sequence.verify(&journal(&[Some(a)])).is_err());
+
+        let sequence = sequence.consecutive();
+        assert!(sequence.verify(&journal(&[None, Some(a), Some(b)])).is_ok());
+        assert!(sequence
+            .verify(&journal(&[Some(a), None, Some(b)]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_attempts() {
+        let (a, b) = (Uuid::now_v7(), Uuid::now_v7());
+        let attempts = Attempts::new(&journal(&[Some(a), Some(b), Some(a), Some(a)]), a);
+        assert_eq!(attempts.count(), 3);
+        assert_eq!(attempts.retries(), 2);
+        assert_eq!(attempts.intervals().len(), 2);
+        assert!(attempts
+            .verify_retried(1, 2, Duration::from_secs(1))
+            .is_ok());
+        assert!(attempts
+            .verify_retried(3, 5, Duration::from_secs(1))
+            .is_err());
+    }
+
+    #[test]
+    fn test_expectation() {
+        let mut journal = Journal::new();
+        for path in ["/orders", "/orders", "/users"] {
+            let request = Request::new(
+                Method::POST,
+                format!("http://localhost{path}").parse().unwrap(),
+            );
+            journal.record(request, None);
+        }
+        let orders = || request_matching(When::new().post().path("/orders"));
+        assert!(orders().verify(&journal).is_ok());
+        assert!(orders().times(2).verify(&journal).is_ok());
+        assert!(orders().times(1).verify(&journal).is_err());
+        assert!(orders().at_least(3).verify(&journal).is_err());
+        assert!(orders().at_most(2).verify(&journal).is_ok());
+        assert!(request_matching(When::new().get())
+            .never()
+            .verify(&journal)
+            .is_ok());
+    }
+}
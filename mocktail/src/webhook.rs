@@ -0,0 +1,152 @@
+//! Webhooks
+use std::time::{Duration, SystemTime};
+
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper_util::{
+    client::legacy::{connect::HttpConnector, Client},
+    rt::TokioExecutor,
+};
+use tokio::task::AbortHandle;
+use tracing::debug;
+use url::Url;
+
+use crate::{headers::Headers, request::Method};
+
+/// A callback request sent by the server when a mock is matched,
+/// e.g. an asynchronous "processing complete" notification.
+///
+/// Webhooks are sent after the delay, or at a time of the server clock,
+/// and retried while the receiver fails or responds with an error status.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Webhook {
+    method: Method,
+    url: Url,
+    headers: Headers,
+    body: Bytes,
+    delay: Duration,
+    at: Option<SystemTime>,
+    retries: usize,
+    retry_delay: Duration,
+}
+
+impl Webhook {
+    /// Creates a webhook sent immediately, without retries.
+    ///
+    /// Only `http` urls are supported; webhooks are sent with HTTP/1.1.
+    pub fn new(method: Method, url: Url) -> Self {
+        Self {
+            method,
+            url,
+            headers: Headers::new(),
+            body: Bytes::new(),
+            delay: Duration::ZERO,
+            at: None,
+            retries: 0,
+            retry_delay: Duration::ZERO,
+        }
+    }
+
+    /// Creates a `POST` webhook.
+    pub fn post(url: Url) -> Self {
+        Self::new(Method::POST, url)
+    }
+
+    /// Adds a header.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Sets the body.
+    pub fn with_body(mut self, body: impl Into<Bytes>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Sets a JSON body.
+    pub fn with_json(self, body: impl serde::Serialize) -> Self {
+        self.with_header("content-type", "application/json")
+            .with_body(serde_json::to_vec(&body).unwrap())
+    }
+
+    /// Sends the webhook after a delay from when the request is matched.
+    pub fn after(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self.at = None;
+        self
+    }
+
+    /// Sends the webhook at a time of the server clock, or immediately
+    /// if the time has passed when the request is matched.
+    pub fn at(mut self, time: SystemTime) -> Self {
+        self.at = Some(time);
+        self
+    }
+
+    /// Retries the webhook up to `retries` times, waiting `delay` between
+    /// attempts, if the receiver fails or responds with an error status.
+    pub fn with_retries(mut self, retries: usize, delay: Duration) -> Self {
+        self.retries = retries;
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Returns the url.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Returns the delay before the webhook is sent, given the server clock time.
+    pub fn delay(&self, now: SystemTime) -> Duration {
+        match self.at {
+            Some(time) => time.duration_since(now).unwrap_or_default(),
+            None => self.delay,
+        }
+    }
+
+    /// Schedules the webhook on the runtime, given the server clock time.
+    ///
+    /// Returns a handle to cancel the webhook.
+    pub(crate) fn schedule(self, now: SystemTime) -> AbortHandle {
+        let delay = self.delay(now);
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            self.send().await;
+        })
+        .abort_handle()
+    }
+
+    /// Sends the webhook, retrying failed attempts.
+    async fn send(&self) {
+        let client: Client<HttpConnector, Full<Bytes>> =
+            Client::builder(TokioExecutor::new()).build_http();
+        for attempt in 0..=self.retries {
+            if attempt > 0 {
+                tokio::time::sleep(self.retry_delay).await;
+            }
+            let mut req = http::Request::builder()
+                .method(http::Method::from(self.method.clone()))
+                .uri(self.url.as_str())
+                .body(Full::new(self.body.clone()))
+                .unwrap();
+            *req.headers_mut() = self.headers.clone().into();
+            match client.request(req).await {
+                Ok(response)
+                    if !response.status().is_client_error()
+                        && !response.status().is_server_error() =>
+                {
+                    debug!(url = %self.url, status = %response.status(), "webhook sent");
+                    return;
+                }
+                Ok(response) => {
+                    debug!(url = %self.url, status = %response.status(), attempt, "webhook failed");
+                }
+                Err(err) => {
+                    debug!(url = %self.url, %err, attempt, "webhook failed");
+                }
+            }
+        }
+        debug!(url = %self.url, "webhook retries exhausted");
+    }
+}
@@ -0,0 +1,219 @@
+//! Protobuf wire format
+//!
+//! Messages are decoded without a schema into a tree of fields by number.
+//! Length-delimited values that decode as messages are compared as messages,
+//! which unpacks `google.protobuf.Any` values and nested messages of any depth.
+use std::{collections::BTreeMap, time::Duration};
+
+use bytes::Bytes;
+
+/// The maximum nanos of a `Timestamp` or `Duration`.
+const MAX_NANOS: u64 = 999_999_999;
+
+/// A decoded field value.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Varint(u64),
+    Fixed64(u64),
+    Fixed32(u32),
+    Bytes(Bytes),
+    Message(Bytes, Fields),
+}
+
+/// The values of a message by field number, in order.
+type Fields = BTreeMap<u32, Vec<Value>>;
+
+/// Semantic comparison of encoded messages.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Compare {
+    /// The tolerance of `Timestamp` and `Duration` values.
+    pub timestamp_tolerance: Option<Duration>,
+}
+
+impl Compare {
+    /// Returns true if the encoded messages are semantically equal.
+    ///
+    /// Falls back to comparing bytes if either fails to decode.
+    pub fn eq(&self, a: &Bytes, b: &Bytes) -> bool {
+        match (decode(a), decode(b)) {
+            (Some(a), Some(b)) => self.fields_eq(&a, &b),
+            _ => a == b,
+        }
+    }
+
+    fn fields_eq(&self, a: &Fields, b: &Fields) -> bool {
+        a.len() == b.len()
+            && a.iter().all(|(number, a)| {
+                b.get(number).is_some_and(|b| {
+                    if is_map(a) && is_map(b) {
+                        self.unordered_eq(a, b)
+                    } else {
+                        a.len() == b.len() && a.iter().zip(b).all(|(a, b)| self.value_eq(a, b))
+                    }
+                })
+            })
+    }
+
+    /// Compares map entries, which may be encoded in any order.
+    fn unordered_eq(&self, a: &[Value], b: &[Value]) -> bool {
+        if a.len() != b.len() {
+            return false;
+        }
+        let mut unmatched = b.iter().collect::<Vec<_>>();
+        a.iter().all(|a| {
+            unmatched
+                .iter()
+                .position(|b| self.value_eq(a, b))
+                .map(|i| unmatched.swap_remove(i))
+                .is_some()
+        })
+    }
+
+    fn value_eq(&self, a: &Value, b: &Value) -> bool {
+        match (a, b) {
+            (Value::Message(_, a), Value::Message(_, b)) => {
+                if let Some(tolerance) = self.timestamp_tolerance {
+                    if let (Some(a), Some(b)) = (timestamp(a), timestamp(b)) {
+                        return a.abs_diff(b) <= tolerance.as_nanos();
+                    }
+                }
+                self.fields_eq(a, b)
+            }
+            (Value::Message(a, _), Value::Bytes(b)) | (Value::Bytes(b), Value::Message(a, _)) => {
+                a == b
+            }
+            (a, b) => a == b,
+        }
+    }
+}
+
+/// Returns the field numbers of a message, in order, with the bytes of
+/// length-delimited values, or `None` if the bytes are not a valid message.
+pub(crate) fn fields(buf: &[u8]) -> Option<Vec<(u32, Option<Bytes>)>> {
+    let fields = decode(buf)?;
+    Some(
+        fields
+            .into_iter()
+            .flat_map(|(number, values)| {
+                values.into_iter().map(move |value| match value {
+                    Value::Bytes(bytes) | Value::Message(bytes, _) => (number, Some(bytes)),
+                    _ => (number, None),
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Decodes a message, returning `None` if the bytes are not a valid message.
+fn decode(mut buf: &[u8]) -> Option<Fields> {
+    let mut fields = Fields::new();
+    while !buf.is_empty() {
+        let key = varint(&mut buf)?;
+        let number = u32::try_from(key >> 3).ok().filter(|n| *n > 0)?;
+        let value = match key & 0x7 {
+            0 => Value::Varint(varint(&mut buf)?),
+            1 => Value::Fixed64(u64::from_le_bytes(take(&mut buf, 8)?.try_into().ok()?)),
+            2 => {
+                let len = usize::try_from(varint(&mut buf)?).ok()?;
+                let bytes = Bytes::copy_from_slice(take(&mut buf, len)?);
+                match decode(&bytes) {
+                    Some(nested) if !nested.is_empty() => Value::Message(bytes, nested),
+                    _ => Value::Bytes(bytes),
+                }
+            }
+            5 => Value::Fixed32(u32::from_le_bytes(take(&mut buf, 4)?.try_into().ok()?)),
+            // Groups are deprecated and not supported
+            _ => return None,
+        };
+        fields.entry(number).or_default().push(value);
+    }
+    Some(fields)
+}
+
+fn varint(buf: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for i in 0..10 {
+        let (&byte, rest) = buf.split_first()?;
+        *buf = rest;
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn take<'a>(buf: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if buf.len() < len {
+        return None;
+    }
+    let (bytes, rest) = buf.split_at(len);
+    *buf = rest;
+    Some(bytes)
+}
+
+/// Returns true if the values are map entries, messages with a single key
+/// (field 1) and a value (field 2).
+fn is_map(values: &[Value]) -> bool {
+    values.len() > 1
+        && values.iter().all(|value| match value {
+            Value::Message(_, fields) => {
+                fields.keys().all(|n| *n == 1 || *n == 2)
+                    && fields.get(&1).is_some_and(|key| key.len() == 1)
+            }
+            _ => false,
+        })
+}
+
+/// Returns the nanos of a message shaped like a `Timestamp` or `Duration`,
+/// with `seconds` (field 1) and `nanos` (field 2) varints.
+fn timestamp(fields: &Fields) -> Option<i128> {
+    let field = |number| match fields.get(&number).map(Vec::as_slice) {
+        None => Some(0),
+        Some([Value::Varint(value)]) => Some(*value),
+        _ => None,
+    };
+    if fields.keys().any(|n| *n != 1 && *n != 2) {
+        return None;
+    }
+    let seconds = field(1)? as i64;
+    let nanos = field(2)?;
+    if nanos > MAX_NANOS {
+        return None;
+    }
+    Some(i128::from(seconds) * 1_000_000_000 + i128::from(nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare() {
+        let compare = Compare::default();
+        // Field order: {1: 150, 2: "hi"} and {2: "hi", 1: 150}
+        let a = Bytes::from_static(&[0x08, 0x96, 0x01, 0x12, 0x02, b'h', b'i']);
+        let b = Bytes::from_static(&[0x12, 0x02, b'h', b'i', 0x08, 0x96, 0x01]);
+        assert!(compare.eq(&a, &b));
+        let c = Bytes::from_static(&[0x08, 0x97, 0x01, 0x12, 0x02, b'h', b'i']);
+        assert!(!compare.eq(&a, &c));
+
+        // Map entries: {1: [{1: 1, 2: 2}, {1: 3, 2: 4}]} in either order
+        let a = Bytes::from_static(&[
+            0x0a, 0x04, 0x08, 0x01, 0x10, 0x02, 0x0a, 0x04, 0x08, 0x03, 0x10, 0x04,
+        ]);
+        let b = Bytes::from_static(&[
+            0x0a, 0x04, 0x08, 0x03, 0x10, 0x04, 0x0a, 0x04, 0x08, 0x01, 0x10, 0x02,
+        ]);
+        assert!(compare.eq(&a, &b));
+
+        // Timestamps: {1: {1: 10}} and {1: {1: 11}}
+        let a = Bytes::from_static(&[0x0a, 0x02, 0x08, 0x0a]);
+        let b = Bytes::from_static(&[0x0a, 0x02, 0x08, 0x0b]);
+        assert!(!compare.eq(&a, &b));
+        let compare = Compare {
+            timestamp_tolerance: Some(Duration::from_secs(1)),
+        };
+        assert!(compare.eq(&a, &b));
+    }
+}